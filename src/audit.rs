@@ -0,0 +1,450 @@
+//! Persistent audit log of executed network actions.
+//!
+//! Every action picked from the menu is appended as one JSON object per line to
+//! `$XDG_STATE_HOME/network-dmenu/audit.jsonl` (usually
+//! `~/.local/state/network-dmenu/audit.jsonl`), together with the commands it
+//! ran through the [`CommandRunner`], the D-Bus calls its handler reported,
+//! their exit status and timings.
+
+use crate::command::{CommandFuture, CommandRunner};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const STATE_DIR_NAME: &str = "network-dmenu";
+const AUDIT_FILE_NAME: &str = "audit.jsonl";
+const REDACTED: &str = "<redacted>";
+
+/// Arguments whose following value is a secret and must never hit the log.
+const SECRET_ARGS: &[&str] = &[
+    "password",
+    "--passphrase",
+    "psk",
+    "sae_password",
+    "wep_key0",
+    "wifi-sec.psk",
+    "wifi-sec.wep-key0",
+    "802-1x.password",
+    "802-1x.private-key-password",
+];
+
+/// A single command executed while handling an action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditedCommand {
+    pub command: String,
+    pub args: Vec<String>,
+    /// Exit code, `None` when the process was killed or never started.
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Local>,
+    /// The menu entry that was selected.
+    pub action: String,
+    /// Action category (wifi, vpn, tailscale, ...).
+    pub category: String,
+    pub commands: Vec<AuditedCommand>,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Network state observed after the action ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Command runner wrapper that records every command it forwards.
+pub struct AuditingCommandRunner<'a> {
+    inner: &'a dyn CommandRunner,
+    commands: Mutex<Vec<AuditedCommand>>,
+}
+
+impl<'a> AuditingCommandRunner<'a> {
+    pub fn new(inner: &'a dyn CommandRunner) -> Self {
+        Self {
+            inner,
+            commands: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the commands recorded so far, leaving the list empty.
+    pub fn take_commands(&self) -> Vec<AuditedCommand> {
+        self.commands
+            .lock()
+            .map(|mut commands| std::mem::take(&mut *commands))
+            .unwrap_or_default()
+    }
+
+    fn record(
+        &self,
+        command: &str,
+        args: &[&str],
+        started: Instant,
+        result: &Result<Output, std::io::Error>,
+    ) {
+        let entry = AuditedCommand {
+            command: command.to_string(),
+            args: redact_args(args),
+            exit_code: result.as_ref().ok().and_then(|output| output.status.code()),
+            duration_ms: started.elapsed().as_millis() as u64,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.push(entry);
+        }
+    }
+}
+
+impl CommandRunner for AuditingCommandRunner<'_> {
+    fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+        let started = Instant::now();
+        let result = self.inner.run_command(command, args);
+        self.record(command, args, started, &result);
+        result
+    }
+
+    fn run_command_with_timeout<'b>(
+        &'b self,
+        command: &'b str,
+        args: &'b [&'b str],
+        timeout: Duration,
+    ) -> CommandFuture<'b> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = self
+                .inner
+                .run_command_with_timeout(command, args, timeout)
+                .await;
+            self.record(command, args, started, &result);
+            result
+        })
+    }
+
+    fn record_operation(
+        &self,
+        operation: &str,
+        args: &[&str],
+        exit_code: Option<i32>,
+        duration: Duration,
+        error: Option<String>,
+    ) {
+        let entry = AuditedCommand {
+            command: operation.to_string(),
+            args: redact_args(args),
+            exit_code,
+            duration_ms: duration.as_millis() as u64,
+            error,
+        };
+
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.push(entry);
+        }
+    }
+}
+
+/// Replaces secrets (passwords, passphrases) in command arguments.
+fn redact_args(args: &[&str]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut hide_next = false;
+
+    for arg in args {
+        if hide_next {
+            redacted.push(REDACTED.to_string());
+            hide_next = false;
+            continue;
+        }
+
+        hide_next = SECRET_ARGS.contains(arg);
+        redacted.push(arg.to_string());
+    }
+
+    redacted
+}
+
+/// Get the audit log file path
+pub fn get_audit_log_path() -> Result<PathBuf, Box<dyn Error>> {
+    let state_dir = dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("state")))
+        .ok_or("Failed to get state directory")?;
+    Ok(state_dir.join(STATE_DIR_NAME).join(AUDIT_FILE_NAME))
+}
+
+/// Append a record to the audit log
+pub fn append_audit_record(record: &AuditRecord) -> Result<(), Box<dyn Error>> {
+    append_audit_record_to(&get_audit_log_path()?, record)
+}
+
+fn append_audit_record_to(path: &Path, record: &AuditRecord) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+
+    Ok(())
+}
+
+/// Read the last `limit` records from the audit log, oldest first
+pub fn read_recent_audit_records(limit: usize) -> Result<Vec<AuditRecord>, Box<dyn Error>> {
+    read_recent_audit_records_from(&get_audit_log_path()?, limit)
+}
+
+fn read_recent_audit_records_from(
+    path: &Path,
+    limit: usize,
+) -> Result<Vec<AuditRecord>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let records: Vec<AuditRecord> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    let skip = records.len().saturating_sub(limit);
+    Ok(records.into_iter().skip(skip).collect())
+}
+
+/// Render audit records as a human readable report, newest first
+pub fn format_audit_records(records: &[AuditRecord]) -> String {
+    if records.is_empty() {
+        return "No network changes recorded yet".to_string();
+    }
+
+    let mut lines = Vec::new();
+    for record in records.iter().rev() {
+        let status = if record.success { "✅" } else { "❌" };
+        let action = record
+            .action
+            .split_once("- ")
+            .map(|(_, text)| text.trim())
+            .unwrap_or(record.action.trim());
+
+        lines.push(format!(
+            "{} {} [{}] {} ({}ms)",
+            status,
+            record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            record.category,
+            action,
+            record.duration_ms
+        ));
+
+        for command in &record.commands {
+            let exit = command
+                .exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            lines.push(format!(
+                "    $ {} {} → {}",
+                command.command,
+                command.args.join(" "),
+                exit
+            ));
+        }
+
+        if let Some(error) = &record.error {
+            lines.push(format!("    error: {}", error));
+        }
+        if let Some(state) = &record.state {
+            lines.push(format!("    state: {}", state));
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    struct MockCommandRunner {
+        exit_code: i32,
+    }
+
+    impl CommandRunner for MockCommandRunner {
+        fn run_command(&self, _command: &str, _args: &[&str]) -> Result<Output, std::io::Error> {
+            Ok(Output {
+                status: ExitStatus::from_raw(self.exit_code << 8),
+                stdout: vec![],
+                stderr: vec![],
+            })
+        }
+    }
+
+    fn sample_record(action: &str, success: bool) -> AuditRecord {
+        AuditRecord {
+            timestamp: Local::now(),
+            action: action.to_string(),
+            category: "wifi".to_string(),
+            commands: vec![],
+            success,
+            duration_ms: 42,
+            state: Some("HomeNetwork".to_string()),
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auditing_runner_records_commands() {
+        let inner = MockCommandRunner { exit_code: 4 };
+        let runner = AuditingCommandRunner::new(&inner);
+
+        let _ = runner.run_command("nmcli", &["connection", "up", "Work VPN"]);
+        let _ = runner
+            .run_command_async("iwctl", &["station", "wlan0", "scan"])
+            .await;
+
+        let commands = runner.take_commands();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].command, "nmcli");
+        assert_eq!(commands[0].args, vec!["connection", "up", "Work VPN"]);
+        assert_eq!(commands[0].exit_code, Some(4));
+        assert_eq!(commands[1].command, "iwctl");
+        assert!(runner.take_commands().is_empty());
+    }
+
+    #[test]
+    fn test_auditing_runner_records_operations() {
+        let inner = MockCommandRunner { exit_code: 0 };
+        let runner = AuditingCommandRunner::new(&inner);
+
+        runner.record_operation(
+            "dbus",
+            &["NetworkManager", "connect_wifi", "Cafe"],
+            None,
+            Duration::from_millis(1500),
+            Some("Secrets were required".to_string()),
+        );
+
+        let commands = runner.take_commands();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].command, "dbus");
+        assert_eq!(
+            commands[0].args,
+            vec!["NetworkManager", "connect_wifi", "Cafe"]
+        );
+        assert_eq!(commands[0].exit_code, None);
+        assert_eq!(commands[0].duration_ms, 1500);
+        assert_eq!(commands[0].error.as_deref(), Some("Secrets were required"));
+    }
+
+    #[test]
+    fn test_redact_args_hides_secrets() {
+        let args = redact_args(&["device", "wifi", "connect", "Home", "password", "hunter2"]);
+        assert_eq!(args[4], "password");
+        assert_eq!(args[5], REDACTED);
+
+        let args = redact_args(&["--passphrase", "secret", "station", "wlan0"]);
+        assert_eq!(args, vec!["--passphrase", REDACTED, "station", "wlan0"]);
+
+        let args = redact_args(&["802-1x.identity", "alice", "802-1x.password", "pw"]);
+        assert_eq!(
+            args,
+            vec!["802-1x.identity", "alice", "802-1x.password", REDACTED]
+        );
+    }
+
+    #[test]
+    fn test_redact_args_hides_wep_key() {
+        let args = redact_args(&[
+            "wifi-sec.wep-key-type",
+            "key",
+            "wifi-sec.wep-key0",
+            "0123456789",
+        ]);
+        assert_eq!(
+            args,
+            vec![
+                "wifi-sec.wep-key-type",
+                "key",
+                "wifi-sec.wep-key0",
+                REDACTED
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redact_args_hides_wpa_cli_secrets() {
+        for key in ["psk", "sae_password", "wep_key0", "password"] {
+            let args = redact_args(&["-i", "wlan0", "set_network", "0", key, "\"pw\""]);
+            assert_eq!(args, vec!["-i", "wlan0", "set_network", "0", key, REDACTED]);
+        }
+    }
+
+    #[test]
+    fn test_append_and_read_recent_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(AUDIT_FILE_NAME);
+
+        for i in 0..5 {
+            append_audit_record_to(&path, &sample_record(&format!("action {}", i), true)).unwrap();
+        }
+
+        let records = read_recent_audit_records_from(&path, 3).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].action, "action 2");
+        assert_eq!(records[2].action, "action 4");
+    }
+
+    #[test]
+    fn test_read_skips_corrupt_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(AUDIT_FILE_NAME);
+
+        append_audit_record_to(&path, &sample_record("first", true)).unwrap();
+        fs::write(
+            &path,
+            format!("{}not json\n", fs::read_to_string(&path).unwrap()),
+        )
+        .unwrap();
+        append_audit_record_to(&path, &sample_record("second", false)).unwrap();
+
+        let records = read_recent_audit_records_from(&path, 10).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(!records[1].success);
+    }
+
+    #[test]
+    fn test_read_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let records = read_recent_audit_records_from(&dir.path().join("missing"), 10).unwrap();
+        assert!(records.is_empty());
+    }
+
+    #[test]
+    fn test_format_audit_records() {
+        assert_eq!(format_audit_records(&[]), "No network changes recorded yet");
+
+        let mut record = sample_record("wifi      - 📶 HomeNetwork", true);
+        record.commands.push(AuditedCommand {
+            command: "nmcli".to_string(),
+            args: vec!["connection".to_string(), "up".to_string()],
+            exit_code: Some(0),
+            duration_ms: 10,
+            error: None,
+        });
+
+        let report = format_audit_records(&[record]);
+        assert!(report.contains("[wifi] 📶 HomeNetwork (42ms)"));
+        assert!(report.contains("$ nmcli connection up → 0"));
+        assert!(report.contains("state: HomeNetwork"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::command::{read_output_lines, CommandRunner};
+use crate::command::{read_output_lines, CommandRunner, CONNECTION_COMMAND_TIMEOUT};
 use crate::constants::{ICON_BLUETOOTH, ICON_CHECK};
 use crate::format_entry;
 use regex::Regex;
@@ -12,11 +12,15 @@ pub enum BluetoothAction {
 }
 
 /// Retrieves a list of paired Bluetooth devices and their connection status.
-pub fn get_paired_bluetooth_devices(
+pub async fn get_paired_bluetooth_devices(
     command_runner: &dyn CommandRunner,
 ) -> Result<Vec<BluetoothAction>, Box<dyn Error>> {
-    let output = command_runner.run_command("bluetoothctl", &["devices"])?;
-    let connected_devices = get_connected_devices(command_runner).unwrap_or_else(|_| vec![]);
+    let output = command_runner
+        .run_command_async("bluetoothctl", &["devices"])
+        .await?;
+    let connected_devices = get_connected_devices(command_runner)
+        .await
+        .unwrap_or_else(|_| vec![]);
 
     if output.status.success() {
         let devices =
@@ -75,20 +79,20 @@ fn parse_bluetooth_device(line: String, connected_devices: &[String]) -> Option<
 }
 
 /// Handles a Bluetooth action, such as connecting or disconnecting a device.
-pub fn handle_bluetooth_action(
+pub async fn handle_bluetooth_action(
     action: &BluetoothAction,
     connected_devices: &[String],
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
     match action {
         BluetoothAction::ToggleConnect(device) => {
-            connect_to_bluetooth_device(device, connected_devices, command_runner)
+            connect_to_bluetooth_device(device, connected_devices, command_runner).await
         }
     }
 }
 
 /// Connects or disconnects a Bluetooth device based on its current status.
-fn connect_to_bluetooth_device(
+async fn connect_to_bluetooth_device(
     device: &str,
     connected_devices: &[String],
     command_runner: &dyn CommandRunner,
@@ -99,7 +103,12 @@ fn connect_to_bluetooth_device(
         #[cfg(debug_assertions)]
         println!("Connect to Bluetooth device: {address}");
         let status = command_runner
-            .run_command("bluetoothctl", &[action, &address])?
+            .run_command_with_timeout(
+                "bluetoothctl",
+                &[action, &address],
+                CONNECTION_COMMAND_TIMEOUT,
+            )
+            .await?
             .status;
 
         if status.success() {
@@ -124,10 +133,12 @@ fn extract_device_address(device: &str) -> Option<String> {
 }
 
 /// Retrieves a list of currently connected Bluetooth devices.
-pub fn get_connected_devices(
+pub async fn get_connected_devices(
     command_runner: &dyn CommandRunner,
 ) -> Result<Vec<String>, Box<dyn Error>> {
-    let output = command_runner.run_command("bluetoothctl", &["info"])?;
+    let output = command_runner
+        .run_command_async("bluetoothctl", &["info"])
+        .await?;
     let mac_addresses = read_output_lines(&output)?
         .into_iter()
         .filter(|line| line.starts_with("Device "))
@@ -273,8 +284,8 @@ mod tests {
         assert_eq!(devices.len(), 0);
     }
 
-    #[test]
-    fn test_get_connected_devices_success() {
+    #[tokio::test]
+    async fn test_get_connected_devices_success() {
         let stdout = b"Device AA:BB:CC:DD:EE:FF\nDevice 11:22:33:44:55:66\n";
         let output = Output {
             status: ExitStatus::from_raw(0),
@@ -283,7 +294,7 @@ mod tests {
         };
 
         let mock_runner = MockCommandRunner::new("bluetoothctl", &["info"], output);
-        let result = get_connected_devices(&mock_runner);
+        let result = get_connected_devices(&mock_runner).await;
 
         assert!(result.is_ok());
         let devices = result.unwrap();
@@ -292,8 +303,8 @@ mod tests {
         assert_eq!(devices[1], "11:22:33:44:55:66");
     }
 
-    #[test]
-    fn test_get_connected_devices_no_devices() {
+    #[tokio::test]
+    async fn test_get_connected_devices_no_devices() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -301,15 +312,15 @@ mod tests {
         };
 
         let mock_runner = MockCommandRunner::new("bluetoothctl", &["info"], output);
-        let result = get_connected_devices(&mock_runner);
+        let result = get_connected_devices(&mock_runner).await;
 
         assert!(result.is_ok());
         let devices = result.unwrap();
         assert_eq!(devices.len(), 0);
     }
 
-    #[test]
-    fn test_get_connected_devices_command_failure() {
+    #[tokio::test]
+    async fn test_get_connected_devices_command_failure() {
         let output = Output {
             status: ExitStatus::from_raw(1),
             stdout: vec![],
@@ -317,7 +328,7 @@ mod tests {
         };
 
         let mock_runner = MockCommandRunner::new("bluetoothctl", &["info"], output);
-        let result = get_connected_devices(&mock_runner);
+        let result = get_connected_devices(&mock_runner).await;
 
         // Function returns Ok with empty vec on failure, not an error
         assert!(result.is_ok());
@@ -358,8 +369,8 @@ mod tests {
         assert_eq!(devices.len(), 0);
     }
 
-    #[test]
-    fn test_connect_to_bluetooth_device_connect() {
+    #[tokio::test]
+    async fn test_connect_to_bluetooth_device_connect() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -371,13 +382,13 @@ mod tests {
         let mock_runner =
             MockCommandRunner::new("bluetoothctl", &["connect", "AA:BB:CC:DD:EE:FF"], output);
 
-        let result = connect_to_bluetooth_device(device, &connected_devices, &mock_runner);
+        let result = connect_to_bluetooth_device(device, &connected_devices, &mock_runner).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
 
-    #[test]
-    fn test_connect_to_bluetooth_device_disconnect() {
+    #[tokio::test]
+    async fn test_connect_to_bluetooth_device_disconnect() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -392,13 +403,13 @@ mod tests {
         let mock_runner =
             MockCommandRunner::new("bluetoothctl", &["disconnect", "AA:BB:CC:DD:EE:FF"], output);
 
-        let result = connect_to_bluetooth_device(&device, &connected_devices, &mock_runner);
+        let result = connect_to_bluetooth_device(&device, &connected_devices, &mock_runner).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
 
-    #[test]
-    fn test_connect_to_bluetooth_device_no_address() {
+    #[tokio::test]
+    async fn test_connect_to_bluetooth_device_no_address() {
         let device = "invalid device string";
         let connected_devices = vec![];
 
@@ -410,13 +421,13 @@ mod tests {
         };
         let mock_runner = MockCommandRunner::new("never_called", &[], output);
 
-        let result = connect_to_bluetooth_device(device, &connected_devices, &mock_runner);
+        let result = connect_to_bluetooth_device(device, &connected_devices, &mock_runner).await;
         assert!(result.is_ok());
         assert!(!result.unwrap());
     }
 
-    #[test]
-    fn test_connect_to_bluetooth_device_command_failure() {
+    #[tokio::test]
+    async fn test_connect_to_bluetooth_device_command_failure() {
         let output = Output {
             status: ExitStatus::from_raw(1),
             stdout: vec![],
@@ -428,13 +439,13 @@ mod tests {
         let mock_runner =
             MockCommandRunner::new("bluetoothctl", &["connect", "AA:BB:CC:DD:EE:FF"], output);
 
-        let result = connect_to_bluetooth_device(device, &connected_devices, &mock_runner);
+        let result = connect_to_bluetooth_device(device, &connected_devices, &mock_runner).await;
         assert!(result.is_ok());
         assert!(!result.unwrap());
     }
 
-    #[test]
-    fn test_handle_bluetooth_action_toggle_connect() {
+    #[tokio::test]
+    async fn test_handle_bluetooth_action_toggle_connect() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -447,7 +458,7 @@ mod tests {
         let mock_runner =
             MockCommandRunner::new("bluetoothctl", &["connect", "AA:BB:CC:DD:EE:FF"], output);
 
-        let result = handle_bluetooth_action(&action, &connected_devices, &mock_runner);
+        let result = handle_bluetooth_action(&action, &connected_devices, &mock_runner).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
@@ -0,0 +1,607 @@
+//! Captive portal detection and login.
+//!
+//! After joining a network, several well-known probe URLs are fetched without
+//! following redirects and NetworkManager's own connectivity check is
+//! consulted. When a portal is found, a per-SSID auto-login script gets the
+//! first try; otherwise a notification offers to open the login page in a
+//! throwaway browser profile, and connectivity is polled until the portal is
+//! passed so that VPNs and exit nodes are only brought up once they can work.
+
+use crate::command::{is_command_installed, CommandRunner, SCRIPT_COMMAND_TIMEOUT};
+use crate::utils::expand_home;
+use log::debug;
+use notify_rust::Notification;
+use reqwest::redirect::Policy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tokio::task::JoinSet;
+use tokio::time::{sleep, Duration, Instant};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time for DHCP and DNS to settle before probing.
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Browsers that can run on a separate profile, with the arguments doing so.
+const PROFILE_BROWSERS: [(&str, &[&str]); 5] = [
+    (
+        "chromium",
+        &["--user-data-dir={profile}", "--no-first-run", "{url}"],
+    ),
+    (
+        "google-chrome-stable",
+        &["--user-data-dir={profile}", "--no-first-run", "{url}"],
+    ),
+    (
+        "brave",
+        &["--user-data-dir={profile}", "--no-first-run", "{url}"],
+    ),
+    (
+        "firefox",
+        &["--profile", "{profile}", "--no-remote", "{url}"],
+    ),
+    (
+        "librewolf",
+        &["--profile", "{profile}", "--no-remote", "{url}"],
+    ),
+];
+
+/// Captive portal settings, the `[captive_portal]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptivePortalConfig {
+    /// Probe for a portal after connecting
+    pub enabled: bool,
+    /// How long to wait for the portal login before giving up
+    pub login_timeout_secs: u64,
+    /// Browser command, with `{url}` and `{profile}` substituted
+    pub browser: Option<String>,
+    /// Login scripts by SSID, called with the login URL and the SSID
+    pub auto_login: HashMap<String, String>,
+}
+
+impl Default for CaptivePortalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            login_timeout_secs: 300,
+            browser: None,
+            auto_login: HashMap::new(),
+        }
+    }
+}
+
+/// What a probe URL answers when there is no portal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeExpectation {
+    /// An empty `204 No Content`
+    NoContent,
+    /// A `200` whose body contains the text
+    Body(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    pub url: &'static str,
+    pub expect: ProbeExpectation,
+}
+
+/// Probes run concurrently; a portal has to intercept all of them.
+pub const PROBES: [Probe; 3] = [
+    Probe {
+        url: "http://detectportal.firefox.com/success.txt",
+        expect: ProbeExpectation::Body("success"),
+    },
+    Probe {
+        url: "http://connectivitycheck.gstatic.com/generate_204",
+        expect: ProbeExpectation::NoContent,
+    },
+    Probe {
+        url: "http://captive.apple.com/hotspot-detect.html",
+        expect: ProbeExpectation::Body("Success"),
+    },
+];
+
+/// Result of a single probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    Online,
+    /// Redirected, usually to the login page
+    Redirect(String),
+    /// Answered with something else, e.g. an injected login page
+    Intercepted,
+    Failed,
+}
+
+/// NetworkManager's view of the connectivity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmConnectivity {
+    Full,
+    Portal,
+    Limited,
+    None,
+    Unknown,
+}
+
+impl NmConnectivity {
+    fn parse(output: &str) -> Self {
+        match output.trim() {
+            "full" => Self::Full,
+            "portal" => Self::Portal,
+            "limited" => Self::Limited,
+            "none" => Self::None,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Connectivity after evaluating the probes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalState {
+    Online,
+    /// Behind a portal, with its login URL when a probe was redirected there
+    Portal(Option<String>),
+    Offline,
+}
+
+impl PortalState {
+    /// Page to open to reach the portal.
+    pub fn login_url(&self) -> &str {
+        match self {
+            Self::Portal(Some(url)) => url,
+            _ => PROBES[0].url,
+        }
+    }
+}
+
+/// Classifies a probe response.
+pub fn classify_response(
+    expect: ProbeExpectation,
+    status: u16,
+    location: Option<&str>,
+    body: &str,
+) -> ProbeOutcome {
+    if (300..400).contains(&status) {
+        return match location {
+            Some(location) => ProbeOutcome::Redirect(location.to_string()),
+            None => ProbeOutcome::Intercepted,
+        };
+    }
+
+    let expected = match expect {
+        ProbeExpectation::NoContent => status == 204 && body.is_empty(),
+        ProbeExpectation::Body(text) => status == 200 && body.contains(text),
+    };
+    if expected {
+        ProbeOutcome::Online
+    } else {
+        ProbeOutcome::Intercepted
+    }
+}
+
+/// Combines the probe outcomes and NetworkManager's connectivity check.
+///
+/// A redirect is the strongest sign of a portal. Otherwise a single probe
+/// getting through is enough to be online, since a portal intercepts all
+/// plain HTTP traffic.
+pub fn evaluate(outcomes: &[ProbeOutcome], nm: Option<NmConnectivity>) -> PortalState {
+    if let Some(url) = outcomes.iter().find_map(|outcome| match outcome {
+        ProbeOutcome::Redirect(url) => Some(url.clone()),
+        _ => None,
+    }) {
+        return PortalState::Portal(Some(url));
+    }
+    if outcomes.contains(&ProbeOutcome::Online) {
+        return PortalState::Online;
+    }
+    if outcomes.contains(&ProbeOutcome::Intercepted) || nm == Some(NmConnectivity::Portal) {
+        return PortalState::Portal(None);
+    }
+    if nm == Some(NmConnectivity::Full) {
+        return PortalState::Online;
+    }
+    PortalState::Offline
+}
+
+async fn run_probe(client: Client, probe: Probe) -> ProbeOutcome {
+    let response = match client.get(probe.url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            debug!("Captive portal probe {} failed: {}", probe.url, e);
+            return ProbeOutcome::Failed;
+        }
+    };
+
+    let status = response.status().as_u16();
+    let location = response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.unwrap_or_default();
+    classify_response(probe.expect, status, location.as_deref(), &body)
+}
+
+/// Asks NetworkManager to recheck connectivity.
+async fn nm_connectivity(command_runner: &dyn CommandRunner) -> Option<NmConnectivity> {
+    if !is_command_installed("nmcli") {
+        return None;
+    }
+    let output = command_runner
+        .run_command_async("nmcli", &["networking", "connectivity", "check"])
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(NmConnectivity::parse(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Probes the connectivity once.
+pub async fn detect(command_runner: &dyn CommandRunner) -> Result<PortalState, Box<dyn Error>> {
+    let client = Client::builder()
+        .redirect(Policy::none())
+        .timeout(PROBE_TIMEOUT)
+        .build()?;
+
+    let mut probes = JoinSet::new();
+    for probe in PROBES {
+        probes.spawn(run_probe(client.clone(), probe));
+    }
+    let outcomes: Vec<ProbeOutcome> = probes.join_all().await;
+
+    let state = evaluate(&outcomes, nm_connectivity(command_runner).await);
+    debug!("Captive portal probes {:?}: {:?}", outcomes, state);
+    Ok(state)
+}
+
+/// Polls until the portal lets traffic through or `timeout` passes.
+pub async fn wait_until_online(
+    timeout: Duration,
+    command_runner: &dyn CommandRunner,
+) -> Result<PortalState, Box<dyn Error>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let state = detect(command_runner).await?;
+        if state == PortalState::Online || Instant::now() >= deadline {
+            return Ok(state);
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Runs the login script configured for `ssid`, if any.
+///
+/// Returns whether a script ran and exited successfully.
+pub async fn run_auto_login(
+    ssid: &str,
+    login_url: &str,
+    config: &CaptivePortalConfig,
+    command_runner: &dyn CommandRunner,
+) -> bool {
+    let Some(script) = config.auto_login.get(ssid) else {
+        return false;
+    };
+
+    let script = expand_home(script);
+    match command_runner
+        .run_command_with_timeout(
+            &script.to_string_lossy(),
+            &[login_url, ssid],
+            SCRIPT_COMMAND_TIMEOUT,
+        )
+        .await
+    {
+        Ok(output) if output.status.success() => true,
+        Ok(output) => {
+            debug!(
+                "Portal login script for {} failed: {}",
+                ssid,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            false
+        }
+        Err(e) => {
+            debug!("Failed to run the portal login script for {}: {}", ssid, e);
+            false
+        }
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Fills in a `browser` command template for `sh -c`.
+fn expand_browser_template(template: &str, url: &str, profile: &Path) -> String {
+    template
+        .replace("{profile}", &shell_quote(&profile.to_string_lossy()))
+        .replace("{url}", &shell_quote(url))
+}
+
+/// Profile directory for the login page, emptied before each use so the
+/// portal's cookies and scripts never touch the user's browser profile.
+fn fresh_browser_profile() -> Result<PathBuf, Box<dyn Error>> {
+    let profile = dirs::cache_dir()
+        .ok_or("Failed to get cache directory")?
+        .join("network-dmenu")
+        .join("portal-browser");
+    if profile.exists() {
+        std::fs::remove_dir_all(&profile)?;
+    }
+    std::fs::create_dir_all(&profile)?;
+    Ok(profile)
+}
+
+/// Opens the login page, preferably in a separate browser profile.
+pub fn open_login_page(url: &str, config: &CaptivePortalConfig) -> Result<(), Box<dyn Error>> {
+    let profile = fresh_browser_profile()?;
+
+    let mut command = if let Some(template) = &config.browser {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(expand_browser_template(template, url, &profile));
+        command
+    } else if let Some((browser, args)) = PROFILE_BROWSERS
+        .iter()
+        .find(|(browser, _)| is_command_installed(browser))
+    {
+        let mut command = Command::new(browser);
+        let profile = profile.to_string_lossy();
+        command.args(
+            args.iter()
+                .map(|arg| arg.replace("{profile}", &profile).replace("{url}", url)),
+        );
+        command
+    } else {
+        webbrowser::open(url)?;
+        return Ok(());
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Shows the portal notification and returns whether the login page should
+/// be opened. Without a notification daemon the page is opened right away.
+async fn offer_login_page(ssid: Option<&str>) -> bool {
+    let body = match ssid {
+        Some(ssid) => format!("{ssid} requires signing in."),
+        None => "This network requires signing in.".to_string(),
+    };
+    // The notification handle is not Send, so it lives on the blocking thread
+    tokio::task::spawn_blocking(move || {
+        let handle = match Notification::new()
+            .summary("Captive Portal Detected")
+            .body(&body)
+            .action("default", "Open login page")
+            .show()
+        {
+            Ok(handle) => handle,
+            Err(e) => {
+                debug!("Failed to show the captive portal notification: {}", e);
+                return true;
+            }
+        };
+
+        let mut open = false;
+        handle.wait_for_action(|action| open = action == "default");
+        open
+    })
+    .await
+    .unwrap_or(false)
+}
+
+fn notify(summary: &str, body: &str) {
+    if let Err(e) = Notification::new().summary(summary).body(body).show() {
+        debug!("Failed to show notification: {}", e);
+    }
+}
+
+/// Checks for a captive portal after connecting to `ssid` and helps getting
+/// through it.
+///
+/// Returns the final state; anything but [`PortalState::Portal`] means the
+/// connection is as usable as it is going to get.
+pub async fn handle_captive_portal(
+    ssid: Option<&str>,
+    config: &CaptivePortalConfig,
+    command_runner: &dyn CommandRunner,
+) -> Result<PortalState, Box<dyn Error>> {
+    if !config.enabled {
+        return Ok(PortalState::Online);
+    }
+
+    sleep(SETTLE_DELAY).await;
+    let state = detect(command_runner).await?;
+    if !matches!(state, PortalState::Portal(_)) {
+        return Ok(state);
+    }
+    let login_url = state.login_url().to_string();
+
+    if let Some(ssid) = ssid {
+        if run_auto_login(ssid, &login_url, config, command_runner).await {
+            let state = wait_until_online(POLL_INTERVAL, command_runner).await?;
+            if state == PortalState::Online {
+                notify("Captive Portal", &format!("Signed in to {ssid}"));
+                return Ok(state);
+            }
+        }
+    }
+
+    if !offer_login_page(ssid).await {
+        return Ok(state);
+    }
+    open_login_page(&login_url, config)?;
+
+    let state = wait_until_online(
+        Duration::from_secs(config.login_timeout_secs),
+        command_runner,
+    )
+    .await?;
+    match state {
+        PortalState::Online => notify("Captive Portal", "Connected to the Internet"),
+        _ => notify(
+            "Captive Portal",
+            "Still not online; VPN, exit node and connect hooks were skipped",
+        ),
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct RecordingCommandRunner {
+        exit_code: i32,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call);
+            Ok(Output {
+                status: ExitStatus::from_raw(self.exit_code << 8),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_classify_response() {
+        let body = ProbeExpectation::Body("success");
+        assert_eq!(
+            classify_response(body, 200, None, "success\n"),
+            ProbeOutcome::Online
+        );
+        assert_eq!(
+            classify_response(body, 200, None, "<html>Welcome</html>"),
+            ProbeOutcome::Intercepted
+        );
+        assert_eq!(
+            classify_response(body, 302, Some("http://portal.example/login"), ""),
+            ProbeOutcome::Redirect("http://portal.example/login".to_string())
+        );
+        assert_eq!(
+            classify_response(ProbeExpectation::NoContent, 204, None, ""),
+            ProbeOutcome::Online
+        );
+        assert_eq!(
+            classify_response(ProbeExpectation::NoContent, 200, None, "<html>"),
+            ProbeOutcome::Intercepted
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        use ProbeOutcome::*;
+
+        let redirect = Redirect("http://portal.example/login".to_string());
+        assert_eq!(
+            evaluate(&[Online, redirect.clone(), Failed], None),
+            PortalState::Portal(Some("http://portal.example/login".to_string()))
+        );
+        assert_eq!(
+            evaluate(&[Online, Intercepted, Failed], None),
+            PortalState::Online
+        );
+        assert_eq!(
+            evaluate(&[Intercepted, Failed, Failed], None),
+            PortalState::Portal(None)
+        );
+        assert_eq!(
+            evaluate(&[Failed, Failed, Failed], Some(NmConnectivity::Portal)),
+            PortalState::Portal(None)
+        );
+        assert_eq!(
+            evaluate(&[Failed, Failed, Failed], Some(NmConnectivity::Full)),
+            PortalState::Online
+        );
+        assert_eq!(
+            evaluate(&[Failed, Failed, Failed], None),
+            PortalState::Offline
+        );
+        assert_eq!(PortalState::Portal(None).login_url(), PROBES[0].url);
+    }
+
+    #[test]
+    fn test_nm_connectivity_parse() {
+        assert_eq!(NmConnectivity::parse("portal\n"), NmConnectivity::Portal);
+        assert_eq!(NmConnectivity::parse("full"), NmConnectivity::Full);
+        assert_eq!(NmConnectivity::parse(""), NmConnectivity::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_auto_login_script() {
+        let mut config = CaptivePortalConfig::default();
+        config.auto_login.insert(
+            "Hotel Guest".to_string(),
+            "/opt/portal/hotel.sh".to_string(),
+        );
+        let runner = RecordingCommandRunner {
+            exit_code: 0,
+            calls: Mutex::new(Vec::new()),
+        };
+
+        assert!(!run_auto_login("Cafe", "http://portal.example/", &config, &runner).await);
+        assert!(run_auto_login("Hotel Guest", "http://portal.example/", &config, &runner).await);
+        assert_eq!(
+            *runner.calls.lock().unwrap(),
+            vec![vec![
+                "/opt/portal/hotel.sh".to_string(),
+                "http://portal.example/".to_string(),
+                "Hotel Guest".to_string()
+            ]]
+        );
+
+        let failing = RecordingCommandRunner {
+            exit_code: 1,
+            calls: Mutex::new(Vec::new()),
+        };
+        assert!(!run_auto_login("Hotel Guest", "http://portal.example/", &config, &failing).await);
+    }
+
+    #[test]
+    fn test_expand_browser_template() {
+        assert_eq!(
+            expand_browser_template(
+                "chromium --user-data-dir={profile} --app={url}",
+                "http://portal.example/?a=1&b='2'",
+                Path::new("/tmp/portal profile"),
+            ),
+            r"chromium --user-data-dir='/tmp/portal profile' --app='http://portal.example/?a=1&b='\''2'\'''"
+        );
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: CaptivePortalConfig = toml::from_str(
+            r#"
+            login_timeout_secs = 60
+            [auto_login]
+            "Hotel Guest" = "~/bin/hotel-login"
+            "#,
+        )
+        .unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.login_timeout_secs, 60);
+        assert_eq!(config.browser, None);
+        assert_eq!(config.auto_login["Hotel Guest"], "~/bin/hotel-login");
+    }
+}
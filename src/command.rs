@@ -1,14 +1,63 @@
 use std::error::Error;
+use std::future::Future;
 use std::io::{BufRead, BufReader};
+use std::pin::Pin;
 use std::process::{Command, Output};
+use std::time::Duration;
+
+/// Default timeout applied to commands run through `run_command_async`.
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeout for privileged commands, which may sit behind a pkexec or sudo
+/// password prompt for as long as the user takes to answer it.
+pub const PRIVILEGED_COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Timeout for commands that bring a connection up or down; nmcli alone
+/// waits up to 90 seconds for an activation.
+pub const CONNECTION_COMMAND_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Timeout for user scripts such as portal login scripts and connect hooks.
+pub const SCRIPT_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Future returned by the async command runner methods.
+pub type CommandFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Output, std::io::Error>> + Send + 'a>>;
 
 /// Trait for running shell commands.
-pub trait CommandRunner {
+pub trait CommandRunner: Send + Sync {
     /// Runs a shell command with the specified arguments.
     fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error>;
-    // fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
-    //     Command::new(command).args(args).env("LC_ALL", "C").output()
-    // }
+
+    /// Runs a shell command asynchronously, giving up after `timeout`.
+    ///
+    /// The default implementation falls back to the blocking `run_command`,
+    /// which keeps simple test runners working without extra code.
+    fn run_command_with_timeout<'a>(
+        &'a self,
+        command: &'a str,
+        args: &'a [&'a str],
+        _timeout: Duration,
+    ) -> CommandFuture<'a> {
+        Box::pin(std::future::ready(self.run_command(command, args)))
+    }
+
+    /// Runs a shell command asynchronously with the default timeout.
+    fn run_command_async<'a>(&'a self, command: &'a str, args: &'a [&'a str]) -> CommandFuture<'a> {
+        self.run_command_with_timeout(command, args, DEFAULT_COMMAND_TIMEOUT)
+    }
+
+    /// Notes work done without spawning a command, such as a D-Bus call, for
+    /// runners that keep track of what an action did. `exit_code` follows the
+    /// command convention, 0 meaning success.
+    fn record_operation(
+        &self,
+        _operation: &str,
+        _args: &[&str],
+        _exit_code: Option<i32>,
+        _duration: Duration,
+        _error: Option<String>,
+    ) {
+    }
 }
 
 /// Struct for running real shell commands.
@@ -18,6 +67,31 @@ impl CommandRunner for RealCommandRunner {
     fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
         Command::new(command).args(args).env("LC_ALL", "C").output()
     }
+
+    fn run_command_with_timeout<'a>(
+        &'a self,
+        command: &'a str,
+        args: &'a [&'a str],
+        timeout: Duration,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            // kill_on_drop makes sure a wedged tool is reaped when the timeout
+            // fires or when the caller stops waiting for it.
+            let child = tokio::process::Command::new(command)
+                .args(args)
+                .env("LC_ALL", "C")
+                .kill_on_drop(true)
+                .output();
+
+            match tokio::time::timeout(timeout, child).await {
+                Ok(result) => result,
+                Err(_) => Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("{command} timed out after {timeout:?}"),
+                )),
+            }
+        })
+    }
 }
 
 /// Checks if a command is installed on the system.
@@ -135,6 +209,50 @@ mod tests {
         assert!(result.is_err(), "Expected error for wrong arguments");
     }
 
+    #[tokio::test]
+    async fn test_real_command_runner_async_with_echo() {
+        let runner = RealCommandRunner;
+        let output = runner
+            .run_command_async("echo", &["hello", "async"])
+            .await
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            "hello async"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_real_command_runner_async_timeout() {
+        let runner = RealCommandRunner;
+        let start = std::time::Instant::now();
+        let result = runner
+            .run_command_with_timeout("sleep", &["5"], Duration::from_millis(100))
+            .await;
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_mock_command_runner_async_fallback() {
+        let output = Output {
+            status: ExitStatus::from_raw(0),
+            stdout: b"mocked".to_vec(),
+            stderr: vec![],
+        };
+
+        let mock_runner = MockCommandRunner::new("test_cmd", &["arg1"], output);
+        let result = mock_runner
+            .run_command_async("test_cmd", &["arg1"])
+            .await
+            .unwrap();
+
+        assert_eq!(result.stdout, b"mocked");
+    }
+
     #[test]
     fn test_is_command_installed_existing() {
         // Test with a command that should exist on most systems
@@ -0,0 +1,1073 @@
+//! ConnMan D-Bus backend.
+//!
+//! Talks to `net.connman` for Wi-Fi, wired and VPN services (the latter
+//! provided by connman-vpn), Wi-Fi tethering and offline mode. Wi-Fi
+//! connections run with a passphrase Agent registered on the same bus
+//! connection, so ConnMan asks us only when it has no saved passphrase.
+//!
+//! The bus can be overridden with `NETWORK_DMENU_CONNMAN_BUS`: `system`
+//! (default), `session`, or a full D-Bus address, which is how the backend is
+//! exercised against a fake ConnMan in tests.
+
+use crate::ethernet::{EthernetInterface, WiredProfile};
+use crate::networkmanager::{vpn_network_action, wifi_network_action};
+use crate::nm_dbus::{call_serving_agent, open_private_connection, strength_to_stars};
+use crate::utils::prompt_for_password;
+use crate::{parse_vpn_action, parse_wifi_action, VpnAction, WifiAction};
+use dbus::arg::{prop_cast, PropMap, RefArg, Variant};
+use dbus::blocking::{Connection, Proxy};
+use dbus::message::MessageType;
+use dbus::strings::ErrorName;
+use dbus::{Message, Path};
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+pub const CONNMAN_BUS_NAME: &str = "net.connman";
+/// Prefix of ConnMan service object paths.
+pub const SERVICE_PATH_PREFIX: &str = "/net/connman/service/";
+
+const MANAGER_IFACE: &str = "net.connman.Manager";
+const SERVICE_IFACE: &str = "net.connman.Service";
+const TECHNOLOGY_IFACE: &str = "net.connman.Technology";
+const AGENT_IFACE: &str = "net.connman.Agent";
+const AGENT_CANCELED: &str = "net.connman.Agent.Error.Canceled";
+const ALREADY_CONNECTED: &str = "net.connman.Error.AlreadyConnected";
+
+/// Object path our passphrase agent is registered at.
+pub const AGENT_PATH: &str = "/org/network_dmenu/connman_agent";
+
+/// Environment variable used to pick the bus ConnMan is reached on.
+pub const CONNMAN_BUS_ENV: &str = "NETWORK_DMENU_CONNMAN_BUS";
+
+const DBUS_CALL_TIMEOUT: Duration = Duration::from_secs(5);
+/// ConnMan answers `Connect` and `Scan` only once done.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A ConnMan service: a Wi-Fi network, wired link or VPN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnmanService {
+    pub path: String,
+    pub name: String,
+    /// `wifi`, `ethernet`, `vpn`, ...
+    pub service_type: String,
+    /// `idle`, `association`, `configuration`, `ready`, `online`, `failure`, ...
+    pub state: String,
+    /// `none`, `wep`, `psk`, `ieee8021x`, `wps`
+    pub security: Vec<String>,
+    pub strength: u8,
+    /// Whether ConnMan has saved settings for it
+    pub favorite: bool,
+    pub interface: Option<String>,
+}
+
+impl ConnmanService {
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state.as_str(), "ready" | "online")
+    }
+
+    /// Security in the notation of the other backends, e.g. `PSK` or `OPEN`.
+    pub fn security_label(&self) -> String {
+        let label = self
+            .security
+            .iter()
+            .find(|security| !security.starts_with("wps"))
+            .map(String::as_str)
+            .unwrap_or("none");
+        match label {
+            "none" => "open",
+            "ieee8021x" => "8021x",
+            other => other,
+        }
+        .to_string()
+    }
+}
+
+/// A ConnMan technology (`wifi`, `ethernet`, `bluetooth`, ...).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnmanTechnology {
+    pub path: String,
+    pub technology_type: String,
+    pub powered: bool,
+    pub connected: bool,
+    pub tethering: bool,
+    pub tethering_identifier: Option<String>,
+    pub tethering_passphrase: Option<String>,
+}
+
+/// Blocking client for the ConnMan D-Bus API.
+pub struct ConnmanClient {
+    connection: Connection,
+}
+
+impl ConnmanClient {
+    /// Connects to the bus selected by `NETWORK_DMENU_CONNMAN_BUS`, returning
+    /// `None` when ConnMan is not running there.
+    pub fn connect() -> Option<Self> {
+        let bus = std::env::var(CONNMAN_BUS_ENV).unwrap_or_else(|_| "system".to_string());
+        let connection = match bus.as_str() {
+            "system" => Connection::new_system(),
+            "session" => Connection::new_session(),
+            address => open_private_connection(address),
+        };
+
+        match connection {
+            Ok(connection) => {
+                let client = Self { connection };
+                if client.is_available() {
+                    Some(client)
+                } else {
+                    debug!("ConnMan is not on the {} bus", bus);
+                    None
+                }
+            }
+            Err(e) => {
+                debug!("Failed to connect to the {} bus: {}", bus, e);
+                None
+            }
+        }
+    }
+
+    /// Wraps an existing bus connection.
+    pub fn from_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    /// Checks whether ConnMan owns its well-known name on this bus.
+    pub fn is_available(&self) -> bool {
+        let proxy = self.connection.with_proxy(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            DBUS_CALL_TIMEOUT,
+        );
+        proxy
+            .method_call::<(bool,), _, _, _>(
+                "org.freedesktop.DBus",
+                "NameHasOwner",
+                (CONNMAN_BUS_NAME,),
+            )
+            .map(|(owned,)| owned)
+            .unwrap_or(false)
+    }
+
+    fn proxy<'a>(&'a self, path: &'a str) -> Proxy<'a, &'a Connection> {
+        self.proxy_with_timeout(path, DBUS_CALL_TIMEOUT)
+    }
+
+    fn proxy_with_timeout<'a>(
+        &'a self,
+        path: &'a str,
+        timeout: Duration,
+    ) -> Proxy<'a, &'a Connection> {
+        self.connection.with_proxy(CONNMAN_BUS_NAME, path, timeout)
+    }
+
+    fn set_property<T: RefArg + dbus::arg::Append + dbus::arg::Arg>(
+        &self,
+        path: &str,
+        iface: &str,
+        name: &str,
+        value: T,
+    ) -> Result<(), dbus::Error> {
+        self.proxy(path)
+            .method_call(iface, "SetProperty", (name, Variant(value)))
+    }
+
+    /// Lists all services in ConnMan's preference order, connected first.
+    pub fn services(&self) -> Result<Vec<ConnmanService>, Box<dyn Error>> {
+        let (services,): (Vec<(Path<'static>, PropMap)>,) =
+            self.proxy("/")
+                .method_call(MANAGER_IFACE, "GetServices", ())?;
+
+        Ok(services
+            .iter()
+            .map(|(path, props)| ConnmanService {
+                path: path.to_string(),
+                name: string_prop(props, "Name").unwrap_or_default(),
+                service_type: string_prop(props, "Type").unwrap_or_default(),
+                state: string_prop(props, "State").unwrap_or_default(),
+                security: props
+                    .get("Security")
+                    .and_then(|security| security.0.as_iter())
+                    .map(|items| {
+                        items
+                            .filter_map(|item| item.as_str().map(str::to_string))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                strength: prop_cast::<u8>(props, "Strength").copied().unwrap_or(0),
+                favorite: prop_cast::<bool>(props, "Favorite")
+                    .copied()
+                    .unwrap_or(false),
+                interface: dict_string(props, "Ethernet", "Interface"),
+            })
+            .collect())
+    }
+
+    fn services_of_type(&self, service_type: &str) -> Result<Vec<ConnmanService>, Box<dyn Error>> {
+        let mut services = self.services()?;
+        services.retain(|service| service.service_type == service_type);
+        Ok(services)
+    }
+
+    /// Lists the technologies ConnMan manages.
+    pub fn technologies(&self) -> Result<Vec<ConnmanTechnology>, Box<dyn Error>> {
+        let (technologies,): (Vec<(Path<'static>, PropMap)>,) =
+            self.proxy("/")
+                .method_call(MANAGER_IFACE, "GetTechnologies", ())?;
+
+        Ok(technologies
+            .iter()
+            .map(|(path, props)| {
+                let flag = |key: &str| prop_cast::<bool>(props, key).copied().unwrap_or(false);
+                ConnmanTechnology {
+                    path: path.to_string(),
+                    technology_type: string_prop(props, "Type").unwrap_or_default(),
+                    powered: flag("Powered"),
+                    connected: flag("Connected"),
+                    tethering: flag("Tethering"),
+                    tethering_identifier: string_prop(props, "TetheringIdentifier"),
+                    tethering_passphrase: string_prop(props, "TetheringPassphrase"),
+                }
+            })
+            .collect())
+    }
+
+    /// The Wi-Fi technology, if ConnMan manages one.
+    pub fn wifi_technology(&self) -> Result<ConnmanTechnology, Box<dyn Error>> {
+        self.technologies()?
+            .into_iter()
+            .find(|technology| technology.technology_type == "wifi")
+            .ok_or_else(|| "No Wi-Fi technology managed by ConnMan".into())
+    }
+
+    /// Scans for Wi-Fi networks, returning once the scan is done.
+    pub fn scan_wifi(&self) -> Result<(), Box<dyn Error>> {
+        let technology = self.wifi_technology()?;
+        self.proxy_with_timeout(&technology.path, SCAN_TIMEOUT)
+            .method_call::<(), _, _, _>(TECHNOLOGY_IFACE, "Scan", ())?;
+        Ok(())
+    }
+
+    /// Builds the Wi-Fi menu entries, rescanning when nothing is connected.
+    pub fn wifi_networks(&self) -> Result<Vec<WifiAction>, Box<dyn Error>> {
+        let mut services = self.services_of_type("wifi")?;
+        if !services.iter().any(ConnmanService::is_connected) {
+            self.scan_wifi()?;
+            services = self.services_of_type("wifi")?;
+        }
+
+        // Hidden networks have no name and cannot be picked from the list
+        Ok(services
+            .iter()
+            .filter(|service| !service.name.is_empty())
+            .map(|service| {
+                wifi_network_action(
+                    service.is_connected(),
+                    &service.name,
+                    &service.security_label(),
+                    &strength_to_stars(service.strength),
+                )
+            })
+            .collect())
+    }
+
+    /// Connects to the Wi-Fi network selected in the menu.
+    pub fn connect_wifi(&self, action: &str) -> Result<bool, Box<dyn Error>> {
+        let (ssid, security) = parse_wifi_action(action)?;
+        let services = self.services_of_type("wifi")?;
+        let service = services
+            .iter()
+            .find(|service| {
+                service.name == ssid && service.security_label().eq_ignore_ascii_case(security)
+            })
+            .or_else(|| services.iter().find(|service| service.name == ssid))
+            .ok_or_else(|| format!("Network {} is not in range", ssid))?;
+
+        self.connect_service_with_agent(service, None)
+    }
+
+    /// Connects a service, answering passphrase requests with `passphrase` or
+    /// by prompting the user.
+    pub fn connect_service_with_agent(
+        &self,
+        service: &ConnmanService,
+        passphrase: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.connect_with_agent(&service.path, &service.name, false, passphrase)
+    }
+
+    /// Connects to a hidden Wi-Fi network, handing ConnMan its name through
+    /// the agent. `security` is in ConnMan's notation: `none`, `wep` or `psk`.
+    pub fn connect_hidden_wifi(
+        &self,
+        ssid: &str,
+        security: &str,
+        passphrase: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let hidden: Vec<ConnmanService> = self
+            .services_of_type("wifi")?
+            .into_iter()
+            .filter(|service| {
+                service.name.is_empty() && service.security.iter().any(|s| s == security)
+            })
+            .collect();
+        if hidden.is_empty() {
+            return Err(format!("No hidden {security} network is in range").into());
+        }
+
+        // ConnMan lists one nameless service per hidden security type and
+        // radio, any of which may carry the network
+        for service in &hidden {
+            if self.connect_with_agent(&service.path, ssid, true, passphrase)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn connect_with_agent(
+        &self,
+        path: &str,
+        name: &str,
+        hidden: bool,
+        passphrase: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let manager = self.proxy("/");
+        manager.method_call::<(), _, _, _>(
+            MANAGER_IFACE,
+            "RegisterAgent",
+            (Path::from(AGENT_PATH),),
+        )?;
+
+        let call = Message::new_method_call(CONNMAN_BUS_NAME, path, SERVICE_IFACE, "Connect")?;
+        let mut passphrase = passphrase.map(str::to_string);
+        let result = call_serving_agent(&self.connection, call, CONNECT_TIMEOUT, |message| {
+            agent_reply(message, name, hidden, &mut passphrase)
+        });
+
+        let _ = manager.method_call::<(), _, _, _>(
+            MANAGER_IFACE,
+            "UnregisterAgent",
+            (Path::from(AGENT_PATH),),
+        );
+
+        connect_result(name, result?)
+    }
+
+    /// Connects a service that needs no secrets, such as a wired link.
+    pub fn connect_service(&self, path: &str) -> Result<bool, Box<dyn Error>> {
+        let result = self
+            .proxy_with_timeout(path, CONNECT_TIMEOUT)
+            .method_call::<(), _, _, _>(SERVICE_IFACE, "Connect", ());
+        connect_result(path, result)
+    }
+
+    /// Disconnects a service.
+    pub fn disconnect_service(&self, path: &str) -> Result<bool, Box<dyn Error>> {
+        self.proxy(path)
+            .method_call::<(), _, _, _>(SERVICE_IFACE, "Disconnect", ())?;
+        Ok(true)
+    }
+
+    /// Disconnects the connected Wi-Fi network.
+    pub fn disconnect_wifi(&self) -> Result<bool, Box<dyn Error>> {
+        match self
+            .services_of_type("wifi")?
+            .into_iter()
+            .find(ConnmanService::is_connected)
+        {
+            Some(service) => self.disconnect_service(&service.path),
+            None => Ok(false),
+        }
+    }
+
+    /// Builds the VPN menu entries from connman-vpn providers.
+    pub fn vpn_networks(&self) -> Result<Vec<VpnAction>, Box<dyn Error>> {
+        Ok(self
+            .services_of_type("vpn")?
+            .iter()
+            .map(|service| vpn_network_action(service.is_connected(), &service.name))
+            .collect())
+    }
+
+    fn vpn_service(&self, action: &str) -> Result<ConnmanService, Box<dyn Error>> {
+        let name = parse_vpn_action(action)?;
+        self.services_of_type("vpn")?
+            .into_iter()
+            .find(|service| service.name == name)
+            .ok_or_else(|| format!("VPN connection {} not found", name).into())
+    }
+
+    /// Brings up the VPN selected in the menu.
+    pub fn connect_vpn(&self, action: &str) -> Result<bool, Box<dyn Error>> {
+        let service = self.vpn_service(action)?;
+        self.connect_service(&service.path)
+    }
+
+    /// Tears down the VPN selected in the menu.
+    pub fn disconnect_vpn(&self, action: &str) -> Result<bool, Box<dyn Error>> {
+        let service = self.vpn_service(action)?;
+        self.disconnect_service(&service.path)
+    }
+
+    /// Wired services as profiles, with the service path in place of the UUID.
+    pub fn wired_profiles(
+        &self,
+        interfaces: &[EthernetInterface],
+    ) -> Result<Vec<WiredProfile>, Box<dyn Error>> {
+        let mut profiles: Vec<WiredProfile> = self
+            .services_of_type("ethernet")?
+            .into_iter()
+            .map(|service| {
+                let device = service.interface.clone().filter(|_| service.is_connected());
+                let link = device.as_ref().and_then(|device| {
+                    interfaces
+                        .iter()
+                        .find(|interface| &interface.name == device)
+                        .map(EthernetInterface::link_summary)
+                });
+                WiredProfile {
+                    name: service.name,
+                    uuid: service.path,
+                    device,
+                    link,
+                }
+            })
+            .collect();
+        profiles.sort_by_key(|profile| profile.device.is_none());
+        Ok(profiles)
+    }
+
+    /// Whether offline (airplane) mode is on.
+    pub fn offline_mode(&self) -> Result<bool, Box<dyn Error>> {
+        let (props,): (PropMap,) =
+            self.proxy("/")
+                .method_call(MANAGER_IFACE, "GetProperties", ())?;
+        Ok(prop_cast::<bool>(&props, "OfflineMode")
+            .copied()
+            .unwrap_or(false))
+    }
+
+    /// Turns offline (airplane) mode on or off.
+    pub fn set_offline_mode(&self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.set_property("/", MANAGER_IFACE, "OfflineMode", enabled)?;
+        Ok(())
+    }
+
+    /// Starts Wi-Fi tethering with the given name and passphrase.
+    pub fn start_tethering(&self, ssid: &str, passphrase: &str) -> Result<(), Box<dyn Error>> {
+        let technology = self.wifi_technology()?;
+        self.set_property(
+            &technology.path,
+            TECHNOLOGY_IFACE,
+            "TetheringIdentifier",
+            ssid.to_string(),
+        )?;
+        self.set_property(
+            &technology.path,
+            TECHNOLOGY_IFACE,
+            "TetheringPassphrase",
+            passphrase.to_string(),
+        )?;
+        self.set_property(&technology.path, TECHNOLOGY_IFACE, "Tethering", true)?;
+        Ok(())
+    }
+
+    /// Stops Wi-Fi tethering.
+    pub fn stop_tethering(&self) -> Result<(), Box<dyn Error>> {
+        let technology = self.wifi_technology()?;
+        self.set_property(&technology.path, TECHNOLOGY_IFACE, "Tethering", false)?;
+        Ok(())
+    }
+}
+
+fn string_prop(props: &PropMap, key: &str) -> Option<String> {
+    prop_cast::<String>(props, key).cloned()
+}
+
+/// Reads `key` from a nested `a{sv}` property such as `Ethernet`.
+fn dict_string(props: &PropMap, dict: &str, key: &str) -> Option<String> {
+    let mut items = props.get(dict)?.0.as_iter()?;
+    while let (Some(name), Some(value)) = (items.next(), items.next()) {
+        if name.as_str() == Some(key) {
+            return value.as_str().map(str::to_string);
+        }
+    }
+    None
+}
+
+/// Maps a `Connect` reply; ConnMan errors such as a wrong passphrase are
+/// `Ok(false)`.
+fn connect_result(name: &str, result: Result<(), dbus::Error>) -> Result<bool, Box<dyn Error>> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if e.name() == Some(ALREADY_CONNECTED) => Ok(true),
+        Err(e) => {
+            debug!("ConnMan failed to connect {}: {}", name, e);
+            Ok(false)
+        }
+    }
+}
+
+/// Answers a call to our agent, `None` for unrelated messages.
+///
+/// Only passphrases and, for `hidden` networks, `name` are provided; WPS and
+/// enterprise identities are canceled.
+fn agent_reply(
+    message: &Message,
+    name: &str,
+    hidden: bool,
+    passphrase: &mut Option<String>,
+) -> Option<Message> {
+    if message.msg_type() != MessageType::MethodCall
+        || message.path().as_deref() != Some(AGENT_PATH)
+        || message.interface().as_deref() != Some(AGENT_IFACE)
+    {
+        return None;
+    }
+
+    let canceled = || message.error(&ErrorName::from(AGENT_CANCELED), c"Canceled");
+    let reply = match message.member().as_deref() {
+        Some("RequestInput") => {
+            let requested = message
+                .read2::<Path, PropMap>()
+                .map(|(_, fields)| fields)
+                .unwrap_or_default();
+            let mut fields: PropMap = HashMap::new();
+            if requested.contains_key("Name") && hidden {
+                fields.insert("Name".to_string(), Variant(Box::new(name.to_string())));
+            }
+            if requested.contains_key("Passphrase") {
+                let answer = match passphrase.take() {
+                    Some(passphrase) => Ok(passphrase),
+                    None => prompt_for_password(name),
+                };
+                if let Some(answer) = answer.ok().filter(|answer| !answer.is_empty()) {
+                    fields.insert("Passphrase".to_string(), Variant(Box::new(answer)));
+                }
+            }
+            if !fields.is_empty()
+                && requested
+                    .keys()
+                    .filter(|field| matches!(String::as_str(field), "Name" | "Passphrase"))
+                    .all(|field| fields.contains_key(field))
+            {
+                message.method_return().append1(fields)
+            } else {
+                canceled()
+            }
+        }
+        Some("ReportError") => {
+            if let Ok((_, error)) = message.read2::<Path, &str>() {
+                debug!("ConnMan reported an error for {}: {}", name, error);
+            }
+            message.method_return()
+        }
+        Some("Release") | Some("Cancel") => message.method_return(),
+        _ => canceled(),
+    };
+    Some(reply)
+}
+
+/// Runs `f` against ConnMan on a blocking thread.
+///
+/// Returns `None` when ConnMan is not reachable over D-Bus, so callers can
+/// try the other backends. Errors are stringified to cross the thread boundary.
+pub async fn with_client<T, F>(f: F) -> Option<Result<T, String>>
+where
+    T: Send + 'static,
+    F: FnOnce(&ConnmanClient) -> Result<T, Box<dyn Error>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let client = ConnmanClient::connect()?;
+        Some(f(&client).map_err(|e| e.to_string()))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::{path, start_test_bus, wait_for_service, TestBus};
+    use dbus_crossroads::{Context, Crossroads, MethodErr};
+    use std::sync::{Arc, Mutex};
+
+    const WIFI_TECHNOLOGY: &str = "/net/connman/technology/wifi";
+
+    fn variant<T: RefArg + 'static>(value: T) -> Variant<Box<dyn RefArg>> {
+        Variant(Box::new(value))
+    }
+
+    /// State of the fake ConnMan shared by its objects.
+    struct FakeConnman {
+        offline: bool,
+        tethering: bool,
+        tethering_identifier: String,
+        tethering_passphrase: String,
+        connected: Vec<String>,
+        agent: Option<(String, Path<'static>)>,
+    }
+
+    struct FakeService {
+        name: &'static str,
+        service_type: &'static str,
+        security: &'static str,
+        strength: u8,
+        /// Passphrase the agent must provide
+        passphrase: Option<&'static str>,
+    }
+
+    /// Name the hidden fake service answers to.
+    const HIDDEN_SSID: &str = "Attic";
+
+    const SERVICES: [FakeService; 6] = [
+        FakeService {
+            name: "Wired",
+            service_type: "ethernet",
+            security: "none",
+            strength: 0,
+            passphrase: None,
+        },
+        FakeService {
+            name: "Home",
+            service_type: "wifi",
+            security: "psk",
+            strength: 85,
+            passphrase: None,
+        },
+        FakeService {
+            name: "Library",
+            service_type: "wifi",
+            security: "psk",
+            strength: 60,
+            passphrase: Some("correct horse"),
+        },
+        FakeService {
+            name: "Cafe",
+            service_type: "wifi",
+            security: "none",
+            strength: 40,
+            passphrase: None,
+        },
+        FakeService {
+            name: "",
+            service_type: "wifi",
+            security: "psk",
+            strength: 30,
+            passphrase: Some("attic pass"),
+        },
+        FakeService {
+            name: "Office",
+            service_type: "vpn",
+            security: "none",
+            strength: 0,
+            passphrase: None,
+        },
+    ];
+
+    fn service_path(service: &FakeService) -> Path<'static> {
+        path(&format!(
+            "{}{}_{}",
+            SERVICE_PATH_PREFIX,
+            service.service_type,
+            service.name.to_lowercase()
+        ))
+    }
+
+    fn service_props(service: &FakeService, state: &FakeConnman) -> PropMap {
+        let connected = state.connected.iter().any(|name| name == service.name);
+        let mut props: PropMap = HashMap::new();
+        props.insert("Name".into(), variant(service.name.to_string()));
+        props.insert("Type".into(), variant(service.service_type.to_string()));
+        props.insert(
+            "State".into(),
+            variant(if connected { "online" } else { "idle" }.to_string()),
+        );
+        props.insert(
+            "Security".into(),
+            variant(vec![service.security.to_string()]),
+        );
+        props.insert("Strength".into(), variant(service.strength));
+        props.insert("Favorite".into(), variant(connected));
+        let mut ethernet: PropMap = HashMap::new();
+        if service.service_type == "ethernet" {
+            ethernet.insert("Interface".into(), variant("eth0".to_string()));
+        }
+        props.insert("Ethernet".into(), variant(ethernet));
+        props
+    }
+
+    /// Asks the registered agent for a passphrase, and the name of hidden
+    /// services, over a connection of our own.
+    fn request_input(
+        address: &str,
+        state: &Arc<Mutex<FakeConnman>>,
+        service: &str,
+        hidden: bool,
+    ) -> Option<PropMap> {
+        let (name, agent_path) = state.lock().unwrap().agent.clone()?;
+        let connection = open_private_connection(address).ok()?;
+        let mut passphrase_field: PropMap = HashMap::new();
+        passphrase_field.insert("Type".into(), variant("psk".to_string()));
+        passphrase_field.insert("Requirement".into(), variant("mandatory".to_string()));
+        let mut fields: PropMap = HashMap::new();
+        fields.insert("Passphrase".into(), variant(passphrase_field));
+        if hidden {
+            let mut name_field: PropMap = HashMap::new();
+            name_field.insert("Type".into(), variant("string".to_string()));
+            name_field.insert("Requirement".into(), variant("mandatory".to_string()));
+            fields.insert("Name".into(), variant(name_field));
+        }
+
+        let (reply,): (PropMap,) = connection
+            .with_proxy(name, agent_path, DBUS_CALL_TIMEOUT)
+            .method_call(AGENT_IFACE, "RequestInput", (path(service), fields))
+            .ok()?;
+        Some(reply)
+    }
+
+    /// Serves a minimal ConnMan with wired, Wi-Fi and VPN services and a
+    /// Wi-Fi technology that can tether.
+    fn serve_fake_connman(address: String) {
+        std::thread::spawn(move || {
+            let connection = open_private_connection(&address).unwrap();
+            connection
+                .request_name(CONNMAN_BUS_NAME, false, true, false)
+                .unwrap();
+
+            let state = Arc::new(Mutex::new(FakeConnman {
+                offline: false,
+                tethering: false,
+                tethering_identifier: String::new(),
+                tethering_passphrase: String::new(),
+                connected: vec!["Wired".to_string(), "Home".to_string()],
+                agent: None,
+            }));
+            let mut cr = Crossroads::new();
+
+            let manager_state = state.clone();
+            let manager = cr.register(MANAGER_IFACE, move |b| {
+                let s = manager_state.clone();
+                b.method(
+                    "GetServices",
+                    (),
+                    ("services",),
+                    move |_: &mut Context, _: &mut (), _: ()| {
+                        let state = s.lock().unwrap();
+                        let services: Vec<(Path<'static>, PropMap)> = SERVICES
+                            .iter()
+                            .map(|service| (service_path(service), service_props(service, &state)))
+                            .collect();
+                        Ok((services,))
+                    },
+                );
+                let s = manager_state.clone();
+                b.method(
+                    "GetTechnologies",
+                    (),
+                    ("technologies",),
+                    move |_: &mut Context, _: &mut (), _: ()| {
+                        let state = s.lock().unwrap();
+                        let mut props: PropMap = HashMap::new();
+                        props.insert("Type".into(), variant("wifi".to_string()));
+                        props.insert("Powered".into(), variant(true));
+                        props.insert("Connected".into(), variant(true));
+                        props.insert("Tethering".into(), variant(state.tethering));
+                        props.insert(
+                            "TetheringIdentifier".into(),
+                            variant(state.tethering_identifier.clone()),
+                        );
+                        props.insert(
+                            "TetheringPassphrase".into(),
+                            variant(state.tethering_passphrase.clone()),
+                        );
+                        Ok((vec![(path(WIFI_TECHNOLOGY), props)],))
+                    },
+                );
+                let s = manager_state.clone();
+                b.method(
+                    "GetProperties",
+                    (),
+                    ("properties",),
+                    move |_: &mut Context, _: &mut (), _: ()| {
+                        let mut props: PropMap = HashMap::new();
+                        props.insert("State".into(), variant("online".to_string()));
+                        props.insert("OfflineMode".into(), variant(s.lock().unwrap().offline));
+                        Ok((props,))
+                    },
+                );
+                let s = manager_state.clone();
+                b.method(
+                    "SetProperty",
+                    ("name", "value"),
+                    (),
+                    move |_: &mut Context,
+                          _: &mut (),
+                          (name, value): (String, Variant<Box<dyn RefArg>>)| {
+                        match name.as_str() {
+                            "OfflineMode" => {
+                                s.lock().unwrap().offline = value.0.as_u64() == Some(1);
+                                Ok(())
+                            }
+                            _ => Err(MethodErr::invalid_arg(&name)),
+                        }
+                    },
+                );
+                let s = manager_state.clone();
+                b.method(
+                    "RegisterAgent",
+                    ("path",),
+                    (),
+                    move |ctx: &mut Context, _: &mut (), (agent_path,): (Path<'static>,)| {
+                        let sender = ctx.message().sender().unwrap().to_string();
+                        s.lock().unwrap().agent = Some((sender, agent_path));
+                        Ok(())
+                    },
+                );
+                let s = manager_state.clone();
+                b.method(
+                    "UnregisterAgent",
+                    ("path",),
+                    (),
+                    move |_: &mut Context, _: &mut (), (_,): (Path<'static>,)| {
+                        s.lock().unwrap().agent = None;
+                        Ok(())
+                    },
+                );
+            });
+
+            let technology_state = state.clone();
+            let technology = cr.register(TECHNOLOGY_IFACE, move |b| {
+                b.method("Scan", (), (), |_: &mut Context, _: &mut (), _: ()| Ok(()));
+                let s = technology_state.clone();
+                b.method(
+                    "SetProperty",
+                    ("name", "value"),
+                    (),
+                    move |_: &mut Context,
+                          _: &mut (),
+                          (name, value): (String, Variant<Box<dyn RefArg>>)| {
+                        let mut state = s.lock().unwrap();
+                        match name.as_str() {
+                            "Tethering" => state.tethering = value.0.as_u64() == Some(1),
+                            "TetheringIdentifier" => {
+                                state.tethering_identifier =
+                                    value.0.as_str().unwrap_or_default().to_string()
+                            }
+                            "TetheringPassphrase" => {
+                                state.tethering_passphrase =
+                                    value.0.as_str().unwrap_or_default().to_string()
+                            }
+                            _ => return Err(MethodErr::invalid_arg(&name)),
+                        }
+                        Ok(())
+                    },
+                );
+            });
+
+            let service_state = state.clone();
+            let service = cr.register(SERVICE_IFACE, move |b| {
+                let s = service_state.clone();
+                let agent_address = address.clone();
+                b.method(
+                    "Connect",
+                    (),
+                    (),
+                    move |ctx: &mut Context, index: &mut usize, _: ()| {
+                        let service = &SERVICES[*index];
+                        if s.lock()
+                            .unwrap()
+                            .connected
+                            .iter()
+                            .any(|n| n == service.name)
+                        {
+                            return Err(MethodErr::from((ALREADY_CONNECTED, "Already connected")));
+                        }
+                        if let Some(expected) = service.passphrase {
+                            let hidden = service.name.is_empty();
+                            let reply =
+                                request_input(&agent_address, &s, &ctx.path().to_string(), hidden)
+                                    .unwrap_or_default();
+                            let named = !hidden
+                                || string_prop(&reply, "Name").as_deref() == Some(HIDDEN_SSID);
+                            if !named
+                                || string_prop(&reply, "Passphrase").as_deref() != Some(expected)
+                            {
+                                return Err(MethodErr::from((
+                                    "net.connman.Error.InvalidKey",
+                                    "Invalid key",
+                                )));
+                            }
+                        }
+                        s.lock().unwrap().connected.push(service.name.to_string());
+                        Ok(())
+                    },
+                );
+                let s = service_state.clone();
+                b.method(
+                    "Disconnect",
+                    (),
+                    (),
+                    move |_: &mut Context, index: &mut usize, _: ()| {
+                        let name = SERVICES[*index].name;
+                        s.lock().unwrap().connected.retain(|n| n != name);
+                        Ok(())
+                    },
+                );
+            });
+
+            cr.insert("/", &[manager], ());
+            cr.insert(WIFI_TECHNOLOGY, &[technology], ());
+            for (index, fake) in SERVICES.iter().enumerate() {
+                cr.insert(service_path(fake), &[service], index);
+            }
+
+            // Returns an error once the test bus goes away
+            let _ = cr.serve(&connection);
+        });
+    }
+
+    fn connect_fake_client(bus: &TestBus) -> ConnmanClient {
+        wait_for_service(
+            bus,
+            ConnmanClient::from_connection,
+            ConnmanClient::is_available,
+        )
+    }
+
+    #[test]
+    fn test_security_label() {
+        let mut service = ConnmanService {
+            path: String::new(),
+            name: "Home".to_string(),
+            service_type: "wifi".to_string(),
+            state: "idle".to_string(),
+            security: vec!["wps".to_string(), "psk".to_string()],
+            strength: 50,
+            favorite: false,
+            interface: None,
+        };
+        assert_eq!(service.security_label(), "psk");
+        service.security = vec!["ieee8021x".to_string()];
+        assert_eq!(service.security_label(), "8021x");
+        service.security = vec!["none".to_string()];
+        assert_eq!(service.security_label(), "open");
+        assert!(!service.is_connected());
+    }
+
+    #[test]
+    fn test_fake_bus_services() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_connman(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        let wifi = client.wifi_networks().unwrap();
+        let parsed: Vec<(String, String)> = wifi
+            .iter()
+            .map(|action| match action {
+                WifiAction::Network(display) => {
+                    let (ssid, security) = parse_wifi_action(display).unwrap();
+                    (ssid.to_string(), security.to_string())
+                }
+                other => panic!("unexpected action {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                ("Home".to_string(), "PSK".to_string()),
+                ("Library".to_string(), "PSK".to_string()),
+                ("Cafe".to_string(), "OPEN".to_string()),
+            ]
+        );
+
+        let wired = client.wired_profiles(&[]).unwrap();
+        assert_eq!(wired.len(), 1);
+        assert_eq!(wired[0].device.as_deref(), Some("eth0"));
+        assert!(wired[0].uuid.starts_with(SERVICE_PATH_PREFIX));
+
+        let vpn = client.vpn_networks().unwrap();
+        assert!(matches!(&vpn[..], [VpnAction::Connect(name)] if name.ends_with("Office")));
+        assert!(client
+            .connect_vpn(&format!("{} Office", crate::constants::ICON_SIGNAL))
+            .unwrap());
+        assert!(client
+            .disconnect_vpn(&format!("{} Office", crate::constants::ICON_CHECK))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_fake_bus_connect_with_agent() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_connman(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        let library = client
+            .services_of_type("wifi")
+            .unwrap()
+            .into_iter()
+            .find(|service| service.name == "Library")
+            .unwrap();
+        assert!(!client
+            .connect_service_with_agent(&library, Some("wrong"))
+            .unwrap());
+        assert!(client
+            .connect_service_with_agent(&library, Some("correct horse"))
+            .unwrap());
+
+        let WifiAction::Network(home) = wifi_network_action(true, "Home", "psk", "****") else {
+            unreachable!()
+        };
+        assert!(client.connect_wifi(&home).unwrap());
+        assert!(client.disconnect_wifi().unwrap());
+    }
+
+    #[test]
+    fn test_fake_bus_connect_hidden_wifi() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_connman(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        assert!(client
+            .connect_hidden_wifi("Attic", "wep", Some("key"))
+            .is_err());
+        assert!(!client
+            .connect_hidden_wifi("Loft", "psk", Some("attic pass"))
+            .unwrap());
+        assert!(client
+            .connect_hidden_wifi(HIDDEN_SSID, "psk", Some("attic pass"))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_fake_bus_offline_mode_and_tethering() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_connman(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        assert!(!client.offline_mode().unwrap());
+        client.set_offline_mode(true).unwrap();
+        assert!(client.offline_mode().unwrap());
+
+        client.start_tethering("Pocket", "s3cret-pass").unwrap();
+        let wifi = client.wifi_technology().unwrap();
+        assert!(wifi.tethering);
+        assert_eq!(wifi.tethering_identifier.as_deref(), Some("Pocket"));
+        assert_eq!(wifi.tethering_passphrase.as_deref(), Some("s3cret-pass"));
+        client.stop_tethering().unwrap();
+        assert!(!client.wifi_technology().unwrap().tethering);
+    }
+}
@@ -3,7 +3,10 @@
 // Action types
 pub const ACTION_TYPE_ACTION: &str = "action";
 pub const ACTION_TYPE_DIAGNOSTIC: &str = "diagnostic";
+pub const ACTION_TYPE_ETHERNET: &str = "ethernet";
 pub const ACTION_TYPE_FIREWALLD: &str = "firewalld";
+pub const ACTION_TYPE_HOTSPOT: &str = "hotspot";
+pub const ACTION_TYPE_NETWORKD: &str = "networkd";
 pub const ACTION_TYPE_NEXTDNS: &str = "nextdns";
 pub const ACTION_TYPE_SYSTEM: &str = "system";
 pub const ACTION_TYPE_TAILSCALE: &str = "tailscale";
@@ -22,6 +25,14 @@ pub const ICON_FIREWALL_ALLOW: &str = "🔓";
 pub const ICON_STAR: &str = "🌟";
 pub const ICON_BLUETOOTH: &str = "";
 pub const ICON_KEY: &str = "🔑";
+pub const ICON_ANTENNA: &str = "📡";
+pub const ICON_SAVED: &str = "💾";
+pub const ICON_QR: &str = "🔳";
+pub const ICON_MASK: &str = "🎭";
+pub const ICON_METERED: &str = "💰";
+pub const ICON_ETHERNET: &str = "🔌";
+pub const ICON_WARNING: &str = "⚠️";
+pub const ICON_SHIELD: &str = "🛡️";
 
 // Security types
 pub const SECURITY_OPEN: &str = "OPEN";
@@ -34,6 +45,8 @@ pub const SECURITY_UNKNOWN: &str = "UNKNOWN";
 pub const SYSTEM_AIRPLANE_MODE_ON: &str = "Turn ON airplane mode";
 pub const SYSTEM_AIRPLANE_MODE_OFF: &str = "Turn OFF airplane mode";
 pub const SYSTEM_EDIT_CONNECTIONS: &str = "Edit connections";
+pub const SYSTEM_SAVED_NETWORKS: &str = "Saved networks";
+pub const SYSTEM_MAC_POLICY: &str = "Wi-Fi MAC address policy";
 
 // Tailscale actions
 pub const TAILSCALE_DISABLE_EXIT_NODE: &str = "Disable exit-node";
@@ -55,6 +68,16 @@ pub const TAILSCALE_SIGN_NODE_DETAILED: &str = "Sign Node: {flag} {hostname} ({k
 pub const WIFI_DISCONNECT: &str = "Disconnect";
 pub const WIFI_CONNECT: &str = "Connect";
 pub const WIFI_CONNECT_HIDDEN: &str = "Connect to hidden network";
+pub const WIFI_ACCESS_POINT_DETAILS: &str = "Access point details";
+pub const WIFI_SHARE: &str = "Share current network (QR code)";
+pub const WIFI_IMPORT_QR: &str = "Connect from WIFI: code";
+
+// VPN actions
+pub const VPN_IMPORT_WIREGUARD: &str = "Import WireGuard config…";
+pub const VPN_WIREGUARD_DETAILS: &str = "WireGuard peers";
+pub const VPN_IMPORT_OPENVPN: &str = "Import OpenVPN config…";
+pub const VPN_ADD_OPENCONNECT: &str = "Add OpenConnect VPN…";
+pub const VPN_STALE_HANDSHAKE: &str = "handshake stale";
 
 // Suggested node format
 
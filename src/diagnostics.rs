@@ -1,3 +1,4 @@
+use crate::audit::{format_audit_records, read_recent_audit_records};
 use crate::command::{is_command_installed, CommandRunner};
 use crate::constants::{ACTION_TYPE_DIAGNOSTIC, ICON_CHECK, ICON_SIGNAL};
 use crate::dns_cache::{get_current_network_id, CachedDnsServer, DnsCacheStorage};
@@ -9,6 +10,16 @@ use std::error::Error;
 use std::net::IpAddr;
 use std::process::Output;
 use std::str::FromStr;
+use std::time::Duration;
+
+/// Timeouts for diagnostics that legitimately run longer than a plain command.
+const PING_SERIES_TIMEOUT: Duration = Duration::from_secs(45);
+const TRACEROUTE_TIMEOUT: Duration = Duration::from_secs(90);
+const SPEEDTEST_TIMEOUT: Duration = Duration::from_secs(120);
+const DNS_BENCHMARK_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Number of audit log entries shown by "Show recent network changes".
+const RECENT_CHANGES_LIMIT: usize = 15;
 
 /// Network diagnostic actions that can be performed
 #[derive(Debug, Clone, PartialEq)]
@@ -26,6 +37,7 @@ pub enum DiagnosticAction {
     SpeedTestFast,
     DnsBenchmark,
     WhatsMyDnsCheck,
+    ShowRecentChanges,
 }
 
 /// Result of a network diagnostic operation
@@ -186,6 +198,7 @@ pub fn get_diagnostic_actions() -> Vec<DiagnosticAction> {
     }
 
     actions.push(DiagnosticAction::WhatsMyDnsCheck);
+    actions.push(DiagnosticAction::ShowRecentChanges);
 
     actions
 }
@@ -209,6 +222,7 @@ pub async fn handle_diagnostic_action(
         DiagnosticAction::SpeedTestFast => run_speedtest_fast(command_runner).await,
         DiagnosticAction::DnsBenchmark => run_dns_benchmark(command_runner).await,
         DiagnosticAction::WhatsMyDnsCheck => run_whatsmydns_check(command_runner).await,
+        DiagnosticAction::ShowRecentChanges => show_recent_changes(),
     }
 }
 
@@ -258,6 +272,9 @@ pub fn diagnostic_action_to_string(action: &DiagnosticAction) -> String {
         DiagnosticAction::WhatsMyDnsCheck => {
             format_entry(ACTION_TYPE_DIAGNOSTIC, "🌐", "WhatsMyDNS DNS Check")
         }
+        DiagnosticAction::ShowRecentChanges => {
+            format_entry(ACTION_TYPE_DIAGNOSTIC, "📜", "Show recent network changes")
+        }
     }
 }
 
@@ -265,7 +282,9 @@ pub fn diagnostic_action_to_string(action: &DiagnosticAction) -> String {
 async fn test_connectivity(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
-    let output = command_runner.run_command("ping", &["-c", "3", "-W", "3", "8.8.8.8"])?;
+    let output = command_runner
+        .run_command_async("ping", &["-c", "3", "-W", "3", "8.8.8.8"])
+        .await?;
 
     let result = if output.status.success() {
         DiagnosticResult {
@@ -287,7 +306,9 @@ async fn ping_gateway(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
     // First, get the default gateway
-    let route_output = command_runner.run_command("ip", &["route", "show", "default"])?;
+    let route_output = command_runner
+        .run_command_async("ip", &["route", "show", "default"])
+        .await?;
 
     if !route_output.status.success() {
         return Ok(DiagnosticResult {
@@ -301,8 +322,9 @@ async fn ping_gateway(
 
     match gateway {
         Some(gw_ip) => {
-            let ping_output =
-                command_runner.run_command("ping", &["-c", "3", "-W", "2", &gw_ip])?;
+            let ping_output = command_runner
+                .run_command_async("ping", &["-c", "3", "-W", "2", &gw_ip])
+                .await?;
 
             if ping_output.status.success() {
                 let output_str = String::from_utf8_lossy(&ping_output.stdout);
@@ -334,7 +356,9 @@ async fn ping_dns_servers(
     let mut all_success = true;
 
     for dns in dns_servers {
-        let output = command_runner.run_command("ping", &["-c", "2", "-W", "2", dns])?;
+        let output = command_runner
+            .run_command_async("ping", &["-c", "2", "-W", "2", dns])
+            .await?;
 
         if output.status.success() {
             let output_str = String::from_utf8_lossy(&output.stdout);
@@ -357,7 +381,13 @@ async fn trace_route(
     command_runner: &dyn CommandRunner,
     target: &str,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
-    let output = command_runner.run_command("traceroute", &["-n", "-m", "15", target])?;
+    let output = command_runner
+        .run_command_with_timeout(
+            "traceroute",
+            &["-n", "-m", "15", target],
+            TRACEROUTE_TIMEOUT,
+        )
+        .await?;
 
     if output.status.success() {
         let output_str = String::from_utf8_lossy(&output.stdout);
@@ -384,10 +414,12 @@ async fn check_mtu(
 
     for size in test_sizes {
         let size_str = size.to_string();
-        let output = command_runner.run_command(
-            "ping",
-            &["-c", "1", "-M", "do", "-s", &size_str, "-W", "3", target],
-        )?;
+        let output = command_runner
+            .run_command_async(
+                "ping",
+                &["-c", "1", "-M", "do", "-s", &size_str, "-W", "3", target],
+            )
+            .await?;
 
         if output.status.success() {
             working_mtu = size + 28; // Add IP + ICMP headers
@@ -413,7 +445,13 @@ async fn check_latency(
     command_runner: &dyn CommandRunner,
     target: &str,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
-    let output = command_runner.run_command("ping", &["-c", "10", "-W", "3", target])?;
+    let output = command_runner
+        .run_command_with_timeout(
+            "ping",
+            &["-c", "10", "-W", "3", target],
+            PING_SERIES_TIMEOUT,
+        )
+        .await?;
 
     if output.status.success() {
         let output_str = String::from_utf8_lossy(&output.stdout);
@@ -436,7 +474,9 @@ async fn check_latency(
 async fn show_routing_table(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
-    let output = command_runner.run_command("ip", &["route", "show"])?;
+    let output = command_runner
+        .run_command_async("ip", &["route", "show"])
+        .await?;
 
     if output.status.success() {
         let output_str = String::from_utf8_lossy(&output.stdout);
@@ -456,7 +496,7 @@ async fn show_routing_table(
 async fn show_netstat(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
-    let output = command_runner.run_command("ss", &["-tuln"])?;
+    let output = command_runner.run_command_async("ss", &["-tuln"]).await?;
 
     if output.status.success() {
         let output_str = String::from_utf8_lossy(&output.stdout);
@@ -466,7 +506,9 @@ async fn show_netstat(
         })
     } else {
         // Fallback to netstat if ss is not available
-        let netstat_output = command_runner.run_command("netstat", &["-tuln"])?;
+        let netstat_output = command_runner
+            .run_command_async("netstat", &["-tuln"])
+            .await?;
 
         if netstat_output.status.success() {
             let output_str = String::from_utf8_lossy(&netstat_output.stdout);
@@ -487,7 +529,9 @@ async fn show_netstat(
 async fn show_network_interfaces(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
-    let output = command_runner.run_command("ip", &["addr", "show"])?;
+    let output = command_runner
+        .run_command_async("ip", &["addr", "show"])
+        .await?;
 
     if output.status.success() {
         let output_str = String::from_utf8_lossy(&output.stdout);
@@ -574,13 +618,25 @@ fn format_interface_output(output: &str) -> String {
     formatted.join("\n")
 }
 
+/// Show the tail of the audit log
+fn show_recent_changes() -> Result<DiagnosticResult, Box<dyn Error>> {
+    let records = read_recent_audit_records(RECENT_CHANGES_LIMIT)?;
+
+    Ok(DiagnosticResult {
+        success: true,
+        output: format_audit_records(&records),
+    })
+}
+
 /// Run internet speed test using speedtest-go, speedtest-cli, or speedtest
 async fn run_speedtest(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
     // Try speedtest-go first for better JSON output
     if is_command_installed("speedtest-go") {
-        let output = command_runner.run_command("speedtest-go", &["--json"])?;
+        let output = command_runner
+            .run_command_with_timeout("speedtest-go", &["--json"], SPEEDTEST_TIMEOUT)
+            .await?;
 
         if output.status.success() {
             let output_str = String::from_utf8_lossy(&output.stdout);
@@ -635,9 +691,13 @@ async fn run_speedtest(
 
     // Fallback to other speedtest tools
     let output = if is_command_installed("speedtest-cli") {
-        command_runner.run_command("speedtest-cli", &["--simple"])?
+        command_runner
+            .run_command_with_timeout("speedtest-cli", &["--simple"], SPEEDTEST_TIMEOUT)
+            .await?
     } else if is_command_installed("speedtest") {
-        command_runner.run_command("speedtest", &[])?
+        command_runner
+            .run_command_with_timeout("speedtest", &[], SPEEDTEST_TIMEOUT)
+            .await?
     } else {
         return Ok(DiagnosticResult {
             success: false,
@@ -664,7 +724,9 @@ async fn run_speedtest(
 async fn run_speedtest_fast(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
-    let output = command_runner.run_command("fast", &[])?;
+    let output = command_runner
+        .run_command_with_timeout("fast", &[], SPEEDTEST_TIMEOUT)
+        .await?;
 
     if output.status.success() {
         let output_str = String::from_utf8_lossy(&output.stdout);
@@ -685,8 +747,13 @@ async fn run_dns_benchmark(
     command_runner: &dyn CommandRunner,
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
     // Run dns-bench with JSON output
-    let output =
-        command_runner.run_command("dns-bench", &["--format", "json", "--skip-system-servers"])?;
+    let output = command_runner
+        .run_command_with_timeout(
+            "dns-bench",
+            &["--format", "json", "--skip-system-servers"],
+            DNS_BENCHMARK_TIMEOUT,
+        )
+        .await?;
 
     if !output.status.success() {
         return Ok(DiagnosticResult {
@@ -776,7 +843,9 @@ async fn run_dns_benchmark(
     let (name, ip, avg_ms, _) = &valid_results[0];
 
     // Get current network interface (assuming default route)
-    let route_output = command_runner.run_command("ip", &["route", "show", "default"])?;
+    let route_output = command_runner
+        .run_command_async("ip", &["route", "show", "default"])
+        .await?;
     let route_str = String::from_utf8_lossy(&route_output.stdout);
 
     // Extract the interface name from the default route
@@ -792,10 +861,12 @@ async fn run_dns_benchmark(
         .unwrap_or(&default_interface);
 
     // Set the DNS using systemd-resolved
-    let set_dns_output = command_runner.run_command(
-        "systemd-resolve",
-        &["--interface", interface, "--set-dns", ip],
-    )?;
+    let set_dns_output = command_runner
+        .run_command_async(
+            "systemd-resolve",
+            &["--interface", interface, "--set-dns", ip],
+        )
+        .await?;
 
     let success = set_dns_output.status.success();
 
@@ -868,10 +939,10 @@ async fn run_whatsmydns_check(
 ) -> Result<DiagnosticResult, Box<dyn Error>> {
     let input = crate::utils::prompt_for_visible_text("Domain and record type (example.com A)")?;
     let (domain, record_type) = parse_dns_check_input(&input)?;
-    let results: Vec<DnsCheckResolverResult> = default_dns_check_resolvers()
-        .iter()
-        .map(|resolver| query_dns_resolver(command_runner, resolver, &domain, &record_type))
-        .collect();
+    let mut results = Vec::new();
+    for resolver in default_dns_check_resolvers() {
+        results.push(query_dns_resolver(command_runner, &resolver, &domain, &record_type).await);
+    }
     let success = results
         .iter()
         .any(|result| result.error.is_none() && !result.answers.is_empty());
@@ -928,7 +999,7 @@ fn parse_dns_check_input(input: &str) -> Result<(String, String), Box<dyn Error>
     Ok((domain, record_type))
 }
 
-fn query_dns_resolver(
+async fn query_dns_resolver(
     command_runner: &dyn CommandRunner,
     resolver: &DnsResolver,
     domain: &str,
@@ -937,7 +1008,7 @@ fn query_dns_resolver(
     let mut errors = Vec::new();
 
     if is_command_installed("dig") {
-        match query_with_dig(command_runner, resolver, domain, record_type) {
+        match query_with_dig(command_runner, resolver, domain, record_type).await {
             Ok(result) if result.error.is_none() => return result,
             Ok(result) => {
                 if let Some(error) = result.error {
@@ -950,7 +1021,8 @@ fn query_dns_resolver(
 
     for command in ["dog", "doggo"] {
         if is_command_installed(command) {
-            match query_with_dog_like(command_runner, resolver, domain, record_type, command) {
+            match query_with_dog_like(command_runner, resolver, domain, record_type, command).await
+            {
                 Ok(result) if result.error.is_none() => return result,
                 Ok(result) => {
                     if let Some(error) = result.error {
@@ -975,28 +1047,30 @@ fn query_dns_resolver(
     }
 }
 
-fn query_with_dig(
+async fn query_with_dig(
     command_runner: &dyn CommandRunner,
     resolver: &DnsResolver,
     domain: &str,
     record_type: &str,
 ) -> Result<DnsCheckResolverResult, Box<dyn Error>> {
     let server = format!("@{}", resolver.address);
-    let output = command_runner.run_command(
-        "dig",
-        &[
-            &server,
-            domain,
-            record_type,
-            "+short",
-            "+time=2",
-            "+tries=1",
-        ],
-    )?;
+    let output = command_runner
+        .run_command_async(
+            "dig",
+            &[
+                &server,
+                domain,
+                record_type,
+                "+short",
+                "+time=2",
+                "+tries=1",
+            ],
+        )
+        .await?;
     Ok(dns_result_from_output(resolver, "dig", output))
 }
 
-fn query_with_dog_like(
+async fn query_with_dog_like(
     command_runner: &dyn CommandRunner,
     resolver: &DnsResolver,
     domain: &str,
@@ -1004,7 +1078,9 @@ fn query_with_dog_like(
     command: &str,
 ) -> Result<DnsCheckResolverResult, Box<dyn Error>> {
     let server = format!("@{}", resolver.address);
-    let output = command_runner.run_command(command, &[domain, record_type, &server, "--short"])?;
+    let output = command_runner
+        .run_command_async(command, &[domain, record_type, &server, "--short"])
+        .await?;
     Ok(dns_result_from_output(resolver, command, output))
 }
 
@@ -1176,6 +1252,11 @@ mod tests {
             diagnostic_action_to_string(&DiagnosticAction::WhatsMyDnsCheck),
             "diagnostic- 🌐 WhatsMyDNS DNS Check"
         );
+
+        assert_eq!(
+            diagnostic_action_to_string(&DiagnosticAction::ShowRecentChanges),
+            "diagnostic- 📜 Show recent network changes"
+        );
     }
 
     #[test]
@@ -1308,6 +1389,7 @@ mod tests {
         }
 
         assert!(actions.contains(&DiagnosticAction::WhatsMyDnsCheck));
+        assert!(actions.contains(&DiagnosticAction::ShowRecentChanges));
 
         // At least some actions should be available on most systems (ping is very common)
         // If no diagnostic tools are available, actions can be empty
@@ -121,8 +121,9 @@ pub async fn get_current_network_id(
     command_runner: &dyn crate::command::CommandRunner,
 ) -> Result<String, Box<dyn Error>> {
     // Try to get WiFi SSID first
-    if let Ok(output) =
-        command_runner.run_command("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"])
+    if let Ok(output) = command_runner
+        .run_command_async("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"])
+        .await
     {
         if output.status.success() {
             let output_str = String::from_utf8_lossy(&output.stdout);
@@ -138,14 +139,19 @@ pub async fn get_current_network_id(
         }
     }
 
-    // Try iwctl as fallback
-    if let Ok(output) = command_runner.run_command("iwctl", &["station", "wlan0", "show"]) {
-        if output.status.success() {
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            for line in output_str.lines() {
-                if line.contains("Connected network") {
-                    if let Some(ssid) = line.split_whitespace().last() {
-                        return Ok(ssid.to_string());
+    // Try iwctl as fallback, on every radio
+    for interface in crate::utils::list_wifi_interfaces() {
+        if let Ok(output) = command_runner
+            .run_command_async("iwctl", &["station", &interface, "show"])
+            .await
+        {
+            if output.status.success() {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+                for line in output_str.lines() {
+                    if line.contains("Connected network") {
+                        if let Some(ssid) = line.split_whitespace().last() {
+                            return Ok(ssid.to_string());
+                        }
                     }
                 }
             }
@@ -153,7 +159,10 @@ pub async fn get_current_network_id(
     }
 
     // Default to gateway IP for wired or unidentified networks
-    if let Ok(output) = command_runner.run_command("ip", &["route", "show", "default"]) {
+    if let Ok(output) = command_runner
+        .run_command_async("ip", &["route", "show", "default"])
+        .await
+    {
         if output.status.success() {
             let output_str = String::from_utf8_lossy(&output.stdout);
             if let Some(line) = output_str.lines().next() {
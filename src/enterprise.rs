@@ -0,0 +1,561 @@
+//! WPA2/WPA3-Enterprise (802.1X) connection flow.
+//!
+//! Asks for the EAP method, phase 2 authentication, identities, CA certificate
+//! and secrets, then creates a NetworkManager connection or installs an iwd
+//! `.8021x` provisioning file. Networks ConnMan or wpa_supplicant already know
+//! are joined as usual, but new ones cannot be set up for them.
+
+use crate::command::{CommandRunner, CONNECTION_COMMAND_TIMEOUT};
+use crate::utils::{
+    prompt_for_password, prompt_for_text, prompt_for_visible_text, select_from_menu,
+};
+use crate::{connman, iwd, networkmanager, wpa_supplicant, WifiBackend};
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Outer EAP method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EapMethod {
+    Peap,
+    Ttls,
+    Tls,
+}
+
+impl EapMethod {
+    pub const ALL: [EapMethod; 3] = [EapMethod::Peap, EapMethod::Ttls, EapMethod::Tls];
+
+    fn nm_value(&self) -> &'static str {
+        match self {
+            Self::Peap => "peap",
+            Self::Ttls => "ttls",
+            Self::Tls => "tls",
+        }
+    }
+
+    fn iwd_value(&self) -> &'static str {
+        match self {
+            Self::Peap => "PEAP",
+            Self::Ttls => "TTLS",
+            Self::Tls => "TLS",
+        }
+    }
+
+    /// Phase 2 methods offered for this EAP method, empty for TLS.
+    pub fn phase2_methods(&self) -> &'static [Phase2Auth] {
+        match self {
+            Self::Peap => &[Phase2Auth::Mschapv2, Phase2Auth::Gtc],
+            Self::Ttls => &[Phase2Auth::Pap, Phase2Auth::Mschapv2],
+            Self::Tls => &[],
+        }
+    }
+}
+
+impl fmt::Display for EapMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.iwd_value())
+    }
+}
+
+/// Inner (phase 2) authentication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase2Auth {
+    Mschapv2,
+    Pap,
+    Gtc,
+}
+
+impl Phase2Auth {
+    fn nm_value(&self) -> &'static str {
+        match self {
+            Self::Mschapv2 => "mschapv2",
+            Self::Pap => "pap",
+            Self::Gtc => "gtc",
+        }
+    }
+
+    fn iwd_value(&self, eap: EapMethod) -> &'static str {
+        match (eap, self) {
+            (EapMethod::Ttls, Self::Mschapv2) => "Tunneled-MSCHAPv2",
+            (EapMethod::Ttls, Self::Pap) => "Tunneled-PAP",
+            (_, Self::Mschapv2) => "MSCHAPV2",
+            (_, Self::Pap) => "PAP",
+            (_, Self::Gtc) => "GTC",
+        }
+    }
+}
+
+impl fmt::Display for Phase2Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mschapv2 => write!(f, "MSCHAPv2"),
+            Self::Pap => write!(f, "PAP"),
+            Self::Gtc => write!(f, "GTC"),
+        }
+    }
+}
+
+/// Everything needed to join an 802.1X network.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EnterpriseCredentials {
+    pub eap: Option<EapMethod>,
+    pub phase2: Option<Phase2Auth>,
+    pub identity: String,
+    pub anonymous_identity: Option<String>,
+    pub ca_cert: Option<PathBuf>,
+    /// Password for PEAP/TTLS.
+    pub password: Option<String>,
+    /// Client certificate for TLS.
+    pub client_cert: Option<PathBuf>,
+    /// Private key for TLS.
+    pub private_key: Option<PathBuf>,
+    pub private_key_password: Option<String>,
+    /// The network does not broadcast its SSID.
+    pub hidden: bool,
+}
+
+/// Returns true when the scanned security string announces 802.1X.
+pub fn is_enterprise_security(security: &str) -> bool {
+    let security = security.to_uppercase();
+    security.contains("802.1X") || security.contains("8021X")
+}
+
+/// Checks whether a profile for `ssid` already exists in the backend managing
+/// `interface`.
+pub async fn is_known_network(
+    ssid: &str,
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    match WifiBackend::detect(interface, command_runner).await {
+        Some(WifiBackend::NetworkManager) => {
+            networkmanager::is_known_network(ssid, command_runner).await
+        }
+        Some(WifiBackend::Connman) => {
+            let ssid = ssid.to_string();
+            let known = connman::with_client(move |client| {
+                Ok(client
+                    .services()?
+                    .iter()
+                    .any(|service| service.favorite && service.name == ssid))
+            })
+            .await;
+            Ok(known.transpose()?.unwrap_or(false))
+        }
+        Some(WifiBackend::Iwd) => iwd::is_known_network(ssid, command_runner).await,
+        Some(WifiBackend::WpaSupplicant) => {
+            wpa_supplicant::is_known_network(ssid, interface, command_runner).await
+        }
+        None => Ok(false),
+    }
+}
+
+/// Walks the user through the 802.1X settings, `None` when cancelled.
+pub fn prompt_enterprise_credentials(
+    ssid: &str,
+    dmenu_cmd: &str,
+    dmenu_args: &str,
+) -> Result<Option<EnterpriseCredentials>, Box<dyn Error>> {
+    let choose = |options: Vec<String>| -> Result<Option<usize>, Box<dyn Error>> {
+        let selected = select_from_menu(dmenu_cmd, dmenu_args, &options)?;
+        Ok(selected.and_then(|selected| options.iter().position(|o| *o == selected)))
+    };
+
+    let Some(eap) = choose(EapMethod::ALL.iter().map(|m| format!("EAP {m}")).collect())?
+        .map(|i| EapMethod::ALL[i])
+    else {
+        return Ok(None);
+    };
+
+    let phase2_methods = eap.phase2_methods();
+    let phase2 = if phase2_methods.is_empty() {
+        None
+    } else {
+        match choose(
+            phase2_methods
+                .iter()
+                .map(|m| format!("Phase 2 {m}"))
+                .collect(),
+        )? {
+            Some(i) => Some(phase2_methods[i]),
+            None => return Ok(None),
+        }
+    };
+
+    let identity = prompt_for_visible_text(&format!("{ssid} identity"))?;
+    if identity.is_empty() {
+        return Ok(None);
+    }
+
+    let mut credentials = EnterpriseCredentials {
+        eap: Some(eap),
+        phase2,
+        identity,
+        anonymous_identity: optional(prompt_for_visible_text(
+            "Anonymous identity (empty for none)",
+        )?),
+        ca_cert: optional_path(prompt_for_visible_text(
+            "CA certificate path (empty for none)",
+        )?)?,
+        ..Default::default()
+    };
+
+    if eap == EapMethod::Tls {
+        credentials.client_cert =
+            optional_path(prompt_for_visible_text("Client certificate path")?)?;
+        credentials.private_key = optional_path(prompt_for_visible_text("Private key path")?)?;
+        if credentials.client_cert.is_none() || credentials.private_key.is_none() {
+            return Err("EAP-TLS needs a client certificate and a private key".into());
+        }
+        credentials.private_key_password =
+            optional(prompt_for_text("Private key password (empty for none)")?);
+    } else {
+        credentials.password = Some(prompt_for_password(ssid)?);
+    }
+
+    Ok(Some(credentials))
+}
+
+fn optional(value: String) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn optional_path(value: String) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    match optional(value) {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if !path.is_file() {
+                return Err(format!("{} does not exist", path.display()).into());
+            }
+            Ok(Some(path))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Creates and activates the enterprise connection with NetworkManager or iwd.
+pub async fn connect_enterprise_wifi(
+    ssid: &str,
+    credentials: &EnterpriseCredentials,
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let backend = WifiBackend::detect(interface, command_runner).await;
+    if backend == Some(WifiBackend::NetworkManager) {
+        let args = nm_connection_args(ssid, credentials)?;
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        if !command_runner
+            .run_command_async("nmcli", &args)
+            .await?
+            .status
+            .success()
+        {
+            return Ok(false);
+        }
+
+        // The secrets stay off the command line
+        networkmanager::connection_up_with_passwd_file(
+            ssid,
+            &nm_passwd_file_content(credentials),
+            command_runner,
+        )
+        .await
+    } else if backend == Some(WifiBackend::Iwd) {
+        install_iwd_provisioning_file(ssid, credentials, command_runner)?;
+
+        let connect = if credentials.hidden {
+            "connect-hidden"
+        } else {
+            "connect"
+        };
+        let status = command_runner
+            .run_command_with_timeout(
+                "iwctl",
+                &["station", interface, connect, ssid],
+                CONNECTION_COMMAND_TIMEOUT,
+            )
+            .await?
+            .status;
+        Ok(status.success())
+    } else {
+        Err("Enterprise networks need NetworkManager or iwd".into())
+    }
+}
+
+/// Builds the `nmcli connection add` arguments for an 802.1X profile, without
+/// its secrets.
+fn nm_connection_args(
+    ssid: &str,
+    credentials: &EnterpriseCredentials,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let eap = credentials.eap.ok_or("No EAP method selected")?;
+    let mut args: Vec<String> = [
+        "connection",
+        "add",
+        "type",
+        "wifi",
+        "con-name",
+        ssid,
+        "ssid",
+        ssid,
+        "wifi-sec.key-mgmt",
+        "wpa-eap",
+        "802-1x.eap",
+        eap.nm_value(),
+        "802-1x.identity",
+        &credentials.identity,
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+
+    let mut push = |setting: &str, value: Option<String>| {
+        if let Some(value) = value {
+            args.push(setting.to_string());
+            args.push(value);
+        }
+    };
+
+    push(
+        "802-1x.anonymous-identity",
+        credentials.anonymous_identity.clone(),
+    );
+    push("802-1x.ca-cert", path_value(&credentials.ca_cert));
+    push(
+        "802-1x.phase2-auth",
+        credentials.phase2.map(|p| p.nm_value().to_string()),
+    );
+    push("802-1x.client-cert", path_value(&credentials.client_cert));
+    push("802-1x.private-key", path_value(&credentials.private_key));
+    push("wifi.hidden", credentials.hidden.then(|| "yes".to_string()));
+
+    Ok(args)
+}
+
+/// Builds the nmcli `passwd-file` holding the 802.1X secrets.
+fn nm_passwd_file_content(credentials: &EnterpriseCredentials) -> String {
+    [
+        ("802-1x.password", &credentials.password),
+        (
+            "802-1x.private-key-password",
+            &credentials.private_key_password,
+        ),
+    ]
+    .iter()
+    .filter_map(|(setting, value)| value.as_ref().map(|value| format!("{setting}:{value}\n")))
+    .collect()
+}
+
+fn path_value(path: &Option<PathBuf>) -> Option<String> {
+    path.as_ref().map(|path| path.display().to_string())
+}
+
+/// Name of the iwd provisioning file for an SSID.
+pub fn iwd_provisioning_file_name(ssid: &str) -> String {
+    iwd::network_file_name(ssid, "8021x")
+}
+
+/// Renders the iwd `.8021x` provisioning file.
+fn iwd_provisioning_file(credentials: &EnterpriseCredentials) -> Result<String, Box<dyn Error>> {
+    let eap = credentials.eap.ok_or("No EAP method selected")?;
+    let method = eap.iwd_value();
+    let outer_identity = credentials
+        .anonymous_identity
+        .as_deref()
+        .unwrap_or(&credentials.identity);
+
+    let mut lines = vec![
+        "[Security]".to_string(),
+        format!("EAP-Method={method}"),
+        format!("EAP-Identity={outer_identity}"),
+    ];
+
+    if let Some(ca_cert) = &credentials.ca_cert {
+        lines.push(format!("EAP-{method}-CACert={}", ca_cert.display()));
+    }
+
+    match eap {
+        EapMethod::Tls => {
+            if let Some(cert) = &credentials.client_cert {
+                lines.push(format!("EAP-TLS-ClientCert={}", cert.display()));
+            }
+            if let Some(key) = &credentials.private_key {
+                lines.push(format!("EAP-TLS-ClientKey={}", key.display()));
+            }
+            if let Some(passphrase) = &credentials.private_key_password {
+                lines.push(format!("EAP-TLS-ClientKeyPassphrase={passphrase}"));
+            }
+        }
+        EapMethod::Peap | EapMethod::Ttls => {
+            let phase2 = credentials.phase2.ok_or("No phase 2 method selected")?;
+            lines.push(format!(
+                "EAP-{method}-Phase2-Method={}",
+                phase2.iwd_value(eap)
+            ));
+            lines.push(format!(
+                "EAP-{method}-Phase2-Identity={}",
+                credentials.identity
+            ));
+            if let Some(password) = &credentials.password {
+                lines.push(format!("EAP-{method}-Phase2-Password={password}"));
+            }
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("[Settings]".to_string());
+    lines.push("AutoConnect=true".to_string());
+    if credentials.hidden {
+        lines.push("Hidden=true".to_string());
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Installs the provisioning file into the iwd state directory.
+fn install_iwd_provisioning_file(
+    ssid: &str,
+    credentials: &EnterpriseCredentials,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
+    let content = iwd_provisioning_file(credentials)?;
+    iwd::install_state_file(&iwd_provisioning_file_name(ssid), &content, command_runner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peap_credentials() -> EnterpriseCredentials {
+        EnterpriseCredentials {
+            eap: Some(EapMethod::Peap),
+            phase2: Some(Phase2Auth::Mschapv2),
+            identity: "alice@example.edu".to_string(),
+            anonymous_identity: Some("anonymous@example.edu".to_string()),
+            ca_cert: Some(PathBuf::from("/etc/ssl/certs/eduroam.pem")),
+            password: Some("hunter2".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_is_enterprise_security() {
+        assert!(is_enterprise_security("WPA2 802.1X"));
+        assert!(is_enterprise_security("WPA3 802.1X"));
+        assert!(is_enterprise_security("8021x"));
+        assert!(!is_enterprise_security("WPA2"));
+        assert!(!is_enterprise_security(""));
+    }
+
+    #[test]
+    fn test_phase2_methods() {
+        assert_eq!(
+            EapMethod::Peap.phase2_methods(),
+            &[Phase2Auth::Mschapv2, Phase2Auth::Gtc]
+        );
+        assert!(EapMethod::Tls.phase2_methods().is_empty());
+    }
+
+    #[test]
+    fn test_nm_connection_args_peap() {
+        let args = nm_connection_args("eduroam", &peap_credentials()).unwrap();
+        let joined = args.join(" ");
+
+        assert!(joined.starts_with("connection add type wifi con-name eduroam ssid eduroam"));
+        assert!(joined.contains("wifi-sec.key-mgmt wpa-eap"));
+        assert!(joined.contains("802-1x.eap peap"));
+        assert!(joined.contains("802-1x.identity alice@example.edu"));
+        assert!(joined.contains("802-1x.anonymous-identity anonymous@example.edu"));
+        assert!(joined.contains("802-1x.ca-cert /etc/ssl/certs/eduroam.pem"));
+        assert!(joined.contains("802-1x.phase2-auth mschapv2"));
+        assert!(!joined.contains("hunter2"));
+        assert!(!joined.contains("private-key"));
+        assert_eq!(
+            nm_passwd_file_content(&peap_credentials()),
+            "802-1x.password:hunter2\n"
+        );
+    }
+
+    #[test]
+    fn test_nm_connection_args_tls() {
+        let credentials = EnterpriseCredentials {
+            eap: Some(EapMethod::Tls),
+            identity: "host/laptop".to_string(),
+            client_cert: Some(PathBuf::from("/home/a/cert.pem")),
+            private_key: Some(PathBuf::from("/home/a/key.pem")),
+            private_key_password: Some("keypass".to_string()),
+            ..Default::default()
+        };
+
+        let joined = nm_connection_args("Corp", &credentials).unwrap().join(" ");
+        assert!(joined.contains("802-1x.eap tls"));
+        assert!(joined.contains("802-1x.client-cert /home/a/cert.pem"));
+        assert!(joined.contains("802-1x.private-key /home/a/key.pem"));
+        assert!(!joined.contains("keypass"));
+        assert_eq!(
+            nm_passwd_file_content(&credentials),
+            "802-1x.private-key-password:keypass\n"
+        );
+        assert!(!joined.contains("phase2"));
+
+        assert!(nm_connection_args("Corp", &EnterpriseCredentials::default()).is_err());
+    }
+
+    #[test]
+    fn test_iwd_provisioning_file_peap() {
+        let content = iwd_provisioning_file(&peap_credentials()).unwrap();
+        assert_eq!(
+            content,
+            "[Security]\n\
+             EAP-Method=PEAP\n\
+             EAP-Identity=anonymous@example.edu\n\
+             EAP-PEAP-CACert=/etc/ssl/certs/eduroam.pem\n\
+             EAP-PEAP-Phase2-Method=MSCHAPV2\n\
+             EAP-PEAP-Phase2-Identity=alice@example.edu\n\
+             EAP-PEAP-Phase2-Password=hunter2\n\
+             \n\
+             [Settings]\n\
+             AutoConnect=true\n"
+        );
+    }
+
+    #[test]
+    fn test_iwd_provisioning_file_ttls_pap() {
+        let credentials = EnterpriseCredentials {
+            eap: Some(EapMethod::Ttls),
+            phase2: Some(Phase2Auth::Pap),
+            identity: "bob".to_string(),
+            password: Some("pw".to_string()),
+            ..Default::default()
+        };
+
+        let content = iwd_provisioning_file(&credentials).unwrap();
+        assert!(content.contains("EAP-Identity=bob\n"));
+        assert!(content.contains("EAP-TTLS-Phase2-Method=Tunneled-PAP\n"));
+        assert!(!content.contains("CACert"));
+    }
+
+    #[test]
+    fn test_hidden_enterprise_profile() {
+        let credentials = EnterpriseCredentials {
+            hidden: true,
+            ..peap_credentials()
+        };
+
+        let joined = nm_connection_args("Corp", &credentials).unwrap().join(" ");
+        assert!(joined.ends_with("wifi.hidden yes"));
+        let content = iwd_provisioning_file(&credentials).unwrap();
+        assert!(content.ends_with("AutoConnect=true\nHidden=true\n"));
+    }
+
+    #[test]
+    fn test_iwd_provisioning_file_name() {
+        assert_eq!(iwd_provisioning_file_name("eduroam"), "eduroam.8021x");
+        assert_eq!(
+            iwd_provisioning_file_name("Corp WiFi-5"),
+            "Corp WiFi-5.8021x"
+        );
+        assert_eq!(iwd_provisioning_file_name("Café"), "=436166c3a9.8021x");
+    }
+}
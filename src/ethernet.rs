@@ -0,0 +1,554 @@
+//! Wired Ethernet interfaces and NetworkManager wired profiles.
+//!
+//! Interfaces come from `/sys/class/net`: physical devices of type
+//! `ARPHRD_ETHER` that are not wireless. The link state, speed and duplex are
+//! read from the same attributes `ethtool` reports. Without NetworkManager,
+//! ConnMan's wired services stand in for the profiles.
+
+use crate::command::{CommandRunner, CONNECTION_COMMAND_TIMEOUT};
+use crate::connman::{self, SERVICE_PATH_PREFIX};
+use crate::constants::{ACTION_TYPE_ETHERNET, ICON_CHECK, ICON_ETHERNET};
+use crate::format_entry;
+use crate::networkmanager::split_nmcli_fields;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Where the kernel exposes network interfaces.
+pub const SYS_CLASS_NET: &str = "/sys/class/net";
+
+/// `ARPHRD_ETHER`, shared by Ethernet and Wi-Fi devices
+const ARPHRD_ETHER: &str = "1";
+const NM_WIRED_TYPE: &str = "802-3-ethernet";
+
+/// A physical wired interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthernetInterface {
+    pub name: String,
+    /// Whether a cable with a live link partner is plugged in
+    pub carrier: bool,
+    /// Negotiated speed in Mb/s
+    pub speed: Option<u32>,
+    /// `full` or `half`
+    pub duplex: Option<String>,
+}
+
+impl EthernetInterface {
+    /// Short link description, e.g. `1000 Mb/s full duplex`.
+    pub fn link_summary(&self) -> String {
+        if !self.carrier {
+            return "no cable".to_string();
+        }
+        match (self.speed, &self.duplex) {
+            (Some(speed), Some(duplex)) => format!("{speed} Mb/s {duplex} duplex"),
+            (Some(speed), None) => format!("{speed} Mb/s"),
+            _ => "link up".to_string(),
+        }
+    }
+}
+
+/// A saved NetworkManager wired profile, or a ConnMan wired service whose
+/// object path is kept in `uuid`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WiredProfile {
+    pub name: String,
+    pub uuid: String,
+    /// Interface the profile is active on
+    pub device: Option<String>,
+    /// Link description of `device`, captured when the menu was built
+    pub link: Option<String>,
+}
+
+/// Ethernet action types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EthernetAction {
+    Connect(WiredProfile),
+    Disconnect(WiredProfile),
+}
+
+pub fn ethernet_action_to_string(action: &EthernetAction) -> String {
+    match action {
+        EthernetAction::Connect(profile) => {
+            format_entry(ACTION_TYPE_ETHERNET, ICON_ETHERNET, &profile.name)
+        }
+        EthernetAction::Disconnect(profile) => {
+            let details = [profile.device.as_deref(), profile.link.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", ");
+            format_entry(
+                ACTION_TYPE_ETHERNET,
+                ICON_CHECK,
+                &format!("{:<25}\t{}", profile.name, details),
+            )
+        }
+    }
+}
+
+/// Lists the wired interfaces on this machine.
+pub fn list_ethernet_interfaces() -> Vec<EthernetInterface> {
+    list_ethernet_interfaces_in(Path::new(SYS_CLASS_NET))
+}
+
+/// Lists the wired interfaces found under a `/sys/class/net`-like directory.
+pub fn list_ethernet_interfaces_in(root: &Path) -> Vec<EthernetInterface> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut interfaces: Vec<EthernetInterface> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| is_wired_interface(dir))
+        .filter_map(|dir| {
+            let name = dir.file_name()?.to_string_lossy().to_string();
+            // Reading carrier, speed or duplex fails while the interface is down
+            let carrier = read_attribute(&dir, "carrier").is_some_and(|value| value == "1");
+            Some(EthernetInterface {
+                name,
+                carrier,
+                speed: read_attribute(&dir, "speed")
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .filter(|speed| carrier && *speed > 0)
+                    .map(|speed| speed as u32),
+                duplex: read_attribute(&dir, "duplex")
+                    .filter(|duplex| carrier && (duplex == "full" || duplex == "half")),
+            })
+        })
+        .collect();
+
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+/// Physical Ethernet devices have a backing `device` and no wireless extensions.
+fn is_wired_interface(dir: &Path) -> bool {
+    read_attribute(dir, "type").as_deref() == Some(ARPHRD_ETHER)
+        && dir.join("device").exists()
+        && !dir.join("wireless").exists()
+        && !dir.join("phy80211").exists()
+}
+
+fn read_attribute(dir: &Path, attribute: &str) -> Option<String> {
+    fs::read_to_string(dir.join(attribute))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Picks the interface to use: the first with a cable plugged in, then the
+/// first one at all.
+pub fn preferred_interface(interfaces: &[EthernetInterface]) -> Option<&EthernetInterface> {
+    interfaces
+        .iter()
+        .find(|interface| interface.carrier)
+        .or_else(|| interfaces.first())
+}
+
+/// Lists wired NetworkManager profiles, active ones first.
+pub async fn get_wired_profiles(
+    interfaces: &[EthernetInterface],
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<WiredProfile>, Box<dyn Error>> {
+    let output = command_runner
+        .run_command_async(
+            "nmcli",
+            &["-t", "-f", "NAME,UUID,TYPE,DEVICE", "connection", "show"],
+        )
+        .await?;
+    if !output.status.success() {
+        return Err("Failed to list NetworkManager connections".into());
+    }
+
+    let mut profiles: Vec<WiredProfile> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| match split_nmcli_fields(line).as_slice() {
+            [name, uuid, kind, device] if kind == NM_WIRED_TYPE => {
+                let device = Some(device.clone()).filter(|device| !device.is_empty());
+                let link = device.as_ref().and_then(|device| {
+                    interfaces
+                        .iter()
+                        .find(|interface| &interface.name == device)
+                        .map(EthernetInterface::link_summary)
+                });
+                Some(WiredProfile {
+                    name: name.clone(),
+                    uuid: uuid.clone(),
+                    device,
+                    link,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    profiles.sort_by_key(|profile| profile.device.is_none());
+    Ok(profiles)
+}
+
+/// Returns connect and disconnect entries for the wired profiles.
+pub async fn get_ethernet_actions(command_runner: &dyn CommandRunner) -> Vec<EthernetAction> {
+    let interfaces = list_ethernet_interfaces();
+    if interfaces.is_empty() {
+        return Vec::new();
+    }
+
+    let profiles = match get_wired_profiles(&interfaces, command_runner).await.ok() {
+        Some(profiles) => profiles,
+        None => connman::with_client(move |client| client.wired_profiles(&interfaces))
+            .await
+            .and_then(Result::ok)
+            .unwrap_or_default(),
+    };
+
+    profiles
+        .into_iter()
+        .map(|profile| {
+            if profile.device.is_some() {
+                EthernetAction::Disconnect(profile)
+            } else {
+                EthernetAction::Connect(profile)
+            }
+        })
+        .collect()
+}
+
+/// Brings a wired profile up.
+///
+/// Profiles not bound to an interface go on the one with a cable plugged in,
+/// replacing whatever profile was active there, so docking station profiles
+/// can be switched in place.
+pub async fn connect_wired_profile(
+    profile: &WiredProfile,
+    interfaces: &[EthernetInterface],
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if let Some(result) = set_connman_service(profile, true).await {
+        return result;
+    }
+
+    let bound = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "-s",
+                "-g",
+                "connection.interface-name",
+                "connection",
+                "show",
+                "uuid",
+                &profile.uuid,
+            ],
+        )
+        .await
+        .is_ok_and(|output| {
+            output.status.success() && !String::from_utf8_lossy(&output.stdout).trim().is_empty()
+        });
+
+    let mut args = vec!["connection", "up", "uuid", &profile.uuid];
+    if !bound {
+        if let Some(interface) = preferred_interface(interfaces) {
+            args.extend(["ifname", &interface.name]);
+        }
+    }
+
+    let status = command_runner
+        .run_command_with_timeout("nmcli", &args, CONNECTION_COMMAND_TIMEOUT)
+        .await?
+        .status;
+    Ok(status.success())
+}
+
+/// Takes a wired profile down.
+pub async fn disconnect_wired_profile(
+    profile: &WiredProfile,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if let Some(result) = set_connman_service(profile, false).await {
+        return result;
+    }
+
+    let status = command_runner
+        .run_command_with_timeout(
+            "nmcli",
+            &["connection", "down", "uuid", &profile.uuid],
+            CONNECTION_COMMAND_TIMEOUT,
+        )
+        .await?
+        .status;
+    Ok(status.success())
+}
+
+/// Connects or disconnects profiles that are ConnMan services, `None` for
+/// NetworkManager profiles or when ConnMan is not running.
+async fn set_connman_service(
+    profile: &WiredProfile,
+    connect: bool,
+) -> Option<Result<bool, Box<dyn Error>>> {
+    if !profile.uuid.starts_with(SERVICE_PATH_PREFIX) {
+        return None;
+    }
+    let path = profile.uuid.clone();
+    let result = connman::with_client(move |client| {
+        if connect {
+            client.connect_service(&path)
+        } else {
+            client.disconnect_service(&path)
+        }
+    })
+    .await?;
+    Some(result.map_err(Into::into))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct ScriptedCommandRunner {
+        responses: Vec<(Vec<&'static str>, &'static str)>,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl ScriptedCommandRunner {
+        fn new(responses: Vec<(Vec<&'static str>, &'static str)>) -> Self {
+            Self {
+                responses,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl CommandRunner for ScriptedCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command];
+            call.extend_from_slice(args);
+            self.calls
+                .lock()
+                .unwrap()
+                .push(call.iter().map(|arg| arg.to_string()).collect());
+            let stdout = self
+                .responses
+                .iter()
+                .find(|(expected, _)| *expected == call)
+                .map(|(_, stdout)| stdout.as_bytes().to_vec());
+            Ok(Output {
+                status: ExitStatus::from_raw(if stdout.is_some() { 0 } else { 1 << 8 }),
+                stdout: stdout.unwrap_or_default(),
+                stderr: vec![],
+            })
+        }
+    }
+
+    fn add_interface(root: &Path, name: &str, attributes: &[(&str, &str)], dirs: &[&str]) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        for (attribute, value) in attributes {
+            fs::write(dir.join(attribute), format!("{value}\n")).unwrap();
+        }
+        for sub in dirs {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+    }
+
+    fn interface(name: &str, carrier: bool) -> EthernetInterface {
+        EthernetInterface {
+            name: name.to_string(),
+            carrier,
+            speed: carrier.then_some(1000),
+            duplex: carrier.then(|| "full".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_list_ethernet_interfaces() {
+        let root = tempfile::tempdir().unwrap();
+        add_interface(
+            root.path(),
+            "enp0s31f6",
+            &[
+                ("type", "1"),
+                ("carrier", "1"),
+                ("speed", "1000"),
+                ("duplex", "full"),
+            ],
+            &["device"],
+        );
+        add_interface(
+            root.path(),
+            "enx00e04c",
+            &[
+                ("type", "1"),
+                ("carrier", "0"),
+                ("speed", "-1"),
+                ("duplex", "unknown"),
+            ],
+            &["device"],
+        );
+        add_interface(
+            root.path(),
+            "wlan0",
+            &[("type", "1"), ("carrier", "1")],
+            &["device", "wireless"],
+        );
+        add_interface(root.path(), "docker0", &[("type", "1")], &[]);
+        add_interface(root.path(), "lo", &[("type", "772")], &[]);
+
+        let interfaces = list_ethernet_interfaces_in(root.path());
+        assert_eq!(
+            interfaces,
+            vec![interface("enp0s31f6", true), interface("enx00e04c", false)]
+        );
+        assert_eq!(interfaces[0].link_summary(), "1000 Mb/s full duplex");
+        assert_eq!(interfaces[1].link_summary(), "no cable");
+    }
+
+    #[test]
+    fn test_preferred_interface() {
+        let interfaces = [interface("enp0s31f6", false), interface("enx00e04c", true)];
+        assert_eq!(
+            preferred_interface(&interfaces).map(|i| i.name.as_str()),
+            Some("enx00e04c")
+        );
+        assert_eq!(
+            preferred_interface(&interfaces[..1]).map(|i| i.name.as_str()),
+            Some("enp0s31f6")
+        );
+        assert_eq!(preferred_interface(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_wired_profiles() {
+        let runner = ScriptedCommandRunner::new(vec![(
+            vec![
+                "nmcli",
+                "-t",
+                "-f",
+                "NAME,UUID,TYPE,DEVICE",
+                "connection",
+                "show",
+            ],
+            "Home:1111:802-11-wireless:wlan0\nDock static:2222:802-3-ethernet:\nDock DHCP:3333:802-3-ethernet:enx00e04c\n",
+        )]);
+        let interfaces = [interface("enx00e04c", true)];
+
+        let profiles = get_wired_profiles(&interfaces, &runner).await.unwrap();
+        assert_eq!(
+            profiles,
+            vec![
+                WiredProfile {
+                    name: "Dock DHCP".to_string(),
+                    uuid: "3333".to_string(),
+                    device: Some("enx00e04c".to_string()),
+                    link: Some("1000 Mb/s full duplex".to_string()),
+                },
+                WiredProfile {
+                    name: "Dock static".to_string(),
+                    uuid: "2222".to_string(),
+                    device: None,
+                    link: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_action_strings() {
+        let active = WiredProfile {
+            name: "Dock DHCP".to_string(),
+            uuid: "3333".to_string(),
+            device: Some("enx00e04c".to_string()),
+            link: Some("1000 Mb/s full duplex".to_string()),
+        };
+        let display = ethernet_action_to_string(&EthernetAction::Disconnect(active));
+        assert!(display.starts_with(ACTION_TYPE_ETHERNET));
+        assert!(display.contains(ICON_CHECK));
+        assert!(display.contains("enx00e04c, 1000 Mb/s full duplex"));
+
+        let inactive = WiredProfile {
+            name: "Dock static".to_string(),
+            uuid: "2222".to_string(),
+            device: None,
+            link: None,
+        };
+        assert!(
+            ethernet_action_to_string(&EthernetAction::Connect(inactive)).ends_with("Dock static")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_unbound_profile_uses_plugged_interface() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                vec![
+                    "nmcli",
+                    "-s",
+                    "-g",
+                    "connection.interface-name",
+                    "connection",
+                    "show",
+                    "uuid",
+                    "2222",
+                ],
+                "\n",
+            ),
+            (
+                vec![
+                    "nmcli",
+                    "connection",
+                    "up",
+                    "uuid",
+                    "2222",
+                    "ifname",
+                    "enx00e04c",
+                ],
+                "",
+            ),
+        ]);
+        let profile = WiredProfile {
+            name: "Dock static".to_string(),
+            uuid: "2222".to_string(),
+            device: None,
+            link: None,
+        };
+        let interfaces = [interface("enp0s31f6", false), interface("enx00e04c", true)];
+
+        assert!(connect_wired_profile(&profile, &interfaces, &runner)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_connect_bound_profile_lets_networkmanager_pick() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                vec![
+                    "nmcli",
+                    "-s",
+                    "-g",
+                    "connection.interface-name",
+                    "connection",
+                    "show",
+                    "uuid",
+                    "4444",
+                ],
+                "enp0s31f6\n",
+            ),
+            (vec!["nmcli", "connection", "up", "uuid", "4444"], ""),
+        ]);
+        let profile = WiredProfile {
+            name: "Office".to_string(),
+            uuid: "4444".to_string(),
+            device: None,
+            link: None,
+        };
+
+        assert!(
+            connect_wired_profile(&profile, &[interface("enx00e04c", true)], &runner)
+                .await
+                .unwrap()
+        );
+    }
+}
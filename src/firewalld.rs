@@ -3,7 +3,9 @@
 //! This module provides functionality to interact with firewalld zones and panic mode
 //! using the firewall-cmd command.
 
-use crate::command::{is_command_installed, CommandRunner};
+use crate::command::{
+    is_command_installed, CommandRunner, RealCommandRunner, PRIVILEGED_COMMAND_TIMEOUT,
+};
 use crate::constants::{ICON_FIREWALL_ALLOW, ICON_FIREWALL_BLOCK, ICON_LOCK};
 use crate::privilege::wrap_privileged_command;
 use log::debug;
@@ -12,8 +14,10 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::process::Command as TokioCommand;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Upper bound for the quick firewall-cmd queries used while building the menu.
+const FIREWALL_CMD_QUERY_TIMEOUT: Duration = Duration::from_millis(500);
 
 /// Firewalld-related actions
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -249,7 +253,7 @@ pub async fn get_firewalld_actions_with_display() -> Vec<(FirewalldAction, Strin
     let mut actions = vec![];
 
     // Fetch current zone for better performance
-    let current_zone = get_current_zone_async().await.ok();
+    let current_zone = get_current_zone_async(&RealCommandRunner).await.ok();
 
     // Add zone information action
     let zone_action = FirewalldAction::GetCurrentZone;
@@ -266,7 +270,7 @@ pub async fn get_firewalld_actions_with_display() -> Vec<(FirewalldAction, Strin
     ));
 
     // Add zone switching actions with proper indicators
-    match get_available_zones_async().await {
+    match get_available_zones_async(&RealCommandRunner).await {
         Ok(zones) => {
             for zone in zones {
                 let action = FirewalldAction::SetZone(zone.name.clone());
@@ -301,11 +305,8 @@ pub async fn get_firewalld_actions_with_display() -> Vec<(FirewalldAction, Strin
 
     // Add panic mode toggle action with state-aware display
     let panic_action = FirewalldAction::TogglePanicMode;
-    let is_panic_on = tokio::process::Command::new("firewall-cmd")
-        .arg("--query-panic")
-        .output()
+    let is_panic_on = is_panic_mode_enabled(&RealCommandRunner)
         .await
-        .map(|output| output.status.success())
         .unwrap_or(false);
 
     let panic_display = if is_panic_on {
@@ -338,7 +339,7 @@ pub async fn handle_firewalld_action(
 
     match action {
         FirewalldAction::SetZone(zone) => {
-            set_default_zone(zone, command_runner)?;
+            set_default_zone(zone, command_runner).await?;
             Ok(FirewalldActionResult {
                 success: true,
                 message: Some(format!("Switched to firewalld zone: {}", zone)),
@@ -346,10 +347,10 @@ pub async fn handle_firewalld_action(
         }
         FirewalldAction::TogglePanicMode => {
             // Check current panic mode state and toggle it
-            let current_panic = is_panic_mode_enabled(command_runner).unwrap_or(false);
+            let current_panic = is_panic_mode_enabled(command_runner).await.unwrap_or(false);
             let new_panic_state = !current_panic;
 
-            set_panic_mode(new_panic_state, command_runner)?;
+            set_panic_mode(new_panic_state, command_runner).await?;
             let message = if new_panic_state {
                 "Firewalld panic mode enabled - all connections blocked"
             } else {
@@ -361,7 +362,9 @@ pub async fn handle_firewalld_action(
             })
         }
         FirewalldAction::GetCurrentZone => {
-            let zone = get_current_zone(command_runner)?;
+            let zone = get_current_zone_async(command_runner)
+                .await
+                .map_err(|e| e.to_string())?;
             debug!("Current firewalld zone: {}", zone);
             Ok(FirewalldActionResult {
                 success: true,
@@ -454,15 +457,16 @@ fn is_firewalld_available() -> bool {
 }
 
 /// Get the current active zone (async version)
-async fn get_current_zone_async() -> Result<String, Box<dyn Error + Send + Sync>> {
-    let output = tokio::time::timeout(
-        std::time::Duration::from_millis(500),
-        tokio::process::Command::new("firewall-cmd")
-            .arg("--get-default-zone")
-            .output(),
-    )
-    .await
-    .map_err(|_| "firewall-cmd timeout")??;
+async fn get_current_zone_async(
+    command_runner: &dyn CommandRunner,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let output = command_runner
+        .run_command_with_timeout(
+            "firewall-cmd",
+            &["--get-default-zone"],
+            FIREWALL_CMD_QUERY_TIMEOUT,
+        )
+        .await?;
 
     if !output.status.success() {
         return Err("Failed to get current zone".into());
@@ -489,16 +493,13 @@ fn get_current_zone(command_runner: &dyn CommandRunner) -> Result<String, Box<dy
 }
 
 /// Get available firewalld zones with information (async version)
-async fn get_available_zones_async() -> Result<Vec<FirewalldZone>, Box<dyn Error + Send + Sync>> {
+async fn get_available_zones_async(
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<FirewalldZone>, Box<dyn Error + Send + Sync>> {
     // Get list of zones with timeout
-    let zones_output = tokio::time::timeout(
-        std::time::Duration::from_millis(500),
-        tokio::process::Command::new("firewall-cmd")
-            .arg("--get-zones")
-            .output(),
-    )
-    .await
-    .map_err(|_| "firewall-cmd --get-zones timeout")??;
+    let zones_output = command_runner
+        .run_command_with_timeout("firewall-cmd", &["--get-zones"], FIREWALL_CMD_QUERY_TIMEOUT)
+        .await?;
     if !zones_output.status.success() {
         return Err("Failed to get zones list".into());
     }
@@ -507,17 +508,18 @@ async fn get_available_zones_async() -> Result<Vec<FirewalldZone>, Box<dyn Error
     let zone_names: Vec<&str> = zones_str.split_whitespace().collect();
 
     // Get current default zone
-    let current_zone = get_current_zone_async().await.unwrap_or_default();
+    let current_zone = get_current_zone_async(command_runner)
+        .await
+        .unwrap_or_default();
 
     // Get active zones with timeout
-    let active_zones_output = tokio::time::timeout(
-        std::time::Duration::from_millis(500),
-        tokio::process::Command::new("firewall-cmd")
-            .arg("--get-active-zones")
-            .output(),
-    )
-    .await
-    .map_err(|_| "firewall-cmd --get-active-zones timeout")??;
+    let active_zones_output = command_runner
+        .run_command_with_timeout(
+            "firewall-cmd",
+            &["--get-active-zones"],
+            FIREWALL_CMD_QUERY_TIMEOUT,
+        )
+        .await?;
     let active_zones_str = if active_zones_output.status.success() {
         String::from_utf8(active_zones_output.stdout).unwrap_or_default()
     } else {
@@ -622,7 +624,10 @@ fn get_available_zones(
 }
 
 /// Set the default firewalld zone
-fn set_default_zone(zone: &str, command_runner: &dyn CommandRunner) -> Result<(), Box<dyn Error>> {
+async fn set_default_zone(
+    zone: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
     debug!("Setting firewalld zone to: {}", zone);
 
     // Use privilege escalation for firewall-cmd commands
@@ -630,7 +635,9 @@ fn set_default_zone(zone: &str, command_runner: &dyn CommandRunner) -> Result<()
     let privileged_cmd = wrap_privileged_command(&command, false);
 
     debug!("Running privileged command: {}", privileged_cmd);
-    let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    let output = command_runner
+        .run_command_with_timeout("sh", &["-c", &privileged_cmd], PRIVILEGED_COMMAND_TIMEOUT)
+        .await?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
@@ -641,16 +648,21 @@ fn set_default_zone(zone: &str, command_runner: &dyn CommandRunner) -> Result<()
     Ok(())
 }
 
-/// Check if panic mode is enabled (sync version)
-fn is_panic_mode_enabled(command_runner: &dyn CommandRunner) -> Result<bool, Box<dyn Error>> {
-    let output = command_runner.run_command("firewall-cmd", &["--query-panic"])?;
+/// Check if panic mode is enabled
+async fn is_panic_mode_enabled(command_runner: &dyn CommandRunner) -> Result<bool, Box<dyn Error>> {
+    let output = command_runner
+        .run_command_async("firewall-cmd", &["--query-panic"])
+        .await?;
 
     // firewall-cmd returns 0 if panic mode is on, 1 if off
     Ok(output.status.success())
 }
 
 /// Set panic mode on or off
-fn set_panic_mode(enable: bool, command_runner: &dyn CommandRunner) -> Result<(), Box<dyn Error>> {
+async fn set_panic_mode(
+    enable: bool,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
     let arg = if enable { "--panic-on" } else { "--panic-off" };
 
     debug!("Setting firewalld panic mode: {}", enable);
@@ -660,7 +672,9 @@ fn set_panic_mode(enable: bool, command_runner: &dyn CommandRunner) -> Result<()
     let privileged_cmd = wrap_privileged_command(&command, false);
 
     debug!("Running privileged command: {}", privileged_cmd);
-    let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    let output = command_runner
+        .run_command_with_timeout("sh", &["-c", &privileged_cmd], PRIVILEGED_COMMAND_TIMEOUT)
+        .await?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
@@ -816,6 +830,7 @@ pub async fn get_or_refresh_firewalld_cache() -> FirewalldCache {
 
 /// Refresh firewalld cache by calling firewall-cmd
 async fn refresh_firewalld_cache() -> FirewalldCache {
+    let command_runner = RealCommandRunner;
     let mut cache = FirewalldCache {
         zones: Vec::new(),
         active_zones: HashMap::new(),
@@ -827,9 +842,8 @@ async fn refresh_firewalld_cache() -> FirewalldCache {
     };
 
     // Get zones: firewall-cmd --get-zones
-    if let Ok(output) = TokioCommand::new("firewall-cmd")
-        .arg("--get-zones")
-        .output()
+    if let Ok(output) = command_runner
+        .run_command_async("firewall-cmd", &["--get-zones"])
         .await
     {
         if output.status.success() {
@@ -843,9 +857,8 @@ async fn refresh_firewalld_cache() -> FirewalldCache {
     }
 
     // Get active zones: firewall-cmd --get-active-zones
-    if let Ok(output) = TokioCommand::new("firewall-cmd")
-        .arg("--get-active-zones")
-        .output()
+    if let Ok(output) = command_runner
+        .run_command_async("firewall-cmd", &["--get-active-zones"])
         .await
     {
         if output.status.success() {
@@ -856,9 +869,8 @@ async fn refresh_firewalld_cache() -> FirewalldCache {
     }
 
     // Get panic mode: firewall-cmd --query-panic
-    if let Ok(output) = TokioCommand::new("firewall-cmd")
-        .arg("--query-panic")
-        .output()
+    if let Ok(output) = command_runner
+        .run_command_async("firewall-cmd", &["--query-panic"])
         .await
     {
         // firewall-cmd returns 0 if panic mode is on, 1 if off
@@ -992,12 +1004,15 @@ mod tests {
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_is_panic_mode_enabled() {
+    #[tokio::test]
+    async fn test_is_panic_mode_enabled() {
         let mock_runner_on = MockCommandRunner::new(true, "");
-        assert_eq!(is_panic_mode_enabled(&mock_runner_on).unwrap(), true);
+        assert_eq!(is_panic_mode_enabled(&mock_runner_on).await.unwrap(), true);
 
         let mock_runner_off = MockCommandRunner::new(false, "");
-        assert_eq!(is_panic_mode_enabled(&mock_runner_off).unwrap(), false);
+        assert_eq!(
+            is_panic_mode_enabled(&mock_runner_off).await.unwrap(),
+            false
+        );
     }
 }
@@ -0,0 +1,312 @@
+//! Joining hidden Wi-Fi networks.
+//!
+//! A hidden network does not announce its security, so the user picks it and
+//! we create a complete profile marked hidden. The backend then probes for the
+//! SSID on its own and autoconnects later like any other saved network.
+
+use crate::command::{CommandRunner, CONNECTION_COMMAND_TIMEOUT};
+use crate::constants::{ICON_LOCK, ICON_SIGNAL};
+use crate::{connman, iwd, wpa_supplicant, WifiBackend};
+use std::error::Error;
+use std::fmt;
+
+/// Security type of a hidden network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HiddenSecurity {
+    WpaPsk,
+    Sae,
+    Enterprise,
+    Owe,
+    Wep,
+    Open,
+}
+
+impl HiddenSecurity {
+    pub const ALL: [HiddenSecurity; 6] = [
+        Self::WpaPsk,
+        Self::Sae,
+        Self::Enterprise,
+        Self::Owe,
+        Self::Wep,
+        Self::Open,
+    ];
+
+    /// Whether the user has to enter a password or key.
+    pub fn needs_password(&self) -> bool {
+        matches!(self, Self::WpaPsk | Self::Sae | Self::Wep)
+    }
+
+    /// NetworkManager `wifi-sec.key-mgmt` value, `None` for open networks.
+    fn nm_key_mgmt(&self) -> Option<&'static str> {
+        match self {
+            Self::WpaPsk => Some("wpa-psk"),
+            Self::Sae => Some("sae"),
+            Self::Enterprise => Some("wpa-eap"),
+            Self::Owe => Some("owe"),
+            Self::Wep => Some("none"),
+            Self::Open => None,
+        }
+    }
+
+    /// ConnMan service security, `None` when ConnMan cannot join it hidden.
+    fn connman_security(&self) -> Option<&'static str> {
+        match self {
+            Self::WpaPsk | Self::Sae => Some("psk"),
+            Self::Wep => Some("wep"),
+            Self::Open => Some("none"),
+            Self::Owe | Self::Enterprise => None,
+        }
+    }
+
+    /// Security in the notation of the wpa_supplicant menu entries.
+    fn wpa_security(&self) -> &'static str {
+        match self {
+            Self::WpaPsk => "WPA2",
+            Self::Sae => "WPA3",
+            Self::Enterprise => "802.1X",
+            Self::Owe => "OWE",
+            Self::Wep => "WEP",
+            Self::Open => "--",
+        }
+    }
+
+    pub fn to_display_string(&self) -> String {
+        let icon = if *self == Self::Open {
+            ICON_SIGNAL
+        } else {
+            ICON_LOCK
+        };
+        format!("{icon} {self}")
+    }
+}
+
+impl fmt::Display for HiddenSecurity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WpaPsk => write!(f, "WPA/WPA2 Personal"),
+            Self::Sae => write!(f, "WPA3 Personal (SAE)"),
+            Self::Enterprise => write!(f, "WPA/WPA2/WPA3 Enterprise (802.1X)"),
+            Self::Owe => write!(f, "Enhanced Open (OWE)"),
+            Self::Wep => write!(f, "WEP"),
+            Self::Open => write!(f, "Open"),
+        }
+    }
+}
+
+/// Builds the `nmcli connection add` arguments for a hidden personal or open
+/// network. Enterprise profiles are built by the enterprise module.
+fn nm_connection_args(
+    ssid: &str,
+    security: HiddenSecurity,
+    password: Option<&str>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut args: Vec<String> = [
+        "connection",
+        "add",
+        "type",
+        "wifi",
+        "con-name",
+        ssid,
+        "ssid",
+        ssid,
+        "wifi.hidden",
+        "yes",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+
+    if let Some(key_mgmt) = security.nm_key_mgmt() {
+        args.extend(["wifi-sec.key-mgmt".to_string(), key_mgmt.to_string()]);
+    }
+
+    let password = match (security.needs_password(), password) {
+        (true, Some(password)) if !password.is_empty() => password,
+        (true, _) => return Err(format!("{security} needs a password").into()),
+        (false, _) => return Ok(args),
+    };
+
+    if security == HiddenSecurity::Wep {
+        args.extend(
+            [
+                "wifi-sec.wep-key-type",
+                "key",
+                "wifi-sec.wep-key0",
+                password,
+            ]
+            .iter()
+            .map(|arg| arg.to_string()),
+        );
+    } else {
+        args.extend(["wifi-sec.psk".to_string(), password.to_string()]);
+    }
+
+    Ok(args)
+}
+
+/// Renders the iwd network file for a hidden network with its extension.
+///
+/// iwd negotiates SAE and OWE itself, so WPA3 shares the `.psk` format and
+/// Enhanced Open the `.open` one. WEP is not supported by iwd.
+fn iwd_network_file(
+    security: HiddenSecurity,
+    password: Option<&str>,
+) -> Result<(&'static str, String), Box<dyn Error>> {
+    let settings = "[Settings]\nHidden=true\nAutoConnect=true\n";
+    match security {
+        HiddenSecurity::WpaPsk | HiddenSecurity::Sae => {
+            let password = password
+                .filter(|password| !password.is_empty())
+                .ok_or_else(|| format!("{security} needs a password"))?;
+            Ok((
+                "psk",
+                format!("[Security]\nPassphrase={password}\n\n{settings}"),
+            ))
+        }
+        HiddenSecurity::Owe | HiddenSecurity::Open => Ok(("open", settings.to_string())),
+        HiddenSecurity::Wep => Err("iwd does not support WEP networks".into()),
+        HiddenSecurity::Enterprise => {
+            Err("Enterprise networks are provisioned by the enterprise module".into())
+        }
+    }
+}
+
+/// Creates a hidden network profile with the Wi-Fi backend in use on
+/// `interface` and connects to it.
+pub async fn connect_hidden_wifi(
+    ssid: &str,
+    security: HiddenSecurity,
+    password: Option<&str>,
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if ssid.is_empty() {
+        return Err("No network name given".into());
+    }
+
+    match WifiBackend::detect(interface, command_runner).await {
+        Some(WifiBackend::NetworkManager) => {
+            let args = nm_connection_args(ssid, security, password)?;
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            if !command_runner
+                .run_command_async("nmcli", &args)
+                .await?
+                .status
+                .success()
+            {
+                return Ok(false);
+            }
+
+            let status = command_runner
+                .run_command_with_timeout(
+                    "nmcli",
+                    &["connection", "up", "id", ssid, "ifname", interface],
+                    CONNECTION_COMMAND_TIMEOUT,
+                )
+                .await?
+                .status;
+            Ok(status.success())
+        }
+        Some(WifiBackend::Connman) => {
+            let connman_security = security
+                .connman_security()
+                .ok_or_else(|| format!("ConnMan cannot join hidden {security} networks"))?;
+            let ssid = ssid.to_string();
+            let password = password.map(str::to_string);
+            connman::with_client(move |client| {
+                client.connect_hidden_wifi(&ssid, connman_security, password.as_deref())
+            })
+            .await
+            .ok_or("ConnMan stopped answering")?
+            .map_err(Into::into)
+        }
+        Some(WifiBackend::Iwd) => {
+            let (extension, content) = iwd_network_file(security, password)?;
+            iwd::install_state_file(
+                &iwd::network_file_name(ssid, extension),
+                &content,
+                command_runner,
+            )?;
+
+            let status = command_runner
+                .run_command_with_timeout(
+                    "iwctl",
+                    &["station", interface, "connect-hidden", ssid],
+                    CONNECTION_COMMAND_TIMEOUT,
+                )
+                .await?
+                .status;
+            Ok(status.success())
+        }
+        Some(WifiBackend::WpaSupplicant) => {
+            wpa_supplicant::connect_to_wpa_network(
+                interface,
+                ssid,
+                security.wpa_security(),
+                password,
+                true,
+                command_runner,
+            )
+            .await
+        }
+        None => Err("Hidden networks need NetworkManager, ConnMan, iwd or wpa_supplicant".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nm_connection_args_per_security() {
+        let joined = |security, password| {
+            nm_connection_args("Attic", security, password)
+                .unwrap()
+                .join(" ")
+        };
+
+        let sae = joined(HiddenSecurity::Sae, Some("s3cret pass"));
+        assert!(
+            sae.starts_with("connection add type wifi con-name Attic ssid Attic wifi.hidden yes")
+        );
+        assert!(sae.ends_with("wifi-sec.key-mgmt sae wifi-sec.psk s3cret pass"));
+
+        let wep = joined(HiddenSecurity::Wep, Some("0123456789"));
+        assert!(wep.ends_with(
+            "wifi-sec.key-mgmt none wifi-sec.wep-key-type key wifi-sec.wep-key0 0123456789"
+        ));
+
+        assert!(
+            joined(HiddenSecurity::Owe, None).ends_with("wifi.hidden yes wifi-sec.key-mgmt owe")
+        );
+        assert!(joined(HiddenSecurity::Open, None).ends_with("wifi.hidden yes"));
+    }
+
+    #[test]
+    fn test_nm_connection_args_requires_password() {
+        assert!(nm_connection_args("Attic", HiddenSecurity::WpaPsk, None).is_err());
+        assert!(nm_connection_args("Attic", HiddenSecurity::WpaPsk, Some("")).is_err());
+    }
+
+    #[test]
+    fn test_backend_security_names() {
+        assert_eq!(HiddenSecurity::Sae.connman_security(), Some("psk"));
+        assert_eq!(HiddenSecurity::Owe.connman_security(), None);
+        assert_eq!(HiddenSecurity::Sae.wpa_security(), "WPA3");
+        assert_eq!(HiddenSecurity::Open.wpa_security(), "--");
+    }
+
+    #[test]
+    fn test_iwd_network_file() {
+        let (extension, content) = iwd_network_file(HiddenSecurity::Sae, Some("pw")).unwrap();
+        assert_eq!(extension, "psk");
+        assert!(content.contains("Passphrase=pw"));
+        assert!(content.contains("Hidden=true"));
+
+        let (extension, content) = iwd_network_file(HiddenSecurity::Owe, None).unwrap();
+        assert_eq!(extension, "open");
+        assert!(content.contains("Hidden=true"));
+
+        assert!(iwd_network_file(HiddenSecurity::Wep, Some("key")).is_err());
+    }
+}
@@ -0,0 +1,756 @@
+//! Wi-Fi hotspot management.
+//!
+//! Starts an access point on the Wi-Fi interface through NetworkManager
+//! (`nmcli device wifi hotspot`), ConnMan tethering or iwd AP mode, shares its credentials and
+//! lists connected clients from the DHCP leases and the neighbour table.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::connman::{self, ConnmanClient};
+use crate::constants::{ACTION_TYPE_HOTSPOT, ICON_ANTENNA, ICON_CHECK, ICON_LIST, ICON_QR};
+use crate::format_entry;
+use crate::iwd;
+use crate::networkmanager::split_nmcli_fields;
+use crate::privilege::wrap_privileged_command;
+use crate::wifi_details::WifiBand;
+use crate::wifi_share::{self, WifiCredentials, WifiQrSecurity};
+use regex::Regex;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bridge ConnMan puts tethering clients on.
+pub const CONNMAN_TETHER_INTERFACE: &str = "tether";
+
+/// Name of the NetworkManager profile created for the hotspot.
+pub const HOTSPOT_CONNECTION: &str = "network-dmenu-hotspot";
+
+const PASSWORD_CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const PASSWORD_LENGTH: usize = 12;
+
+/// Hotspot action types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotspotAction {
+    Start,
+    Stop(String),
+    Share(String),
+    Clients(usize),
+}
+
+/// Backend running the hotspot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotspotBackend {
+    NetworkManager,
+    /// ConnMan Wi-Fi tethering, bridging clients on `tether`
+    Connman,
+    Iwd,
+}
+
+/// A running hotspot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotspotStatus {
+    pub backend: HotspotBackend,
+    pub ssid: String,
+    pub interface: String,
+    /// NetworkManager profile UUID
+    pub uuid: Option<String>,
+}
+
+/// Settings for a new hotspot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotspotSettings {
+    pub ssid: String,
+    pub password: String,
+    pub band: WifiBand,
+}
+
+/// A device connected to the hotspot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotspotClient {
+    pub mac: String,
+    pub ip: Option<String>,
+    pub hostname: Option<String>,
+}
+
+impl HotspotClient {
+    pub fn to_display_string(&self) -> String {
+        format!(
+            "{} {} ({})",
+            self.ip.as_deref().unwrap_or("-"),
+            self.hostname.as_deref().unwrap_or("unknown"),
+            self.mac
+        )
+    }
+}
+
+/// Convert hotspot action to display string
+pub fn hotspot_action_to_string(action: &HotspotAction) -> String {
+    match action {
+        HotspotAction::Start => format_entry(ACTION_TYPE_HOTSPOT, ICON_ANTENNA, "Start hotspot"),
+        HotspotAction::Stop(ssid) => format_entry(
+            ACTION_TYPE_HOTSPOT,
+            ICON_CHECK,
+            &format!("Stop hotspot {ssid}"),
+        ),
+        HotspotAction::Share(ssid) => format_entry(
+            ACTION_TYPE_HOTSPOT,
+            ICON_QR,
+            &format!("Share hotspot {ssid}"),
+        ),
+        HotspotAction::Clients(count) => format_entry(
+            ACTION_TYPE_HOTSPOT,
+            ICON_LIST,
+            &format!("Hotspot clients ({count})"),
+        ),
+    }
+}
+
+/// Returns the hotspot entries matching the current state.
+pub async fn get_hotspot_actions(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Vec<HotspotAction> {
+    match get_hotspot_status(interface, command_runner).await {
+        Some(status) => {
+            let clients = get_hotspot_clients(&status.interface, command_runner).await;
+            vec![
+                HotspotAction::Stop(status.ssid.clone()),
+                HotspotAction::Share(status.ssid),
+                HotspotAction::Clients(clients.len()),
+            ]
+        }
+        None => vec![HotspotAction::Start],
+    }
+}
+
+/// Finds the running hotspot, if any.
+pub async fn get_hotspot_status(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Option<HotspotStatus> {
+    if is_command_installed("nmcli") {
+        nm_hotspot_status(command_runner).await
+    } else if let Some(status) =
+        connman::with_client(|client| Ok(connman_hotspot_status(client))).await
+    {
+        status.ok().flatten()
+    } else if is_command_installed("iwctl") {
+        iwd_hotspot_status(interface, command_runner).await
+    } else {
+        None
+    }
+}
+
+async fn nm_hotspot_status(command_runner: &dyn CommandRunner) -> Option<HotspotStatus> {
+    let output = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "-t",
+                "-f",
+                "UUID,TYPE,DEVICE",
+                "connection",
+                "show",
+                "--active",
+            ],
+        )
+        .await
+        .ok()?;
+
+    for fields in String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(split_nmcli_fields)
+    {
+        let [uuid, connection_type, device] = fields.as_slice() else {
+            continue;
+        };
+        if connection_type != "802-11-wireless" {
+            continue;
+        }
+
+        let settings = command_runner
+            .run_command_async(
+                "nmcli",
+                &[
+                    "-t",
+                    "-f",
+                    "802-11-wireless.mode,802-11-wireless.ssid",
+                    "connection",
+                    "show",
+                    "uuid",
+                    uuid,
+                ],
+            )
+            .await
+            .ok()?;
+        let settings = parse_nm_settings(&String::from_utf8_lossy(&settings.stdout));
+        if settings.get("802-11-wireless.mode").map(String::as_str) == Some("ap") {
+            return Some(HotspotStatus {
+                backend: HotspotBackend::NetworkManager,
+                ssid: settings
+                    .get("802-11-wireless.ssid")
+                    .cloned()
+                    .unwrap_or_default(),
+                interface: device.clone(),
+                uuid: Some(uuid.clone()),
+            });
+        }
+    }
+
+    None
+}
+
+/// Parses `nmcli -t -f <settings> connection show <id>` into a map.
+fn parse_nm_settings(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.to_string(), split_nmcli_fields(value).join(":")))
+        .collect()
+}
+
+fn connman_hotspot_status(client: &ConnmanClient) -> Option<HotspotStatus> {
+    let technology = client.wifi_technology().ok()?;
+    if !technology.tethering {
+        return None;
+    }
+
+    Some(HotspotStatus {
+        backend: HotspotBackend::Connman,
+        ssid: technology.tethering_identifier.unwrap_or_default(),
+        interface: CONNMAN_TETHER_INTERFACE.to_string(),
+        uuid: None,
+    })
+}
+
+async fn iwd_hotspot_status(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Option<HotspotStatus> {
+    let output = command_runner
+        .run_command_async("iwctl", &["ap", interface, "show"])
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let ssid = parse_iwd_ap_show(&String::from_utf8_lossy(&output.stdout))?;
+    Some(HotspotStatus {
+        backend: HotspotBackend::Iwd,
+        ssid,
+        interface: interface.to_string(),
+        uuid: None,
+    })
+}
+
+/// Returns the SSID from `iwctl ap <iface> show` when the AP is started.
+fn parse_iwd_ap_show(output: &str) -> Option<String> {
+    let ansi = Regex::new(r"\x1B\[[0-?]*[ -/]*[@-~]").ok()?;
+    let output = ansi.replace_all(output, "");
+    let re = Regex::new(r"^\s*(Started|Name)\s+(.+?)\s*$").ok()?;
+
+    let mut started = false;
+    let mut name = None;
+    for caps in output.lines().filter_map(|line| re.captures(line)) {
+        match &caps[1] {
+            "Started" => started = &caps[2] == "yes",
+            _ => name = Some(caps[2].to_string()),
+        }
+    }
+
+    name.filter(|_| started)
+}
+
+/// Starts a WPA2 hotspot on `interface`.
+pub async fn start_hotspot(
+    interface: &str,
+    settings: &HotspotSettings,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if !(8..=63).contains(&settings.password.len()) {
+        return Err("Hotspot passwords need 8 to 63 characters".into());
+    }
+
+    if is_command_installed("nmcli") {
+        let band = settings.band.nm_band().ok_or("Unsupported hotspot band")?;
+        let output = command_runner.run_command(
+            "nmcli",
+            &[
+                "device",
+                "wifi",
+                "hotspot",
+                "ifname",
+                interface,
+                "con-name",
+                HOTSPOT_CONNECTION,
+                "ssid",
+                &settings.ssid,
+                "band",
+                band,
+                "password",
+                &settings.password,
+            ],
+        )?;
+        Ok(output.status.success())
+    } else if let Some(result) = connman::with_client({
+        let (ssid, password) = (settings.ssid.clone(), settings.password.clone());
+        // ConnMan picks the channel itself, so the band is not applied
+        move |client| client.start_tethering(&ssid, &password)
+    })
+    .await
+    {
+        result?;
+        Ok(true)
+    } else if is_command_installed("iwctl") {
+        start_iwd_hotspot(interface, settings, command_runner)
+    } else {
+        Ok(false)
+    }
+}
+
+fn start_iwd_hotspot(
+    interface: &str,
+    settings: &HotspotSettings,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if settings.ssid.contains('/') {
+        return Err("iwd hotspot names cannot contain '/'".into());
+    }
+
+    // The profile keeps the passphrase off the command line and sets the band
+    iwd::install_state_file(
+        &iwd_ap_profile_name(&settings.ssid),
+        &iwd_ap_profile(settings),
+        command_runner,
+    )?;
+
+    let status = command_runner
+        .run_command(
+            "iwctl",
+            &["device", interface, "set-property", "Mode", "ap"],
+        )?
+        .status;
+    if !status.success() {
+        return Ok(false);
+    }
+
+    let status = command_runner
+        .run_command("iwctl", &["ap", interface, "start-profile", &settings.ssid])?
+        .status;
+    if !status.success() {
+        let _ = command_runner.run_command(
+            "iwctl",
+            &["device", interface, "set-property", "Mode", "station"],
+        );
+    }
+    Ok(status.success())
+}
+
+fn iwd_ap_profile_name(ssid: &str) -> String {
+    format!("ap/{ssid}.ap")
+}
+
+/// Renders the iwd AP profile for the hotspot.
+fn iwd_ap_profile(settings: &HotspotSettings) -> String {
+    let channel = match settings.band {
+        WifiBand::Band5GHz => 36,
+        _ => 6,
+    };
+    format!(
+        "[Security]\nPassphrase={}\n\n[General]\nChannel={channel}\n",
+        settings.password
+    )
+}
+
+/// Stops the hotspot and gives the interface back to station mode.
+pub async fn stop_hotspot(
+    status: &HotspotStatus,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    match status.backend {
+        HotspotBackend::NetworkManager => {
+            let uuid = status.uuid.as_deref().ok_or("Hotspot has no profile")?;
+            let output =
+                command_runner.run_command("nmcli", &["connection", "down", "uuid", uuid])?;
+            Ok(output.status.success())
+        }
+        HotspotBackend::Connman => {
+            connman::with_client(|client| client.stop_tethering())
+                .await
+                .ok_or("ConnMan is not running")??;
+            Ok(true)
+        }
+        HotspotBackend::Iwd => {
+            let stopped = command_runner
+                .run_command("iwctl", &["ap", &status.interface, "stop"])?
+                .status
+                .success();
+            let station = command_runner
+                .run_command(
+                    "iwctl",
+                    &[
+                        "device",
+                        &status.interface,
+                        "set-property",
+                        "Mode",
+                        "station",
+                    ],
+                )?
+                .status
+                .success();
+            Ok(stopped && station)
+        }
+    }
+}
+
+/// Reads the SSID and password of the running hotspot.
+pub async fn hotspot_credentials(
+    status: &HotspotStatus,
+    command_runner: &dyn CommandRunner,
+) -> Result<WifiCredentials, Box<dyn Error>> {
+    match status.backend {
+        HotspotBackend::NetworkManager => {
+            let uuid = status.uuid.as_deref().ok_or("Hotspot has no profile")?;
+            wifi_share::nm_connection_credentials(uuid, command_runner)
+        }
+        HotspotBackend::Connman => {
+            let technology = connman::with_client(|client| client.wifi_technology())
+                .await
+                .ok_or("ConnMan is not running")??;
+            Ok(WifiCredentials {
+                ssid: status.ssid.clone(),
+                security: WifiQrSecurity::Wpa,
+                password: Some(
+                    technology
+                        .tethering_passphrase
+                        .ok_or("Tethering has no passphrase")?,
+                ),
+                hidden: false,
+            })
+        }
+        HotspotBackend::Iwd => {
+            let profile = Path::new(iwd::IWD_STATE_DIR).join(iwd_ap_profile_name(&status.ssid));
+            let command = format!(
+                "cat '{}'",
+                profile.display().to_string().replace('\'', r"'\''")
+            );
+            let privileged_cmd = wrap_privileged_command(&command, false);
+            let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+            if !output.status.success() {
+                return Err(format!("Failed to read {}", profile.display()).into());
+            }
+
+            let (password, _) =
+                wifi_share::parse_iwd_network_file(&String::from_utf8_lossy(&output.stdout));
+            Ok(WifiCredentials {
+                ssid: status.ssid.clone(),
+                security: WifiQrSecurity::Wpa,
+                password: Some(password.ok_or("Hotspot profile has no passphrase")?),
+                hidden: false,
+            })
+        }
+    }
+}
+
+/// Lists the clients of the hotspot on `interface`.
+///
+/// Addresses come from the neighbour table, names from the dnsmasq leases
+/// NetworkManager keeps for shared connections.
+pub async fn get_hotspot_clients(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Vec<HotspotClient> {
+    let neighbours = command_runner
+        .run_command_async("ip", &["-4", "neigh", "show", "dev", interface])
+        .await
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default();
+    let leases = fs::read_to_string(format!(
+        "/var/lib/NetworkManager/dnsmasq-{interface}.leases"
+    ))
+    .unwrap_or_default();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    merge_clients(&parse_neighbours(&neighbours), &parse_leases(&leases, now))
+}
+
+/// Parses `ip neigh show dev <iface>` into `(ip, mac)` pairs, keeping only
+/// neighbours that answered recently or are being confirmed.
+fn parse_neighbours(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let ip = parts.first()?;
+            let mac = parts
+                .iter()
+                .position(|part| *part == "lladdr")
+                .and_then(|i| parts.get(i + 1))?;
+            let state = parts.last()?;
+            matches!(*state, "REACHABLE" | "DELAY" | "PROBE" | "STALE")
+                .then(|| (ip.to_string(), mac.to_lowercase()))
+        })
+        .collect()
+}
+
+/// Parses dnsmasq leases (`<expiry> <mac> <ip> <hostname> <client-id>`),
+/// skipping the ones that expired before `now`. An expiry of 0 never expires.
+fn parse_leases(contents: &str, now: u64) -> Vec<HotspotClient> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let expiry: u64 = parts.first()?.parse().ok()?;
+            if expiry != 0 && expiry <= now {
+                return None;
+            }
+            let mac = parts.get(1)?;
+            let ip = parts.get(2)?;
+            let hostname = parts.get(3).filter(|name| **name != "*");
+            Some(HotspotClient {
+                mac: mac.to_lowercase(),
+                ip: Some(ip.to_string()),
+                hostname: hostname.map(|name| name.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Lists the live neighbours, named after their lease. Leases outlive the
+/// clients that left, so those without a neighbour are not listed.
+fn merge_clients(neighbours: &[(String, String)], leases: &[HotspotClient]) -> Vec<HotspotClient> {
+    neighbours
+        .iter()
+        .map(|(ip, mac)| HotspotClient {
+            mac: mac.clone(),
+            ip: Some(ip.clone()),
+            hostname: leases
+                .iter()
+                .find(|lease| lease.mac == *mac)
+                .and_then(|lease| lease.hostname.clone()),
+        })
+        .collect()
+}
+
+/// Generates a random hotspot password without look-alike characters.
+///
+/// Bytes past the last whole multiple of the charset length are rejected so
+/// every character is equally likely.
+pub fn generate_password() -> Result<String, Box<dyn Error>> {
+    let limit = 256 - 256 % PASSWORD_CHARSET.len();
+    let mut urandom = fs::File::open("/dev/urandom")?;
+    let mut password = String::with_capacity(PASSWORD_LENGTH);
+    let mut bytes = [0u8; PASSWORD_LENGTH];
+    while password.len() < PASSWORD_LENGTH {
+        urandom.read_exact(&mut bytes)?;
+        password.extend(
+            bytes
+                .iter()
+                .filter(|b| (**b as usize) < limit)
+                .map(|b| PASSWORD_CHARSET[*b as usize % PASSWORD_CHARSET.len()] as char)
+                .take(PASSWORD_LENGTH - password.len()),
+        );
+    }
+    Ok(password)
+}
+
+/// Default hotspot name, based on the hostname.
+pub fn default_ssid() -> String {
+    let hostname = fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+    match hostname.trim() {
+        "" => "network-dmenu".to_string(),
+        hostname => format!("{hostname}-hotspot"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    struct ScriptedCommandRunner {
+        responses: Vec<(Vec<&'static str>, &'static str)>,
+    }
+
+    impl CommandRunner for ScriptedCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command];
+            call.extend_from_slice(args);
+            let response = self
+                .responses
+                .iter()
+                .find(|(expected, _)| *expected == call)
+                .map(|(_, stdout)| *stdout);
+            Ok(Output {
+                status: ExitStatus::from_raw(if response.is_some() { 0 } else { 1 << 8 }),
+                stdout: response.unwrap_or_default().as_bytes().to_vec(),
+                stderr: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn test_hotspot_action_to_string() {
+        assert!(hotspot_action_to_string(&HotspotAction::Start).contains("Start hotspot"));
+        let stop = hotspot_action_to_string(&HotspotAction::Stop("laptop".to_string()));
+        assert!(stop.starts_with("hotspot"));
+        assert!(stop.contains(ICON_CHECK));
+        assert!(stop.contains("Stop hotspot laptop"));
+        assert!(hotspot_action_to_string(&HotspotAction::Clients(2)).contains("clients (2)"));
+    }
+
+    #[tokio::test]
+    async fn test_nm_hotspot_status() {
+        let runner = ScriptedCommandRunner {
+            responses: vec![
+                (
+                    vec![
+                        "nmcli",
+                        "-t",
+                        "-f",
+                        "UUID,TYPE,DEVICE",
+                        "connection",
+                        "show",
+                        "--active",
+                    ],
+                    "aaaa:802-3-ethernet:eth0\nbbbb:802-11-wireless:wlan0\n",
+                ),
+                (
+                    vec![
+                        "nmcli",
+                        "-t",
+                        "-f",
+                        "802-11-wireless.mode,802-11-wireless.ssid",
+                        "connection",
+                        "show",
+                        "uuid",
+                        "bbbb",
+                    ],
+                    "802-11-wireless.mode:ap\n802-11-wireless.ssid:my\\:laptop\n",
+                ),
+            ],
+        };
+
+        let status = nm_hotspot_status(&runner).await.unwrap();
+        assert_eq!(status.backend, HotspotBackend::NetworkManager);
+        assert_eq!(status.ssid, "my:laptop");
+        assert_eq!(status.interface, "wlan0");
+        assert_eq!(status.uuid.as_deref(), Some("bbbb"));
+    }
+
+    #[tokio::test]
+    async fn test_nm_hotspot_status_ignores_station_mode() {
+        let runner = ScriptedCommandRunner {
+            responses: vec![
+                (
+                    vec![
+                        "nmcli",
+                        "-t",
+                        "-f",
+                        "UUID,TYPE,DEVICE",
+                        "connection",
+                        "show",
+                        "--active",
+                    ],
+                    "bbbb:802-11-wireless:wlan0\n",
+                ),
+                (
+                    vec![
+                        "nmcli",
+                        "-t",
+                        "-f",
+                        "802-11-wireless.mode,802-11-wireless.ssid",
+                        "connection",
+                        "show",
+                        "uuid",
+                        "bbbb",
+                    ],
+                    "802-11-wireless.mode:infrastructure\n802-11-wireless.ssid:Home\n",
+                ),
+            ],
+        };
+
+        assert!(nm_hotspot_status(&runner).await.is_none());
+    }
+
+    #[test]
+    fn test_parse_iwd_ap_show() {
+        let output = "                     Access Point Interface: wlan0\n\
+            ----------------------------------------------------------\n\
+              Settable  Property              Value\n\
+            ----------------------------------------------------------\n\
+                        Started               yes\n\
+                        Name                  laptop-hotspot\n\
+                        Frequency             2437\n";
+        assert_eq!(
+            parse_iwd_ap_show(output),
+            Some("laptop-hotspot".to_string())
+        );
+        assert_eq!(parse_iwd_ap_show(&output.replace("yes", "no")), None);
+    }
+
+    #[test]
+    fn test_clients_from_neighbours_and_leases() {
+        let neighbours = parse_neighbours(
+            "10.42.0.23 lladdr AA:BB:CC:DD:EE:01 REACHABLE\n\
+             10.42.0.99 INCOMPLETE\n\
+             10.42.0.40 lladdr aa:bb:cc:dd:ee:02 FAILED\n\
+             10.42.0.41 lladdr aa:bb:cc:dd:ee:04 STALE\n",
+        );
+        let leases = parse_leases(
+            "1760000000 aa:bb:cc:dd:ee:01 10.42.0.23 pixel-7 01:aa:bb:cc:dd:ee:01\n\
+             0 aa:bb:cc:dd:ee:03 10.42.0.57 * *\n\
+             1750000000 aa:bb:cc:dd:ee:05 10.42.0.60 old-phone *\n",
+            1755000000,
+        );
+
+        let clients = merge_clients(&neighbours, &leases);
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients[0].hostname.as_deref(), Some("pixel-7"));
+        assert_eq!(
+            clients[0].to_display_string(),
+            "10.42.0.23 pixel-7 (aa:bb:cc:dd:ee:01)"
+        );
+        assert_eq!(clients[1].ip.as_deref(), Some("10.42.0.41"));
+        assert_eq!(clients[1].hostname, None);
+    }
+
+    #[test]
+    fn test_iwd_ap_profile() {
+        let settings = HotspotSettings {
+            ssid: "laptop".to_string(),
+            password: "correct-horse".to_string(),
+            band: WifiBand::Band5GHz,
+        };
+        assert_eq!(iwd_ap_profile_name(&settings.ssid), "ap/laptop.ap");
+        assert_eq!(
+            iwd_ap_profile(&settings),
+            "[Security]\nPassphrase=correct-horse\n\n[General]\nChannel=36\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_hotspot_rejects_short_password() {
+        let runner = ScriptedCommandRunner { responses: vec![] };
+        let settings = HotspotSettings {
+            ssid: "laptop".to_string(),
+            password: "short".to_string(),
+            band: WifiBand::Band2_4GHz,
+        };
+        assert!(start_hotspot("wlan0", &settings, &runner).await.is_err());
+    }
+
+    #[test]
+    fn test_generate_password() {
+        let password = generate_password().unwrap();
+        assert_eq!(password.len(), PASSWORD_LENGTH);
+        assert!(password.bytes().all(|b| PASSWORD_CHARSET.contains(&b)));
+    }
+}
@@ -1,27 +1,32 @@
-use crate::command::{read_output_lines, CommandRunner};
+use crate::command::{read_output_lines, CommandRunner, CONNECTION_COMMAND_TIMEOUT};
 use crate::constants::{ICON_CHECK, ICON_SIGNAL, SECURITY_OPEN, SECURITY_UNKNOWN};
+use crate::privilege::install_file_privileged;
 use crate::utils::{convert_network_strength, prompt_for_password};
 use crate::{parse_wifi_action, WifiAction};
 use regex::Regex;
 use std::error::Error;
 use std::io::{BufRead, BufReader};
+use std::path::Path;
 
 /// Retrieves available Wi-Fi networks using IWD.
-pub fn get_iwd_networks(
+pub async fn get_iwd_networks(
     interface: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<Vec<WifiAction>, Box<dyn Error>> {
     let mut actions = Vec::new();
 
-    if let Some(networks) = fetch_iwd_networks(interface, command_runner)? {
+    let networks = fetch_iwd_networks(interface, command_runner).await?;
+    if let Some(networks) = networks {
         let has_connected = networks.iter().any(|network| network.starts_with('>'));
 
         if !has_connected {
-            let rescan_output =
-                command_runner.run_command("iwctl", &["station", interface, "scan"])?;
+            let rescan_output = command_runner
+                .run_command_async("iwctl", &["station", interface, "scan"])
+                .await?;
 
             if rescan_output.status.success() {
-                if let Some(rescan_networks) = fetch_iwd_networks(interface, command_runner)? {
+                let rescan_networks = fetch_iwd_networks(interface, command_runner).await?;
+                if let Some(rescan_networks) = rescan_networks {
                     parse_iwd_networks(&mut actions, rescan_networks)?;
                 }
             }
@@ -34,11 +39,13 @@ pub fn get_iwd_networks(
 }
 
 /// Fetches raw Wi-Fi network data from IWD.
-fn fetch_iwd_networks(
+async fn fetch_iwd_networks(
     interface: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<Option<Vec<String>>, Box<dyn Error>> {
-    let output = command_runner.run_command("iwctl", &["station", interface, "get-networks"])?;
+    let output = command_runner
+        .run_command_async("iwctl", &["station", interface, "get-networks"])
+        .await?;
 
     if output.status.success() {
         let reader = read_output_lines(&output)?;
@@ -87,8 +94,36 @@ fn parse_iwd_networks(
     Ok(())
 }
 
+/// Directory where iwd keeps its network configuration files.
+pub const IWD_STATE_DIR: &str = "/var/lib/iwd";
+
+/// Name of the iwd network file for an SSID, e.g. `home.psk`.
+pub fn network_file_name(ssid: &str, extension: &str) -> String {
+    let plain = ssid
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == ' ');
+
+    if plain {
+        format!("{ssid}.{extension}")
+    } else {
+        // iwd hex-encodes names with other characters
+        let hex: String = ssid.bytes().map(|b| format!("{b:02x}")).collect();
+        format!("={hex}.{extension}")
+    }
+}
+
+/// Installs `content` as `file_name` under the iwd state directory, as root.
+pub fn install_state_file(
+    file_name: &str,
+    content: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
+    let target = Path::new(IWD_STATE_DIR).join(file_name);
+    install_file_privileged(&target, content, 0o600, command_runner)
+}
+
 /// Connects to a Wi-Fi network using IWD.
-pub fn connect_to_iwd_wifi(
+pub async fn connect_to_iwd_wifi(
     interface: &str,
     action: &str,
     hidden: bool,
@@ -99,19 +134,19 @@ pub fn connect_to_iwd_wifi(
     #[cfg(debug_assertions)]
     println!("Connecting to Wi-Fi network: {ssid} with security {security}");
 
-    if is_known_network(ssid, command_runner)?
+    if is_known_network(ssid, command_runner).await?
         || security == SECURITY_OPEN
         || security == SECURITY_UNKNOWN
     {
-        attempt_connection(interface, ssid, hidden, None, command_runner)
+        attempt_connection(interface, ssid, hidden, None, command_runner).await
     } else {
         let password = prompt_for_password(ssid)?;
-        attempt_connection(interface, ssid, hidden, Some(&password), command_runner)
+        attempt_connection(interface, ssid, hidden, Some(&password), command_runner).await
     }
 }
 
 /// Attempts to connect to a Wi-Fi network, optionally using a password.
-fn attempt_connection(
+pub(crate) async fn attempt_connection(
     interface: &str,
     ssid: &str,
     hidden: bool,
@@ -130,7 +165,10 @@ fn attempt_connection(
         command_args.push(pass);
     }
 
-    let status = command_runner.run_command("iwctl", &command_args)?.status;
+    let status = command_runner
+        .run_command_with_timeout("iwctl", &command_args, CONNECTION_COMMAND_TIMEOUT)
+        .await?
+        .status;
 
     if status.success() {
         Ok(true)
@@ -142,22 +180,29 @@ fn attempt_connection(
 }
 
 /// Disconnects from a Wi-Fi network.
-pub fn disconnect_iwd_wifi(
+pub async fn disconnect_iwd_wifi(
     interface: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
     let status = command_runner
-        .run_command("iwctl", &["station", interface, "disconnect"])?
+        .run_command_with_timeout(
+            "iwctl",
+            &["station", interface, "disconnect"],
+            CONNECTION_COMMAND_TIMEOUT,
+        )
+        .await?
         .status;
     Ok(status.success())
 }
 
 /// Checks if a Wi-Fi network is known (i.e., previously connected).
-pub fn is_known_network(
+pub async fn is_known_network(
     ssid: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
-    let output = command_runner.run_command("iwctl", &["known-networks", "list"])?;
+    let output = command_runner
+        .run_command_async("iwctl", &["known-networks", "list"])
+        .await?;
     if output.status.success() {
         let reader = BufReader::new(output.stdout.as_slice());
         let ssid_pattern = format!(r"\b{}\b", regex::escape(ssid));
@@ -209,8 +254,8 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_get_iwd_networks_success() {
+    #[tokio::test]
+    async fn test_get_iwd_networks_success() {
         let stdout = b"Available networks\n------\n\n> TestNetwork1       psk\n  TestNetwork2       psk    ****\n";
         let output = Output {
             status: ExitStatus::from_raw(0),
@@ -220,15 +265,15 @@ mod tests {
 
         let mock_runner =
             MockCommandRunner::new("iwctl", &["station", "wlan0", "get-networks"], output);
-        let result = get_iwd_networks("wlan0", &mock_runner);
+        let result = get_iwd_networks("wlan0", &mock_runner).await;
 
         assert!(result.is_ok());
         let networks = result.unwrap();
         assert!(!networks.is_empty());
     }
 
-    #[test]
-    fn test_get_iwd_networks_command_failure() {
+    #[tokio::test]
+    async fn test_get_iwd_networks_command_failure() {
         let output = Output {
             status: ExitStatus::from_raw(1),
             stdout: vec![],
@@ -237,15 +282,15 @@ mod tests {
 
         let mock_runner =
             MockCommandRunner::new("iwctl", &["station", "wlan0", "get-networks"], output);
-        let result = get_iwd_networks("wlan0", &mock_runner);
+        let result = get_iwd_networks("wlan0", &mock_runner).await;
 
         assert!(result.is_ok());
         let networks = result.unwrap();
         assert!(networks.is_empty());
     }
 
-    #[test]
-    fn test_disconnect_iwd_wifi_success() {
+    #[tokio::test]
+    async fn test_disconnect_iwd_wifi_success() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -255,8 +300,14 @@ mod tests {
         let mock_runner =
             MockCommandRunner::new("iwctl", &["station", "wlan0", "disconnect"], output);
 
-        let result = disconnect_iwd_wifi("wlan0", &mock_runner);
+        let result = disconnect_iwd_wifi("wlan0", &mock_runner).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
+
+    #[test]
+    fn test_network_file_name() {
+        assert_eq!(network_file_name("Home", "psk"), "Home.psk");
+        assert_eq!(network_file_name("Café", "open"), "=436166c3a9.open");
+    }
 }
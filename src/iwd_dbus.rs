@@ -0,0 +1,782 @@
+//! iwd D-Bus backend.
+//!
+//! Talks to `net.connman.iwd` directly instead of scraping `iwctl` tables.
+//! Objects are discovered through the ObjectManager; Wi-Fi connections run
+//! with a passphrase Agent registered on the same bus connection, so iwd asks
+//! us for secrets only when it has none saved. The iwctl functions in
+//! [`crate::iwd`] remain the fallback when iwd is not reachable on the bus.
+//!
+//! The bus can be overridden with `NETWORK_DMENU_IWD_BUS`: `system` (default),
+//! `session`, or a full D-Bus address, which is how the backend is exercised
+//! against a fake iwd in tests.
+
+use crate::networkmanager::wifi_network_action;
+use crate::nm_dbus::{call_serving_agent, open_private_connection};
+use crate::utils::prompt_for_password;
+use crate::{parse_wifi_action, WifiAction};
+use dbus::arg::{prop_cast, PropMap};
+use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+use dbus::blocking::{Connection, Proxy};
+use dbus::message::MessageType;
+use dbus::strings::ErrorName;
+use dbus::{Message, Path};
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+pub const IWD_BUS_NAME: &str = "net.connman.iwd";
+pub const IWD_PATH: &str = "/net/connman/iwd";
+
+const OBJECT_MANAGER_IFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const DEVICE_IFACE: &str = "net.connman.iwd.Device";
+const STATION_IFACE: &str = "net.connman.iwd.Station";
+const NETWORK_IFACE: &str = "net.connman.iwd.Network";
+const KNOWN_NETWORK_IFACE: &str = "net.connman.iwd.KnownNetwork";
+const AGENT_MANAGER_IFACE: &str = "net.connman.iwd.AgentManager";
+const AGENT_IFACE: &str = "net.connman.iwd.Agent";
+const AGENT_CANCELED: &str = "net.connman.iwd.Agent.Error.Canceled";
+
+/// Object path our passphrase agent is registered at.
+pub const AGENT_PATH: &str = "/org/network_dmenu/iwd_agent";
+
+/// Environment variable used to pick the bus iwd is reached on.
+pub const IWD_BUS_ENV: &str = "NETWORK_DMENU_IWD_BUS";
+
+const DBUS_CALL_TIMEOUT: Duration = Duration::from_secs(5);
+/// iwd only answers `Connect` once associated, including the time spent in
+/// the passphrase prompt.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type ManagedObjects = HashMap<Path<'static>, HashMap<String, PropMap>>;
+
+/// A network seen by a station, strongest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IwdNetwork {
+    pub path: String,
+    pub name: String,
+    /// `open`, `psk`, `8021x` or `wep`
+    pub security: String,
+    /// Signal strength in 100 * dBm
+    pub signal: i16,
+    pub connected: bool,
+}
+
+/// A network iwd has saved credentials for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IwdKnownNetwork {
+    pub path: String,
+    pub name: String,
+    pub security: String,
+    pub hidden: bool,
+    pub autoconnect: bool,
+}
+
+/// Blocking client for the iwd D-Bus API.
+pub struct IwdDbusClient {
+    connection: Connection,
+}
+
+impl IwdDbusClient {
+    /// Connects to the bus selected by `NETWORK_DMENU_IWD_BUS`, returning `None`
+    /// when iwd is not running there.
+    pub fn connect() -> Option<Self> {
+        let bus = std::env::var(IWD_BUS_ENV).unwrap_or_else(|_| "system".to_string());
+        let connection = match bus.as_str() {
+            "system" => Connection::new_system(),
+            "session" => Connection::new_session(),
+            address => open_private_connection(address),
+        };
+
+        match connection {
+            Ok(connection) => {
+                let client = Self { connection };
+                if client.is_available() {
+                    Some(client)
+                } else {
+                    debug!("iwd is not on the {} bus", bus);
+                    None
+                }
+            }
+            Err(e) => {
+                debug!("Failed to connect to the {} bus: {}", bus, e);
+                None
+            }
+        }
+    }
+
+    /// Wraps an existing bus connection.
+    pub fn from_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    /// Checks whether iwd owns its well-known name on this bus.
+    pub fn is_available(&self) -> bool {
+        let proxy = self.connection.with_proxy(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            DBUS_CALL_TIMEOUT,
+        );
+        proxy
+            .method_call::<(bool,), _, _, _>(
+                "org.freedesktop.DBus",
+                "NameHasOwner",
+                (IWD_BUS_NAME,),
+            )
+            .map(|(owned,)| owned)
+            .unwrap_or(false)
+    }
+
+    fn proxy<'a>(&'a self, path: &'a str) -> Proxy<'a, &'a Connection> {
+        self.connection
+            .with_proxy(IWD_BUS_NAME, path, DBUS_CALL_TIMEOUT)
+    }
+
+    fn managed_objects(&self) -> Result<ManagedObjects, dbus::Error> {
+        let (objects,): (ManagedObjects,) =
+            self.proxy("/")
+                .method_call(OBJECT_MANAGER_IFACE, "GetManagedObjects", ())?;
+        Ok(objects)
+    }
+
+    /// Returns `(station path, interface name)` for every device in station mode.
+    pub fn stations(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let mut stations: Vec<(String, String)> = self
+            .managed_objects()?
+            .iter()
+            .filter(|(_, interfaces)| interfaces.contains_key(STATION_IFACE))
+            .map(|(path, interfaces)| {
+                let name = interfaces
+                    .get(DEVICE_IFACE)
+                    .and_then(|device| prop_cast::<String>(device, "Name"))
+                    .cloned()
+                    .unwrap_or_default();
+                (path.to_string(), name)
+            })
+            .collect();
+        stations.sort();
+        Ok(stations)
+    }
+
+    /// Finds the station for `interface`, or the first one when the name is unknown.
+    fn station(&self, interface: Option<&str>) -> Result<String, Box<dyn Error>> {
+        let stations = self.stations()?;
+        interface
+            .and_then(|name| stations.iter().find(|(_, iface)| iface == name))
+            .or_else(|| stations.first())
+            .map(|(path, _)| path.clone())
+            .ok_or_else(|| "No Wi-Fi station managed by iwd".into())
+    }
+
+    /// Scans and waits until the station is done, so the results are fresh.
+    pub fn scan(&self, station: &str) -> Result<(), Box<dyn Error>> {
+        let proxy = self.proxy(station);
+        proxy.method_call::<(), _, _, _>(STATION_IFACE, "Scan", ())?;
+
+        let started = Instant::now();
+        while started.elapsed() < SCAN_TIMEOUT {
+            let scanning: bool = proxy.get(STATION_IFACE, "Scanning")?;
+            if !scanning {
+                break;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Lists the networks a station sees, strongest first.
+    pub fn networks(&self, interface: Option<&str>) -> Result<Vec<IwdNetwork>, Box<dyn Error>> {
+        let station = self.station(interface)?;
+        let (ordered,): (Vec<(Path<'static>, i16)>,) =
+            self.proxy(&station)
+                .method_call(STATION_IFACE, "GetOrderedNetworks", ())?;
+        let objects = self.managed_objects()?;
+
+        Ok(ordered
+            .into_iter()
+            .filter_map(|(path, signal)| {
+                let network = objects.get(&path)?.get(NETWORK_IFACE)?;
+                let string = |key: &str| {
+                    prop_cast::<String>(network, key)
+                        .cloned()
+                        .unwrap_or_default()
+                };
+                Some(IwdNetwork {
+                    path: path.to_string(),
+                    name: string("Name"),
+                    security: string("Type"),
+                    signal,
+                    connected: prop_cast::<bool>(network, "Connected")
+                        .copied()
+                        .unwrap_or(false),
+                })
+            })
+            .collect())
+    }
+
+    /// Builds the Wi-Fi menu entries for a station, rescanning when it is not
+    /// connected.
+    pub fn wifi_networks(
+        &self,
+        interface: Option<&str>,
+    ) -> Result<Vec<WifiAction>, Box<dyn Error>> {
+        let mut networks = self.networks(interface)?;
+        if !networks.iter().any(|network| network.connected) {
+            self.scan(&self.station(interface)?)?;
+            networks = self.networks(interface)?;
+        }
+
+        // Stable sort keeps iwd's strongest-first order after the connected one
+        networks.sort_by_key(|network| !network.connected);
+
+        Ok(networks
+            .iter()
+            .map(|network| {
+                wifi_network_action(
+                    network.connected,
+                    &network.name,
+                    &network.security,
+                    &signal_to_stars(network.signal),
+                )
+            })
+            .collect())
+    }
+
+    /// Lists the networks iwd has saved.
+    pub fn known_networks(&self) -> Result<Vec<IwdKnownNetwork>, Box<dyn Error>> {
+        let mut known: Vec<IwdKnownNetwork> = self
+            .managed_objects()?
+            .iter()
+            .filter_map(|(path, interfaces)| {
+                let network = interfaces.get(KNOWN_NETWORK_IFACE)?;
+                let string = |key: &str| {
+                    prop_cast::<String>(network, key)
+                        .cloned()
+                        .unwrap_or_default()
+                };
+                let flag = |key: &str, default: bool| {
+                    prop_cast::<bool>(network, key).copied().unwrap_or(default)
+                };
+                Some(IwdKnownNetwork {
+                    path: path.to_string(),
+                    name: string("Name"),
+                    security: string("Type"),
+                    hidden: flag("Hidden", false),
+                    autoconnect: flag("AutoConnect", true),
+                })
+            })
+            .collect();
+        known.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(known)
+    }
+
+    fn known_network(&self, name: &str) -> Result<IwdKnownNetwork, Box<dyn Error>> {
+        self.known_networks()?
+            .into_iter()
+            .find(|network| network.name == name)
+            .ok_or_else(|| format!("Known network {} not found", name).into())
+    }
+
+    /// Checks whether iwd has saved credentials for `ssid`.
+    pub fn is_known_network(&self, ssid: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self
+            .known_networks()?
+            .iter()
+            .any(|network| network.name == ssid))
+    }
+
+    /// Removes a saved network.
+    pub fn forget_known_network(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let network = self.known_network(name)?;
+        self.proxy(&network.path)
+            .method_call::<(), _, _, _>(KNOWN_NETWORK_IFACE, "Forget", ())?;
+        Ok(())
+    }
+
+    /// Enables or disables autoconnect for a saved network.
+    pub fn set_autoconnect(&self, name: &str, enabled: bool) -> Result<(), Box<dyn Error>> {
+        let network = self.known_network(name)?;
+        self.proxy(&network.path)
+            .set(KNOWN_NETWORK_IFACE, "AutoConnect", enabled)?;
+        Ok(())
+    }
+
+    /// Connects to the Wi-Fi network selected in the menu.
+    pub fn connect_wifi(
+        &self,
+        action: &str,
+        interface: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let (ssid, _) = parse_wifi_action(action)?;
+        self.connect_network(ssid, interface, None)
+    }
+
+    /// Connects to a visible network. iwd asks the agent for a passphrase
+    /// when it has none saved; `passphrase` answers that request, otherwise
+    /// the user is prompted.
+    pub fn connect_network(
+        &self,
+        ssid: &str,
+        interface: Option<&str>,
+        passphrase: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let network = self
+            .networks(interface)?
+            .into_iter()
+            .find(|network| network.name == ssid)
+            .ok_or_else(|| format!("Network {} is not in range", ssid))?;
+
+        let call = Message::new_method_call(IWD_BUS_NAME, network.path, NETWORK_IFACE, "Connect")?;
+        self.call_with_agent(call, ssid, passphrase)
+    }
+
+    /// Connects to a hidden network by name.
+    pub fn connect_hidden(
+        &self,
+        ssid: &str,
+        interface: Option<&str>,
+        passphrase: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let station = self.station(interface)?;
+        let call =
+            Message::new_method_call(IWD_BUS_NAME, station, STATION_IFACE, "ConnectHiddenNetwork")?
+                .append1(ssid);
+        self.call_with_agent(call, ssid, passphrase)
+    }
+
+    /// Disconnects a station.
+    pub fn disconnect(&self, interface: Option<&str>) -> Result<bool, Box<dyn Error>> {
+        let station = self.station(interface)?;
+        self.proxy(&station)
+            .method_call::<(), _, _, _>(STATION_IFACE, "Disconnect", ())?;
+        Ok(true)
+    }
+
+    /// Sends `call` with our agent registered, answering agent requests until
+    /// iwd replies. iwd errors such as a wrong passphrase map to `Ok(false)`.
+    fn call_with_agent(
+        &self,
+        call: Message,
+        ssid: &str,
+        passphrase: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let manager = self.proxy(IWD_PATH);
+        manager.method_call::<(), _, _, _>(
+            AGENT_MANAGER_IFACE,
+            "RegisterAgent",
+            (Path::from(AGENT_PATH),),
+        )?;
+
+        let mut passphrase = passphrase.map(str::to_string);
+        let result = call_serving_agent(&self.connection, call, CONNECT_TIMEOUT, |message| {
+            agent_reply(message, ssid, &mut passphrase)
+        });
+
+        let _ = manager.method_call::<(), _, _, _>(
+            AGENT_MANAGER_IFACE,
+            "UnregisterAgent",
+            (Path::from(AGENT_PATH),),
+        );
+
+        match result? {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                debug!("iwd failed to connect to {}: {}", ssid, e);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Answers a call to our agent, `None` for unrelated messages.
+///
+/// Only passphrases are handled; enterprise credentials are provisioned by
+/// the enterprise module, so those requests are canceled.
+fn agent_reply(message: &Message, ssid: &str, passphrase: &mut Option<String>) -> Option<Message> {
+    if message.msg_type() != MessageType::MethodCall
+        || message.path().as_deref() != Some(AGENT_PATH)
+        || message.interface().as_deref() != Some(AGENT_IFACE)
+    {
+        return None;
+    }
+
+    let canceled = || message.error(&ErrorName::from(AGENT_CANCELED), c"Canceled");
+    let reply = match message.member().as_deref() {
+        Some("RequestPassphrase") => {
+            let answer = match passphrase.take() {
+                Some(passphrase) => Ok(passphrase),
+                None => prompt_for_password(ssid),
+            };
+            match answer {
+                Ok(answer) if !answer.is_empty() => message.method_return().append1(answer),
+                _ => canceled(),
+            }
+        }
+        Some("Release") | Some("Cancel") => message.method_return(),
+        _ => canceled(),
+    };
+    Some(reply)
+}
+
+/// Runs `f` against iwd on a blocking thread.
+///
+/// Returns `None` when iwd is not reachable over D-Bus, so callers can fall
+/// back to iwctl. Errors are stringified to cross the thread boundary.
+pub async fn with_client<T, F>(f: F) -> Option<Result<T, String>>
+where
+    T: Send + 'static,
+    F: FnOnce(&IwdDbusClient) -> Result<T, Box<dyn Error>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let client = IwdDbusClient::connect()?;
+        Some(f(&client).map_err(|e| e.to_string()))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Converts iwd's signal strength (100 * dBm) to iwctl's `****` bars.
+pub fn signal_to_stars(signal: i16) -> String {
+    let bars = match signal {
+        s if s >= -6000 => 4,
+        s if s >= -6700 => 3,
+        s if s >= -7500 => 2,
+        _ => 1,
+    };
+    "*".repeat(bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::{path, start_test_bus, wait_for_service, TestBus};
+    use dbus_crossroads::{Context, Crossroads, MethodErr};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_signal_to_stars() {
+        assert_eq!(signal_to_stars(-4500), "****");
+        assert_eq!(signal_to_stars(-6500), "***");
+        assert_eq!(signal_to_stars(-7000), "**");
+        assert_eq!(signal_to_stars(-9000), "*");
+    }
+
+    #[test]
+    fn test_agent_reply_uses_preset_passphrase_once() {
+        let mut request = Message::new_method_call(
+            "net.connman.iwd.test",
+            AGENT_PATH,
+            AGENT_IFACE,
+            "RequestPassphrase",
+        )
+        .unwrap()
+        .append1(path("/net/connman/iwd/0/3/4c6962726172795f70736b"));
+        // Received messages carry a serial the reply refers to
+        request.set_serial(1);
+
+        let mut passphrase = Some("correct horse".to_string());
+        let reply = agent_reply(&request, "Library", &mut passphrase).unwrap();
+        assert_eq!(reply.msg_type(), MessageType::MethodReturn);
+        assert_eq!(reply.read1::<&str>().unwrap(), "correct horse");
+        assert!(passphrase.is_none());
+
+        let other =
+            Message::new_method_call("net.connman.iwd.test", "/elsewhere", AGENT_IFACE, "Release")
+                .unwrap();
+        assert!(agent_reply(&other, "Library", &mut passphrase).is_none());
+    }
+
+    const STATION: &str = "/net/connman/iwd/0/3";
+    const HOME: &str = "/net/connman/iwd/0/3/486f6d65_psk";
+    const CAFE: &str = "/net/connman/iwd/0/3/43616665_open";
+    const LIBRARY: &str = "/net/connman/iwd/0/3/4c696272617279_psk";
+    const KNOWN_HOME: &str = "/net/connman/iwd/486f6d65_psk";
+
+    struct FakeStation {
+        name: String,
+    }
+
+    struct FakeNetwork {
+        name: String,
+        kind: String,
+        connected: bool,
+        /// Passphrase the agent must provide, `None` for saved or open networks
+        passphrase: Option<String>,
+    }
+
+    struct FakeKnownNetwork {
+        name: String,
+        kind: String,
+        autoconnect: bool,
+    }
+
+    /// Unique name and path of the agent registered with the fake iwd.
+    type AgentSlot = Arc<Mutex<Option<(String, Path<'static>)>>>;
+
+    /// Asks the registered agent for a passphrase over a connection of our own.
+    fn request_passphrase(address: &str, agent: &AgentSlot, network: &str) -> Option<String> {
+        let (name, agent_path) = agent.lock().unwrap().clone()?;
+        let connection = open_private_connection(address).ok()?;
+        let (passphrase,): (String,) = connection
+            .with_proxy(name, agent_path, DBUS_CALL_TIMEOUT)
+            .method_call(AGENT_IFACE, "RequestPassphrase", (path(network),))
+            .ok()?;
+        Some(passphrase)
+    }
+
+    /// Serves a minimal iwd with one station, three networks of which one is
+    /// known, and an agent manager.
+    fn serve_fake_iwd(address: String) {
+        std::thread::spawn(move || {
+            let connection = open_private_connection(&address).unwrap();
+            connection
+                .request_name(IWD_BUS_NAME, false, true, false)
+                .unwrap();
+
+            let agent: AgentSlot = Arc::new(Mutex::new(None));
+            let mut cr = Crossroads::new();
+
+            let register_slot = agent.clone();
+            let unregister_slot = agent.clone();
+            let manager = cr.register(AGENT_MANAGER_IFACE, move |b| {
+                b.method(
+                    "RegisterAgent",
+                    ("path",),
+                    (),
+                    move |ctx: &mut Context, _: &mut (), (agent_path,): (Path<'static>,)| {
+                        let sender = ctx.message().sender().unwrap().to_string();
+                        *register_slot.lock().unwrap() = Some((sender, agent_path));
+                        Ok(())
+                    },
+                );
+                b.method(
+                    "UnregisterAgent",
+                    ("path",),
+                    (),
+                    move |_: &mut Context, _: &mut (), (_,): (Path<'static>,)| {
+                        *unregister_slot.lock().unwrap() = None;
+                        Ok(())
+                    },
+                );
+            });
+
+            let device = cr.register(DEVICE_IFACE, |b| {
+                b.property::<String, _>("Name")
+                    .get(|_, o: &mut FakeStation| Ok(o.name.clone()));
+            });
+
+            let station = cr.register(STATION_IFACE, |b| {
+                b.property::<bool, _>("Scanning")
+                    .get(|_, _: &mut FakeStation| Ok(false));
+                b.method(
+                    "Scan",
+                    (),
+                    (),
+                    |_: &mut Context, _: &mut FakeStation, _: ()| Ok(()),
+                );
+                b.method(
+                    "Disconnect",
+                    (),
+                    (),
+                    |_: &mut Context, _: &mut FakeStation, _: ()| Ok(()),
+                );
+                b.method(
+                    "GetOrderedNetworks",
+                    (),
+                    ("networks",),
+                    |_: &mut Context, _: &mut FakeStation, _: ()| {
+                        Ok((vec![
+                            (path(HOME), -5200i16),
+                            (path(LIBRARY), -6900i16),
+                            (path(CAFE), -8000i16),
+                        ],))
+                    },
+                );
+            });
+
+            let connect_address = address.clone();
+            let connect_slot = agent.clone();
+            let network = cr.register(NETWORK_IFACE, move |b| {
+                b.property::<String, _>("Name")
+                    .get(|_, o: &mut FakeNetwork| Ok(o.name.clone()));
+                b.property::<String, _>("Type")
+                    .get(|_, o: &mut FakeNetwork| Ok(o.kind.clone()));
+                b.property::<bool, _>("Connected")
+                    .get(|_, o: &mut FakeNetwork| Ok(o.connected));
+                b.method(
+                    "Connect",
+                    (),
+                    (),
+                    move |ctx: &mut Context, o: &mut FakeNetwork, _: ()| {
+                        if let Some(expected) = &o.passphrase {
+                            let path = ctx.path().to_string();
+                            let given = request_passphrase(&connect_address, &connect_slot, &path);
+                            if given.as_ref() != Some(expected) {
+                                return Err(MethodErr::from((
+                                    "net.connman.iwd.Failed",
+                                    "Operation failed",
+                                )));
+                            }
+                        }
+                        o.connected = true;
+                        Ok(())
+                    },
+                );
+            });
+
+            let known = cr.register(KNOWN_NETWORK_IFACE, |b| {
+                b.property::<String, _>("Name")
+                    .get(|_, o: &mut FakeKnownNetwork| Ok(o.name.clone()));
+                b.property::<String, _>("Type")
+                    .get(|_, o: &mut FakeKnownNetwork| Ok(o.kind.clone()));
+                b.property::<bool, _>("Hidden")
+                    .get(|_, _: &mut FakeKnownNetwork| Ok(false));
+                b.property::<bool, _>("AutoConnect")
+                    .get(|_, o: &mut FakeKnownNetwork| Ok(o.autoconnect))
+                    .set(|_, o: &mut FakeKnownNetwork, value| {
+                        o.autoconnect = value;
+                        Ok(Some(value))
+                    });
+                b.method(
+                    "Forget",
+                    (),
+                    (),
+                    |_: &mut Context, _: &mut FakeKnownNetwork, _: ()| Ok(()),
+                );
+            });
+
+            let object_manager = cr.object_manager();
+            cr.insert("/", &[object_manager], ());
+            cr.insert(IWD_PATH, &[manager], ());
+            cr.insert(
+                STATION,
+                &[device, station],
+                FakeStation {
+                    name: "wlan0".to_string(),
+                },
+            );
+            for (object, name, kind, connected, passphrase) in [
+                (HOME, "Home", "psk", true, None),
+                (CAFE, "Cafe", "open", false, None),
+                (LIBRARY, "Library", "psk", false, Some("correct horse")),
+            ] {
+                cr.insert(
+                    object,
+                    &[network],
+                    FakeNetwork {
+                        name: name.to_string(),
+                        kind: kind.to_string(),
+                        connected,
+                        passphrase: passphrase.map(str::to_string),
+                    },
+                );
+            }
+            cr.insert(
+                KNOWN_HOME,
+                &[known],
+                FakeKnownNetwork {
+                    name: "Home".to_string(),
+                    kind: "psk".to_string(),
+                    autoconnect: true,
+                },
+            );
+
+            // Returns an error once the test bus goes away
+            let _ = cr.serve(&connection);
+        });
+    }
+
+    fn connect_fake_client(bus: &TestBus) -> IwdDbusClient {
+        wait_for_service(
+            bus,
+            IwdDbusClient::from_connection,
+            IwdDbusClient::is_available,
+        )
+    }
+
+    #[test]
+    fn test_fake_bus_network_listing() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_iwd(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        assert_eq!(
+            client.stations().unwrap(),
+            vec![(STATION.to_string(), "wlan0".to_string())]
+        );
+
+        let networks = client.networks(Some("wlan0")).unwrap();
+        assert_eq!(networks.len(), 3);
+        assert_eq!(networks[0].name, "Home");
+        assert!(networks[0].connected);
+        assert_eq!(networks[1].signal, -6900);
+
+        let actions = client.wifi_networks(Some("wlan0")).unwrap();
+        let parsed: Vec<(String, String)> = actions
+            .iter()
+            .map(|action| match action {
+                WifiAction::Network(display) => {
+                    let (ssid, security) = parse_wifi_action(display).unwrap();
+                    (ssid.to_string(), security.to_string())
+                }
+                other => panic!("unexpected action {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                ("Home".to_string(), "PSK".to_string()),
+                ("Library".to_string(), "PSK".to_string()),
+                ("Cafe".to_string(), "OPEN".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fake_bus_known_networks() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_iwd(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        let known = client.known_networks().unwrap();
+        assert_eq!(known.len(), 1);
+        assert_eq!(known[0].name, "Home");
+        assert!(known[0].autoconnect);
+        assert!(client.is_known_network("Home").unwrap());
+        assert!(!client.is_known_network("Cafe").unwrap());
+
+        client.set_autoconnect("Home", false).unwrap();
+        assert!(!client.known_networks().unwrap()[0].autoconnect);
+        client.forget_known_network("Home").unwrap();
+        assert!(client.forget_known_network("Cafe").is_err());
+    }
+
+    #[test]
+    fn test_fake_bus_connect_with_agent() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_iwd(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        assert!(client
+            .connect_network("Library", Some("wlan0"), Some("correct horse"))
+            .unwrap());
+        assert!(!client
+            .connect_network("Library", Some("wlan0"), Some("wrong"))
+            .unwrap());
+        let WifiAction::Network(cafe) = wifi_network_action(false, "Cafe", "open", "*") else {
+            unreachable!()
+        };
+        assert!(client.connect_wifi(&cafe, None).unwrap());
+        assert!(client.connect_network("Missing", None, None).is_err());
+        assert!(client.disconnect(Some("wlan0")).unwrap());
+    }
+}
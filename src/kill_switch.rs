@@ -0,0 +1,695 @@
+//! VPN kill switch.
+//!
+//! While armed for a tunnel, an nftables table of its own drops everything
+//! leaving or forwarded through the machine, such as container and VM
+//! traffic, except through the tunnel interface, the tunnel's own
+//! encrypted traffic (endpoint addresses and fwmarks), loopback, DHCP and
+//! neighbour discovery, and optionally the local network. The table sits next
+//! to the firewalld one, so zones and panic mode keep working. The armed
+//! tunnel is remembered in the state directory; disconnecting it from the
+//! menu lifts the kill switch.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::constants::{ICON_LOCK, ICON_SHIELD, ICON_WARNING};
+use crate::ethernet::SYS_CLASS_NET;
+use crate::networkmanager::split_nmcli_fields;
+use crate::privilege::{wrap_privileged_command, write_private_file};
+use crate::vpn_profiles::load_vpn_profile;
+use crate::wg_quick;
+use crate::wireguard::get_wireguard_interfaces;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+pub const KILL_SWITCH_TABLE: &str = "network_dmenu_killswitch";
+pub const TAILSCALE_EXIT_NODE: &str = "Tailscale exit node";
+const TAILSCALE_INTERFACE: &str = "tailscale0";
+
+/// Mark wg-quick puts on the packets of its tunnels.
+const WG_QUICK_FWMARK: &str = "0xca6c";
+/// Bit tailscaled sets on its own packets, under the mask that follows.
+const TAILSCALE_BYPASS_MARK: (&str, &str) = ("0x80000", "0xff0000");
+
+const STATE_DIR_NAME: &str = "network-dmenu";
+const STATE_FILE_NAME: &str = "kill-switch.json";
+
+/// Kill switch settings from the `[kill_switch]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KillSwitchConfig {
+    /// Keep private and link-local networks reachable (printers, NAS)
+    pub allow_lan: bool,
+}
+
+/// What brings the tunnel up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TunnelKind {
+    NetworkManager,
+    WgQuick,
+    TailscaleExitNode,
+}
+
+/// A tunnel the kill switch can be armed for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tunnel {
+    pub kind: TunnelKind,
+    pub name: String,
+    pub interface: String,
+    /// Where the encrypted traffic goes, allowed outside the tunnel
+    pub endpoints: Vec<IpAddr>,
+}
+
+impl Tunnel {
+    pub fn tailscale_exit_node() -> Self {
+        Self {
+            kind: TunnelKind::TailscaleExitNode,
+            name: TAILSCALE_EXIT_NODE.to_string(),
+            interface: TAILSCALE_INTERFACE.to_string(),
+            endpoints: Vec::new(),
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        Path::new(SYS_CLASS_NET).join(&self.interface).exists()
+    }
+
+    pub fn to_display_string(&self) -> String {
+        format!("{ICON_LOCK} {} ({})", self.name, self.interface)
+    }
+}
+
+/// Whether the kill switch is armed, and for which tunnel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KillSwitchStatus {
+    Off,
+    Armed { tunnel: Tunnel, up: bool },
+}
+
+impl KillSwitchStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Armed { up: false, .. } => ICON_WARNING,
+            _ => ICON_SHIELD,
+        }
+    }
+
+    pub fn to_display_string(&self) -> String {
+        match self {
+            Self::Off => "Enable VPN kill switch".to_string(),
+            Self::Armed { tunnel, up: true } => {
+                format!("Kill switch on for {}: disable", tunnel.name)
+            }
+            Self::Armed { tunnel, up: false } => format!(
+                "Kill switch blocking traffic, {} is down: disable",
+                tunnel.name
+            ),
+        }
+    }
+}
+
+pub fn state_path() -> Result<PathBuf, Box<dyn Error>> {
+    let state_dir = dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("state")))
+        .ok_or("Failed to get state directory")?;
+    Ok(state_dir.join(STATE_DIR_NAME).join(STATE_FILE_NAME))
+}
+
+pub fn load_state(path: &Path) -> Option<Tunnel> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn save_state(path: &Path, tunnel: &Tunnel) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(tunnel)?)?;
+    Ok(())
+}
+
+/// Reads the kill switch status for the menu from the loaded nftables table.
+///
+/// The state file only names the tunnel. A table flushed by a firewall reload
+/// or a reboot is reported as off, and a table loaded without a state file
+/// still shows as armed.
+pub async fn get_status(command_runner: &dyn CommandRunner) -> KillSwitchStatus {
+    let state = state_path().ok().and_then(|path| load_state(&path));
+    let table = list_table(command_runner).await;
+
+    let tunnel = match (state, table) {
+        (_, Some(None)) => None,
+        (Some(tunnel), _) => Some(tunnel),
+        (None, Some(Some(table))) => table_tunnel(&table),
+        // nft cannot list rulesets without privileges
+        (None, None) => None,
+    };
+    match tunnel {
+        Some(tunnel) => KillSwitchStatus::Armed {
+            up: tunnel.is_up(),
+            tunnel,
+        },
+        None => KillSwitchStatus::Off,
+    }
+}
+
+/// Lists the kill switch table: `Some(None)` when it is not loaded, `None`
+/// when nft cannot tell.
+async fn list_table(command_runner: &dyn CommandRunner) -> Option<Option<String>> {
+    let output = command_runner
+        .run_command_async("nft", &["list", "table", "inet", KILL_SWITCH_TABLE])
+        .await
+        .ok()?;
+    if output.status.success() {
+        return Some(Some(String::from_utf8_lossy(&output.stdout).to_string()));
+    }
+    is_missing_table(&String::from_utf8_lossy(&output.stderr)).then_some(None)
+}
+
+/// Whether an nft error says the table does not exist.
+fn is_missing_table(error: &str) -> bool {
+    error.contains("No such file or directory")
+}
+
+/// Recovers the tunnel from the interface the kill switch table lets through.
+fn table_tunnel(table: &str) -> Option<Tunnel> {
+    let interface = table.lines().find_map(|line| {
+        let interface = line
+            .trim()
+            .strip_prefix("oifname \"")?
+            .strip_suffix("\" accept")?;
+        (interface != "lo").then(|| interface.to_string())
+    })?;
+
+    if interface == TAILSCALE_INTERFACE {
+        return Some(Tunnel::tailscale_exit_node());
+    }
+    Some(Tunnel {
+        kind: TunnelKind::NetworkManager,
+        name: interface.clone(),
+        interface,
+        endpoints: Vec::new(),
+    })
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace(['"', '\\'], ""))
+}
+
+/// Builds the nftables script that replaces the kill switch table.
+pub fn ruleset(tunnel: &Tunnel, config: &KillSwitchConfig) -> String {
+    let mut rules = vec![
+        "oifname \"lo\" accept".to_string(),
+        format!("oifname {} accept", quote(&tunnel.interface)),
+    ];
+
+    match tunnel.kind {
+        TunnelKind::WgQuick => rules.push(format!("meta mark {WG_QUICK_FWMARK} accept")),
+        TunnelKind::TailscaleExitNode => {
+            let (mark, mask) = TAILSCALE_BYPASS_MARK;
+            rules.push(format!("meta mark & {mask} == {mark} accept"));
+        }
+        TunnelKind::NetworkManager => {}
+    }
+
+    let (v4, v6): (Vec<&IpAddr>, Vec<&IpAddr>) =
+        tunnel.endpoints.iter().partition(|ip| ip.is_ipv4());
+    for (family, addresses) in [("ip", v4), ("ip6", v6)] {
+        if !addresses.is_empty() {
+            let addresses: Vec<String> = addresses.iter().map(|ip| ip.to_string()).collect();
+            rules.push(format!(
+                "{family} daddr {{ {} }} accept",
+                addresses.join(", ")
+            ));
+        }
+    }
+
+    // Keep the underlying link configured
+    rules.push("udp sport 68 udp dport 67 accept".to_string());
+    rules.push("udp sport 546 udp dport 547 accept".to_string());
+    rules.push(
+        "icmpv6 type { nd-router-solicit, nd-neighbor-solicit, nd-neighbor-advert } accept"
+            .to_string(),
+    );
+
+    if config.allow_lan {
+        rules.push(
+            "ip daddr { 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16, 169.254.0.0/16, 224.0.0.0/4 } accept"
+                .to_string(),
+        );
+        rules.push("ip6 daddr { fe80::/10, fc00::/7, ff00::/8 } accept".to_string());
+    }
+
+    // Fail fast instead of letting applications time out
+    rules.push("counter reject with icmpx type admin-prohibited".to_string());
+
+    let mut script = format!(
+        "table inet {KILL_SWITCH_TABLE}\ndelete table inet {KILL_SWITCH_TABLE}\ntable inet {KILL_SWITCH_TABLE} {{\n"
+    );
+    for (index, hook) in ["output", "forward"].iter().enumerate() {
+        if index > 0 {
+            script.push('\n');
+        }
+        script.push_str(&format!(
+            "\tchain {hook} {{\n\t\ttype filter hook {hook} priority filter; policy drop;\n"
+        ));
+        for rule in &rules {
+            script.push_str(&format!("\t\t{rule}\n"));
+        }
+        script.push_str("\t}\n");
+    }
+    script.push_str("}\n");
+    script
+}
+
+/// Extracts the host from a VPN `remote` or `gateway` value, such as
+/// `vpn.example.com:1194`, `vpn.example.com 443 tcp` or
+/// `https://vpn.example.com/group`.
+pub fn endpoint_host(value: &str) -> Option<&str> {
+    let value = value.trim();
+    let value = value.split_once("://").map_or(value, |(_, rest)| rest);
+    let value = value.split(['/', ' ']).next()?;
+    if let Some(bracketed) = value.strip_prefix('[') {
+        return bracketed.split(']').next();
+    }
+    let host = match value.matches(':').count() {
+        // host:port
+        1 => value.split(':').next()?,
+        // bare host, or a bare IPv6 address
+        _ => value,
+    };
+    Some(host).filter(|host| !host.is_empty())
+}
+
+async fn resolve(host: &str) -> Vec<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return vec![ip];
+    }
+    tokio::net::lookup_host((host, 0))
+        .await
+        .map(|addresses| addresses.map(|address| address.ip()).collect())
+        .unwrap_or_default()
+}
+
+async fn wireguard_endpoints(interface: &str, command_runner: &dyn CommandRunner) -> Vec<IpAddr> {
+    get_wireguard_interfaces(false, command_runner)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|wg| wg.name == interface)
+        .flat_map(|wg| wg.peers)
+        .filter_map(|peer| peer.endpoint?.parse::<SocketAddr>().ok())
+        .map(|endpoint| endpoint.ip())
+        .collect()
+}
+
+async fn vpn_endpoints(name: &str, command_runner: &dyn CommandRunner) -> Vec<IpAddr> {
+    let Ok(profile) = load_vpn_profile(name, command_runner) else {
+        return Vec::new();
+    };
+    let hosts: Vec<String> = ["remote", "gateway"]
+        .iter()
+        .filter_map(|key| profile.data.get(*key))
+        .flat_map(|value| value.split(','))
+        .filter_map(|value| endpoint_host(value).map(str::to_string))
+        .collect();
+
+    let mut endpoints = Vec::new();
+    for host in hosts {
+        endpoints.extend(resolve(&host).await);
+    }
+    endpoints.sort();
+    endpoints.dedup();
+    endpoints
+}
+
+/// Lists the NetworkManager VPN and WireGuard connections and wg-quick
+/// tunnels that are up.
+pub async fn active_tunnels(command_runner: &dyn CommandRunner) -> Vec<Tunnel> {
+    let mut tunnels = Vec::new();
+
+    if let Ok(output) = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "-t",
+                "-f",
+                "NAME,TYPE,DEVICE",
+                "connection",
+                "show",
+                "--active",
+            ],
+        )
+        .await
+    {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let [name, kind, device] = split_nmcli_fields(line).try_into().unwrap_or_default();
+            let (interface, endpoints) = match kind.as_str() {
+                "wireguard" => (
+                    device.clone(),
+                    wireguard_endpoints(&device, command_runner).await,
+                ),
+                "vpn" => {
+                    // The tunnel device, not the one the VPN runs over
+                    let interface = command_runner
+                        .run_command_async(
+                            "nmcli",
+                            &["-g", "GENERAL.IP-IFACE", "connection", "show", &name],
+                        )
+                        .await
+                        .ok()
+                        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+                        .filter(|interface| !interface.is_empty())
+                        .unwrap_or(device);
+                    (interface, vpn_endpoints(&name, command_runner).await)
+                }
+                _ => continue,
+            };
+            tunnels.push(Tunnel {
+                kind: TunnelKind::NetworkManager,
+                name,
+                interface,
+                endpoints,
+            });
+        }
+    }
+
+    if is_command_installed("wg-quick") {
+        let configs = wg_quick::list_configs();
+        for interface in wg_quick::active_interfaces(command_runner).await {
+            if configs.contains(&interface) && !tunnels.iter().any(|t| t.interface == interface) {
+                tunnels.push(Tunnel {
+                    kind: TunnelKind::WgQuick,
+                    name: interface.clone(),
+                    endpoints: wireguard_endpoints(&interface, command_runner).await,
+                    interface,
+                });
+            }
+        }
+    }
+
+    tunnels
+}
+
+fn run_nft(command: &str, command_runner: &dyn CommandRunner) -> Result<(), Box<dyn Error>> {
+    let privileged_cmd = wrap_privileged_command(command, false);
+    let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    if !output.status.success() {
+        return Err(format!(
+            "nft failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Arms the kill switch for `tunnel`.
+pub fn enable(
+    tunnel: &Tunnel,
+    config: &KillSwitchConfig,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
+    enable_with_state(tunnel, config, &state_path()?, command_runner)
+}
+
+fn enable_with_state(
+    tunnel: &Tunnel,
+    config: &KillSwitchConfig,
+    state: &Path,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
+    if !is_command_installed("nft") {
+        return Err("nft is not installed".into());
+    }
+
+    let script = std::env::temp_dir().join(format!(
+        "network-dmenu-{}-kill-switch.nft",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&script);
+    write_private_file(&script, ruleset(tunnel, config))?;
+    let result = run_nft(
+        &format!(
+            "nft -f '{}'",
+            script.display().to_string().replace('\'', r"'\''")
+        ),
+        command_runner,
+    );
+    let _ = fs::remove_file(&script);
+    result?;
+
+    save_state(state, tunnel)
+}
+
+/// Lifts the kill switch; returns false when it was not armed.
+///
+/// A table that is already gone counts as lifted, and the state file is
+/// removed either way.
+pub fn disable(command_runner: &dyn CommandRunner) -> Result<bool, Box<dyn Error>> {
+    disable_with_state(&state_path()?, command_runner)
+}
+
+fn disable_with_state(
+    state: &Path,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let armed = load_state(state).is_some();
+    let deleted = match run_nft(
+        &format!("nft delete table inet {KILL_SWITCH_TABLE}"),
+        command_runner,
+    ) {
+        Ok(()) => true,
+        Err(e) if is_missing_table(&e.to_string()) => false,
+        Err(e) => return Err(e),
+    };
+
+    match fs::remove_file(state) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    Ok(armed || deleted)
+}
+
+/// Lifts the kill switch if it is armed for the tunnel called `name`, which
+/// the user just took down on purpose.
+pub fn lift_for(name: &str, command_runner: &dyn CommandRunner) -> Result<bool, Box<dyn Error>> {
+    let state = state_path()?;
+    match load_state(&state) {
+        Some(tunnel) if tunnel.name == name => disable_with_state(&state, command_runner),
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct RecordingCommandRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+        /// The loaded kill switch table, `None` when there is none
+        table: Option<&'static str>,
+    }
+
+    impl RecordingCommandRunner {
+        fn new(table: Option<&'static str>) -> Self {
+            Self {
+                calls: Mutex::new(Vec::new()),
+                table,
+            }
+        }
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call);
+            let is_nft = command == "nft" || args.iter().any(|arg| arg.contains("nft "));
+            if is_nft && self.table.is_none() {
+                return Ok(Output {
+                    status: ExitStatus::from_raw(1 << 8),
+                    stdout: Vec::new(),
+                    stderr: b"Error: Could not process rule: No such file or directory\n".to_vec(),
+                });
+            }
+            if command == "nft" {
+                return Ok(Output {
+                    status: ExitStatus::from_raw(0),
+                    stdout: self.table.unwrap_or_default().as_bytes().to_vec(),
+                    stderr: Vec::new(),
+                });
+            }
+            let stdout = match args {
+                ["-t", "-f", "NAME,TYPE,DEVICE", ..] => {
+                    "Home Wi-Fi:802-11-wireless:wlan0\nOffice:vpn:wlan0\nhome-wg:wireguard:wg1\n"
+                }
+                ["-g", "GENERAL.IP-IFACE", ..] => "tun0\n",
+                ["-t", "-f", "vpn.service-type,vpn.user-name,vpn.data", ..] => {
+                    "vpn.service-type:org.freedesktop.NetworkManager.openvpn\nvpn.data:remote = 203.0.113.10:1194\\, 198.51.100.4 443\n"
+                }
+                _ => "",
+            };
+            Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: Vec::new(),
+            })
+        }
+    }
+
+    fn tunnel(kind: TunnelKind) -> Tunnel {
+        Tunnel {
+            kind,
+            name: "office".to_string(),
+            interface: "wg0".to_string(),
+            endpoints: vec![
+                "203.0.113.5".parse().unwrap(),
+                "2001:db8::1".parse().unwrap(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_ruleset() {
+        let rules = ruleset(&tunnel(TunnelKind::WgQuick), &KillSwitchConfig::default());
+        assert!(rules.starts_with(&format!(
+            "table inet {KILL_SWITCH_TABLE}\ndelete table inet {KILL_SWITCH_TABLE}\n"
+        )));
+        assert!(rules.contains("policy drop;"));
+        assert!(rules.contains("\t\toifname \"wg0\" accept\n"));
+        assert!(rules.contains("meta mark 0xca6c accept"));
+        assert!(rules.contains("ip daddr { 203.0.113.5 } accept"));
+        assert!(rules.contains("ip6 daddr { 2001:db8::1 } accept"));
+        assert!(!rules.contains("192.168.0.0/16"));
+        assert!(rules.contains(
+            "\tchain output {\n\t\ttype filter hook output priority filter; policy drop;\n"
+        ));
+        assert!(rules.contains(
+            "\tchain forward {\n\t\ttype filter hook forward priority filter; policy drop;\n"
+        ));
+        assert_eq!(rules.matches("\t\toifname \"wg0\" accept\n").count(), 2);
+        assert!(rules.trim_end().ends_with("admin-prohibited\n\t}\n}"));
+
+        let tailscale = ruleset(
+            &Tunnel::tailscale_exit_node(),
+            &KillSwitchConfig { allow_lan: true },
+        );
+        assert!(tailscale.contains("oifname \"tailscale0\" accept"));
+        assert!(tailscale.contains("meta mark & 0xff0000 == 0x80000 accept"));
+        assert!(tailscale.contains("192.168.0.0/16"));
+        assert!(!tailscale.contains("daddr { 203"));
+    }
+
+    #[test]
+    fn test_endpoint_host() {
+        assert_eq!(
+            endpoint_host("vpn.example.com:1194"),
+            Some("vpn.example.com")
+        );
+        assert_eq!(
+            endpoint_host(" vpn.example.com 443 tcp"),
+            Some("vpn.example.com")
+        );
+        assert_eq!(
+            endpoint_host("https://vpn.example.com/group"),
+            Some("vpn.example.com")
+        );
+        assert_eq!(endpoint_host("[2001:db8::1]:443"), Some("2001:db8::1"));
+        assert_eq!(endpoint_host("2001:db8::1"), Some("2001:db8::1"));
+        assert_eq!(endpoint_host(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_active_tunnels() {
+        let runner = RecordingCommandRunner::new(None);
+        let tunnels: Vec<_> = active_tunnels(&runner)
+            .await
+            .into_iter()
+            .filter(|tunnel| tunnel.kind == TunnelKind::NetworkManager)
+            .collect();
+
+        assert_eq!(tunnels.len(), 2);
+        assert_eq!(tunnels[0].name, "Office");
+        assert_eq!(tunnels[0].interface, "tun0");
+        assert_eq!(
+            tunnels[0].endpoints,
+            vec![
+                "198.51.100.4".parse::<IpAddr>().unwrap(),
+                "203.0.113.10".parse().unwrap()
+            ]
+        );
+        assert_eq!(tunnels[1].name, "home-wg");
+        assert_eq!(tunnels[1].interface, "wg1");
+    }
+
+    #[test]
+    fn test_state_and_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state").join(STATE_FILE_NAME);
+        let runner = RecordingCommandRunner::new(Some(""));
+
+        let tunnel = tunnel(TunnelKind::NetworkManager);
+        save_state(&state, &tunnel).unwrap();
+        assert_eq!(load_state(&state), Some(tunnel.clone()));
+
+        assert!(disable_with_state(&state, &runner).unwrap());
+        assert!(runner.calls.lock().unwrap()[0][2]
+            .ends_with(&format!("nft delete table inet {KILL_SWITCH_TABLE}")));
+        assert_eq!(load_state(&state), None);
+
+        let down = KillSwitchStatus::Armed { tunnel, up: false };
+        assert_eq!(down.icon(), ICON_WARNING);
+        assert_eq!(
+            down.to_display_string(),
+            "Kill switch blocking traffic, office is down: disable"
+        );
+        assert_eq!(KillSwitchStatus::Off.icon(), ICON_SHIELD);
+    }
+
+    #[test]
+    fn test_disable_without_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join(STATE_FILE_NAME);
+        let runner = RecordingCommandRunner::new(None);
+
+        assert!(!disable_with_state(&state, &runner).unwrap());
+
+        // Flushed by a firewall reload: lifting still clears the state
+        save_state(&state, &tunnel(TunnelKind::WgQuick)).unwrap();
+        assert!(disable_with_state(&state, &runner).unwrap());
+        assert_eq!(load_state(&state), None);
+    }
+
+    #[tokio::test]
+    async fn test_list_table() {
+        assert_eq!(
+            list_table(&RecordingCommandRunner::new(None)).await,
+            Some(None)
+        );
+
+        let table = "table inet network_dmenu_killswitch {\n\
+                     \tchain output {\n\
+                     \t\ttype filter hook output priority filter; policy drop;\n\
+                     \t\toifname \"lo\" accept\n\
+                     \t\toifname \"wg0\" accept\n\
+                     \t}\n\
+                     }\n";
+        let listed = list_table(&RecordingCommandRunner::new(Some(table)))
+            .await
+            .unwrap()
+            .unwrap();
+        let recovered = table_tunnel(&listed).unwrap();
+        assert_eq!(recovered.interface, "wg0");
+        assert_eq!(recovered.name, "wg0");
+
+        assert_eq!(
+            table_tunnel("\t\toifname \"tailscale0\" accept\n"),
+            Some(Tunnel::tailscale_exit_node())
+        );
+        assert_eq!(table_tunnel("\t\toifname \"lo\" accept\n"), None);
+    }
+}
@@ -3,34 +3,65 @@
 //! A Rust library for working with network interfaces through dmenu-style interfaces. Supports
 //! Wi-Fi networks (via NetworkManager and iwd), VPN connections, and Bluetooth devices.
 
+pub mod audit;
 pub mod bluetooth;
+pub mod captive_portal;
 pub mod command;
+pub mod connman;
 pub mod constants;
 pub mod diagnostics;
 pub mod dns_cache;
+pub mod enterprise;
+pub mod ethernet;
 #[cfg(feature = "firewalld")]
 pub mod firewalld;
+pub mod hidden_network;
+pub mod hotspot;
 pub mod iwd;
+pub mod iwd_dbus;
+pub mod kill_switch;
 pub mod logger;
+pub mod mac_policy;
+pub mod metered;
+pub mod networkd;
 pub mod networkmanager;
 pub mod nextdns;
+pub mod nm_dbus;
 pub mod notifications;
 pub mod port_utils;
 pub mod privilege;
 pub mod rfkill;
+pub mod roaming;
+pub mod saved_networks;
 pub mod ssh;
 #[cfg(feature = "tailscale")]
 pub mod tailscale;
 #[cfg(feature = "tailscale")]
 pub mod tailscale_prefs;
+#[cfg(test)]
+mod test_bus;
 pub mod tor;
 pub mod utils;
+pub mod vpn_profiles;
+pub mod wg_quick;
+pub mod wifi_backend;
+pub mod wifi_details;
+pub mod wifi_security;
+pub mod wifi_share;
+pub mod wireguard;
+pub mod wpa_supplicant;
 
-use constants::{ICON_CHECK, ICON_CROSS, ICON_SIGNAL};
+use constants::{ICON_ANTENNA, ICON_CHECK, ICON_CROSS, ICON_SIGNAL};
 
 // Re-export commonly used types and functions
+pub use audit::{
+    append_audit_record, read_recent_audit_records, AuditRecord, AuditedCommand,
+    AuditingCommandRunner,
+};
 pub use bluetooth::{get_paired_bluetooth_devices, handle_bluetooth_action, BluetoothAction};
+pub use captive_portal::{handle_captive_portal, CaptivePortalConfig, PortalState};
 pub use command::{is_command_installed, read_output_lines, CommandRunner, RealCommandRunner};
+pub use connman::ConnmanClient;
 pub use diagnostics::{
     diagnostic_action_to_string, get_diagnostic_actions, handle_diagnostic_action, DiagnosticAction,
 };
@@ -38,26 +69,44 @@ pub use dns_cache::{
     generate_dns_actions_from_cache, get_current_network_id, CachedDnsServer, DnsBenchmarkCache,
     DnsCacheStorage,
 };
+pub use ethernet::{
+    ethernet_action_to_string, get_ethernet_actions, list_ethernet_interfaces, EthernetAction,
+    EthernetInterface, WiredProfile,
+};
 #[cfg(feature = "firewalld")]
 pub use firewalld::{
     get_firewalld_actions, get_firewalld_actions_async, handle_firewalld_action, FirewalldAction,
     FirewalldActionResult,
 };
+pub use hidden_network::HiddenSecurity;
+pub use hotspot::{
+    get_hotspot_actions, hotspot_action_to_string, HotspotAction, HotspotClient, HotspotStatus,
+};
 pub use iwd::{
     connect_to_iwd_wifi, disconnect_iwd_wifi, get_iwd_networks,
     is_known_network as is_known_iwd_network,
 };
+pub use iwd_dbus::IwdDbusClient;
+pub use kill_switch::{KillSwitchConfig, KillSwitchStatus, Tunnel, TunnelKind};
+pub use mac_policy::MacPolicy;
+pub use metered::{MeteredConnection, MeteredSetting};
+pub use networkd::{
+    get_networkd_actions, networkd_action_to_string, LinkOperation, NetworkdAction, NetworkdLink,
+};
 pub use networkmanager::{
     connect_to_nm_vpn, connect_to_nm_wifi, disconnect_nm_vpn, disconnect_nm_wifi,
-    get_nm_vpn_networks, get_nm_wifi_networks, is_known_network as is_known_nm_network,
+    get_nm_vpn_networks, get_nm_wifi_networks, get_nm_wifi_networks_on,
+    is_known_network as is_known_nm_network,
 };
 pub use nextdns::{get_nextdns_actions, handle_nextdns_action, NextDnsAction};
+pub use nm_dbus::NmDbusClient;
 pub use notifications::{NotificationConfig, NotificationManager, NotificationUrgency};
 pub use port_utils::{is_any_port_listening, is_port_listening};
 pub use privilege::{
     get_privilege_command, has_privilege_escalation, wrap_privileged_command,
     wrap_privileged_commands,
 };
+pub use roaming::RoamingConfig;
 pub use ssh::{
     get_ssh_proxy_actions, handle_ssh_action, ssh_action_to_string, SshAction, SshProxyConfig,
 };
@@ -78,8 +127,22 @@ pub use nextdns::fetch_profiles_blocking;
 // Re-export logger
 pub use logger::Profiler;
 
+pub use saved_networks::{SavedConnection, SavedConnectionAction};
 pub use utils::{
-    check_captive_portal, convert_network_strength, prompt_for_password, prompt_for_ssid,
+    check_captive_portal, convert_network_strength, copy_to_clipboard, list_wifi_interfaces,
+    prompt_for_password, prompt_for_ssid, read_clipboard, select_from_menu,
+};
+pub use vpn_profiles::{OpenconnectProtocol, VpnProfile};
+pub use wifi_backend::WifiBackend;
+pub use wifi_details::{BssInfo, WifiBand, WifiDetailAction};
+pub use wifi_security::{InsecureWifiConfig, SecurityWarning};
+pub use wifi_share::{PasswordTarget, ShareTarget, WifiCredentials, WifiQrSecurity};
+pub use wireguard::{
+    get_wireguard_interfaces, import_wireguard_config, WireguardInterface, WireguardPeer,
+};
+pub use wpa_supplicant::{
+    connect_to_wpa_wifi, disconnect_wpa_wifi, get_wpa_networks,
+    is_known_network as is_known_wpa_network,
 };
 
 use notify_rust::Notification;
@@ -163,7 +226,12 @@ pub fn parse_vpn_action(action: &str) -> Result<&str, Box<dyn std::error::Error>
         .ok_or("Emoji not found in action")?;
 
     let name_start = emoji_pos + action[emoji_pos..].chars().next().unwrap().len_utf8();
-    let name = action[name_start..].trim();
+    // Anything after a tab is a status badge, not part of the name
+    let name = action[name_start..]
+        .split('\t')
+        .next()
+        .unwrap_or_default()
+        .trim();
 
     if name.is_empty() {
         return Err("No name found after emoji".into());
@@ -209,6 +277,21 @@ pub fn parse_wifi_action(action: &str) -> Result<(&str, &str), Box<dyn Error>> {
     Ok((ssid, security))
 }
 
+/// Marks a Wi-Fi entry as seen by `interface`, for machines with several radios.
+pub fn tag_wifi_interface(action: &str, interface: &str) -> String {
+    format!("{action}\t{ICON_ANTENNA} {interface}")
+}
+
+/// Returns the interface a Wi-Fi entry was tagged with, if any.
+pub fn parse_wifi_interface(action: &str) -> Option<&str> {
+    action
+        .split('\t')
+        .skip(1)
+        .find_map(|field| field.trim().strip_prefix(ICON_ANTENNA))
+        .map(str::trim)
+        .filter(|interface| !interface.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,4 +328,13 @@ mod tests {
             _ => panic!("Unexpected action type"),
         }
     }
+
+    #[test]
+    fn test_wifi_interface_tag() {
+        let entry = "✅ Home                     \tWPA2       \t▂▄▆█\t🎭 aa:bb:cc:dd:ee:ff";
+        let tagged = tag_wifi_interface(entry, "wlx00c0ca");
+        assert_eq!(parse_wifi_interface(&tagged), Some("wlx00c0ca"));
+        assert_eq!(parse_wifi_action(&tagged).unwrap(), ("Home", "WPA2"));
+        assert_eq!(parse_wifi_interface(entry), None);
+    }
 }
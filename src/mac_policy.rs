@@ -0,0 +1,626 @@
+//! Wi-Fi MAC address randomization policy.
+//!
+//! NetworkManager takes a policy per connection (`802-11-wireless.cloned-mac-address`)
+//! and a global default from a `conf.d` drop-in. iwd has the global
+//! `[General] AddressRandomization` setting in `main.conf`, and per network
+//! `[Settings] AlwaysRandomizeAddress` / `AddressOverride` in the network file.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::constants::{ACTION_TYPE_SYSTEM, ICON_CHECK, ICON_MASK};
+use crate::format_entry;
+use crate::iwd;
+use crate::privilege::{install_file_privileged, wrap_privileged_command};
+use crate::saved_networks::SavedBackend;
+use crate::wifi_share;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Drop-in holding the NetworkManager default policy.
+pub const NM_GLOBAL_CONF: &str = "/etc/NetworkManager/conf.d/90-network-dmenu-mac.conf";
+/// iwd main configuration file.
+pub const IWD_MAIN_CONF: &str = "/etc/iwd/main.conf";
+
+const NM_MAC_SETTING: &str = "wifi.cloned-mac-address";
+
+/// MAC address used when connecting to a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacPolicy {
+    /// The hardware address
+    Permanent,
+    /// A new address on every connection
+    Random,
+    /// A fixed generated address per connection profile
+    Stable,
+    /// A fixed generated address per SSID
+    StableSsid,
+}
+
+impl MacPolicy {
+    pub const ALL: [MacPolicy; 4] = [
+        Self::Permanent,
+        Self::Random,
+        Self::Stable,
+        Self::StableSsid,
+    ];
+
+    /// Value of NetworkManager's `cloned-mac-address` setting.
+    pub fn nm_value(&self) -> &'static str {
+        match self {
+            Self::Permanent => "permanent",
+            Self::Random => "random",
+            Self::Stable => "stable",
+            Self::StableSsid => "stable-ssid",
+        }
+    }
+
+    pub fn from_nm_value(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.nm_value() == value.trim())
+    }
+
+    /// Value of iwd's `AddressRandomization`, if iwd has an equivalent.
+    ///
+    /// iwd randomizes once per daemon start rather than per connection.
+    pub fn iwd_value(&self) -> Option<&'static str> {
+        match self {
+            Self::Permanent => Some("disabled"),
+            Self::Random => Some("once"),
+            Self::Stable => None,
+            Self::StableSsid => Some("network"),
+        }
+    }
+
+    pub fn from_iwd_value(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.iwd_value() == Some(value.trim()))
+    }
+
+    /// Menu entry for the policy, checked when it is the current one.
+    pub fn to_display_string(&self, current: bool) -> String {
+        format_entry(
+            ACTION_TYPE_SYSTEM,
+            if current { ICON_CHECK } else { ICON_MASK },
+            &self.to_string(),
+        )
+    }
+}
+
+impl fmt::Display for MacPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Permanent => write!(f, "Permanent (hardware address)"),
+            Self::Random => write!(f, "Random"),
+            Self::Stable => write!(f, "Stable per connection"),
+            Self::StableSsid => write!(f, "Stable per SSID"),
+        }
+    }
+}
+
+/// Returns the current MAC address of `interface`.
+pub fn interface_mac(interface: &str) -> Option<String> {
+    let address = fs::read_to_string(format!("/sys/class/net/{interface}/address")).ok()?;
+    Some(address.trim().to_string()).filter(|address| !address.is_empty())
+}
+
+/// Policies the active backend supports globally.
+pub fn global_policies() -> Vec<MacPolicy> {
+    if is_command_installed("nmcli") {
+        MacPolicy::ALL.to_vec()
+    } else {
+        MacPolicy::ALL
+            .into_iter()
+            .filter(|policy| policy.iwd_value().is_some())
+            .collect()
+    }
+}
+
+/// Policies a saved connection of `backend` can take.
+///
+/// iwd keeps one profile per SSID, so per connection and per SSID are the same.
+pub fn connection_policies(backend: SavedBackend) -> Vec<MacPolicy> {
+    match backend {
+        SavedBackend::NetworkManager => MacPolicy::ALL.to_vec(),
+        SavedBackend::Iwd => vec![
+            MacPolicy::Permanent,
+            MacPolicy::Random,
+            MacPolicy::StableSsid,
+        ],
+    }
+}
+
+/// Reads the policy of a NetworkManager connection, `None` when it follows
+/// the global default.
+pub fn get_connection_policy(uuid: &str, command_runner: &dyn CommandRunner) -> Option<MacPolicy> {
+    wifi_share::nm_field(uuid, "802-11-wireless.cloned-mac-address", command_runner)
+        .ok()
+        .and_then(|value| MacPolicy::from_nm_value(&value))
+}
+
+/// Sets the policy of a NetworkManager connection, reactivating it if it is up.
+pub fn set_connection_policy(
+    uuid: &str,
+    policy: MacPolicy,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let status = command_runner
+        .run_command(
+            "nmcli",
+            &[
+                "connection",
+                "modify",
+                "uuid",
+                uuid,
+                "802-11-wireless.cloned-mac-address",
+                policy.nm_value(),
+            ],
+        )?
+        .status;
+    if !status.success() {
+        return Ok(false);
+    }
+
+    let active = command_runner.run_command(
+        "nmcli",
+        &["-t", "-f", "UUID", "connection", "show", "--active"],
+    )?;
+    let is_active = String::from_utf8_lossy(&active.stdout)
+        .lines()
+        .any(|line| line.trim() == uuid);
+    if is_active {
+        // The new address is only used when the connection comes up again
+        let status = command_runner
+            .run_command("nmcli", &["connection", "up", "uuid", uuid])?
+            .status;
+        return Ok(status.success());
+    }
+
+    Ok(true)
+}
+
+/// Reads the policy iwd saved for `ssid`, `None` when it follows `main.conf`.
+pub fn get_iwd_network_policy(
+    ssid: &str,
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Option<MacPolicy> {
+    let (_, contents) = read_iwd_network_file(ssid, command_runner).ok()?;
+    let permanent = permanent_mac(interface, command_runner);
+    parse_iwd_network_policy(&contents, permanent.as_deref())
+}
+
+/// Writes the policy into the iwd network file of `ssid`.
+///
+/// iwd reloads the file on its own; the address changes on the next connection.
+pub fn set_iwd_network_policy(
+    ssid: &str,
+    interface: &str,
+    policy: MacPolicy,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let (file_name, contents) = read_iwd_network_file(ssid, command_runner)?;
+    let permanent = permanent_mac(interface, command_runner)
+        .ok_or_else(|| format!("Cannot read the hardware address of {interface}"))?;
+
+    let contents = match policy {
+        MacPolicy::Random => {
+            let contents = remove_ini_value(&contents, "Settings", "AddressOverride");
+            set_ini_value(&contents, "Settings", "AlwaysRandomizeAddress", "true")
+        }
+        MacPolicy::Permanent | MacPolicy::Stable | MacPolicy::StableSsid => {
+            let address = if policy == MacPolicy::Permanent {
+                permanent
+            } else {
+                stable_ssid_mac(ssid, &permanent)
+            };
+            let contents = remove_ini_value(&contents, "Settings", "AlwaysRandomizeAddress");
+            set_ini_value(&contents, "Settings", "AddressOverride", &address)
+        }
+    };
+
+    iwd::install_state_file(&file_name, &contents, command_runner)?;
+    Ok(true)
+}
+
+/// Reads the saved iwd network file of `ssid`, returning its name and contents.
+fn read_iwd_network_file(
+    ssid: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<(String, String), Box<dyn Error>> {
+    let candidates: Vec<String> = ["psk", "open", "8021x"]
+        .iter()
+        .map(|extension| {
+            let path = Path::new(iwd::IWD_STATE_DIR).join(iwd::network_file_name(ssid, extension));
+            format!("'{}'", path.display().to_string().replace('\'', r"'\''"))
+        })
+        .collect();
+    let command = format!(
+        "for f in {}; do if test -e \"$f\"; then basename \"$f\"; cat \"$f\"; exit 0; fi; done; exit 1",
+        candidates.join(" ")
+    );
+    let privileged_cmd = wrap_privileged_command(&command, true);
+    let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    if !output.status.success() {
+        return Err(format!("No saved iwd profile for {ssid}").into());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (file_name, contents) = stdout.split_once('\n').unwrap_or((&stdout, ""));
+    Ok((file_name.trim().to_string(), contents.to_string()))
+}
+
+/// Maps the `[Settings]` of an iwd network file to a policy.
+fn parse_iwd_network_policy(contents: &str, permanent: Option<&str>) -> Option<MacPolicy> {
+    if get_ini_value(contents, "Settings", "AlwaysRandomizeAddress").as_deref() == Some("true") {
+        return Some(MacPolicy::Random);
+    }
+    let address = get_ini_value(contents, "Settings", "AddressOverride")?.to_lowercase();
+    if Some(address.as_str()) == permanent {
+        Some(MacPolicy::Permanent)
+    } else {
+        Some(MacPolicy::StableSsid)
+    }
+}
+
+/// Returns the hardware address of `interface`, even while it uses another one.
+fn permanent_mac(interface: &str, command_runner: &dyn CommandRunner) -> Option<String> {
+    let output = command_runner
+        .run_command("ip", &["link", "show", "dev", interface])
+        .ok()?;
+    parse_permanent_mac(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extracts the hardware address from `ip link show`, which only prints
+/// `permaddr` when the current address differs from it.
+fn parse_permanent_mac(output: &str) -> Option<String> {
+    let parts: Vec<&str> = output.split_whitespace().collect();
+    let value_after = |key: &str| {
+        parts
+            .iter()
+            .position(|part| *part == key)
+            .and_then(|i| parts.get(i + 1))
+            .map(|value| value.to_lowercase())
+    };
+    value_after("permaddr").or_else(|| value_after("link/ether"))
+}
+
+/// Generates a locally administered address that stays the same for an SSID
+/// on this device.
+fn stable_ssid_mac(ssid: &str, permanent: &str) -> String {
+    // FNV-1a, so the address does not change between builds
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in permanent.bytes().chain([0]).chain(ssid.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    let mut octets = hash.to_be_bytes();
+    // Unicast, locally administered
+    octets[2] = (octets[2] & 0xfc) | 0x02;
+    octets[2..]
+        .iter()
+        .map(|octet| format!("{octet:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Reads the global policy, `None` when the backend default is in use.
+pub fn get_global_policy() -> Option<MacPolicy> {
+    if is_command_installed("nmcli") {
+        let contents = fs::read_to_string(NM_GLOBAL_CONF).ok()?;
+        get_ini_value(&contents, "connection", NM_MAC_SETTING)
+            .and_then(|value| MacPolicy::from_nm_value(&value))
+    } else {
+        let contents = fs::read_to_string(IWD_MAIN_CONF).ok()?;
+        get_ini_value(&contents, "General", "AddressRandomization")
+            .and_then(|value| MacPolicy::from_iwd_value(&value))
+    }
+}
+
+/// Sets the global policy and makes the running daemon pick it up.
+pub fn set_global_policy(
+    policy: MacPolicy,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if is_command_installed("nmcli") {
+        let content = format!(
+            "# Managed by network-dmenu\n[connection]\n{NM_MAC_SETTING}={}\n",
+            policy.nm_value()
+        );
+        install_file_privileged(Path::new(NM_GLOBAL_CONF), &content, 0o644, command_runner)?;
+        let status = command_runner
+            .run_command("nmcli", &["general", "reload", "conf"])?
+            .status;
+        Ok(status.success())
+    } else if is_command_installed("iwctl") {
+        let value = policy
+            .iwd_value()
+            .ok_or_else(|| format!("iwd has no \"{policy}\" policy"))?;
+        let current = fs::read_to_string(IWD_MAIN_CONF).unwrap_or_default();
+        let content = set_ini_value(&current, "General", "AddressRandomization", value);
+        install_file_privileged(Path::new(IWD_MAIN_CONF), &content, 0o644, command_runner)?;
+
+        // iwd only reads main.conf on startup
+        let restart = wrap_privileged_command("systemctl restart iwd", false);
+        let status = command_runner.run_command("sh", &["-c", &restart])?.status;
+        Ok(status.success())
+    } else {
+        Ok(false)
+    }
+}
+
+/// Returns `key` from `[section]` of an INI-style file.
+fn get_ini_value(contents: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == format!("[{section}]");
+        } else if in_section {
+            if let Some((k, v)) = line.split_once('=') {
+                if k.trim() == key {
+                    return Some(v.trim().to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Sets `key` in `[section]`, adding the key or section when missing and
+/// keeping every other line as is.
+fn set_ini_value(contents: &str, section: &str, key: &str, value: &str) -> String {
+    let header = format!("[{section}]");
+    let entry = format!("{key}={value}");
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+
+    let Some(start) = lines.iter().position(|line| line.trim() == header) else {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(header);
+        lines.push(entry);
+        return lines.join("\n") + "\n";
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.trim().starts_with('['))
+        .map_or(lines.len(), |i| start + 1 + i);
+
+    match lines[start + 1..end].iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(k, _)| k.trim() == key && !line.trim().starts_with('#'))
+    }) {
+        Some(i) => lines[start + 1 + i] = entry,
+        None => {
+            // Keep blank lines separating the next section
+            let mut insert_at = end;
+            while insert_at > start + 1 && lines[insert_at - 1].trim().is_empty() {
+                insert_at -= 1;
+            }
+            lines.insert(insert_at, entry);
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Removes `key` from `[section]`, keeping every other line as is.
+fn remove_ini_value(contents: &str, section: &str, key: &str) -> String {
+    let mut in_section = false;
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                in_section = trimmed == format!("[{section}]");
+                return true;
+            }
+            !(in_section
+                && trimmed
+                    .split_once('=')
+                    .is_some_and(|(k, _)| k.trim() == key))
+        })
+        .collect();
+    if lines.is_empty() {
+        String::new()
+    } else {
+        lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct RecordingCommandRunner {
+        active: &'static str,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            let stdout = if args.contains(&"--active") {
+                self.active.as_bytes().to_vec()
+            } else {
+                vec![]
+            };
+            self.calls.lock().unwrap().push(call);
+            Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout,
+                stderr: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn test_policy_values() {
+        for policy in MacPolicy::ALL {
+            assert_eq!(MacPolicy::from_nm_value(policy.nm_value()), Some(policy));
+        }
+        assert_eq!(MacPolicy::from_nm_value("preserve"), None);
+        assert_eq!(
+            MacPolicy::from_iwd_value("network"),
+            Some(MacPolicy::StableSsid)
+        );
+        assert_eq!(MacPolicy::Stable.iwd_value(), None);
+    }
+
+    #[test]
+    fn test_policy_display_marks_current() {
+        let current = MacPolicy::Random.to_display_string(true);
+        assert!(current.contains(ICON_CHECK));
+        assert!(current.contains("Random"));
+        assert!(MacPolicy::Random
+            .to_display_string(false)
+            .contains(ICON_MASK));
+    }
+
+    #[test]
+    fn test_set_connection_policy_reactivates_active_connection() {
+        let runner = RecordingCommandRunner {
+            active: "aaaa\n5b1e-uuid\n",
+            calls: Mutex::new(Vec::new()),
+        };
+        assert!(set_connection_policy("5b1e-uuid", MacPolicy::Stable, &runner).unwrap());
+
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(
+            calls[0],
+            [
+                "nmcli",
+                "connection",
+                "modify",
+                "uuid",
+                "5b1e-uuid",
+                "802-11-wireless.cloned-mac-address",
+                "stable"
+            ]
+        );
+        assert_eq!(
+            calls.last().unwrap(),
+            &["nmcli", "connection", "up", "uuid", "5b1e-uuid"]
+        );
+    }
+
+    #[test]
+    fn test_set_connection_policy_leaves_inactive_connection_down() {
+        let runner = RecordingCommandRunner {
+            active: "aaaa\n",
+            calls: Mutex::new(Vec::new()),
+        };
+        assert!(set_connection_policy("5b1e-uuid", MacPolicy::Random, &runner).unwrap());
+        assert_eq!(runner.calls.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_iwd_network_policy() {
+        let permanent = parse_permanent_mac(
+            "3: wlan0: <BROADCAST,MULTICAST,UP> mtu 1500 qdisc noqueue state UP\n    \
+             link/ether 9a:11:22:33:44:55 brd ff:ff:ff:ff:ff:ff permaddr 00:1B:2C:3D:4E:5F\n",
+        );
+        assert_eq!(permanent.as_deref(), Some("00:1b:2c:3d:4e:5f"));
+        assert_eq!(
+            parse_permanent_mac("    link/ether 00:1b:2c:3d:4e:5f brd ff:ff:ff:ff:ff:ff\n")
+                .as_deref(),
+            Some("00:1b:2c:3d:4e:5f")
+        );
+
+        let stable = stable_ssid_mac("Cafe", "00:1b:2c:3d:4e:5f");
+        assert_eq!(stable, stable_ssid_mac("Cafe", "00:1b:2c:3d:4e:5f"));
+        assert_ne!(stable, stable_ssid_mac("Office", "00:1b:2c:3d:4e:5f"));
+        let first = u8::from_str_radix(&stable[..2], 16).unwrap();
+        assert_eq!(first & 0x03, 0x02);
+
+        let random = "[Security]\nPassphrase=secret\n\n[Settings]\nAlwaysRandomizeAddress=true\n";
+        let fixed = format!("[Settings]\nAddressOverride={stable}\n");
+        let hardware = "[Settings]\nAddressOverride=00:1B:2C:3D:4E:5F\n";
+        let permanent = permanent.as_deref();
+        assert_eq!(
+            parse_iwd_network_policy(random, permanent),
+            Some(MacPolicy::Random)
+        );
+        assert_eq!(
+            parse_iwd_network_policy(&fixed, permanent),
+            Some(MacPolicy::StableSsid)
+        );
+        assert_eq!(
+            parse_iwd_network_policy(hardware, permanent),
+            Some(MacPolicy::Permanent)
+        );
+        assert_eq!(
+            parse_iwd_network_policy("[Security]\nPassphrase=secret\n", permanent),
+            None
+        );
+    }
+
+    #[test]
+    fn test_remove_ini_value() {
+        let contents = "[Security]\nPassphrase=secret\n\n[Settings]\nAutoConnect=false\nAlwaysRandomizeAddress=true\n";
+        assert_eq!(
+            remove_ini_value(contents, "Settings", "AlwaysRandomizeAddress"),
+            "[Security]\nPassphrase=secret\n\n[Settings]\nAutoConnect=false\n"
+        );
+        assert_eq!(
+            remove_ini_value(contents, "Security", "AutoConnect"),
+            contents
+        );
+    }
+
+    #[test]
+    fn test_get_ini_value() {
+        let contents = "[General]\nEnableNetworkConfiguration=true\nAddressRandomization = network\n\n[Network]\nNameResolvingService=systemd\n";
+        assert_eq!(
+            get_ini_value(contents, "General", "AddressRandomization"),
+            Some("network".to_string())
+        );
+        assert_eq!(
+            get_ini_value(contents, "Network", "AddressRandomization"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_set_ini_value_replaces_existing_key() {
+        let contents = "[General]\nAddressRandomization=disabled\n\n[Network]\nEnableIPv6=true\n";
+        assert_eq!(
+            set_ini_value(contents, "General", "AddressRandomization", "network"),
+            "[General]\nAddressRandomization=network\n\n[Network]\nEnableIPv6=true\n"
+        );
+    }
+
+    #[test]
+    fn test_set_ini_value_adds_key_to_section() {
+        let contents = "[General]\nEnableNetworkConfiguration=true\n\n[Network]\nEnableIPv6=true\n";
+        assert_eq!(
+            set_ini_value(contents, "General", "AddressRandomization", "once"),
+            "[General]\nEnableNetworkConfiguration=true\nAddressRandomization=once\n\n[Network]\nEnableIPv6=true\n"
+        );
+    }
+
+    #[test]
+    fn test_set_ini_value_adds_section() {
+        assert_eq!(
+            set_ini_value("", "General", "AddressRandomization", "once"),
+            "[General]\nAddressRandomization=once\n"
+        );
+        assert_eq!(
+            set_ini_value(
+                "[Network]\nEnableIPv6=true\n",
+                "General",
+                "AddressRandomization",
+                "once"
+            ),
+            "[Network]\nEnableIPv6=true\n\n[General]\nAddressRandomization=once\n"
+        );
+    }
+}
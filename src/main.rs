@@ -2,8 +2,14 @@ mod streaming;
 
 // Import modules from the library crate
 use network_dmenu::{
-    bluetooth, command, constants, diagnostics, iwd, logger, networkmanager, nextdns, rfkill, ssh,
-    utils, SshProxyConfig, TorsocksConfig,
+    audit, bluetooth, captive_portal, command, connman, constants, diagnostics, dns_cache,
+    enterprise, ethernet, hidden_network, hotspot, iwd, iwd_dbus, kill_switch, logger, mac_policy,
+    metered, networkd, networkmanager, nextdns, nm_dbus, rfkill, roaming, saved_networks, ssh,
+    utils, vpn_profiles, wg_quick, wifi_details, wifi_security, wifi_share, wireguard,
+    wpa_supplicant, CaptivePortalConfig, HiddenSecurity, InsecureWifiConfig, KillSwitchConfig,
+    KillSwitchStatus, MacPolicy, MeteredConnection, MeteredSetting, OpenconnectProtocol,
+    PortalState, RoamingConfig, SecurityWarning, SshProxyConfig, TorsocksConfig, WifiBackend,
+    WifiBand,
 };
 
 #[cfg(feature = "firewalld")]
@@ -13,27 +19,36 @@ use network_dmenu::firewalld;
 extern crate log;
 #[cfg(feature = "tailscale")]
 use crate::utils::get_flag;
+use audit::{append_audit_record, AuditRecord, AuditingCommandRunner};
 use bluetooth::{get_connected_devices, handle_bluetooth_action, BluetoothAction};
 use clap::Parser;
-use command::{is_command_installed, CommandRunner, RealCommandRunner};
+use command::{is_command_installed, CommandRunner, RealCommandRunner, SCRIPT_COMMAND_TIMEOUT};
 use constants::*;
 use diagnostics::{diagnostic_action_to_string, handle_diagnostic_action, DiagnosticAction};
 use dirs::config_dir;
+use ethernet::EthernetAction;
 #[cfg(feature = "firewalld")]
 use firewalld::{handle_firewalld_action, FirewalldAction};
+use hotspot::{HotspotAction, HotspotSettings};
 use iwd::{connect_to_iwd_wifi, disconnect_iwd_wifi};
 use log::error;
+use networkd::NetworkdAction;
 use networkmanager::{
-    connect_to_nm_vpn, connect_to_nm_wifi, disconnect_nm_vpn, disconnect_nm_wifi,
+    connect_to_nm_vpn, connect_to_nm_wifi_on, disconnect_nm_vpn, disconnect_nm_wifi,
 };
 use nextdns::handle_nextdns_action;
 use nextdns::NextDnsAction;
 use notify_rust::Notification;
+use saved_networks::{SavedBackend, SavedConnectionAction};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs;
+use std::future::Future;
 use std::path::PathBuf;
 use std::process::Command;
+use std::time::{Duration, Instant};
+use wifi_share::{PasswordTarget, ShareTarget, WifiCredentials};
+use wpa_supplicant::{connect_to_wpa_wifi, disconnect_wpa_wifi};
 
 #[cfg(feature = "tailscale")]
 use network_dmenu::tailscale::{
@@ -41,7 +56,7 @@ use network_dmenu::tailscale::{
     DefaultNotificationSender, TailscaleAction, TailscaleState,
 };
 use network_dmenu::tor::{handle_tor_action, tor_action_to_string, TorAction};
-use utils::{check_captive_portal, get_wifi_interface};
+use utils::get_wifi_interface;
 
 /// Command-line arguments structure for the application.
 #[derive(Parser, Debug, Clone)]
@@ -62,6 +77,12 @@ struct Args {
     no_nextdns: bool,
     #[arg(long)]
     no_tor: bool,
+    #[arg(long)]
+    no_hotspot: bool,
+    #[arg(long)]
+    no_ethernet: bool,
+    #[arg(long)]
+    no_networkd: bool,
     #[cfg(feature = "firewalld")]
     #[arg(long)]
     no_firewalld: bool,
@@ -115,6 +136,12 @@ struct Args {
 
     #[arg(long, help = "Validate configuration file and exit")]
     validate_config: bool,
+
+    #[arg(
+        long,
+        help = "Watch the Wi-Fi signal and roam to better known networks"
+    )]
+    roam: bool,
 }
 
 /// Configuration structure for the application.
@@ -140,6 +167,24 @@ struct Config {
     ssh_proxies: std::collections::HashMap<String, SshProxyConfig>,
     #[serde(default)]
     torsocks_apps: std::collections::HashMap<String, TorsocksConfig>,
+    /// Leave speed tests out of the menu on metered connections instead of
+    /// asking for confirmation
+    #[serde(default)]
+    hide_speedtest_when_metered: bool,
+    #[serde(default)]
+    roaming: RoamingConfig,
+    /// Seconds before a copied Wi-Fi password is cleared from the clipboard
+    #[serde(default = "default_clipboard_clear_secs")]
+    clipboard_clear_secs: u64,
+    #[serde(default)]
+    insecure_wifi: InsecureWifiConfig,
+    #[serde(default)]
+    captive_portal: CaptivePortalConfig,
+    #[serde(default)]
+    kill_switch: KillSwitchConfig,
+    /// Shell commands run after joining a network, once past any captive portal
+    #[serde(default)]
+    on_connect: Vec<String>,
     dmenu_cmd: String,
     dmenu_args: String,
 }
@@ -149,6 +194,10 @@ struct Config {
 struct CustomAction {
     display: String,
     cmd: String,
+    /// Only show the action when the connection is (`true`) or is not
+    /// (`false`) metered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metered: Option<bool>,
 }
 
 /// Enum representing different types of actions that can be performed.
@@ -157,8 +206,11 @@ enum ActionType {
     Bluetooth(BluetoothAction),
     Custom(CustomAction),
     Diagnostic(DiagnosticAction),
+    Ethernet(EthernetAction),
     #[cfg(feature = "firewalld")]
     Firewalld(FirewalldAction),
+    Hotspot(HotspotAction),
+    Networkd(NetworkdAction),
     NextDns(nextdns::NextDnsAction),
     Ssh(network_dmenu::SshAction),
     System(SystemAction),
@@ -173,6 +225,9 @@ enum ActionType {
 #[derive(Debug)]
 enum SystemAction {
     EditConnections,
+    MacPolicy,
+    Metered(MeteredConnection),
+    SavedNetworks,
     RfkillBlock(String, String),   // (device_id, display_text)
     RfkillUnblock(String, String), // (device_id, display_text)
     AirplaneMode(bool),
@@ -181,10 +236,14 @@ enum SystemAction {
 /// Enum representing Wi-Fi-related actions.
 #[derive(Debug)]
 enum WifiAction {
-    Connect,
-    ConnectHidden,
-    Disconnect,
+    /// The radio is set when several radios are listed
+    Connect(Option<String>),
+    ConnectHidden(Option<String>),
+    Details,
+    Disconnect(Option<String>),
+    ImportQr,
     Network(String),
+    Share,
 }
 
 /// Enum representing VPN-related actions.
@@ -192,6 +251,11 @@ enum WifiAction {
 enum VpnAction {
     Connect(String),
     Disconnect(String),
+    ImportWireguard,
+    WireguardDetails,
+    ImportOpenvpn,
+    AddOpenconnect,
+    KillSwitch(KillSwitchStatus),
 }
 
 /// Formats an entry for display in the menu.
@@ -204,6 +268,10 @@ pub fn format_entry(action: &str, icon: &str, text: &str) -> String {
 }
 
 /// Helper function for serde default value
+fn default_clipboard_clear_secs() -> u64 {
+    30
+}
+
 fn default_true() -> bool {
     true
 }
@@ -243,6 +311,52 @@ use_dns_cache = true
 # Filter by country name (e.g., "USA", "Japan")
 # country_filter = "USA"
 
+# Speed tests ask for confirmation on metered connections; set to true to
+# hide them from the menu instead
+# hide_speedtest_when_metered = false
+
+# Seconds before a Wi-Fi password copied from the saved networks menu is
+# cleared from the clipboard
+# clipboard_clear_secs = 30
+
+# Open, WEP, WPA1 networks and known networks advertising weaker security
+# than saved ask for confirmation before connecting; a VPN or Tailscale exit
+# node can come up automatically once connected
+# [insecure_wifi]
+# confirm = true
+# vpn = "Work VPN"
+# exit_node = "exit-node-hostname"
+
+# Captive portals are detected after connecting; the login page opens in a
+# separate browser profile. The VPN and exit node above, and the on_connect
+# hooks, wait until the portal is passed
+# [captive_portal]
+# enabled = true
+# login_timeout_secs = 300
+# browser = "chromium --user-data-dir={{profile}} --app={{url}}"
+# Login scripts by SSID, called with the login URL and the SSID
+# [captive_portal.auto_login]
+# "Hotel Guest" = "~/.config/network-dmenu/hotel-login.sh"
+
+# The VPN kill switch, toggled from the menu, blocks traffic that does not go
+# through the VPN, WireGuard tunnel or Tailscale exit node it was enabled for,
+# until that tunnel is disconnected from the menu. Needs nft
+# [kill_switch]
+# allow_lan = false
+
+# Shell commands run after joining a network, with the SSID as $1
+# on_connect = ["notify-send \"Connected to $1\""]
+
+# Roaming watcher, started with --roam: switches to a known access point with
+# clearly better signal when the current one gets weak
+# [roaming]
+# interval_secs = 30
+# trigger_signal = 60
+# min_signal_gain = 15
+# confirm_scans = 2
+# cooldown_secs = 300
+# notify = true
+
 # Tor proxy configurations (requires tor and torsocks packages)
 # Disable with --no-tor flag
 [torsocks_apps]
@@ -262,6 +376,12 @@ use_dns_cache = true
 [[actions]]
 display = "🛡️ Example"
 cmd = "notify-send 'hello' 'world'"
+
+# Custom actions can be limited to metered (true) or unmetered (false) connections
+# [[actions]]
+# display = "🔄 Update system"
+# cmd = "pkcon update"
+# metered = false
 "#,
         DEFAULT_DMENU_CMD, DEFAULT_DMENU_ARGS
     )
@@ -434,6 +554,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let config = get_config(args.config.as_ref())?; // Load the configuration once
 
+    if args.roam {
+        let wifi_interface = get_wifi_interface(args.wifi_interface.as_deref());
+        return roaming::run_watcher(&wifi_interface, &config.roaming, &RealCommandRunner).await;
+    }
+
     check_required_commands(&config)?;
 
     // Performance optimization: We're using a more efficient approach for network scanning
@@ -466,18 +591,22 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     if !action.is_empty() {
         let selected_action = find_selected_action(&action, &actions)?;
-        let connected_devices = get_connected_devices(&command_runner)?;
+        let connected_devices = get_connected_devices(&command_runner).await?;
 
         let wifi_interface = get_wifi_interface(args.wifi_interface.as_deref());
-        set_action(
+        let audit_runner = AuditingCommandRunner::new(&command_runner);
+        let started = Instant::now();
+        let result = set_action(
             &wifi_interface,
             selected_action,
             &connected_devices,
-            &command_runner,
+            &audit_runner,
             args.profile,
             args.config.as_ref(),
         )
-        .await?;
+        .await;
+        audit_action(selected_action, &audit_runner, started.elapsed(), &result).await;
+        result?;
     }
     // When action is empty (user pressed Escape or closed window), just exit silently
 
@@ -505,6 +634,13 @@ fn action_to_string(action: &ActionType) -> String {
             SystemAction::EditConnections => {
                 format_entry(ACTION_TYPE_SYSTEM, ICON_SIGNAL, SYSTEM_EDIT_CONNECTIONS)
             }
+            SystemAction::SavedNetworks => {
+                format_entry(ACTION_TYPE_SYSTEM, ICON_SAVED, SYSTEM_SAVED_NETWORKS)
+            }
+            SystemAction::MacPolicy => {
+                format_entry(ACTION_TYPE_SYSTEM, ICON_MASK, SYSTEM_MAC_POLICY)
+            }
+            SystemAction::Metered(connection) => connection.to_toggle_string(),
             SystemAction::AirplaneMode(enable) => {
                 if *enable {
                     format_entry(ACTION_TYPE_SYSTEM, ICON_CROSS, SYSTEM_AIRPLANE_MODE_ON)
@@ -623,25 +759,50 @@ fn action_to_string(action: &ActionType) -> String {
         ActionType::Vpn(vpn_action) => match vpn_action {
             VpnAction::Connect(network) => format_entry(ACTION_TYPE_VPN, "", network),
             VpnAction::Disconnect(network) => format_entry(ACTION_TYPE_VPN, ICON_CROSS, network),
+            VpnAction::ImportWireguard => {
+                format_entry(ACTION_TYPE_VPN, ICON_KEY, VPN_IMPORT_WIREGUARD)
+            }
+            VpnAction::WireguardDetails => {
+                format_entry(ACTION_TYPE_VPN, ICON_LIST, VPN_WIREGUARD_DETAILS)
+            }
+            VpnAction::ImportOpenvpn => format_entry(ACTION_TYPE_VPN, ICON_KEY, VPN_IMPORT_OPENVPN),
+            VpnAction::AddOpenconnect => {
+                format_entry(ACTION_TYPE_VPN, ICON_LOCK, VPN_ADD_OPENCONNECT)
+            }
+            VpnAction::KillSwitch(status) => {
+                format_entry(ACTION_TYPE_VPN, status.icon(), &status.to_display_string())
+            }
         },
         ActionType::Wifi(wifi_action) => match wifi_action {
             WifiAction::Network(network) => format_entry(ACTION_TYPE_WIFI, "", network),
-            WifiAction::Disconnect => format_entry(ACTION_TYPE_WIFI, ICON_CROSS, WIFI_DISCONNECT),
-            WifiAction::Connect => format_entry(ACTION_TYPE_WIFI, ICON_SIGNAL, WIFI_CONNECT),
-            WifiAction::ConnectHidden => {
-                format_entry(ACTION_TYPE_WIFI, ICON_SIGNAL, WIFI_CONNECT_HIDDEN)
+            WifiAction::Disconnect(radio) => wifi_radio_entry(ICON_CROSS, WIFI_DISCONNECT, radio),
+            WifiAction::Connect(radio) => wifi_radio_entry(ICON_SIGNAL, WIFI_CONNECT, radio),
+            WifiAction::ConnectHidden(radio) => {
+                wifi_radio_entry(ICON_SIGNAL, WIFI_CONNECT_HIDDEN, radio)
+            }
+            WifiAction::Details => {
+                format_entry(ACTION_TYPE_WIFI, ICON_ANTENNA, WIFI_ACCESS_POINT_DETAILS)
             }
+            WifiAction::Share => format_entry(ACTION_TYPE_WIFI, ICON_QR, WIFI_SHARE),
+            WifiAction::ImportQr => format_entry(ACTION_TYPE_WIFI, ICON_QR, WIFI_IMPORT_QR),
         },
         ActionType::Bluetooth(bluetooth_action) => match bluetooth_action {
             BluetoothAction::ToggleConnect(device) => device.to_string(),
         },
         ActionType::Diagnostic(diagnostic_action) => diagnostic_action_to_string(diagnostic_action),
+        ActionType::Ethernet(ethernet_action) => {
+            ethernet::ethernet_action_to_string(ethernet_action)
+        }
+        ActionType::Networkd(networkd_action) => {
+            networkd::networkd_action_to_string(networkd_action)
+        }
         #[cfg(feature = "firewalld")]
         ActionType::Firewalld(firewalld_action) => firewalld_action.to_display_string_simple(),
         ActionType::NextDns(nextdns_action) => {
             format_entry(ACTION_TYPE_NEXTDNS, "", &nextdns_action.to_string())
         }
         ActionType::Ssh(ssh_action) => ssh::ssh_action_to_string(ssh_action),
+        ActionType::Hotspot(hotspot_action) => hotspot::hotspot_action_to_string(hotspot_action),
         ActionType::Tor(tor_action) => tor_action_to_string(tor_action),
     }
 }
@@ -663,6 +824,13 @@ fn find_selected_action<'a>(
                 SystemAction::EditConnections => {
                     action == format_entry(ACTION_TYPE_SYSTEM, ICON_SIGNAL, SYSTEM_EDIT_CONNECTIONS)
                 }
+                SystemAction::SavedNetworks => {
+                    action == format_entry(ACTION_TYPE_SYSTEM, ICON_SAVED, SYSTEM_SAVED_NETWORKS)
+                }
+                SystemAction::MacPolicy => {
+                    action == format_entry(ACTION_TYPE_SYSTEM, ICON_MASK, SYSTEM_MAC_POLICY)
+                }
+                SystemAction::Metered(connection) => action == connection.to_toggle_string(),
                 SystemAction::AirplaneMode(enable) => {
                     if *enable {
                         action
@@ -800,19 +968,43 @@ fn find_selected_action<'a>(
                 VpnAction::Disconnect(network) => {
                     action == format_entry(ACTION_TYPE_VPN, ICON_CROSS, network)
                 }
+                VpnAction::ImportWireguard => {
+                    action == format_entry(ACTION_TYPE_VPN, ICON_KEY, VPN_IMPORT_WIREGUARD)
+                }
+                VpnAction::WireguardDetails => {
+                    action == format_entry(ACTION_TYPE_VPN, ICON_LIST, VPN_WIREGUARD_DETAILS)
+                }
+                VpnAction::ImportOpenvpn => {
+                    action == format_entry(ACTION_TYPE_VPN, ICON_KEY, VPN_IMPORT_OPENVPN)
+                }
+                VpnAction::AddOpenconnect => {
+                    action == format_entry(ACTION_TYPE_VPN, ICON_LOCK, VPN_ADD_OPENCONNECT)
+                }
+                VpnAction::KillSwitch(status) => {
+                    action
+                        == format_entry(ACTION_TYPE_VPN, status.icon(), &status.to_display_string())
+                }
             },
             ActionType::Wifi(wifi_action) => match wifi_action {
                 WifiAction::Network(network) => {
                     action == format_entry(ACTION_TYPE_WIFI, "", network)
                 }
-                WifiAction::Disconnect => {
-                    action == format_entry(ACTION_TYPE_WIFI, ICON_CROSS, WIFI_DISCONNECT)
+                WifiAction::Disconnect(radio) => {
+                    action == wifi_radio_entry(ICON_CROSS, WIFI_DISCONNECT, radio)
                 }
-                WifiAction::Connect => {
-                    action == format_entry(ACTION_TYPE_WIFI, ICON_SIGNAL, WIFI_CONNECT)
+                WifiAction::Connect(radio) => {
+                    action == wifi_radio_entry(ICON_SIGNAL, WIFI_CONNECT, radio)
                 }
-                WifiAction::ConnectHidden => {
-                    action == format_entry(ACTION_TYPE_WIFI, ICON_SIGNAL, WIFI_CONNECT_HIDDEN)
+                WifiAction::ConnectHidden(radio) => {
+                    action == wifi_radio_entry(ICON_SIGNAL, WIFI_CONNECT_HIDDEN, radio)
+                }
+                WifiAction::Details => {
+                    action
+                        == format_entry(ACTION_TYPE_WIFI, ICON_ANTENNA, WIFI_ACCESS_POINT_DETAILS)
+                }
+                WifiAction::Share => action == format_entry(ACTION_TYPE_WIFI, ICON_QR, WIFI_SHARE),
+                WifiAction::ImportQr => {
+                    action == format_entry(ACTION_TYPE_WIFI, ICON_QR, WIFI_IMPORT_QR)
                 }
             },
             ActionType::Bluetooth(bluetooth_action) => match bluetooth_action {
@@ -821,6 +1013,12 @@ fn find_selected_action<'a>(
             ActionType::Diagnostic(diagnostic_action) => {
                 action == diagnostic_action_to_string(diagnostic_action)
             }
+            ActionType::Ethernet(ethernet_action) => {
+                action == ethernet::ethernet_action_to_string(ethernet_action)
+            }
+            ActionType::Networkd(networkd_action) => {
+                action == networkd::networkd_action_to_string(networkd_action)
+            }
             #[cfg(feature = "firewalld")]
             ActionType::Firewalld(firewalld_action) => {
                 action == firewalld_action.to_display_string_simple()
@@ -829,6 +1027,9 @@ fn find_selected_action<'a>(
                 action == format_entry(ACTION_TYPE_NEXTDNS, "", &nextdns_action.to_string())
             }
             ActionType::Ssh(ssh_action) => action == ssh::ssh_action_to_string(ssh_action),
+            ActionType::Hotspot(hotspot_action) => {
+                action == hotspot::hotspot_action_to_string(hotspot_action)
+            }
             ActionType::Tor(tor_action) => action == tor_action_to_string(tor_action),
         })
         .ok_or(format!("Action not found: {action}").into())
@@ -875,6 +1076,8 @@ fn handle_custom_action(action: &CustomAction) -> Result<bool, Box<dyn Error>> {
 /// Handles a system action.
 async fn handle_system_action(
     action: &SystemAction,
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
     profile: bool,
 ) -> Result<bool, Box<dyn Error>> {
     // Helper function to handle rfkill block/unblock operations
@@ -939,18 +1142,44 @@ async fn handle_system_action(
             let status = Command::new("nm-connection-editor").status()?;
             Ok(status.success())
         }
+        SystemAction::SavedNetworks => handle_saved_networks(command_runner, config_path).await,
+        SystemAction::MacPolicy => {
+            let config = get_config(config_path)?;
+            let current = mac_policy::get_global_policy();
+            match select_mac_policy(&config, &mac_policy::global_policies(), current)? {
+                Some(policy) => mac_policy::set_global_policy(policy, command_runner),
+                None => Ok(false),
+            }
+        }
+        SystemAction::Metered(connection) => {
+            let setting = if connection.metered {
+                MeteredSetting::No
+            } else {
+                MeteredSetting::Yes
+            };
+            metered::set_connection_metered(&connection.uuid, setting, command_runner)
+        }
         SystemAction::AirplaneMode(enable) => {
-            if *enable {
+            let enable_offline = *enable;
+            if let Some(result) =
+                connman::with_client(move |client| client.set_offline_mode(enable_offline)).await
+            {
+                // ConnMan keeps its own offline mode, which also drives rfkill
+                result?;
+            } else if *enable {
                 // Block all radio devices (wifi, bluetooth, etc.)
                 rfkill::block_device_type("all").await?;
+            } else {
+                // Unblock all radio devices
+                rfkill::unblock_device_type("all").await?;
+            }
+            if *enable {
                 // Notify user
                 let _ = Notification::new()
                     .summary("Airplane Mode Enabled")
                     .body("All wireless devices have been turned off")
                     .show();
             } else {
-                // Unblock all radio devices
-                rfkill::unblock_device_type("all").await?;
                 // Notify user
                 let _ = Notification::new()
                     .summary("Airplane Mode Disabled")
@@ -967,6 +1196,9 @@ async fn handle_system_action(
             SystemAction::RfkillBlock(device_id, _) => format!("Block {}", device_id),
             SystemAction::RfkillUnblock(device_id, _) => format!("Unblock {}", device_id),
             SystemAction::EditConnections => "Edit connections".to_string(),
+            SystemAction::SavedNetworks => "Saved networks".to_string(),
+            SystemAction::MacPolicy => "MAC address policy".to_string(),
+            SystemAction::Metered(connection) => format!("Metered {}", connection.name),
             SystemAction::AirplaneMode(enable) => {
                 format!("Airplane mode {}", if *enable { "ON" } else { "OFF" })
             }
@@ -1001,7 +1233,12 @@ pub fn parse_vpn_action(action: &str) -> Result<&str, Box<dyn std::error::Error>
     // Use unwrap_or to handle cases where there might not be a next character
     let first_char = action[emoji_pos..].chars().next().unwrap_or(' ');
     let name_start = emoji_pos + first_char.len_utf8();
-    let name = action[name_start..].trim();
+    // Anything after a tab is a status badge, not part of the name
+    let name = action[name_start..]
+        .split('\t')
+        .next()
+        .unwrap_or_default()
+        .trim();
 
     if name.is_empty() {
         return Err("No name found after emoji".into());
@@ -1037,11 +1274,51 @@ pub fn parse_wifi_action(action: &str) -> Result<(&str, &str), Box<dyn Error>> {
 async fn handle_vpn_action(
     action: &VpnAction,
     command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
 ) -> Result<bool, Box<dyn Error>> {
     match action {
         VpnAction::Connect(network) => {
-            if is_command_installed("nmcli") {
-                connect_to_nm_vpn(network, command_runner)?;
+            // NetworkManager has no secret agent to ask over D-Bus, so profiles
+            // wanting a password or one-time code go through nmcli and a prompt
+            let needs_secrets = || {
+                is_command_installed("nmcli")
+                    && parse_vpn_action(network)
+                        .ok()
+                        .and_then(|name| vpn_profiles::load_vpn_profile(name, command_runner).ok())
+                        .is_some_and(|profile| profile.needs_interactive_secrets())
+            };
+            if wg_quick::is_wg_quick_action(network) {
+                wg_quick::set_tunnel(network, true, command_runner).await?;
+            } else if needs_secrets() {
+                connect_to_nm_vpn(network, command_runner).await?;
+            } else if let Some(result) = audited_dbus_call(
+                command_runner,
+                "NetworkManager",
+                "connect_vpn",
+                network,
+                nm_dbus::with_client({
+                    let network = network.clone();
+                    move |client| client.connect_vpn(&network)
+                }),
+            )
+            .await
+            {
+                result?;
+            } else if is_command_installed("nmcli") {
+                connect_to_nm_vpn(network, command_runner).await?;
+            } else if let Some(result) = audited_dbus_call(
+                command_runner,
+                "connman",
+                "connect_vpn",
+                network,
+                connman::with_client({
+                    let network = network.clone();
+                    move |client| client.connect_vpn(&network)
+                }),
+            )
+            .await
+            {
+                result?;
             }
 
             // Check mullvad status, assert errors in debug mode
@@ -1057,13 +1334,250 @@ async fn handle_vpn_action(
             Ok(true)
         }
         VpnAction::Disconnect(network) => {
-            let status = if is_command_installed("nmcli") {
-                disconnect_nm_vpn(network, command_runner)?
+            let status = if wg_quick::is_wg_quick_action(network) {
+                wg_quick::set_tunnel(network, false, command_runner).await?
+            } else if let Some(result) = audited_dbus_call(
+                command_runner,
+                "NetworkManager",
+                "disconnect_vpn",
+                network,
+                nm_dbus::with_client({
+                    let network = network.clone();
+                    move |client| client.disconnect_vpn(&network)
+                }),
+            )
+            .await
+            {
+                result?
+            } else if is_command_installed("nmcli") {
+                disconnect_nm_vpn(parse_vpn_action(network)?, command_runner).await?
+            } else if let Some(result) = audited_dbus_call(
+                command_runner,
+                "connman",
+                "disconnect_vpn",
+                network,
+                connman::with_client({
+                    let network = network.clone();
+                    move |client| client.disconnect_vpn(&network)
+                }),
+            )
+            .await
+            {
+                result?
             } else {
                 true
             };
+            if status {
+                lift_kill_switch_for(parse_vpn_action(network)?, command_runner);
+            }
             Ok(status)
         }
+        VpnAction::ImportWireguard => {
+            let path = utils::prompt_for_visible_text("WireGuard config (.conf)")?;
+            if path.is_empty() {
+                return Ok(false);
+            }
+            wireguard::import_wireguard_config(&path, command_runner)?;
+            notify_vpn_profile(&format!("Imported {path}"));
+            Ok(true)
+        }
+        VpnAction::WireguardDetails => handle_wireguard_details(command_runner, config_path).await,
+        VpnAction::ImportOpenvpn => {
+            let path = utils::prompt_for_visible_text("OpenVPN config (.ovpn)")?;
+            if path.is_empty() {
+                return Ok(false);
+            }
+            vpn_profiles::import_openvpn_config(&path, command_runner)?;
+            notify_vpn_profile(&format!("Imported {path}"));
+            Ok(true)
+        }
+        VpnAction::AddOpenconnect => handle_add_openconnect(command_runner, config_path),
+        VpnAction::KillSwitch(status) => {
+            handle_kill_switch(status, command_runner, config_path).await
+        }
+    }
+}
+
+/// Enables the kill switch for the tunnel that is up, or lifts it.
+async fn handle_kill_switch(
+    status: &KillSwitchStatus,
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    if let KillSwitchStatus::Armed { tunnel, .. } = status {
+        kill_switch::disable(command_runner)?;
+        notify_vpn_profile(&format!("Kill switch for {} lifted", tunnel.name));
+        return Ok(true);
+    }
+
+    #[allow(unused_mut)]
+    let mut tunnels = kill_switch::active_tunnels(command_runner).await;
+    #[cfg(feature = "tailscale")]
+    if is_command_installed("tailscale")
+        && network_dmenu::tailscale::is_exit_node_active(
+            &TailscaleState::fetch(command_runner).await,
+        )
+    {
+        tunnels.push(kill_switch::Tunnel::tailscale_exit_node());
+    }
+
+    let tunnel = match tunnels.as_slice() {
+        [] => return Err("Bring a VPN, WireGuard tunnel or exit node up first".into()),
+        [tunnel] => tunnel,
+        _ => match select_in_submenu(&config, &tunnels, |tunnel| tunnel.to_display_string())? {
+            Some(tunnel) => tunnel,
+            None => return Ok(false),
+        },
+    };
+
+    kill_switch::enable(tunnel, &config.kill_switch, command_runner)?;
+    notify_vpn_profile(&format!(
+        "Kill switch on: traffic outside {} is blocked",
+        tunnel.interface
+    ));
+    Ok(true)
+}
+
+/// Lifts the kill switch when its tunnel was taken down on purpose.
+fn lift_kill_switch_for(name: &str, command_runner: &dyn CommandRunner) {
+    match kill_switch::lift_for(name, command_runner) {
+        Ok(true) => notify_vpn_profile(&format!("Kill switch for {name} lifted")),
+        Ok(false) => {}
+        Err(e) => error!("Failed to lift the kill switch: {}", e),
+    }
+}
+
+fn notify_vpn_profile(body: &str) {
+    let _e = Notification::new().summary("VPN").body(body).show();
+    if let Err(ref e) = _e {
+        error!("Failed to show notification: {}", e);
+    }
+}
+
+/// Asks for the protocol, gateway, name and user of a new OpenConnect VPN.
+fn handle_add_openconnect(
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    let Some(protocol) = select_in_submenu(&config, &OpenconnectProtocol::ALL, |protocol| {
+        protocol.to_display_string()
+    })?
+    else {
+        return Ok(false);
+    };
+
+    let gateway = utils::prompt_for_visible_text("VPN gateway")?;
+    if gateway.is_empty() {
+        return Ok(false);
+    }
+    let name = utils::prompt_for_visible_text(&format!("Connection name [{gateway}]"))?;
+    let name = if name.is_empty() {
+        gateway.clone()
+    } else {
+        name
+    };
+    let user = utils::prompt_for_visible_text("Username (optional)")?;
+
+    vpn_profiles::add_openconnect_profile(
+        &name,
+        *protocol,
+        &gateway,
+        Some(user.as_str()),
+        command_runner,
+    )?;
+    notify_vpn_profile(&format!("Added {name}"));
+    Ok(true)
+}
+
+/// Lists the peers of every WireGuard interface and shows the one picked.
+async fn handle_wireguard_details(
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    let now = std::time::SystemTime::now();
+    let interfaces = wireguard::get_wireguard_interfaces(true, command_runner).await?;
+    let peers: Vec<_> = interfaces
+        .iter()
+        .flat_map(|interface| interface.peers.iter().map(move |peer| (interface, peer)))
+        .collect();
+    if peers.is_empty() {
+        return Err("No WireGuard peers found".into());
+    }
+
+    let Some((interface, peer)) = select_in_submenu(&config, &peers, |(interface, peer)| {
+        format!("{}: {}", interface.name, peer.to_display_string(now))
+    })?
+    else {
+        return Ok(false);
+    };
+
+    let handshake = match peer.handshake_age(now) {
+        Some(age) => format!("{} ago", wireguard::format_age(age)),
+        None => "never".to_string(),
+    };
+    let body = format!(
+        "Peer: {}\nEndpoint: {}\nLatest handshake: {}{}\nReceived: {}\nSent: {}\nAllowed IPs: {}",
+        peer.public_key,
+        peer.endpoint.as_deref().unwrap_or("none"),
+        handshake,
+        if peer.is_stale(now) { " (stale)" } else { "" },
+        wireguard::format_bytes(peer.rx_bytes),
+        wireguard::format_bytes(peer.tx_bytes),
+        peer.allowed_ips.join(", "),
+    );
+    let _e = Notification::new()
+        .summary(&format!("WireGuard {}", interface.name))
+        .body(&body)
+        .show();
+    if let Err(ref e) = _e {
+        error!("Failed to show notification: {}", e);
+    }
+    Ok(true)
+}
+
+/// Brings a wired profile up or down.
+async fn handle_ethernet_action(
+    action: &EthernetAction,
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    match action {
+        EthernetAction::Connect(profile) => {
+            let interfaces = ethernet::list_ethernet_interfaces();
+            let connected =
+                ethernet::connect_wired_profile(profile, &interfaces, command_runner).await?;
+            if connected {
+                after_connect(None, command_runner, config_path).await;
+            }
+            Ok(connected)
+        }
+        EthernetAction::Disconnect(profile) => {
+            ethernet::disconnect_wired_profile(profile, command_runner).await
+        }
+    }
+}
+
+/// Shows the operations for a networkd link and runs the one picked.
+fn handle_networkd_action(
+    action: &NetworkdAction,
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    let NetworkdAction::Link(link) = action;
+    let operations = networkd::get_link_operations(link);
+    match select_in_submenu(&config, &operations, |operation| {
+        operation.to_display_string()
+    })? {
+        Some(operation) => networkd::run_link_operation(link, *operation, command_runner),
+        None => Ok(false),
     }
 }
 
@@ -1072,17 +1586,82 @@ async fn handle_wifi_action(
     action: &WifiAction,
     wifi_interface: &str,
     command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
 ) -> Result<bool, Box<dyn Error>> {
+    let wifi_interface = match action {
+        WifiAction::Connect(Some(radio))
+        | WifiAction::ConnectHidden(Some(radio))
+        | WifiAction::Disconnect(Some(radio)) => radio.as_str(),
+        _ => wifi_interface,
+    };
+
     match action {
-        WifiAction::Disconnect => {
-            let status = if is_command_installed("nmcli") {
-                disconnect_nm_wifi(wifi_interface, command_runner)?
-            } else {
-                disconnect_iwd_wifi(wifi_interface, command_runner)?
+        WifiAction::Details => handle_wifi_details(command_runner, config_path).await,
+        WifiAction::Share => handle_wifi_share(wifi_interface, command_runner, config_path).await,
+        WifiAction::ImportQr => {
+            let text = match utils::read_clipboard() {
+                Ok(text) if wifi_share::is_qr_payload(&text) => text,
+                _ => utils::prompt_for_visible_text("WIFI: code")?,
+            };
+            let credentials = WifiCredentials::from_qr_payload(&text)?;
+            let result =
+                wifi_share::connect_with_credentials(&credentials, wifi_interface, command_runner)
+                    .await?;
+            if result {
+                after_connect(Some(&credentials.ssid), command_runner, config_path).await;
+            }
+            Ok(result)
+        }
+        WifiAction::Disconnect(_) => {
+            let status = match WifiBackend::detect(wifi_interface, command_runner).await {
+                Some(WifiBackend::NetworkManager) => match audited_dbus_call(
+                    command_runner,
+                    "NetworkManager",
+                    "disconnect_wifi",
+                    wifi_interface,
+                    nm_dbus::with_client({
+                        let interface = wifi_interface.to_string();
+                        move |client| client.disconnect_wifi(Some(&interface))
+                    }),
+                )
+                .await
+                {
+                    Some(result) => result?,
+                    None => disconnect_nm_wifi(wifi_interface, command_runner).await?,
+                },
+                Some(WifiBackend::Connman) => audited_dbus_call(
+                    command_runner,
+                    "connman",
+                    "disconnect_wifi",
+                    wifi_interface,
+                    connman::with_client(|client| client.disconnect_wifi()),
+                )
+                .await
+                .transpose()?
+                .unwrap_or(false),
+                Some(WifiBackend::Iwd) => match audited_dbus_call(
+                    command_runner,
+                    "iwd",
+                    "disconnect",
+                    wifi_interface,
+                    iwd_dbus::with_client({
+                        let interface = wifi_interface.to_string();
+                        move |client| client.disconnect(Some(&interface))
+                    }),
+                )
+                .await
+                {
+                    Some(result) => result?,
+                    None => disconnect_iwd_wifi(wifi_interface, command_runner).await?,
+                },
+                Some(WifiBackend::WpaSupplicant) => {
+                    disconnect_wpa_wifi(wifi_interface, command_runner).await?
+                }
+                None => false,
             };
             Ok(status)
         }
-        WifiAction::Connect => {
+        WifiAction::Connect(_) => {
             let status = Command::new("nmcli")
                 .arg("device")
                 .arg("connect")
@@ -1091,61 +1670,173 @@ async fn handle_wifi_action(
 
             // Only check for captive portal if connection was successful
             if status.success() {
-                // Check for captive portal, log errors in debug mode
-                let _e = check_captive_portal().await;
-                if let Err(ref e) = _e {
-                    error!("Failed to check captive portal: {}", e);
-                }
+                after_connect(None, command_runner, config_path).await;
             }
 
             Ok(status.success())
         }
-        WifiAction::ConnectHidden => {
+        WifiAction::ConnectHidden(_) => {
+            let config = get_config(config_path)?;
             let ssid = utils::prompt_for_ssid()?;
-            let network = format_entry("wifi", ICON_SIGNAL, &format!("{ssid}\tUNKNOWN\t"));
-            // Note: nmcli hidden network connection requires SSID input
-            // so we will use iwd directly for the moment
-            let connection_result = if is_command_installed("iwctl") {
-                let result = connect_to_iwd_wifi(wifi_interface, &network, true, command_runner)?;
-                if result {
-                    // Check for captive portal, log errors in debug mode
-                    let _e = check_captive_portal().await;
-                    if let Err(ref e) = _e {
-                        error!("Failed to check captive portal: {}", e);
-                    }
-                }
-                result
+            let Some(security) = select_in_submenu(&config, &HiddenSecurity::ALL, |security| {
+                security.to_display_string()
+            })?
+            else {
+                return Ok(false);
+            };
+
+            let connected = if *security == HiddenSecurity::Enterprise {
+                let Some(credentials) = enterprise::prompt_enterprise_credentials(
+                    &ssid,
+                    &config.dmenu_cmd,
+                    &config.dmenu_args,
+                )?
+                else {
+                    return Ok(false);
+                };
+                let credentials = enterprise::EnterpriseCredentials {
+                    hidden: true,
+                    ..credentials
+                };
+                enterprise::connect_enterprise_wifi(
+                    &ssid,
+                    &credentials,
+                    wifi_interface,
+                    command_runner,
+                )
+                .await?
             } else {
-                false
+                let password = if security.needs_password() {
+                    Some(utils::prompt_for_password(&ssid)?)
+                } else {
+                    None
+                };
+                hidden_network::connect_hidden_wifi(
+                    &ssid,
+                    *security,
+                    password.as_deref(),
+                    wifi_interface,
+                    command_runner,
+                )
+                .await?
             };
 
-            Ok(connection_result)
+            if connected {
+                after_connect(Some(&ssid), command_runner, config_path).await;
+            }
+
+            Ok(connected)
         }
         WifiAction::Network(network) => {
-            let connection_result = if is_command_installed("nmcli") {
-                // For NetworkManager, we ensure connection is complete before checking captive portal
-                let result = connect_to_nm_wifi(network, false, command_runner)?;
-                // Only check for captive portal if connection was successful
-                if result {
-                    // Check for captive portal, log errors in debug mode
-                    let _e = check_captive_portal().await;
-                    if let Err(ref e) = _e {
-                        error!("Failed to check captive portal: {}", e);
-                    }
-                }
-                result
-            } else if is_command_installed("iwctl") {
-                let result = connect_to_iwd_wifi(wifi_interface, network, false, command_runner)?;
-                // For IWD, we check after connection attempt
-                let _e = check_captive_portal().await;
-                if let Err(ref e) = _e {
-                    error!("Failed to check captive portal: {}", e);
+            let (ssid, security) = network_dmenu::parse_wifi_action(network)?;
+            // Entries are tagged with their radio when several are present
+            let radio = network_dmenu::parse_wifi_interface(network);
+            let wifi_interface = radio.unwrap_or(wifi_interface);
+            let warning = if network.starts_with(ICON_CHECK) {
+                None
+            } else {
+                wifi_security::check_network(ssid, security, command_runner).await
+            };
+            if let Some(warning) = &warning {
+                if !confirm_insecure_wifi(ssid, warning, config_path)? {
+                    return Ok(false);
                 }
-                result
+            }
+            let needs_enterprise_profile = enterprise::is_enterprise_security(security)
+                && !enterprise::is_known_network(ssid, wifi_interface, command_runner).await?;
+
+            // For NetworkManager, we ensure connection is complete before checking captive portal
+            let connection_result = if needs_enterprise_profile {
+                let config = get_config(config_path)?;
+                let Some(credentials) = enterprise::prompt_enterprise_credentials(
+                    ssid,
+                    &config.dmenu_cmd,
+                    &config.dmenu_args,
+                )?
+                else {
+                    return Ok(false);
+                };
+                enterprise::connect_enterprise_wifi(
+                    ssid,
+                    &credentials,
+                    wifi_interface,
+                    command_runner,
+                )
+                .await?
             } else {
-                false
+                match WifiBackend::detect(wifi_interface, command_runner).await {
+                    Some(WifiBackend::NetworkManager) => {
+                        // Profiles the D-Bus settings builder cannot express go through nmcli
+                        let nm_result = if nm_dbus::supports_security(security) {
+                            audited_dbus_call(
+                                command_runner,
+                                "NetworkManager",
+                                "connect_wifi",
+                                ssid,
+                                nm_dbus::with_client({
+                                    let (network, interface) =
+                                        (network.clone(), wifi_interface.to_string());
+                                    move |client| client.connect_wifi(&network, Some(&interface))
+                                }),
+                            )
+                            .await
+                        } else {
+                            None
+                        };
+                        match nm_result {
+                            Some(result) => result?,
+                            None => {
+                                connect_to_nm_wifi_on(network, false, radio, command_runner).await?
+                            }
+                        }
+                    }
+                    Some(WifiBackend::Connman) => audited_dbus_call(
+                        command_runner,
+                        "connman",
+                        "connect_wifi",
+                        ssid,
+                        connman::with_client({
+                            let network = network.clone();
+                            move |client| client.connect_wifi(&network)
+                        }),
+                    )
+                    .await
+                    .transpose()?
+                    .unwrap_or(false),
+                    Some(WifiBackend::Iwd) => match audited_dbus_call(
+                        command_runner,
+                        "iwd",
+                        "connect_wifi",
+                        ssid,
+                        iwd_dbus::with_client({
+                            let network = network.to_string();
+                            let interface = wifi_interface.to_string();
+                            move |client| client.connect_wifi(&network, Some(&interface))
+                        }),
+                    )
+                    .await
+                    {
+                        Some(result) => result?,
+                        None => {
+                            connect_to_iwd_wifi(wifi_interface, network, false, command_runner)
+                                .await?
+                        }
+                    },
+                    Some(WifiBackend::WpaSupplicant) => {
+                        connect_to_wpa_wifi(wifi_interface, network, false, command_runner).await?
+                    }
+                    None => false,
+                }
             };
 
+            // The VPN and exit node cannot come up before the portal is passed
+            if connection_result
+                && after_connect(Some(ssid), command_runner, config_path).await
+                && warning.is_some()
+            {
+                protect_insecure_wifi(command_runner, config_path).await?;
+            }
+
             // Check mullvad status, log errors in debug mode
             #[cfg(feature = "tailscale")]
             {
@@ -1160,6 +1851,398 @@ async fn handle_wifi_action(
     }
 }
 
+/// Shows `items` in a submenu and returns the one picked, if any.
+fn select_in_submenu<'a, T>(
+    config: &Config,
+    items: &'a [T],
+    to_display: impl Fn(&T) -> String,
+) -> Result<Option<&'a T>, Box<dyn Error>> {
+    let entries: Vec<String> = items.iter().map(to_display).collect();
+    let selected =
+        network_dmenu::select_from_menu(&config.dmenu_cmd, &config.dmenu_args, &entries)?;
+
+    Ok(selected.and_then(|selected| {
+        entries
+            .iter()
+            .position(|entry| *entry == selected)
+            .map(|i| &items[i])
+    }))
+}
+
+/// Shows the access points of a network and handles the one picked.
+async fn handle_wifi_details(
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    let access_points = wifi_details::get_access_points(command_runner).await?;
+    let networks = wifi_details::group_by_ssid(&access_points);
+    let Some((ssid, bsses)) = select_in_submenu(&config, &networks, |(ssid, bsses)| {
+        wifi_details::format_network_summary(ssid, bsses)
+    })?
+    else {
+        return Ok(false);
+    };
+
+    let actions = wifi_details::get_detail_actions(ssid, bsses);
+    match select_in_submenu(&config, &actions, |action| action.to_display_string())? {
+        Some(action) => wifi_details::handle_wifi_detail_action(action, command_runner).await,
+        None => Ok(false),
+    }
+}
+
+/// Shares the active Wi-Fi network as a QR code or `WIFI:` text.
+async fn handle_wifi_share(
+    wifi_interface: &str,
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    let credentials =
+        wifi_share::get_active_wifi_credentials(wifi_interface, command_runner).await?;
+    share_wifi_credentials(&config, &credentials)
+}
+
+/// Asks where to send `credentials` and shares them there.
+fn share_wifi_credentials(
+    config: &Config,
+    credentials: &WifiCredentials,
+) -> Result<bool, Box<dyn Error>> {
+    let Some(target) = select_in_submenu(config, &ShareTarget::ALL, |target| {
+        target.to_display_string()
+    })?
+    else {
+        return Ok(false);
+    };
+
+    let message = wifi_share::share_credentials(credentials, *target)?;
+    let _e = Notification::new()
+        .summary("Wi-Fi share")
+        .body(&message)
+        .show();
+    if let Err(ref e) = _e {
+        error!("Failed to show notification: {}", e);
+    }
+
+    Ok(true)
+}
+
+/// Starts, stops or inspects the Wi-Fi hotspot.
+async fn handle_hotspot_action(
+    action: &HotspotAction,
+    wifi_interface: &str,
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    let HotspotAction::Start = action else {
+        let status = hotspot::get_hotspot_status(wifi_interface, command_runner)
+            .await
+            .ok_or("No hotspot is running")?;
+        return match action {
+            HotspotAction::Share(_) => {
+                let credentials = hotspot::hotspot_credentials(&status, command_runner).await?;
+                share_wifi_credentials(&config, &credentials)
+            }
+            HotspotAction::Clients(_) => {
+                let clients = hotspot::get_hotspot_clients(&status.interface, command_runner).await;
+                if clients.is_empty() {
+                    let _ = Notification::new()
+                        .summary("Hotspot")
+                        .body(&format!("No clients connected to {}", status.ssid))
+                        .show();
+                } else {
+                    select_in_submenu(&config, &clients, |client| client.to_display_string())?;
+                }
+                Ok(true)
+            }
+            _ => hotspot::stop_hotspot(&status, command_runner).await,
+        };
+    };
+
+    let bands = [WifiBand::Band2_4GHz, WifiBand::Band5GHz];
+    let Some(band) = select_in_submenu(&config, &bands, |band| format!("{ICON_ANTENNA} {band}"))?
+    else {
+        return Ok(false);
+    };
+
+    let default_ssid = hotspot::default_ssid();
+    let ssid = utils::prompt_for_visible_text(&format!("Hotspot name [{default_ssid}]"))?;
+    let ssid = if ssid.is_empty() { default_ssid } else { ssid };
+    let password = utils::prompt_for_text(&format!("Password for {ssid} (empty to generate one)"))?;
+    let password = if password.is_empty() {
+        hotspot::generate_password()?
+    } else {
+        password
+    };
+
+    let settings = HotspotSettings {
+        ssid,
+        password,
+        band: *band,
+    };
+    let started = hotspot::start_hotspot(wifi_interface, &settings, command_runner).await?;
+    if started {
+        let _ = Notification::new()
+            .summary("Hotspot")
+            .body(&format!(
+                "{} is up on {}\nPassword: {}",
+                settings.ssid, settings.band, settings.password
+            ))
+            .show();
+    }
+    Ok(started)
+}
+
+/// Lists saved connections and applies the management action picked.
+async fn handle_saved_networks(
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    let connections = saved_networks::get_saved_connections(command_runner).await?;
+    let Some(connection) = select_in_submenu(&config, &connections, |connection| {
+        connection.to_display_string()
+    })?
+    else {
+        return Ok(false);
+    };
+
+    let actions = saved_networks::get_saved_connection_actions(connection);
+    let Some(action) = select_in_submenu(&config, &actions, |action| action.to_display_string())?
+    else {
+        return Ok(false);
+    };
+
+    match action {
+        SavedConnectionAction::Forget => {
+            let choices = [format!("Forget {}", connection.name), "Cancel".to_string()];
+            match select_in_submenu(&config, &choices, |choice| choice.clone())? {
+                Some(choice) if *choice == choices[0] => {
+                    saved_networks::forget_connection(connection, command_runner).await
+                }
+                _ => Ok(false),
+            }
+        }
+        SavedConnectionAction::SetAutoconnect(enabled) => {
+            saved_networks::set_autoconnect(connection, *enabled, command_runner).await
+        }
+        SavedConnectionAction::SetPriority => {
+            let priority = utils::prompt_for_visible_text("Autoconnect priority")?
+                .trim()
+                .parse::<i32>()
+                .map_err(|_| "Priority must be a number")?;
+            saved_networks::set_priority(connection, priority, command_runner)
+        }
+        SavedConnectionAction::Rename => {
+            let name = utils::prompt_for_visible_text(&format!("Rename {}", connection.name))?;
+            saved_networks::rename_connection(connection, &name, command_runner)
+        }
+        SavedConnectionAction::SetMacPolicy => {
+            let interface = get_wifi_interface(None);
+            let current = match connection.backend {
+                SavedBackend::NetworkManager => {
+                    mac_policy::get_connection_policy(&connection.id, command_runner)
+                }
+                SavedBackend::Iwd => {
+                    mac_policy::get_iwd_network_policy(&connection.id, &interface, command_runner)
+                }
+            };
+            let policies = mac_policy::connection_policies(connection.backend);
+            match select_mac_policy(&config, &policies, current)? {
+                Some(policy) => match connection.backend {
+                    SavedBackend::NetworkManager => {
+                        mac_policy::set_connection_policy(&connection.id, policy, command_runner)
+                    }
+                    SavedBackend::Iwd => mac_policy::set_iwd_network_policy(
+                        &connection.id,
+                        &interface,
+                        policy,
+                        command_runner,
+                    ),
+                },
+                None => Ok(false),
+            }
+        }
+        SavedConnectionAction::SetMetered => {
+            let current = metered::get_connection_metered(&connection.id, command_runner);
+            match select_in_submenu(&config, &MeteredSetting::ALL, |setting| {
+                setting.to_display_string(Some(*setting) == current)
+            })? {
+                Some(setting) => {
+                    metered::set_connection_metered(&connection.id, *setting, command_runner)
+                }
+                None => Ok(false),
+            }
+        }
+        SavedConnectionAction::ShowPassword => {
+            let clear_after = Duration::from_secs(config.clipboard_clear_secs);
+            let Some(target) = select_in_submenu(&config, &PasswordTarget::ALL, |target| {
+                target.to_display_string(clear_after)
+            })?
+            else {
+                return Ok(false);
+            };
+
+            let credentials = wifi_share::get_saved_credentials(connection, command_runner)?;
+            let message = wifi_share::reveal_password(&credentials, *target, clear_after)?;
+            let _ = Notification::new()
+                .summary(&credentials.ssid)
+                .body(&message)
+                .show();
+            Ok(true)
+        }
+    }
+}
+
+/// Menu entry of a Wi-Fi action, tagged with its radio when it has one.
+fn wifi_radio_entry(icon: &str, text: &str, radio: &Option<String>) -> String {
+    let entry = format_entry(ACTION_TYPE_WIFI, icon, text);
+    match radio {
+        Some(radio) => network_dmenu::tag_wifi_interface(&entry, radio),
+        None => entry,
+    }
+}
+
+/// Shows the MAC address policies with the current one checked.
+fn select_mac_policy(
+    config: &Config,
+    policies: &[MacPolicy],
+    current: Option<MacPolicy>,
+) -> Result<Option<MacPolicy>, Box<dyn Error>> {
+    Ok(select_in_submenu(config, policies, |policy| {
+        policy.to_display_string(Some(*policy) == current)
+    })?
+    .copied())
+}
+
+/// Asks before joining a weakly secured network.
+fn confirm_insecure_wifi(
+    ssid: &str,
+    warning: &SecurityWarning,
+    config_path: Option<&PathBuf>,
+) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+    if !config.insecure_wifi.confirm {
+        return Ok(true);
+    }
+
+    let choices = [
+        format!("{ICON_WARNING} Connect to {ssid} anyway: {warning}"),
+        "Cancel".to_string(),
+    ];
+    Ok(matches!(
+        select_in_submenu(&config, &choices, |choice| choice.clone())?,
+        Some(choice) if *choice == choices[0]
+    ))
+}
+
+/// Gets through any captive portal after joining a network (`ssid` for
+/// Wi-Fi), then runs the `on_connect` hooks.
+///
+/// Returns whether the connection made it past the portal, as VPNs and exit
+/// nodes cannot come up before.
+async fn after_connect(
+    ssid: Option<&str>,
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> bool {
+    let config = get_config(config_path).unwrap_or_default();
+
+    let state =
+        match captive_portal::handle_captive_portal(ssid, &config.captive_portal, command_runner)
+            .await
+        {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Failed to check captive portal: {}", e);
+                PortalState::Offline
+            }
+        };
+    if matches!(state, PortalState::Portal(_)) {
+        return false;
+    }
+
+    for hook in &config.on_connect {
+        // The SSID, if any, is passed as $1
+        let result = command_runner
+            .run_command_with_timeout(
+                "sh",
+                &["-c", hook, "network-dmenu", ssid.unwrap_or("")],
+                SCRIPT_COMMAND_TIMEOUT,
+            )
+            .await;
+        match result {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => error!(
+                "Connect hook '{}' failed: {}",
+                hook,
+                String::from_utf8_lossy(&output.stderr)
+            ),
+            Err(e) => error!("Failed to run connect hook '{}': {}", hook, e),
+        }
+    }
+    true
+}
+
+/// Brings up the configured VPN or exit node after joining a weakly secured
+/// network.
+async fn protect_insecure_wifi(
+    command_runner: &dyn CommandRunner,
+    config_path: Option<&PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let config = get_config(config_path)?;
+
+    if let Some(vpn) = &config.insecure_wifi.vpn {
+        let status = command_runner
+            .run_command("nmcli", &["connection", "up", "id", vpn])?
+            .status;
+        let body = if status.success() {
+            format!("{vpn} is up")
+        } else {
+            format!("Failed to bring up {vpn}")
+        };
+        let _ = Notification::new()
+            .summary("Insecure Wi-Fi")
+            .body(&body)
+            .show();
+    }
+
+    #[cfg(feature = "tailscale")]
+    if let Some(exit_node) = &config.insecure_wifi.exit_node {
+        let body = if network_dmenu::tailscale::set_exit_node_by_hostname(command_runner, exit_node)
+            .await
+        {
+            format!("Using exit node {exit_node}")
+        } else {
+            format!("Failed to use exit node {exit_node}")
+        };
+        let _ = Notification::new()
+            .summary("Insecure Wi-Fi")
+            .body(&body)
+            .show();
+    }
+
+    Ok(())
+}
+
+/// Asks before a speed test burns through a metered data plan.
+fn confirm_speedtest_on_metered(config_path: Option<&PathBuf>) -> Result<bool, Box<dyn Error>> {
+    let config = get_config(config_path)?;
+    let choices = [
+        "Run speed test on metered connection".to_string(),
+        "Cancel".to_string(),
+    ];
+    Ok(matches!(
+        select_in_submenu(&config, &choices, |choice| choice.clone())?,
+        Some(choice) if *choice == choices[0]
+    ))
+}
+
 /// Sets and handles the selected action.
 async fn set_action(
     wifi_interface: &str,
@@ -1233,27 +2316,51 @@ async fn set_action(
             debug!("handle_nextdns_action result: {:?}", result);
             result
         }
-        ActionType::System(system_action) => handle_system_action(system_action, profile).await,
+        ActionType::System(system_action) => {
+            handle_system_action(system_action, command_runner, config_path, profile).await
+        }
         #[cfg(feature = "tailscale")]
         ActionType::Tailscale(mullvad_action) => {
             let notification_sender = DefaultNotificationSender;
-            let tailscale_state = TailscaleState::new(command_runner);
-            handle_tailscale_action(
+            let tailscale_state = TailscaleState::fetch(command_runner).await;
+            let result = handle_tailscale_action(
                 mullvad_action,
                 command_runner,
                 Some(&notification_sender),
                 Some(&tailscale_state),
             )
-            .await
+            .await;
+            if matches!(mullvad_action, TailscaleAction::DisableExitNode)
+                && matches!(result, Ok(true))
+            {
+                lift_kill_switch_for(kill_switch::TAILSCALE_EXIT_NODE, command_runner);
+            }
+            result
+        }
+        ActionType::Vpn(vpn_action) => {
+            handle_vpn_action(vpn_action, command_runner, config_path).await
         }
-        ActionType::Vpn(vpn_action) => handle_vpn_action(vpn_action, command_runner).await,
         ActionType::Wifi(wifi_action) => {
-            handle_wifi_action(wifi_action, wifi_interface, command_runner).await
+            handle_wifi_action(wifi_action, wifi_interface, command_runner, config_path).await
         }
         ActionType::Bluetooth(bluetooth_action) => {
-            handle_bluetooth_action(bluetooth_action, connected_devices, command_runner)
+            handle_bluetooth_action(bluetooth_action, connected_devices, command_runner).await
+        }
+        ActionType::Ethernet(ethernet_action) => {
+            handle_ethernet_action(ethernet_action, command_runner, config_path).await
+        }
+        ActionType::Networkd(networkd_action) => {
+            handle_networkd_action(networkd_action, command_runner, config_path)
         }
         ActionType::Diagnostic(diagnostic_action) => {
+            if matches!(
+                diagnostic_action,
+                DiagnosticAction::SpeedTest | DiagnosticAction::SpeedTestFast
+            ) && metered::is_metered(command_runner).await
+                && !confirm_speedtest_on_metered(config_path)?
+            {
+                return Ok(false);
+            }
             let result = handle_diagnostic_action(diagnostic_action, command_runner).await?;
             // Show the result in a notification
             let summary = if result.success {
@@ -1289,35 +2396,40 @@ async fn set_action(
                 }
             }
         }
-        ActionType::Ssh(ssh_action) => match ssh::handle_ssh_action(ssh_action, command_runner) {
-            Ok(_) => {
-                let message = match ssh_action {
-                    network_dmenu::SshAction::StartProxy(config) => {
-                        format!(
-                            "SSH SOCKS proxy {} started on port {}",
-                            config.name, config.port
-                        )
-                    }
-                    network_dmenu::SshAction::StopProxy(config) => {
-                        format!("SSH SOCKS proxy {} stopped", config.name)
-                    }
-                };
-                let _ = Notification::new()
-                    .summary("SSH Proxy")
-                    .body(&message)
-                    .show();
-                Ok(true)
-            }
-            Err(e) => {
-                let error_msg = format!("SSH proxy operation failed: {}", e);
-                let _ = Notification::new()
-                    .summary("SSH Proxy Error")
-                    .body(&error_msg)
-                    .show();
-                Ok(false)
+        ActionType::Hotspot(hotspot_action) => {
+            handle_hotspot_action(hotspot_action, wifi_interface, command_runner, config_path).await
+        }
+        ActionType::Ssh(ssh_action) => {
+            match ssh::handle_ssh_action(ssh_action, command_runner).await {
+                Ok(_) => {
+                    let message = match ssh_action {
+                        network_dmenu::SshAction::StartProxy(config) => {
+                            format!(
+                                "SSH SOCKS proxy {} started on port {}",
+                                config.name, config.port
+                            )
+                        }
+                        network_dmenu::SshAction::StopProxy(config) => {
+                            format!("SSH SOCKS proxy {} stopped", config.name)
+                        }
+                    };
+                    let _ = Notification::new()
+                        .summary("SSH Proxy")
+                        .body(&message)
+                        .show();
+                    Ok(true)
+                }
+                Err(e) => {
+                    let error_msg = format!("SSH proxy operation failed: {}", e);
+                    let _ = Notification::new()
+                        .summary("SSH Proxy Error")
+                        .body(&error_msg)
+                        .show();
+                    Ok(false)
+                }
             }
-        },
-        ActionType::Tor(tor_action) => match handle_tor_action(tor_action, command_runner) {
+        }
+        ActionType::Tor(tor_action) => match handle_tor_action(tor_action, command_runner).await {
             Ok(result) => {
                 let message = match tor_action {
                     TorAction::StartTor => "Tor daemon started successfully".to_string(),
@@ -1359,6 +2471,85 @@ async fn set_action(
     }
 }
 
+/// Returns the category an action is filed under in the audit log.
+fn action_category(action: &ActionType) -> &'static str {
+    match action {
+        ActionType::Bluetooth(_) => "bluetooth",
+        ActionType::Custom(_) => "custom",
+        ActionType::Diagnostic(_) => "diagnostic",
+        ActionType::Ethernet(_) => "ethernet",
+        #[cfg(feature = "firewalld")]
+        ActionType::Firewalld(_) => "firewalld",
+        ActionType::NextDns(_) => "nextdns",
+        ActionType::Hotspot(_) => "hotspot",
+        ActionType::Networkd(_) => "networkd",
+        ActionType::Ssh(_) => "ssh",
+        ActionType::System(_) => "system",
+        #[cfg(feature = "tailscale")]
+        ActionType::Tailscale(_) => "tailscale",
+        ActionType::Tor(_) => "tor",
+        ActionType::Vpn(_) => "vpn",
+        ActionType::Wifi(_) => "wifi",
+    }
+}
+
+/// Awaits a D-Bus backend call for an action and files it with the action's
+/// audit record, next to the commands the action ran.
+///
+/// Returns `None` when the service is not on the bus.
+async fn audited_dbus_call(
+    command_runner: &dyn CommandRunner,
+    service: &str,
+    method: &str,
+    target: &str,
+    call: impl Future<Output = Option<Result<bool, String>>>,
+) -> Option<Result<bool, Box<dyn Error>>> {
+    let started = Instant::now();
+    let result = call.await?;
+    command_runner.record_operation(
+        "dbus",
+        &[service, method, target],
+        match result {
+            Ok(true) => Some(0),
+            Ok(false) => Some(1),
+            Err(_) => None,
+        },
+        started.elapsed(),
+        result.as_ref().err().cloned(),
+    );
+    Some(result.map_err(Into::into))
+}
+
+/// Appends the executed action, its commands and the resulting state to the audit log.
+async fn audit_action(
+    action: &ActionType,
+    audit_runner: &AuditingCommandRunner<'_>,
+    duration: Duration,
+    result: &Result<bool, Box<dyn Error>>,
+) {
+    // Diagnostics only read state, keep them out of the change log
+    if matches!(action, ActionType::Diagnostic(_)) {
+        return;
+    }
+
+    let record = AuditRecord {
+        timestamp: chrono::Local::now(),
+        action: action_to_string(action),
+        category: action_category(action).to_string(),
+        commands: audit_runner.take_commands(),
+        success: matches!(result, Ok(true)),
+        duration_ms: duration.as_millis() as u64,
+        state: dns_cache::get_current_network_id(&RealCommandRunner)
+            .await
+            .ok(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+    };
+
+    if let Err(e) = append_audit_record(&record) {
+        warn!("Failed to write audit log: {}", e);
+    }
+}
+
 /// Sends a notification about the connection.
 pub fn notify_connection(summary: &str, name: &str) -> Result<(), Box<dyn Error>> {
     let _e = Notification::new()
@@ -1414,6 +2605,34 @@ mod tests {
         assert!(config.contains("cmd = \"notify-send 'hello' 'world'\""));
     }
 
+    #[test]
+    fn test_custom_action_metered_condition() {
+        let config: Config = toml::from_str(
+            r#"
+dmenu_cmd = "dmenu"
+dmenu_args = ""
+hide_speedtest_when_metered = true
+
+[[actions]]
+display = "Update"
+cmd = "pkcon update"
+metered = false
+
+[[actions]]
+display = "Lock"
+cmd = "loginctl lock-session"
+"#,
+        )
+        .unwrap();
+        assert!(config.hide_speedtest_when_metered);
+        assert_eq!(config.actions[0].metered, Some(false));
+        assert_eq!(config.actions[1].metered, None);
+
+        let invalid: toml::Value =
+            toml::from_str("display = \"Update\"\ncmd = \"true\"\nmetered = \"no\"").unwrap();
+        assert!(validate_custom_action(&invalid, 0).is_err());
+    }
+
     #[test]
     fn test_action_to_string_bluetooth() {
         let action =
@@ -1427,6 +2646,7 @@ mod tests {
         let custom_action = CustomAction {
             display: "Custom Action".to_string(),
             cmd: "echo test".to_string(),
+            metered: None,
         };
         let action = ActionType::Custom(custom_action);
         let result = action_to_string(&action);
@@ -1550,29 +2770,44 @@ mod tests {
 
     #[test]
     fn test_action_to_string_wifi_disconnect() {
-        let action = ActionType::Wifi(WifiAction::Disconnect);
+        let action = ActionType::Wifi(WifiAction::Disconnect(None));
         let result = action_to_string(&action);
         assert_eq!(result, "wifi      - ❌ Disconnect");
     }
 
     #[test]
     fn test_action_to_string_wifi_connect() {
-        let action = ActionType::Wifi(WifiAction::Connect);
+        let action = ActionType::Wifi(WifiAction::Connect(None));
         let result = action_to_string(&action);
         assert_eq!(result, "wifi      - 📶 Connect");
     }
 
     #[test]
     fn test_action_to_string_wifi_connect_hidden() {
-        let action = ActionType::Wifi(WifiAction::ConnectHidden);
+        let action = ActionType::Wifi(WifiAction::ConnectHidden(None));
         let result = action_to_string(&action);
         assert_eq!(result, "wifi      - 📶 Connect to hidden network");
     }
 
+    #[test]
+    fn test_wifi_actions_tagged_with_radio() {
+        let actions = vec![
+            ActionType::Wifi(WifiAction::Disconnect(Some("wlan0".to_string()))),
+            ActionType::Wifi(WifiAction::Disconnect(Some("wlan1".to_string()))),
+        ];
+        let entry = action_to_string(&actions[1]);
+        assert_eq!(network_dmenu::parse_wifi_interface(&entry), Some("wlan1"));
+
+        match find_selected_action(&entry, &actions).unwrap() {
+            ActionType::Wifi(WifiAction::Disconnect(Some(radio))) => assert_eq!(radio, "wlan1"),
+            _ => panic!("Expected the wlan1 Disconnect action"),
+        }
+    }
+
     #[test]
     fn test_find_selected_action_success() {
         let actions = vec![
-            ActionType::Wifi(WifiAction::Connect),
+            ActionType::Wifi(WifiAction::Connect(None)),
             ActionType::System(SystemAction::RfkillBlock(
                 "wlan".to_string(),
                 "system    - ❌ Turn OFF all WiFi devices".to_string(),
@@ -1583,7 +2818,7 @@ mod tests {
         assert!(result.is_ok());
 
         match result.unwrap() {
-            ActionType::Wifi(WifiAction::Connect) => (),
+            ActionType::Wifi(WifiAction::Connect(None)) => (),
             _ => panic!("Expected WiFi Connect action"),
         }
     }
@@ -1591,7 +2826,7 @@ mod tests {
     #[test]
     fn test_find_selected_action_not_found() {
         let actions = vec![
-            ActionType::Wifi(WifiAction::Connect),
+            ActionType::Wifi(WifiAction::Connect(None)),
             ActionType::System(SystemAction::RfkillBlock(
                 "wlan".to_string(),
                 "system    - ❌ Turn OFF all WiFi devices".to_string(),
@@ -1606,7 +2841,7 @@ mod tests {
     #[test]
     fn test_find_selected_action_airplane_mode() {
         let actions = vec![
-            ActionType::Wifi(WifiAction::Connect),
+            ActionType::Wifi(WifiAction::Connect(None)),
             ActionType::System(SystemAction::AirplaneMode(true)),
             ActionType::System(SystemAction::AirplaneMode(false)),
         ];
@@ -1661,6 +2896,12 @@ mod tests {
         assert_eq!(name, "TestVPN");
     }
 
+    #[test]
+    fn test_parse_vpn_action_with_badge() {
+        let line = "vpn       - ✅ office\t⚠️ handshake stale";
+        assert_eq!(parse_vpn_action(line).unwrap(), "office");
+    }
+
     #[test]
     fn test_parse_vpn_action_invalid() {
         let line = "invalid line";
@@ -1793,6 +3034,13 @@ mod tests {
             nextdns_toggle_profiles: None,
             ssh_proxies: std::collections::HashMap::new(),
             torsocks_apps: std::collections::HashMap::new(),
+            hide_speedtest_when_metered: false,
+            roaming: RoamingConfig::default(),
+            clipboard_clear_secs: 30,
+            insecure_wifi: InsecureWifiConfig::default(),
+            captive_portal: CaptivePortalConfig::default(),
+            kill_switch: KillSwitchConfig::default(),
+            on_connect: Vec::new(),
             dmenu_cmd: "dmenu".to_string(),
             dmenu_args: String::new(),
         };
@@ -1807,6 +3055,9 @@ mod tests {
             no_tailscale: false,
             no_nextdns: false,
             no_tor: false,
+            no_hotspot: false,
+            no_ethernet: false,
+            no_networkd: false,
             #[cfg(feature = "firewalld")]
             no_firewalld: false,
             nextdns_api_key: String::new(),
@@ -1825,6 +3076,7 @@ mod tests {
             stdout: false,
             config: None,
             validate_config: false,
+            roam: false,
         };
 
         let max_per_country = args.max_nodes_per_country.or(config.max_nodes_per_country);
@@ -1935,6 +3187,10 @@ fn validate_custom_action(action: &toml::Value, index: usize) -> Result<(), Box<
         return Err(format!("Action {} 'cmd' field must be a string", index).into());
     }
 
+    if action_table.get("metered").is_some_and(|v| !v.is_bool()) {
+        return Err(format!("Action {} 'metered' field must be a boolean", index).into());
+    }
+
     debug!("✅ Action {} configuration is valid", index);
     Ok(())
 }
@@ -0,0 +1,457 @@
+//! Metered connection awareness.
+//!
+//! NetworkManager keeps a `connection.metered` setting per profile and guesses
+//! the value for tethered phones and mobile broadband when it is left unset.
+
+use crate::command::CommandRunner;
+use crate::constants::{ACTION_TYPE_SYSTEM, ICON_CHECK, ICON_METERED};
+use crate::format_entry;
+use crate::networkmanager::split_nmcli_fields;
+use crate::nm_dbus;
+use crate::wifi_share;
+use std::error::Error;
+use std::fmt;
+
+/// Connection types that never carry the default route themselves.
+const IGNORED_TYPES: [&str; 6] = ["loopback", "bridge", "tun", "vpn", "wireguard", "dummy"];
+
+/// Value of a connection's `connection.metered` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeteredSetting {
+    Yes,
+    No,
+    /// Let NetworkManager guess from the device
+    Auto,
+}
+
+impl MeteredSetting {
+    pub const ALL: [MeteredSetting; 3] = [Self::Yes, Self::No, Self::Auto];
+
+    pub fn nm_value(&self) -> &'static str {
+        match self {
+            Self::Yes => "yes",
+            Self::No => "no",
+            Self::Auto => "unknown",
+        }
+    }
+
+    pub fn from_nm_value(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|setting| setting.nm_value() == value.trim())
+    }
+
+    /// Menu entry for the setting, checked when it is the current one.
+    pub fn to_display_string(&self, current: bool) -> String {
+        format_entry(
+            ACTION_TYPE_SYSTEM,
+            if current { ICON_CHECK } else { ICON_METERED },
+            &self.to_string(),
+        )
+    }
+}
+
+impl fmt::Display for MeteredSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Yes => write!(f, "Metered"),
+            Self::No => write!(f, "Not metered"),
+            Self::Auto => write!(f, "Detect automatically"),
+        }
+    }
+}
+
+/// The active connection carrying the default route.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeteredConnection {
+    pub uuid: String,
+    pub name: String,
+    pub device: String,
+    /// Whether NetworkManager currently treats it as metered
+    pub metered: bool,
+}
+
+impl MeteredConnection {
+    /// Menu entry flipping the metered state of the connection.
+    pub fn to_toggle_string(&self) -> String {
+        let text = if self.metered {
+            format!("Mark {} as not metered", self.name)
+        } else {
+            format!("Mark {} as metered", self.name)
+        };
+        format_entry(ACTION_TYPE_SYSTEM, ICON_METERED, &text)
+    }
+}
+
+/// Parses a `GENERAL.METERED` value such as `yes (guessed)`.
+pub fn parse_metered(value: &str) -> bool {
+    value.trim().starts_with("yes")
+}
+
+/// Whether `device` is on a metered connection.
+pub async fn is_device_metered(device: &str, command_runner: &dyn CommandRunner) -> bool {
+    command_runner
+        .run_command_async(
+            "nmcli",
+            &["-g", "GENERAL.METERED", "device", "show", device],
+        )
+        .await
+        .map(|output| parse_metered(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or(false)
+}
+
+/// Whether the connection carrying the default route is metered.
+pub async fn is_metered(command_runner: &dyn CommandRunner) -> bool {
+    match nm_dbus::with_client(|client| client.metered()).await {
+        Some(Ok(metered)) => metered,
+        _ => get_primary_connection(command_runner)
+            .await
+            .is_some_and(|connection| connection.metered),
+    }
+}
+
+/// Returns the active connection carrying the default route.
+pub async fn get_primary_connection(
+    command_runner: &dyn CommandRunner,
+) -> Option<MeteredConnection> {
+    let (name, uuid, device) =
+        match nm_dbus::with_client(|client| client.primary_connection()).await {
+            Some(Ok(primary)) => {
+                primary.map(|primary| (primary.id, primary.uuid, primary.interface))?
+            }
+            _ => nmcli_primary_connection(command_runner).await?,
+        };
+
+    Some(MeteredConnection {
+        metered: is_device_metered(&device, command_runner).await,
+        uuid,
+        name,
+        device,
+    })
+}
+
+/// Finds the primary connection with nmcli, through the device of the
+/// default route with the lowest metric.
+async fn nmcli_primary_connection(
+    command_runner: &dyn CommandRunner,
+) -> Option<(String, String, String)> {
+    let routes = command_runner
+        .run_command_async("ip", &["route", "show", "default"])
+        .await
+        .ok()?;
+    let output = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "-t",
+                "-f",
+                "NAME,UUID,TYPE,DEVICE",
+                "connection",
+                "show",
+                "--active",
+            ],
+        )
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let active = String::from_utf8_lossy(&output.stdout);
+    let connections: Vec<(String, String, String)> = active
+        .lines()
+        .filter_map(|line| match split_nmcli_fields(line).as_slice() {
+            [name, uuid, kind, device]
+                if !device.is_empty() && !IGNORED_TYPES.iter().any(|t| kind.contains(t)) =>
+            {
+                Some((name.clone(), uuid.clone(), device.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    parse_default_routes(&String::from_utf8_lossy(&routes.stdout))
+        .into_iter()
+        .find_map(|device| {
+            connections
+                .iter()
+                .find(|(_, _, connection_device)| *connection_device == device)
+                .cloned()
+        })
+}
+
+/// Lists the devices of `ip route show default`, lowest metric first.
+fn parse_default_routes(output: &str) -> Vec<String> {
+    let mut routes: Vec<(u32, String)> = output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let value_after = |key: &str| {
+                parts
+                    .iter()
+                    .position(|part| *part == key)
+                    .and_then(|i| parts.get(i + 1))
+            };
+            let device = value_after("dev")?;
+            let metric = value_after("metric")
+                .and_then(|metric| metric.parse().ok())
+                .unwrap_or(0);
+            Some((metric, device.to_string()))
+        })
+        .collect();
+    routes.sort_by_key(|(metric, _)| *metric);
+    routes.into_iter().map(|(_, device)| device).collect()
+}
+
+/// Reads the metered setting of a NetworkManager connection.
+pub fn get_connection_metered(
+    uuid: &str,
+    command_runner: &dyn CommandRunner,
+) -> Option<MeteredSetting> {
+    wifi_share::nm_field(uuid, "connection.metered", command_runner)
+        .ok()
+        .and_then(|value| MeteredSetting::from_nm_value(&value))
+}
+
+/// Sets the metered setting of a NetworkManager connection.
+///
+/// An active connection gets the new value through `device reapply`, without
+/// going down.
+pub fn set_connection_metered(
+    uuid: &str,
+    setting: MeteredSetting,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let status = command_runner
+        .run_command(
+            "nmcli",
+            &[
+                "connection",
+                "modify",
+                "uuid",
+                uuid,
+                "connection.metered",
+                setting.nm_value(),
+            ],
+        )?
+        .status;
+    if !status.success() {
+        return Ok(false);
+    }
+
+    let active = command_runner.run_command(
+        "nmcli",
+        &["-t", "-f", "UUID,DEVICE", "connection", "show", "--active"],
+    )?;
+    let device = String::from_utf8_lossy(&active.stdout)
+        .lines()
+        .find_map(|line| {
+            line.split_once(':')
+                .filter(|(active_uuid, device)| *active_uuid == uuid && !device.is_empty())
+                .map(|(_, device)| device.to_string())
+        });
+
+    match device {
+        Some(device) => {
+            let status = command_runner
+                .run_command("nmcli", &["device", "reapply", &device])?
+                .status;
+            Ok(status.success())
+        }
+        None => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct ScriptedCommandRunner {
+        responses: Vec<(Vec<&'static str>, &'static str)>,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl ScriptedCommandRunner {
+        fn new(responses: Vec<(Vec<&'static str>, &'static str)>) -> Self {
+            Self {
+                responses,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl CommandRunner for ScriptedCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command];
+            call.extend_from_slice(args);
+            self.calls
+                .lock()
+                .unwrap()
+                .push(call.iter().map(|arg| arg.to_string()).collect());
+            let stdout = self
+                .responses
+                .iter()
+                .find(|(expected, _)| *expected == call)
+                .map(|(_, stdout)| stdout.as_bytes().to_vec());
+            Ok(Output {
+                status: ExitStatus::from_raw(if stdout.is_some() { 0 } else { 1 << 8 }),
+                stdout: stdout.unwrap_or_default(),
+                stderr: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn test_setting_values() {
+        for setting in MeteredSetting::ALL {
+            assert_eq!(
+                MeteredSetting::from_nm_value(setting.nm_value()),
+                Some(setting)
+            );
+        }
+        assert_eq!(MeteredSetting::from_nm_value("maybe"), None);
+        assert!(MeteredSetting::Auto
+            .to_display_string(true)
+            .contains(ICON_CHECK));
+    }
+
+    #[test]
+    fn test_parse_metered() {
+        assert!(parse_metered("yes"));
+        assert!(parse_metered("yes (guessed)\n"));
+        assert!(!parse_metered("no (guessed)"));
+        assert!(!parse_metered("unknown"));
+    }
+
+    #[test]
+    fn test_parse_default_routes_orders_by_metric() {
+        let output = "default via 192.168.1.1 dev enp3s0 proto dhcp src 192.168.1.20 metric 600\n\
+                      default via 172.20.10.1 dev wlan0 proto dhcp src 172.20.10.2 metric 100\n\
+                      default dev tun0 scope link\n";
+        assert_eq!(parse_default_routes(output), ["tun0", "wlan0", "enp3s0"]);
+    }
+
+    #[tokio::test]
+    async fn test_primary_connection_follows_default_route() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                vec!["ip", "route", "show", "default"],
+                "default via 192.168.1.1 dev enp3s0 proto dhcp metric 100\n\
+                 default via 172.20.10.1 dev wlan0 proto dhcp metric 600\n",
+            ),
+            (
+                vec![
+                    "nmcli",
+                    "-t",
+                    "-f",
+                    "NAME,UUID,TYPE,DEVICE",
+                    "connection",
+                    "show",
+                    "--active",
+                ],
+                "lo:1111:loopback:lo\nwork:2222:vpn:\nPhone\\: hotspot:3333:802-11-wireless:wlan0\nWired:4444:802-3-ethernet:enp3s0\n",
+            ),
+            (
+                vec!["nmcli", "-g", "GENERAL.METERED", "device", "show", "enp3s0"],
+                "no (guessed)\n",
+            ),
+        ]);
+
+        assert_eq!(
+            get_primary_connection(&runner).await,
+            Some(MeteredConnection {
+                uuid: "4444".to_string(),
+                name: "Wired".to_string(),
+                device: "enp3s0".to_string(),
+                metered: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_toggle_string() {
+        let mut connection = MeteredConnection {
+            uuid: "3333".to_string(),
+            name: "Home".to_string(),
+            device: "wlan0".to_string(),
+            metered: false,
+        };
+        assert!(connection
+            .to_toggle_string()
+            .contains("Mark Home as metered"));
+        connection.metered = true;
+        assert!(connection
+            .to_toggle_string()
+            .contains("Mark Home as not metered"));
+    }
+
+    #[test]
+    fn test_set_connection_metered_reapplies_active_device() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                vec![
+                    "nmcli",
+                    "connection",
+                    "modify",
+                    "uuid",
+                    "3333",
+                    "connection.metered",
+                    "yes",
+                ],
+                "",
+            ),
+            (
+                vec![
+                    "nmcli",
+                    "-t",
+                    "-f",
+                    "UUID,DEVICE",
+                    "connection",
+                    "show",
+                    "--active",
+                ],
+                "1111:lo\n3333:wlan0\n",
+            ),
+            (vec!["nmcli", "device", "reapply", "wlan0"], ""),
+        ]);
+
+        assert!(set_connection_metered("3333", MeteredSetting::Yes, &runner).unwrap());
+        assert_eq!(runner.calls.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_set_connection_metered_inactive_connection() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                vec![
+                    "nmcli",
+                    "connection",
+                    "modify",
+                    "uuid",
+                    "4444",
+                    "connection.metered",
+                    "unknown",
+                ],
+                "",
+            ),
+            (
+                vec![
+                    "nmcli",
+                    "-t",
+                    "-f",
+                    "UUID,DEVICE",
+                    "connection",
+                    "show",
+                    "--active",
+                ],
+                "1111:lo\n",
+            ),
+        ]);
+
+        assert!(set_connection_metered("4444", MeteredSetting::Auto, &runner).unwrap());
+        assert_eq!(runner.calls.lock().unwrap().len(), 2);
+    }
+}
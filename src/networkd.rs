@@ -0,0 +1,466 @@
+//! systemd-networkd links.
+//!
+//! Lists the links networkd knows about with `networkctl`, together with their
+//! operational state, addresses and the per-link DNS servers systemd-resolved
+//! uses, and reconfigures, renews or brings them up or down. Changing a link
+//! needs privileges, so those commands go through pkexec or sudo.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::constants::{
+    ACTION_TYPE_NETWORKD, ICON_ANTENNA, ICON_CHECK, ICON_CROSS, ICON_ETHERNET, ICON_SIGNAL,
+};
+use crate::format_entry;
+use crate::privilege::wrap_privileged_command;
+use std::error::Error;
+
+/// A network link as reported by networkd.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkdLink {
+    pub index: u32,
+    pub name: String,
+    /// `ether`, `wlan`, `wireguard`, ...
+    pub link_type: String,
+    /// `off`, `no-carrier`, `dormant`, `carrier`, `degraded`, `routable`, ...
+    pub operational: String,
+    /// `configured`, `configuring`, `unmanaged`, `failed`, ...
+    pub setup: String,
+    /// Addresses without their prefix length, link-local ones left out
+    pub addresses: Vec<String>,
+    pub dns: Vec<String>,
+}
+
+impl NetworkdLink {
+    /// Whether the link is administratively up.
+    pub fn is_up(&self) -> bool {
+        self.operational != "off"
+    }
+
+    /// Whether a cable is plugged in or a Wi-Fi network associated.
+    pub fn has_carrier(&self) -> bool {
+        !matches!(self.operational.as_str(), "off" | "no-carrier" | "dormant")
+    }
+
+    pub fn to_display_string(&self) -> String {
+        let icon = match self.link_type.as_str() {
+            "ether" => ICON_ETHERNET,
+            "wlan" => ICON_SIGNAL,
+            _ => ICON_ANTENNA,
+        };
+
+        let mut details = vec![self.operational.clone()];
+        if !self.has_carrier() && self.is_up() {
+            details[0] = "no carrier".to_string();
+        }
+        if self.setup != "configured" {
+            details.push(self.setup.clone());
+        }
+        details.extend(self.addresses.iter().cloned());
+        if !self.dns.is_empty() {
+            details.push(format!("DNS {}", self.dns.join(" ")));
+        }
+
+        format_entry(
+            ACTION_TYPE_NETWORKD,
+            icon,
+            &format!("{} ({})", self.name, details.join(", ")),
+        )
+    }
+}
+
+/// networkd action types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkdAction {
+    Link(NetworkdLink),
+}
+
+pub fn networkd_action_to_string(action: &NetworkdAction) -> String {
+    match action {
+        NetworkdAction::Link(link) => link.to_display_string(),
+    }
+}
+
+/// Operations offered for a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkOperation {
+    /// Reapplies the `.network` file
+    Reconfigure,
+    /// Renews the DHCP lease
+    Renew,
+    Up,
+    Down,
+}
+
+impl LinkOperation {
+    /// The `networkctl` verb.
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Self::Reconfigure => "reconfigure",
+            Self::Renew => "renew",
+            Self::Up => "up",
+            Self::Down => "down",
+        }
+    }
+
+    pub fn to_display_string(&self) -> String {
+        match self {
+            Self::Reconfigure => format_entry(ACTION_TYPE_NETWORKD, "🔄", "Reconfigure"),
+            Self::Renew => format_entry(ACTION_TYPE_NETWORKD, "🔁", "Renew DHCP lease"),
+            Self::Up => format_entry(ACTION_TYPE_NETWORKD, ICON_CHECK, "Bring up"),
+            Self::Down => format_entry(ACTION_TYPE_NETWORKD, ICON_CROSS, "Bring down"),
+        }
+    }
+}
+
+/// Returns the operations that make sense for `link` in its current state.
+pub fn get_link_operations(link: &NetworkdLink) -> Vec<LinkOperation> {
+    if link.is_up() {
+        vec![
+            LinkOperation::Reconfigure,
+            LinkOperation::Renew,
+            LinkOperation::Down,
+        ]
+    } else {
+        vec![LinkOperation::Up, LinkOperation::Reconfigure]
+    }
+}
+
+/// Checks whether systemd-networkd is running.
+pub async fn is_networkd_active(command_runner: &dyn CommandRunner) -> bool {
+    command_runner
+        .run_command_async("systemctl", &["is-active", "--quiet", "systemd-networkd"])
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Parses `networkctl list --no-legend`, skipping the loopback device.
+fn parse_networkctl_list(output: &str) -> Vec<NetworkdLink> {
+    output
+        .lines()
+        .filter_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [index, name, link_type, operational, setup, ..] if link_type != "loopback" => {
+                    Some(NetworkdLink {
+                        index: index.parse().ok()?,
+                        name: name.to_string(),
+                        link_type: link_type.to_string(),
+                        operational: operational.to_string(),
+                        setup: setup.to_string(),
+                        addresses: Vec::new(),
+                        dns: Vec::new(),
+                    })
+                }
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Collects the values of `key` from `networkctl status <link>`.
+///
+/// Multi-valued fields such as `Address` put one value per line, with the
+/// following lines left blank where the key would be.
+fn status_values(output: &str, key: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut in_key = false;
+    for line in output.lines() {
+        let value = match line.split_once(": ") {
+            Some((name, value)) => {
+                in_key = name.trim() == key;
+                value
+            }
+            None => line,
+        };
+        if in_key {
+            // `10.0.2.15 (DHCP4 via 10.0.2.2)`
+            if let Some(value) = value.split_whitespace().next() {
+                values.push(value.to_string());
+            }
+        }
+    }
+    values
+}
+
+/// Parses `resolvectl dns <link>`, e.g. `Link 2 (eth0): 10.0.2.3 fec0::3`.
+fn parse_resolvectl_dns(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once("): "))
+        .flat_map(|(_, servers)| servers.split_whitespace().map(str::to_string))
+        .collect()
+}
+
+/// Fills in the addresses and DNS servers of `link`.
+///
+/// DNS servers come from systemd-resolved when it is available, as that is
+/// what is actually queried, and from networkd's own configuration otherwise.
+async fn load_link_details(link: &mut NetworkdLink, command_runner: &dyn CommandRunner) {
+    let status = command_runner
+        .run_command_async("networkctl", &["status", "--no-pager", &link.name])
+        .await
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default();
+
+    link.addresses = status_values(&status, "Address")
+        .into_iter()
+        .filter(|address| !address.starts_with("fe80:"))
+        .collect();
+
+    link.dns = if is_command_installed("resolvectl") {
+        command_runner
+            .run_command_async("resolvectl", &["dns", &link.name])
+            .await
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| parse_resolvectl_dns(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default()
+    } else {
+        status_values(&status, "DNS")
+    };
+}
+
+/// Lists the links networkd manages, with their addresses and DNS servers.
+pub async fn get_networkd_links(
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<NetworkdLink>, Box<dyn Error>> {
+    let output = command_runner
+        .run_command_async("networkctl", &["list", "--no-legend", "--no-pager"])
+        .await?;
+    if !output.status.success() {
+        return Err("Failed to list networkd links".into());
+    }
+
+    let mut links = parse_networkctl_list(&String::from_utf8_lossy(&output.stdout));
+    links.retain(|link| link.setup != "unmanaged");
+    for link in &mut links {
+        load_link_details(link, command_runner).await;
+    }
+    Ok(links)
+}
+
+/// Returns an entry per networkd link, or nothing when networkd is not running.
+pub async fn get_networkd_actions(command_runner: &dyn CommandRunner) -> Vec<NetworkdAction> {
+    if !is_networkd_active(command_runner).await {
+        return Vec::new();
+    }
+
+    get_networkd_links(command_runner)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(NetworkdAction::Link)
+        .collect()
+}
+
+/// Runs `operation` on `link` with elevated privileges.
+pub fn run_link_operation(
+    link: &NetworkdLink,
+    operation: LinkOperation,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let command = format!(
+        "networkctl {} '{}'",
+        operation.verb(),
+        link.name.replace('\'', r"'\''")
+    );
+    let privileged_cmd = wrap_privileged_command(&command, false);
+    let status = command_runner
+        .run_command("sh", &["-c", &privileged_cmd])?
+        .status;
+    Ok(status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    const LIST: &str = "  1 lo     loopback carrier    unmanaged
+  2 eth0   ether    routable    configured
+  3 wlan0  wlan     no-carrier  configuring
+  4 virbr0 bridge   off         unmanaged
+";
+
+    const STATUS: &str = "● 2: eth0
+                     Link File: /usr/lib/systemd/network/99-default.link
+                  Network File: /etc/systemd/network/20-wired.network
+                         State: routable (configured)
+                           MTU: 1500 (min: 46, max: 16110)
+      Number of Queues (Tx/Rx): 1/1
+                       Address: 10.0.2.15 (DHCP4 via 10.0.2.2)
+                                fec0::5054:ff:fe12:3456
+                                fe80::5054:ff:fe12:3456
+                       Gateway: 10.0.2.2
+                           DNS: 10.0.2.3
+                                9.9.9.9
+             Activation Policy: up
+";
+
+    struct ScriptedCommandRunner {
+        responses: Vec<(Vec<&'static str>, &'static str)>,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl ScriptedCommandRunner {
+        fn new(responses: Vec<(Vec<&'static str>, &'static str)>) -> Self {
+            Self {
+                responses,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl CommandRunner for ScriptedCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call.clone());
+
+            let stdout = self
+                .responses
+                .iter()
+                .find(|(prefix, _)| {
+                    call.starts_with(&prefix.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+                })
+                .map(|(_, stdout)| *stdout);
+            Ok(Output {
+                status: ExitStatus::from_raw(if stdout.is_some() { 0 } else { 1 << 8 }),
+                stdout: stdout.unwrap_or_default().as_bytes().to_vec(),
+                stderr: Vec::new(),
+            })
+        }
+    }
+
+    fn link(name: &str, operational: &str) -> NetworkdLink {
+        NetworkdLink {
+            index: 2,
+            name: name.to_string(),
+            link_type: "ether".to_string(),
+            operational: operational.to_string(),
+            setup: "configured".to_string(),
+            addresses: vec!["10.0.2.15".to_string()],
+            dns: vec!["10.0.2.3".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_parse_networkctl_list() {
+        let links = parse_networkctl_list(LIST);
+        let names: Vec<(&str, &str)> = links
+            .iter()
+            .map(|link| (link.name.as_str(), link.operational.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("eth0", "routable"),
+                ("wlan0", "no-carrier"),
+                ("virbr0", "off")
+            ]
+        );
+        assert_eq!(links[0].index, 2);
+        assert!(links[0].has_carrier());
+        assert!(!links[1].has_carrier());
+        assert!(!links[2].is_up());
+    }
+
+    #[test]
+    fn test_status_values() {
+        assert_eq!(
+            status_values(STATUS, "Address"),
+            vec![
+                "10.0.2.15",
+                "fec0::5054:ff:fe12:3456",
+                "fe80::5054:ff:fe12:3456"
+            ]
+        );
+        assert_eq!(status_values(STATUS, "DNS"), vec!["10.0.2.3", "9.9.9.9"]);
+        assert_eq!(status_values(STATUS, "Gateway"), vec!["10.0.2.2"]);
+        assert!(status_values(STATUS, "Search Domains").is_empty());
+    }
+
+    #[test]
+    fn test_parse_resolvectl_dns() {
+        assert_eq!(
+            parse_resolvectl_dns("Link 2 (eth0): 10.0.2.3 fec0::3\n"),
+            vec!["10.0.2.3", "fec0::3"]
+        );
+        assert!(parse_resolvectl_dns("Link 3 (wlan0):\n").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_networkd_links_skips_unmanaged() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (vec!["networkctl", "list"], LIST),
+            (vec!["networkctl", "status", "--no-pager", "eth0"], STATUS),
+            (
+                vec!["resolvectl", "dns", "eth0"],
+                "Link 2 (eth0): 1.1.1.1\n",
+            ),
+        ]);
+
+        let links = get_networkd_links(&runner).await.unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(
+            links[0].addresses,
+            vec!["10.0.2.15", "fec0::5054:ff:fe12:3456"]
+        );
+        if is_command_installed("resolvectl") {
+            assert_eq!(links[0].dns, vec!["1.1.1.1"]);
+        } else {
+            assert_eq!(links[0].dns, vec!["10.0.2.3", "9.9.9.9"]);
+        }
+        assert!(links[1].addresses.is_empty());
+    }
+
+    #[test]
+    fn test_display_and_operations() {
+        let up = link("eth0", "routable");
+        assert_eq!(
+            up.to_display_string(),
+            format_entry(
+                ACTION_TYPE_NETWORKD,
+                ICON_ETHERNET,
+                "eth0 (routable, 10.0.2.15, DNS 10.0.2.3)"
+            )
+        );
+        assert_eq!(
+            get_link_operations(&up),
+            vec![
+                LinkOperation::Reconfigure,
+                LinkOperation::Renew,
+                LinkOperation::Down
+            ]
+        );
+
+        let mut unplugged = link("eth1", "no-carrier");
+        unplugged.addresses.clear();
+        unplugged.dns.clear();
+        unplugged.setup = "configuring".to_string();
+        assert!(unplugged
+            .to_display_string()
+            .ends_with("eth1 (no carrier, configuring)"));
+
+        let down = link("eth2", "off");
+        assert_eq!(
+            get_link_operations(&down),
+            vec![LinkOperation::Up, LinkOperation::Reconfigure]
+        );
+    }
+
+    #[test]
+    fn test_run_link_operation_is_privileged() {
+        let runner = ScriptedCommandRunner::new(vec![(vec!["sh", "-c"], "")]);
+        assert!(
+            run_link_operation(&link("eth0", "routable"), LinkOperation::Renew, &runner).unwrap()
+        );
+
+        let calls = runner.calls.lock().unwrap();
+        let command = &calls[0][2];
+        assert!(command.ends_with("networkctl renew 'eth0'"));
+        assert_ne!(command, "networkctl renew 'eth0'");
+    }
+}
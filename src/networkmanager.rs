@@ -1,28 +1,48 @@
-use crate::command::{read_output_lines, CommandRunner};
+use crate::command::{read_output_lines, CommandRunner, CONNECTION_COMMAND_TIMEOUT};
 use crate::constants::{ICON_CHECK, ICON_SIGNAL};
-use crate::utils::{convert_network_strength, prompt_for_password};
+use crate::privilege::write_private_file;
+use crate::utils::{convert_network_strength, prompt_for_password, prompt_for_text};
+use crate::vpn_profiles;
 use crate::{parse_vpn_action, parse_wifi_action, VpnAction, WifiAction};
 use regex::Regex;
 use std::error::Error;
+use std::fs;
 use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+/// A forced rescan can take a while on busy channels.
+const WIFI_RESCAN_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Retrieves available Wi-Fi networks using NetworkManager.
-pub fn get_nm_wifi_networks(
+pub async fn get_nm_wifi_networks(
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<WifiAction>, Box<dyn Error>> {
+    get_nm_wifi_networks_on(None, command_runner).await
+}
+
+/// Retrieves the Wi-Fi networks seen by one interface, or by all of them.
+pub async fn get_nm_wifi_networks_on(
+    interface: Option<&str>,
     command_runner: &dyn CommandRunner,
 ) -> Result<Vec<WifiAction>, Box<dyn Error>> {
     let mut actions = Vec::new();
 
-    if let Some(lines) = fetch_wifi_lines(command_runner)? {
+    let lines = fetch_wifi_lines(interface, command_runner).await?;
+    if let Some(lines) = lines {
         let has_in_use = lines.iter().any(|line| line.starts_with('*'));
 
         if !has_in_use {
-            let rescan_output = command_runner.run_command(
-                "nmcli",
-                &["--colors", "no", "dev", "wifi", "list", "--rescan", "auto"],
-            )?;
+            let mut rescan = vec!["--colors", "no", "dev", "wifi", "list", "--rescan", "auto"];
+            if let Some(interface) = interface {
+                rescan.extend(["ifname", interface]);
+            }
+            let rescan_output = command_runner
+                .run_command_with_timeout("nmcli", &rescan, WIFI_RESCAN_TIMEOUT)
+                .await?;
 
             if rescan_output.status.success() {
-                if let Some(rescan_lines) = fetch_wifi_lines(command_runner)? {
+                let rescan_lines = fetch_wifi_lines(interface, command_runner).await?;
+                if let Some(rescan_lines) = rescan_lines {
                     parse_wifi_lines(&mut actions, rescan_lines);
                 }
             }
@@ -35,12 +55,12 @@ pub fn get_nm_wifi_networks(
 }
 
 /// Retrieves available VPN networks using NetworkManager.
-pub fn get_nm_vpn_networks(
+pub async fn get_nm_vpn_networks(
     command_runner: &dyn CommandRunner,
 ) -> Result<Vec<VpnAction>, Box<dyn Error>> {
     let mut actions = Vec::new();
 
-    if let Some(lines) = fetch_vpn_lines(command_runner)? {
+    if let Some(lines) = fetch_vpn_lines(command_runner).await? {
         parse_vpn_lines(&mut actions, lines);
     }
 
@@ -48,21 +68,23 @@ pub fn get_nm_vpn_networks(
 }
 
 /// Fetches raw VPN network data from NetworkManager.
-fn fetch_vpn_lines(
+async fn fetch_vpn_lines(
     command_runner: &dyn CommandRunner,
 ) -> Result<Option<Vec<String>>, Box<dyn Error>> {
-    let output = command_runner.run_command(
-        "nmcli",
-        &[
-            "--colors",
-            "no",
-            "-t",
-            "-f",
-            "ACTIVE,TYPE,NAME",
-            "connection",
-            "show",
-        ],
-    )?;
+    let output = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "--colors",
+                "no",
+                "-t",
+                "-f",
+                "ACTIVE,TYPE,NAME",
+                "connection",
+                "show",
+            ],
+        )
+        .await?;
 
     if output.status.success() {
         let reader = read_output_lines(&output)?;
@@ -73,21 +95,23 @@ fn fetch_vpn_lines(
 }
 
 /// Fetches raw Wi-Fi network data from NetworkManager.
-fn fetch_wifi_lines(
+async fn fetch_wifi_lines(
+    interface: Option<&str>,
     command_runner: &dyn CommandRunner,
 ) -> Result<Option<Vec<String>>, Box<dyn Error>> {
-    let output = command_runner.run_command(
-        "nmcli",
-        &[
-            "--colors",
-            "no",
-            "-t",
-            "-f",
-            "IN-USE,SSID,BARS,SECURITY",
-            "device",
-            "wifi",
-        ],
-    )?;
+    let mut args = vec![
+        "--colors",
+        "no",
+        "-t",
+        "-f",
+        "IN-USE,SSID,BARS,SECURITY",
+        "device",
+        "wifi",
+    ];
+    if let Some(interface) = interface {
+        args.extend(["list", "ifname", interface]);
+    }
+    let output = command_runner.run_command_async("nmcli", &args).await?;
 
     if output.status.success() {
         let reader = read_output_lines(&output)?;
@@ -97,29 +121,63 @@ fn fetch_wifi_lines(
     }
 }
 
+/// Splits a `nmcli -t` line on unescaped colons, unescaping `\:` and `\\`.
+pub fn split_nmcli_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("fields is never empty");
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    field.push(escaped);
+                }
+            }
+            ':' => fields.push(String::new()),
+            _ => field.push(c),
+        }
+    }
+
+    fields
+}
+
+/// Builds the menu entry for a VPN connection.
+pub(crate) fn vpn_network_action(active: bool, name: &str) -> VpnAction {
+    let display = format!("{} {}", if active { ICON_CHECK } else { ICON_SIGNAL }, name);
+    if active {
+        VpnAction::Disconnect(display)
+    } else {
+        VpnAction::Connect(display)
+    }
+}
+
+/// Builds the menu entry for a Wi-Fi network.
+pub(crate) fn wifi_network_action(
+    in_use: bool,
+    ssid: &str,
+    security: &str,
+    bars: &str,
+) -> WifiAction {
+    WifiAction::Network(format!(
+        "{} {:<25}\t{:<11}\t{}",
+        if in_use { ICON_CHECK } else { ICON_SIGNAL },
+        ssid,
+        security.to_uppercase(),
+        convert_network_strength(bars),
+    ))
+}
+
 /// Parses the raw VPN network data into a structured format.
 fn parse_vpn_lines(actions: &mut Vec<VpnAction>, vpn_lines: Vec<String>) {
     vpn_lines.into_iter().for_each(|line| {
-        let parts: Vec<&str> = line.split(':').collect();
+        let parts = split_nmcli_fields(&line);
         if parts.len() == 3 {
             let in_use = parts[0].trim();
             let typ = parts[1].trim();
             let name = parts[2].trim();
             if !name.is_empty() && (typ == "vpn" || typ == "wireguard") {
-                let display = format!(
-                    "{} {}",
-                    if in_use == "yes" {
-                        ICON_CHECK
-                    } else {
-                        ICON_SIGNAL
-                    },
-                    name
-                );
-                if in_use == "yes" {
-                    actions.push(VpnAction::Disconnect(display));
-                } else {
-                    actions.push(VpnAction::Connect(display));
-                }
+                actions.push(vpn_network_action(in_use == "yes", name));
             }
         }
     });
@@ -128,32 +186,21 @@ fn parse_vpn_lines(actions: &mut Vec<VpnAction>, vpn_lines: Vec<String>) {
 /// Parses the raw Wi-Fi network data into a structured format.
 fn parse_wifi_lines(actions: &mut Vec<WifiAction>, wifi_lines: Vec<String>) {
     wifi_lines.into_iter().for_each(|line| {
-        let parts: Vec<&str> = line.split(':').collect();
+        let parts = split_nmcli_fields(&line);
         if parts.len() == 4 {
             let in_use = parts[0].trim();
             let ssid = parts[1].trim();
             let signal = parts[2].trim();
             let security = parts[3].trim();
             if !ssid.is_empty() {
-                let display = format!(
-                    "{} {:<25}\t{:<11}\t{}",
-                    if in_use == "*" {
-                        ICON_CHECK
-                    } else {
-                        ICON_SIGNAL
-                    },
-                    ssid,
-                    security.to_uppercase(),
-                    convert_network_strength(signal),
-                );
-                actions.push(WifiAction::Network(display));
+                actions.push(wifi_network_action(in_use == "*", ssid, security, signal));
             }
         }
     });
 }
 
 /// Connects to a Wi-Fi network using NetworkManager.
-pub fn connect_to_nm_vpn(
+pub async fn connect_to_nm_vpn(
     action: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
@@ -161,44 +208,64 @@ pub fn connect_to_nm_vpn(
     #[cfg(debug_assertions)]
     println!("Connecting to VPN network: {name}");
 
-    attempt_vpn_connection(name, command_runner)
+    attempt_vpn_connection(name, command_runner).await
 }
 /// Connects to a Wi-Fi network using NetworkManager.
-pub fn connect_to_nm_wifi(
+pub async fn connect_to_nm_wifi(
     action: &str,
     hidden: bool,
     command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    connect_to_nm_wifi_on(action, hidden, None, command_runner).await
+}
+
+/// Connects to a Wi-Fi network using a specific interface, or any when `None`.
+pub async fn connect_to_nm_wifi_on(
+    action: &str,
+    hidden: bool,
+    interface: Option<&str>,
+    command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
     let (ssid, security) = parse_wifi_action(action)?;
     #[cfg(debug_assertions)]
     println!("Connecting to Wi-Fi network: {ssid} with security {security}");
 
-    // Helper function for attempting connection with or without a password
-    let connect = |hidden: bool| -> Result<bool, Box<dyn Error>> {
-        if is_known_network(ssid, command_runner)? || security.is_empty() {
-            attempt_wifi_connection(ssid, hidden, None, command_runner)
+    // Attempt the connection with or without a password
+    let connect = async {
+        if is_known_network(ssid, command_runner).await? || security.is_empty() {
+            attempt_wifi_connection(ssid, hidden, None, interface, command_runner).await
         } else {
             let password = prompt_for_password(ssid)?;
-            attempt_wifi_connection(ssid, hidden, Some(password), command_runner)
+            attempt_wifi_connection(ssid, hidden, Some(password), interface, command_runner).await
         }
     };
 
     // Main connection logic
-    match connect(hidden) {
+    match connect.await {
         Ok(true) => Ok(true),
         Err(_) if hidden => {
             // Retry with password if the first attempt failed and the network is hidden
             let password = prompt_for_password(ssid)?;
-            attempt_wifi_connection(ssid, true, Some(password), command_runner)
+            attempt_wifi_connection(ssid, true, Some(password), interface, command_runner).await
         }
         result => result, // Return the original result for non-hidden networks
     }
 }
 
-/// Attempts to connect to a VPN network, optionally using a password.
-fn attempt_vpn_connection(
+/// Attempts to connect to a VPN network, prompting for secrets if needed.
+async fn attempt_vpn_connection(
+    name: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    attempt_vpn_connection_with(name, command_runner, &prompt_for_text).await
+}
+
+/// Brings the VPN up, asking for its secrets through `prompt` when
+/// NetworkManager has none to use.
+pub(crate) async fn attempt_vpn_connection_with(
     name: &str,
     command_runner: &dyn CommandRunner,
+    prompt: &vpn_profiles::SecretPrompter,
 ) -> Result<bool, Box<dyn Error>> {
     if name.is_empty() {
         #[cfg(debug_assertions)]
@@ -206,25 +273,68 @@ fn attempt_vpn_connection(
         return Ok(false);
     }
 
-    let status = command_runner
-        .run_command("nmcli", &["connection", "up", name])?
-        .status;
-
-    if status.success() {
+    // A running secret agent may still provide the secrets itself
+    let output = command_runner
+        .run_command_with_timeout(
+            "nmcli",
+            &["connection", "up", name],
+            CONNECTION_COMMAND_TIMEOUT,
+        )
+        .await?;
+    if output.status.success() {
         // Connection successful
-        Ok(true)
-    } else {
+        return Ok(true);
+    }
+    if !vpn_profiles::is_missing_secrets(&String::from_utf8_lossy(&output.stderr)) {
         #[cfg(debug_assertions)]
         eprintln!("Failed to connect to VPN network: {name}");
-        Ok(false)
+        return Ok(false);
+    }
+
+    let profile = vpn_profiles::load_vpn_profile(name, command_runner)?;
+    let secrets = profile.collect_secrets(prompt)?;
+    vpn_profiles::connect_with_secrets(name, &secrets, command_runner).await
+}
+
+/// Brings the profile up with secrets in nmcli `passwd-file` format.
+///
+/// They are written to a private file that only lives for the activation,
+/// so they never show up in the process list.
+pub(crate) async fn connection_up_with_passwd_file(
+    name: &str,
+    content: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("network-dmenu-{}-secrets", std::process::id()));
+    let _ = fs::remove_file(&path);
+    write_private_file(&path, content)?;
+
+    let path_arg = path.to_string_lossy().to_string();
+    let result = command_runner
+        .run_command_with_timeout(
+            "nmcli",
+            &["connection", "up", name, "passwd-file", &path_arg],
+            CONNECTION_COMMAND_TIMEOUT,
+        )
+        .await;
+    let _ = fs::remove_file(&path);
+
+    let output = result?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
     }
+    Ok(true)
 }
 
 /// Attempts to connect to a Wi-Fi network, optionally using a password.
-fn attempt_wifi_connection(
+pub(crate) async fn attempt_wifi_connection(
     ssid: &str,
     hidden: bool,
     password: Option<String>,
+    interface: Option<&str>,
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
     let mut command = match password {
@@ -232,13 +342,21 @@ fn attempt_wifi_connection(
         None => vec!["device", "wifi", "connect", ssid],
     };
 
+    if let Some(interface) = interface {
+        command.push("ifname");
+        command.push(interface);
+    }
+
     // Add hidden parameter only if needed
     if hidden {
         command.push("hidden");
         command.push("yes");
     }
 
-    let status = command_runner.run_command("nmcli", &command)?.status;
+    let status = command_runner
+        .run_command_with_timeout("nmcli", &command, CONNECTION_COMMAND_TIMEOUT)
+        .await?
+        .status;
 
     if status.success() {
         // Connection successful
@@ -251,33 +369,45 @@ fn attempt_wifi_connection(
 }
 
 /// Disconnects from a VPN network.
-pub fn disconnect_nm_vpn(
+pub async fn disconnect_nm_vpn(
     name: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
     let status = command_runner
-        .run_command("nmcli", &["connection", "down", name])?
+        .run_command_with_timeout(
+            "nmcli",
+            &["connection", "down", name],
+            CONNECTION_COMMAND_TIMEOUT,
+        )
+        .await?
         .status;
     Ok(status.success())
 }
 /// Disconnects from a Wi-Fi network.
-pub fn disconnect_nm_wifi(
+pub async fn disconnect_nm_wifi(
     interface: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
     let status = command_runner
-        .run_command("nmcli", &["device", "disconnect", interface])?
+        .run_command_with_timeout(
+            "nmcli",
+            &["device", "disconnect", interface],
+            CONNECTION_COMMAND_TIMEOUT,
+        )
+        .await?
         .status;
     Ok(status.success())
 }
 
 /// Checks if a Wi-Fi network is known (i.e., previously connected).
-pub fn is_known_network(
+pub async fn is_known_network(
     ssid: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<bool, Box<dyn Error>> {
     // Run the `nmcli connection show` command
-    let output = command_runner.run_command("nmcli", &["--colors", "no", "connection", "show"])?;
+    let output = command_runner
+        .run_command_async("nmcli", &["--colors", "no", "connection", "show"])
+        .await?;
 
     // Check if the command executed successfully
     if output.status.success() {
@@ -339,8 +469,8 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_get_nm_wifi_networks_success() {
+    #[tokio::test]
+    async fn test_get_nm_wifi_networks_success() {
         let stdout = "*:TestNetwork1:****:WPA2\n :TestNetwork2:***:WPA2\n";
         let output = Output {
             status: ExitStatus::from_raw(0),
@@ -361,15 +491,46 @@ mod tests {
             ],
             output,
         );
-        let result = get_nm_wifi_networks(&mock_runner);
+        let result = get_nm_wifi_networks(&mock_runner).await;
 
         assert!(result.is_ok());
         let networks = result.unwrap();
         assert!(!networks.is_empty());
     }
 
-    #[test]
-    fn test_get_nm_wifi_networks_command_failure() {
+    #[tokio::test]
+    async fn test_get_nm_wifi_networks_on_interface() {
+        let output = Output {
+            status: ExitStatus::from_raw(0),
+            stdout: b"*:Office:****:WPA2\n".to_vec(),
+            stderr: vec![],
+        };
+
+        let mock_runner = MockCommandRunner::new(
+            "nmcli",
+            &[
+                "--colors",
+                "no",
+                "-t",
+                "-f",
+                "IN-USE,SSID,BARS,SECURITY",
+                "device",
+                "wifi",
+                "list",
+                "ifname",
+                "wlx00c0ca",
+            ],
+            output,
+        );
+        let networks = get_nm_wifi_networks_on(Some("wlx00c0ca"), &mock_runner)
+            .await
+            .unwrap();
+
+        assert_eq!(networks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_nm_wifi_networks_command_failure() {
         let output = Output {
             status: ExitStatus::from_raw(1),
             stdout: vec![],
@@ -389,15 +550,15 @@ mod tests {
             ],
             output,
         );
-        let result = get_nm_wifi_networks(&mock_runner);
+        let result = get_nm_wifi_networks(&mock_runner).await;
 
         assert!(result.is_ok());
         let networks = result.unwrap();
         assert!(networks.is_empty());
     }
 
-    #[test]
-    fn test_get_nm_vpn_networks_success() {
+    #[tokio::test]
+    async fn test_get_nm_vpn_networks_success() {
         let stdout = "yes:vpn:TestVPN1\nno:vpn:TestVPN2\n";
         let output = Output {
             status: ExitStatus::from_raw(0),
@@ -418,15 +579,15 @@ mod tests {
             ],
             output,
         );
-        let result = get_nm_vpn_networks(&mock_runner);
+        let result = get_nm_vpn_networks(&mock_runner).await;
 
         assert!(result.is_ok());
         let networks = result.unwrap();
         assert!(!networks.is_empty());
     }
 
-    #[test]
-    fn test_get_nm_vpn_networks_command_failure() {
+    #[tokio::test]
+    async fn test_get_nm_vpn_networks_command_failure() {
         let output = Output {
             status: ExitStatus::from_raw(1),
             stdout: vec![],
@@ -446,15 +607,15 @@ mod tests {
             ],
             output,
         );
-        let result = get_nm_vpn_networks(&mock_runner);
+        let result = get_nm_vpn_networks(&mock_runner).await;
 
         assert!(result.is_ok());
         let networks = result.unwrap();
         assert!(networks.is_empty());
     }
 
-    #[test]
-    fn test_disconnect_nm_wifi_success() {
+    #[tokio::test]
+    async fn test_disconnect_nm_wifi_success() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -464,13 +625,13 @@ mod tests {
         let mock_runner =
             MockCommandRunner::new("nmcli", &["device", "disconnect", "wlan0"], output);
 
-        let result = disconnect_nm_wifi("wlan0", &mock_runner);
+        let result = disconnect_nm_wifi("wlan0", &mock_runner).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
 
-    #[test]
-    fn test_connect_to_nm_vpn_success() {
+    #[tokio::test]
+    async fn test_connect_to_nm_vpn_success() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -479,13 +640,61 @@ mod tests {
 
         let mock_runner = MockCommandRunner::new("nmcli", &["connection", "up", "TestVPN"], output);
 
-        let result = connect_to_nm_vpn("vpn       - 📶 TestVPN", &mock_runner);
+        let result = connect_to_nm_vpn("vpn       - 📶 TestVPN", &mock_runner).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
 
-    #[test]
-    fn test_disconnect_nm_vpn_success() {
+    /// Refuses the first activation for lack of secrets, like nmcli without an agent
+    struct SecretsCommandRunner {
+        calls: std::sync::Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for SecretsCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call);
+            let (code, stdout, stderr) = match args {
+                ["connection", "up", _] => (
+                    4,
+                    "",
+                    "Error: Connection activation failed: Secrets were required, but not provided",
+                ),
+                ["-t", "-f", _, "connection", "show", _] => (
+                    0,
+                    "vpn.service-type:org.freedesktop.NetworkManager.openvpn\nvpn.data:connection-type = password, password-flags = 2\n",
+                    "",
+                ),
+                _ => (0, "", ""),
+            };
+            Ok(Output {
+                status: ExitStatus::from_raw(code << 8),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: stderr.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_attempt_vpn_connection_prompts_for_secrets() {
+        let runner = SecretsCommandRunner {
+            calls: std::sync::Mutex::new(Vec::new()),
+        };
+
+        let connected =
+            attempt_vpn_connection_with("Corp", &runner, &|_| Ok("hunter2".to_string()))
+                .await
+                .unwrap();
+        assert!(connected);
+
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[2][1..5], ["connection", "up", "Corp", "passwd-file"]);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_nm_vpn_success() {
         let output = Output {
             status: ExitStatus::from_raw(0),
             stdout: vec![],
@@ -495,8 +704,57 @@ mod tests {
         let mock_runner =
             MockCommandRunner::new("nmcli", &["connection", "down", "TestVPN"], output);
 
-        let result = disconnect_nm_vpn("TestVPN", &mock_runner);
+        let result = disconnect_nm_vpn("TestVPN", &mock_runner).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
+
+    #[test]
+    fn test_split_nmcli_fields_unescapes_colons() {
+        assert_eq!(
+            split_nmcli_fields(r"*:Cafe\:Guest:***:WPA2"),
+            vec!["*", "Cafe:Guest", "***", "WPA2"]
+        );
+        assert_eq!(
+            split_nmcli_fields(r"no:vpn:Back\\slash"),
+            vec!["no", "vpn", r"Back\slash"]
+        );
+        assert_eq!(split_nmcli_fields(""), vec![""]);
+    }
+
+    #[test]
+    fn test_parse_wifi_lines_with_colon_in_ssid() {
+        let mut actions = Vec::new();
+        parse_wifi_lines(
+            &mut actions,
+            vec![
+                r"*:Home\:Net:****:WPA2".to_string(),
+                r" :a\:b\:c:**:".to_string(),
+            ],
+        );
+
+        assert_eq!(actions.len(), 2);
+        let WifiAction::Network(display) = &actions[0] else {
+            panic!("expected a network entry");
+        };
+        let (ssid, security) = parse_wifi_action(display).unwrap();
+        assert_eq!(ssid, "Home:Net");
+        assert_eq!(security, "WPA2");
+    }
+
+    #[test]
+    fn test_parse_vpn_lines_with_colon_in_name() {
+        let mut actions = Vec::new();
+        parse_vpn_lines(&mut actions, vec![r"yes:vpn:Office\:Paris".to_string()]);
+
+        assert_eq!(actions.len(), 1);
+        let VpnAction::Disconnect(display) = &actions[0] else {
+            panic!("expected a disconnect entry");
+        };
+        assert_eq!(parse_vpn_action(display).unwrap(), "Office:Paris");
+        assert_eq!(
+            parse_vpn_action(&format!("{display}\t⚠️ handshake stale")).unwrap(),
+            "Office:Paris"
+        );
+    }
 }
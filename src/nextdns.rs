@@ -5,7 +5,7 @@
 //! NextDNS module for interacting with the NextDNS API and profiles
 //! Allows fetching, switching, and toggling between profiles using the NextDNS API.
 
-use crate::command::CommandRunner;
+use crate::command::{CommandRunner, PRIVILEGED_COMMAND_TIMEOUT};
 use crate::constants::ICON_CHECK;
 use crate::privilege::wrap_privileged_command;
 use log::{debug, error, warn};
@@ -282,7 +282,7 @@ pub fn get_current_profile() -> Result<Option<NextDnsProfile>, Box<dyn Error>> {
 }
 
 /// Set the current NextDNS profile
-pub fn set_current_profile(
+pub async fn set_current_profile(
     profile_id: &str,
     command_runner: &dyn CommandRunner,
 ) -> Result<(), Box<dyn Error>> {
@@ -319,7 +319,9 @@ pub fn set_current_profile(
     let privileged_cmd = wrap_privileged_command(&full_command, true);
 
     debug!("Running command: {}", privileged_cmd);
-    let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    let output = command_runner
+        .run_command_with_timeout("sh", &["-c", &privileged_cmd], PRIVILEGED_COMMAND_TIMEOUT)
+        .await?;
     debug!("Command output: {:?}", output);
 
     // Find the profile name if available
@@ -346,7 +348,7 @@ pub fn set_current_profile(
 }
 
 /// Disable NextDNS (revert to system DNS)
-pub fn disable_nextdns(command_runner: &dyn CommandRunner) -> Result<(), Box<dyn Error>> {
+pub async fn disable_nextdns(command_runner: &dyn CommandRunner) -> Result<(), Box<dyn Error>> {
     // Revert DNS settings to DHCP
     let commands = [
         "iface=$(ip route show default | grep -oP 'dev \\K\\S+' | head -1); iface=${iface:-wlan0}",
@@ -359,7 +361,9 @@ pub fn disable_nextdns(command_runner: &dyn CommandRunner) -> Result<(), Box<dyn
     let full_command = commands.join("; ");
     let privileged_cmd = wrap_privileged_command(&full_command, true);
 
-    let result = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    let result = command_runner
+        .run_command_with_timeout("sh", &["-c", &privileged_cmd], PRIVILEGED_COMMAND_TIMEOUT)
+        .await?;
 
     if result.status.success() {
         // Update the state
@@ -488,7 +492,7 @@ pub async fn handle_nextdns_action(
 ) -> Result<bool, Box<dyn Error>> {
     match action {
         NextDnsAction::SetProfile { profile } => {
-            set_current_profile(&profile.id, command_runner)?;
+            set_current_profile(&profile.id, command_runner).await?;
             println!(
                 "Switched to NextDNS profile: {}",
                 profile.name.as_deref().unwrap_or(&profile.id)
@@ -507,7 +511,7 @@ pub async fn handle_nextdns_action(
                 profile_a
             };
 
-            set_current_profile(&next_profile.id, command_runner)?;
+            set_current_profile(&next_profile.id, command_runner).await?;
             println!(
                 "Toggled to NextDNS profile: {}",
                 next_profile.name.as_deref().unwrap_or(&next_profile.id)
@@ -516,7 +520,7 @@ pub async fn handle_nextdns_action(
         }
 
         NextDnsAction::Disable => {
-            disable_nextdns(command_runner)?;
+            disable_nextdns(command_runner).await?;
             println!("NextDNS disabled, reverted to system DNS");
             Ok(true)
         }
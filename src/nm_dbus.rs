@@ -0,0 +1,1210 @@
+//! NetworkManager D-Bus backend.
+//!
+//! Talks to `org.freedesktop.NetworkManager` directly instead of scraping
+//! `nmcli -t` output. The nmcli functions in [`crate::networkmanager`] remain the
+//! fallback when the daemon is not reachable on the bus.
+//!
+//! The bus can be overridden with `NETWORK_DMENU_NM_BUS`: `system` (default),
+//! `session`, or a full D-Bus address such as `unix:path=/tmp/fake-nm`. This is
+//! how the backend is exercised against a fake NetworkManager in tests.
+
+use crate::networkmanager::{vpn_network_action, wifi_network_action};
+use crate::utils::prompt_for_password;
+use crate::{parse_vpn_action, parse_wifi_action, VpnAction, WifiAction};
+use dbus::arg::{prop_cast, PropMap, RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+use dbus::blocking::{Connection, Proxy};
+use dbus::{Message, Path};
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+pub const NM_BUS_NAME: &str = "org.freedesktop.NetworkManager";
+pub const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+pub const NM_SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+
+const NM_IFACE: &str = "org.freedesktop.NetworkManager";
+const DEVICE_IFACE: &str = "org.freedesktop.NetworkManager.Device";
+const WIRELESS_IFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const AP_IFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const SETTINGS_IFACE: &str = "org.freedesktop.NetworkManager.Settings";
+const SETTINGS_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+const ACTIVE_IFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+
+/// Environment variable used to pick the bus NetworkManager is reached on.
+pub const NM_BUS_ENV: &str = "NETWORK_DMENU_NM_BUS";
+
+const DBUS_CALL_TIMEOUT: Duration = Duration::from_secs(5);
+const ACTIVATION_TIMEOUT: Duration = Duration::from_secs(45);
+const ACTIVATION_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const AGENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `NM_DEVICE_TYPE_WIFI`
+const DEVICE_TYPE_WIFI: u32 = 2;
+/// `NM_ACTIVE_CONNECTION_STATE_ACTIVATED`
+const ACTIVE_STATE_ACTIVATED: u32 = 2;
+/// `NM_ACTIVE_CONNECTION_STATE_DEACTIVATED`
+const ACTIVE_STATE_DEACTIVATED: u32 = 4;
+/// `NM_METERED_YES` and `NM_METERED_GUESS_YES`
+const METERED_YES: [u32; 2] = [1, 3];
+
+// NM80211ApFlags / NM80211ApSecurityFlags
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+const AP_SEC_KEY_MGMT_OWE: u32 = 0x800;
+const AP_SEC_KEY_MGMT_OWE_TM: u32 = 0x1000;
+
+/// A Wi-Fi access point as reported by NetworkManager.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPoint {
+    pub path: String,
+    pub device: String,
+    pub ssid: String,
+    pub bssid: String,
+    pub strength: u8,
+    pub frequency: u32,
+    pub max_bitrate: u32,
+    pub security: String,
+    pub in_use: bool,
+}
+
+/// A saved connection profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionProfile {
+    pub path: String,
+    pub id: String,
+    pub uuid: String,
+    pub connection_type: String,
+    /// Raw SSID for Wi-Fi profiles.
+    pub ssid: Option<String>,
+    /// `802-11-wireless-security.key-mgmt`, `None` for open profiles.
+    pub key_mgmt: Option<String>,
+    pub autoconnect: bool,
+    pub autoconnect_priority: i32,
+}
+
+/// A currently active connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveConnection {
+    pub path: String,
+    pub id: String,
+    pub uuid: String,
+    pub connection_type: String,
+    pub state: u32,
+}
+
+/// The connection carrying the default route.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimaryConnection {
+    pub id: String,
+    pub uuid: String,
+    pub interface: String,
+}
+
+/// Blocking client for the NetworkManager D-Bus API.
+pub struct NmDbusClient {
+    connection: Connection,
+}
+
+impl NmDbusClient {
+    /// Connects to the bus selected by `NETWORK_DMENU_NM_BUS`, returning `None`
+    /// when NetworkManager is not running there.
+    pub fn connect() -> Option<Self> {
+        let bus = std::env::var(NM_BUS_ENV).unwrap_or_else(|_| "system".to_string());
+        let connection = match bus.as_str() {
+            "system" => Connection::new_system(),
+            "session" => Connection::new_session(),
+            address => open_private_connection(address),
+        };
+
+        match connection {
+            Ok(connection) => {
+                let client = Self { connection };
+                if client.is_available() {
+                    Some(client)
+                } else {
+                    debug!("NetworkManager is not on the {} bus", bus);
+                    None
+                }
+            }
+            Err(e) => {
+                debug!("Failed to connect to the {} bus: {}", bus, e);
+                None
+            }
+        }
+    }
+
+    /// Wraps an existing bus connection.
+    pub fn from_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    /// Checks whether NetworkManager owns its well-known name on this bus.
+    pub fn is_available(&self) -> bool {
+        let proxy = self.connection.with_proxy(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            DBUS_CALL_TIMEOUT,
+        );
+        proxy
+            .method_call::<(bool,), _, _, _>("org.freedesktop.DBus", "NameHasOwner", (NM_BUS_NAME,))
+            .map(|(owned,)| owned)
+            .unwrap_or(false)
+    }
+
+    fn proxy<'a>(&'a self, path: &'a str) -> Proxy<'a, &'a Connection> {
+        self.connection
+            .with_proxy(NM_BUS_NAME, path, DBUS_CALL_TIMEOUT)
+    }
+
+    fn paths(&self, path: &str, iface: &str, property: &str) -> Result<Vec<String>, dbus::Error> {
+        let paths: Vec<Path<'static>> = self.proxy(path).get(iface, property)?;
+        Ok(paths.into_iter().map(|p| p.to_string()).collect())
+    }
+
+    /// Returns `(device path, interface name)` for every Wi-Fi device.
+    pub fn wifi_devices(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let mut devices = Vec::new();
+
+        for device in self.paths(NM_PATH, NM_IFACE, "Devices")? {
+            let proxy = self.proxy(&device);
+            let device_type: u32 = proxy.get(DEVICE_IFACE, "DeviceType")?;
+            if device_type == DEVICE_TYPE_WIFI {
+                let interface: String = proxy.get(DEVICE_IFACE, "Interface")?;
+                devices.push((device, interface));
+            }
+        }
+
+        Ok(devices)
+    }
+
+    /// Finds the Wi-Fi device for `interface`, or the first one when the name is unknown.
+    fn wifi_device(&self, interface: Option<&str>) -> Result<String, Box<dyn Error>> {
+        let devices = self.wifi_devices()?;
+        interface
+            .and_then(|name| devices.iter().find(|(_, iface)| iface == name))
+            .or_else(|| devices.first())
+            .map(|(path, _)| path.clone())
+            .ok_or_else(|| "No Wi-Fi device managed by NetworkManager".into())
+    }
+
+    /// Asks a Wi-Fi device to scan for access points.
+    pub fn request_scan(&self, device: &str) -> Result<(), Box<dyn Error>> {
+        let options: PropMap = HashMap::new();
+        self.proxy(device)
+            .method_call::<(), _, _, _>(WIRELESS_IFACE, "RequestScan", (options,))?;
+        Ok(())
+    }
+
+    /// Lists the access points seen by all Wi-Fi devices.
+    pub fn access_points(&self) -> Result<Vec<AccessPoint>, Box<dyn Error>> {
+        let mut access_points = Vec::new();
+
+        for (device, _) in self.wifi_devices()? {
+            let active: Path<'static> = self
+                .proxy(&device)
+                .get(WIRELESS_IFACE, "ActiveAccessPoint")
+                .unwrap_or_else(|_| Path::from("/"));
+
+            for ap_path in self.paths(&device, WIRELESS_IFACE, "AccessPoints")? {
+                match self.access_point(&device, &ap_path, &active) {
+                    Ok(ap) => access_points.push(ap),
+                    // Access points come and go while scanning
+                    Err(e) => debug!("Skipping access point {}: {}", ap_path, e),
+                }
+            }
+        }
+
+        Ok(access_points)
+    }
+
+    fn access_point(
+        &self,
+        device: &str,
+        ap_path: &str,
+        active: &Path<'static>,
+    ) -> Result<AccessPoint, Box<dyn Error>> {
+        let proxy = self.proxy(ap_path);
+        let ssid: Vec<u8> = proxy.get(AP_IFACE, "Ssid")?;
+        let flags: u32 = proxy.get(AP_IFACE, "Flags")?;
+        let wpa_flags: u32 = proxy.get(AP_IFACE, "WpaFlags")?;
+        let rsn_flags: u32 = proxy.get(AP_IFACE, "RsnFlags")?;
+
+        Ok(AccessPoint {
+            path: ap_path.to_string(),
+            device: device.to_string(),
+            ssid: String::from_utf8_lossy(&ssid).to_string(),
+            bssid: proxy.get(AP_IFACE, "HwAddress").unwrap_or_default(),
+            strength: proxy.get(AP_IFACE, "Strength")?,
+            frequency: proxy.get(AP_IFACE, "Frequency").unwrap_or_default(),
+            max_bitrate: proxy.get(AP_IFACE, "MaxBitrate").unwrap_or_default(),
+            security: security_from_flags(flags, wpa_flags, rsn_flags),
+            in_use: &**active == ap_path,
+        })
+    }
+
+    /// Lists saved connection profiles.
+    pub fn connections(&self) -> Result<Vec<ConnectionProfile>, Box<dyn Error>> {
+        let (paths,): (Vec<Path<'static>>,) =
+            self.proxy(NM_SETTINGS_PATH)
+                .method_call(SETTINGS_IFACE, "ListConnections", ())?;
+
+        let mut profiles = Vec::new();
+        for path in paths {
+            match self.connection_settings(&path) {
+                Ok(settings) => profiles.push(profile_from_settings(&path, &settings)),
+                Err(e) => debug!("Skipping connection {}: {}", path, e),
+            }
+        }
+
+        Ok(profiles)
+    }
+
+    /// Returns the raw settings of a saved connection.
+    pub fn connection_settings(
+        &self,
+        path: &str,
+    ) -> Result<HashMap<String, PropMap>, Box<dyn Error>> {
+        let (settings,): (HashMap<String, PropMap>,) =
+            self.proxy(path)
+                .method_call(SETTINGS_CONNECTION_IFACE, "GetSettings", ())?;
+        Ok(settings)
+    }
+
+    /// Lists active connections.
+    pub fn active_connections(&self) -> Result<Vec<ActiveConnection>, Box<dyn Error>> {
+        let mut active = Vec::new();
+
+        for path in self.paths(NM_PATH, NM_IFACE, "ActiveConnections")? {
+            let proxy = self.proxy(&path);
+            active.push(ActiveConnection {
+                id: proxy.get(ACTIVE_IFACE, "Id")?,
+                uuid: proxy.get(ACTIVE_IFACE, "Uuid")?,
+                connection_type: proxy.get(ACTIVE_IFACE, "Type")?,
+                state: proxy.get(ACTIVE_IFACE, "State").unwrap_or_default(),
+                path,
+            });
+        }
+
+        Ok(active)
+    }
+
+    /// Whether the primary connection is metered, explicitly or by guess.
+    pub fn metered(&self) -> Result<bool, Box<dyn Error>> {
+        let metered: u32 = self.proxy(NM_PATH).get(NM_IFACE, "Metered")?;
+        Ok(METERED_YES.contains(&metered))
+    }
+
+    /// Returns the primary connection, the one NetworkManager routes the
+    /// default traffic through.
+    pub fn primary_connection(&self) -> Result<Option<PrimaryConnection>, Box<dyn Error>> {
+        let path: Path<'static> = self.proxy(NM_PATH).get(NM_IFACE, "PrimaryConnection")?;
+        if &*path == "/" {
+            return Ok(None);
+        }
+
+        let path = path.to_string();
+        let Some(device) = self
+            .paths(&path, ACTIVE_IFACE, "Devices")?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+        let proxy = self.proxy(&path);
+        Ok(Some(PrimaryConnection {
+            id: proxy.get(ACTIVE_IFACE, "Id")?,
+            uuid: proxy.get(ACTIVE_IFACE, "Uuid")?,
+            interface: self.proxy(&device).get(DEVICE_IFACE, "Interface")?,
+        }))
+    }
+
+    /// Activates a saved connection and waits until it is up.
+    pub fn activate_connection(
+        &self,
+        connection: &str,
+        device: &str,
+        specific_object: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let (active,): (Path<'static>,) = self.proxy(NM_PATH).method_call(
+            NM_IFACE,
+            "ActivateConnection",
+            (
+                Path::new(connection)?,
+                Path::new(device)?,
+                Path::new(specific_object)?,
+            ),
+        )?;
+
+        self.wait_for_activation(&active)
+    }
+
+    /// Creates a new connection from `settings`, activates it and waits until it is up.
+    pub fn add_and_activate_connection(
+        &self,
+        settings: HashMap<&str, PropMap>,
+        device: &str,
+        specific_object: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let (_, active): (Path<'static>, Path<'static>) = self.proxy(NM_PATH).method_call(
+            NM_IFACE,
+            "AddAndActivateConnection",
+            (settings, Path::new(device)?, Path::new(specific_object)?),
+        )?;
+
+        self.wait_for_activation(&active)
+    }
+
+    /// Deletes a saved connection.
+    pub fn delete_connection(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        self.proxy(path)
+            .method_call::<(), _, _, _>(SETTINGS_CONNECTION_IFACE, "Delete", ())?;
+        Ok(())
+    }
+
+    /// Deactivates an active connection.
+    pub fn deactivate_connection(&self, active: &str) -> Result<(), Box<dyn Error>> {
+        self.proxy(NM_PATH).method_call::<(), _, _, _>(
+            NM_IFACE,
+            "DeactivateConnection",
+            (Path::new(active)?,),
+        )?;
+        Ok(())
+    }
+
+    /// Disconnects a device, preventing it from autoconnecting again.
+    pub fn disconnect_device(&self, device: &str) -> Result<(), Box<dyn Error>> {
+        self.proxy(device)
+            .method_call::<(), _, _, _>(DEVICE_IFACE, "Disconnect", ())?;
+        Ok(())
+    }
+
+    /// Polls the active connection state until it is activated or gone.
+    fn wait_for_activation(&self, active: &Path<'static>) -> Result<bool, Box<dyn Error>> {
+        let started = Instant::now();
+
+        while started.elapsed() < ACTIVATION_TIMEOUT {
+            let state: Result<u32, _> = self.proxy(active).get(ACTIVE_IFACE, "State");
+            match state {
+                Ok(ACTIVE_STATE_ACTIVATED) => return Ok(true),
+                Ok(ACTIVE_STATE_DEACTIVATED) => return Ok(false),
+                Ok(_) => {}
+                // The object disappears when activation fails
+                Err(_) => return Ok(false),
+            }
+            std::thread::sleep(ACTIVATION_POLL_INTERVAL);
+        }
+
+        Ok(false)
+    }
+
+    /// Builds the Wi-Fi menu entries, one per SSID with the strongest signal.
+    pub fn wifi_networks(&self) -> Result<Vec<WifiAction>, Box<dyn Error>> {
+        self.wifi_networks_on(None)
+    }
+
+    /// Builds the Wi-Fi menu entries from the access points one interface
+    /// sees, or all of them when `interface` is `None`.
+    pub fn wifi_networks_on(
+        &self,
+        interface: Option<&str>,
+    ) -> Result<Vec<WifiAction>, Box<dyn Error>> {
+        let device = interface
+            .map(|name| self.wifi_device(Some(name)))
+            .transpose()?;
+        let mut access_points = self.access_points()?;
+        if let Some(device) = &device {
+            access_points.retain(|ap| &ap.device == device);
+        }
+
+        if !access_points.iter().any(|ap| ap.in_use) {
+            for (path, _) in self.wifi_devices()? {
+                if device.as_ref().is_none_or(|device| *device == path) {
+                    let _ = self.request_scan(&path);
+                }
+            }
+        }
+
+        // In-use first, then strongest first, so deduplication keeps the best entry
+        access_points.sort_by(|a, b| {
+            b.in_use
+                .cmp(&a.in_use)
+                .then_with(|| b.strength.cmp(&a.strength))
+        });
+
+        let mut seen = Vec::new();
+        let mut actions = Vec::new();
+        for ap in access_points {
+            if ap.ssid.is_empty() || seen.contains(&ap.ssid) {
+                continue;
+            }
+            actions.push(wifi_network_action(
+                ap.in_use,
+                &ap.ssid,
+                &ap.security,
+                &strength_to_stars(ap.strength),
+            ));
+            seen.push(ap.ssid);
+        }
+
+        Ok(actions)
+    }
+
+    /// Builds the VPN menu entries from saved VPN and WireGuard profiles.
+    pub fn vpn_networks(&self) -> Result<Vec<VpnAction>, Box<dyn Error>> {
+        let active = self.active_connections()?;
+
+        Ok(self
+            .connections()?
+            .into_iter()
+            .filter(|profile| is_vpn_type(&profile.connection_type))
+            .map(|profile| {
+                let is_active = active.iter().any(|a| a.uuid == profile.uuid);
+                vpn_network_action(is_active, &profile.id)
+            })
+            .collect())
+    }
+
+    /// Brings up the VPN selected in the menu.
+    pub fn connect_vpn(&self, action: &str) -> Result<bool, Box<dyn Error>> {
+        let name = parse_vpn_action(action)?;
+        let profile = self
+            .connections()?
+            .into_iter()
+            .find(|profile| profile.id == name && is_vpn_type(&profile.connection_type))
+            .ok_or_else(|| format!("VPN connection {} not found", name))?;
+
+        self.activate_connection(&profile.path, "/", "/")
+    }
+
+    /// Tears down the VPN selected in the menu.
+    pub fn disconnect_vpn(&self, action: &str) -> Result<bool, Box<dyn Error>> {
+        let name = parse_vpn_action(action)?;
+        match self
+            .active_connections()?
+            .into_iter()
+            .find(|active| active.id == name)
+        {
+            Some(active) => {
+                self.deactivate_connection(&active.path)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Connects to the Wi-Fi network selected in the menu, creating a profile if needed.
+    pub fn connect_wifi(
+        &self,
+        action: &str,
+        interface: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let (ssid, security) = parse_wifi_action(action)?;
+        let device = self.wifi_device(interface)?;
+        self.activate_wifi(ssid, security, &device, "/")
+    }
+
+    /// Connects to one specific access point (BSSID) of a network.
+    pub fn connect_access_point(&self, ap: &AccessPoint) -> Result<bool, Box<dyn Error>> {
+        self.activate_wifi(&ap.ssid, &ap.security, &ap.device, &ap.path)
+    }
+
+    /// Connects to `ssid` with credentials known up front, without prompting.
+    pub fn connect_wifi_with_credentials(
+        &self,
+        ssid: &str,
+        security: &str,
+        password: Option<&str>,
+        hidden: bool,
+        interface: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let device = self.wifi_device(interface)?;
+        if let Some(profile) = self.saved_wifi_profile(ssid)? {
+            return self.activate_connection(&profile.path, &device, "/");
+        }
+
+        let settings = wifi_connection_settings(ssid, security, password, hidden)?;
+        self.add_and_activate_connection(settings, &device, "/")
+    }
+
+    /// Activates the saved profile for `ssid`, or creates one, on `device`.
+    fn activate_wifi(
+        &self,
+        ssid: &str,
+        security: &str,
+        device: &str,
+        specific_object: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        if let Some(profile) = self.saved_wifi_profile(ssid)? {
+            return self.activate_connection(&profile.path, device, specific_object);
+        }
+        if !supports_security(security) {
+            return Err(format!("Cannot create a {security} profile over D-Bus").into());
+        }
+
+        let password = if is_open_security(security) {
+            None
+        } else {
+            Some(prompt_for_password(ssid)?)
+        };
+
+        let settings = wifi_connection_settings(ssid, security, password.as_deref(), false)?;
+        self.add_and_activate_connection(settings, device, specific_object)
+    }
+
+    fn saved_wifi_profile(&self, ssid: &str) -> Result<Option<ConnectionProfile>, Box<dyn Error>> {
+        Ok(self
+            .connections()?
+            .into_iter()
+            .find(|profile| profile.ssid.as_deref() == Some(ssid)))
+    }
+
+    /// Disconnects the Wi-Fi device.
+    pub fn disconnect_wifi(&self, interface: Option<&str>) -> Result<bool, Box<dyn Error>> {
+        let device = self.wifi_device(interface)?;
+        self.disconnect_device(&device)?;
+        Ok(true)
+    }
+}
+
+/// Runs `f` against NetworkManager on a blocking thread.
+///
+/// Returns `None` when NetworkManager is not reachable over D-Bus, so callers
+/// can fall back to nmcli. Errors are stringified to cross the thread boundary.
+pub async fn with_client<T, F>(f: F) -> Option<Result<T, String>>
+where
+    T: Send + 'static,
+    F: FnOnce(&NmDbusClient) -> Result<T, Box<dyn Error>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let client = NmDbusClient::connect()?;
+        Some(f(&client).map_err(|e| e.to_string()))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+pub(crate) fn open_private_connection(address: &str) -> Result<Connection, dbus::Error> {
+    let mut channel = dbus::channel::Channel::open_private(address)?;
+    channel.register()?;
+    Ok(Connection::from(channel))
+}
+
+/// Sends `call` and hands every other incoming message to `answer` until the
+/// reply arrives, for daemons that call back an agent on the same connection
+/// while the request is pending (iwd, ConnMan).
+///
+/// The outer error is about the bus, the inner one is the method's error reply.
+pub(crate) fn call_serving_agent(
+    connection: &Connection,
+    call: Message,
+    timeout: Duration,
+    mut answer: impl FnMut(&Message) -> Option<Message>,
+) -> Result<Result<(), dbus::Error>, Box<dyn Error>> {
+    let channel = connection.channel();
+    let serial = channel
+        .send(call)
+        .map_err(|_| "Failed to send the D-Bus request")?;
+
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        channel
+            .read_write(Some(AGENT_POLL_INTERVAL))
+            .map_err(|_| "Lost the D-Bus connection")?;
+
+        while let Some(mut message) = channel.pop_message() {
+            if message.get_reply_serial() == Some(serial) {
+                return Ok(message.as_result().map(|_| ()));
+            }
+            if let Some(reply) = answer(&message) {
+                let _ = channel.send(reply);
+            }
+        }
+    }
+
+    Err("Timed out waiting for the D-Bus reply".into())
+}
+
+fn is_vpn_type(connection_type: &str) -> bool {
+    connection_type == "vpn" || connection_type == "wireguard"
+}
+
+fn is_open_security(security: &str) -> bool {
+    matches!(
+        key_management(security),
+        Ok(KeyManagement::Open | KeyManagement::Owe)
+    )
+}
+
+/// How a new profile secures the link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyManagement {
+    Open,
+    Owe,
+    Wep,
+    Psk,
+    Sae,
+}
+
+/// Maps a scanned security string to the key management of a new profile.
+///
+/// 802.1X and unknown schemes need settings this builder cannot produce, so
+/// they are errors and the caller falls back to nmcli.
+fn key_management(security: &str) -> Result<KeyManagement, Box<dyn Error>> {
+    let upper = security.trim().to_uppercase();
+    let tokens: Vec<&str> = upper.split_whitespace().collect();
+    let has = |token: &str| tokens.contains(&token);
+
+    if has("802.1X") || has("8021X") {
+        Err(format!("{security} networks need 802.1X settings").into())
+    } else if tokens.is_empty() || tokens == ["--"] {
+        Ok(KeyManagement::Open)
+    } else if tokens.iter().all(|token| token.starts_with("OWE")) {
+        Ok(KeyManagement::Owe)
+    } else if has("WEP") {
+        Ok(KeyManagement::Wep)
+    } else if has("WPA3") && !has("WPA2") && !has("WPA1") {
+        Ok(KeyManagement::Sae)
+    } else if has("WPA1") || has("WPA2") || has("WPA3") || has("WPA") {
+        Ok(KeyManagement::Psk)
+    } else {
+        Err(format!("Unsupported Wi-Fi security: {security}").into())
+    }
+}
+
+/// Whether a profile for a network with this security can be created over
+/// D-Bus; nmcli has to handle the others.
+pub fn supports_security(security: &str) -> bool {
+    key_management(security).is_ok()
+}
+
+/// NM_WEP_KEY_TYPE_KEY for 40/104-bit keys in ASCII or hex, else
+/// NM_WEP_KEY_TYPE_PASSPHRASE.
+fn wep_key_type(key: &str) -> u32 {
+    let hex = key.chars().all(|c| c.is_ascii_hexdigit());
+    match key.len() {
+        5 | 13 => 1,
+        10 | 26 if hex => 1,
+        _ => 2,
+    }
+}
+
+fn profile_from_settings(path: &str, settings: &HashMap<String, PropMap>) -> ConnectionProfile {
+    let connection = settings.get("connection");
+    let string_setting = |key: &str| {
+        connection
+            .and_then(|c| prop_cast::<String>(c, key))
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    ConnectionProfile {
+        path: path.to_string(),
+        id: string_setting("id"),
+        uuid: string_setting("uuid"),
+        connection_type: string_setting("type"),
+        ssid: settings
+            .get("802-11-wireless")
+            .and_then(|w| prop_cast::<Vec<u8>>(w, "ssid"))
+            .map(|ssid| String::from_utf8_lossy(ssid).to_string()),
+        key_mgmt: settings
+            .get("802-11-wireless-security")
+            .and_then(|s| prop_cast::<String>(s, "key-mgmt"))
+            .cloned(),
+        // NetworkManager omits settings left at their default
+        autoconnect: connection
+            .and_then(|c| prop_cast::<bool>(c, "autoconnect"))
+            .copied()
+            .unwrap_or(true),
+        autoconnect_priority: connection
+            .and_then(|c| prop_cast::<i32>(c, "autoconnect-priority"))
+            .copied()
+            .unwrap_or_default(),
+    }
+}
+
+/// Builds the settings dictionary for a new Wi-Fi connection.
+pub fn wifi_connection_settings(
+    ssid: &str,
+    security: &str,
+    password: Option<&str>,
+    hidden: bool,
+) -> Result<HashMap<&'static str, PropMap>, Box<dyn Error>> {
+    let key_mgmt = key_management(security)?;
+    let mut settings = HashMap::new();
+
+    let mut connection: PropMap = HashMap::new();
+    connection.insert("id".into(), variant(ssid.to_string()));
+    connection.insert("type".into(), variant("802-11-wireless".to_string()));
+    settings.insert("connection", connection);
+
+    let mut wireless: PropMap = HashMap::new();
+    wireless.insert("ssid".into(), variant(ssid.as_bytes().to_vec()));
+    if hidden {
+        wireless.insert("hidden".into(), variant(true));
+    }
+    settings.insert("802-11-wireless", wireless);
+
+    let mut wireless_security: PropMap = HashMap::new();
+    let require_password = || password.ok_or_else(|| format!("{ssid} needs a password"));
+    match key_mgmt {
+        KeyManagement::Open => return Ok(settings),
+        KeyManagement::Owe => {
+            wireless_security.insert("key-mgmt".into(), variant("owe".to_string()));
+        }
+        KeyManagement::Wep => {
+            let key = require_password()?;
+            wireless_security.insert("key-mgmt".into(), variant("none".to_string()));
+            wireless_security.insert("wep-key0".into(), variant(key.to_string()));
+            wireless_security.insert("wep-key-type".into(), variant(wep_key_type(key)));
+        }
+        KeyManagement::Psk | KeyManagement::Sae => {
+            // WPA3-only networks need SAE, anything else accepts a plain PSK
+            let name = if key_mgmt == KeyManagement::Sae {
+                "sae"
+            } else {
+                "wpa-psk"
+            };
+            wireless_security.insert("key-mgmt".into(), variant(name.to_string()));
+            wireless_security.insert("psk".into(), variant(require_password()?.to_string()));
+        }
+    }
+    settings.insert("802-11-wireless-security", wireless_security);
+
+    Ok(settings)
+}
+
+fn variant<T: RefArg + 'static>(value: T) -> Variant<Box<dyn RefArg>> {
+    Variant(Box::new(value))
+}
+
+/// Converts access point flags to the security string nmcli would print.
+pub fn security_from_flags(flags: u32, wpa_flags: u32, rsn_flags: u32) -> String {
+    let mut parts = Vec::new();
+
+    if flags & AP_FLAGS_PRIVACY != 0 && wpa_flags == 0 && rsn_flags == 0 {
+        parts.push("WEP");
+    }
+    if wpa_flags != 0 {
+        parts.push("WPA1");
+    }
+    if rsn_flags & (AP_SEC_KEY_MGMT_PSK | AP_SEC_KEY_MGMT_802_1X) != 0 {
+        parts.push("WPA2");
+    }
+    if rsn_flags & AP_SEC_KEY_MGMT_SAE != 0 {
+        parts.push("WPA3");
+    }
+    if rsn_flags & (AP_SEC_KEY_MGMT_OWE | AP_SEC_KEY_MGMT_OWE_TM) != 0 {
+        parts.push("OWE");
+    }
+    if (wpa_flags | rsn_flags) & AP_SEC_KEY_MGMT_802_1X != 0 {
+        parts.push("802.1X");
+    }
+
+    parts.join(" ")
+}
+
+/// Converts a signal strength percentage to nmcli's `****` bars.
+pub fn strength_to_stars(strength: u8) -> String {
+    let bars = match strength {
+        s if s > 80 => 4,
+        s if s > 55 => 3,
+        s if s > 30 => 2,
+        s if s > 5 => 1,
+        _ => 0,
+    };
+    "*".repeat(bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::{path, start_test_bus, wait_for_service, TestBus};
+    use dbus_crossroads::{Context, Crossroads, MethodErr};
+
+    #[test]
+    fn test_security_from_flags() {
+        assert_eq!(security_from_flags(0, 0, 0), "");
+        assert_eq!(security_from_flags(AP_FLAGS_PRIVACY, 0, 0), "WEP");
+        assert_eq!(
+            security_from_flags(AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_PSK),
+            "WPA2"
+        );
+        assert_eq!(
+            security_from_flags(AP_FLAGS_PRIVACY, AP_SEC_KEY_MGMT_PSK, AP_SEC_KEY_MGMT_PSK),
+            "WPA1 WPA2"
+        );
+        assert_eq!(
+            security_from_flags(AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_SAE),
+            "WPA3"
+        );
+        assert_eq!(
+            security_from_flags(AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_802_1X),
+            "WPA2 802.1X"
+        );
+        assert_eq!(security_from_flags(0, 0, AP_SEC_KEY_MGMT_OWE), "OWE");
+    }
+
+    #[test]
+    fn test_strength_to_stars() {
+        assert_eq!(strength_to_stars(95), "****");
+        assert_eq!(strength_to_stars(60), "***");
+        assert_eq!(strength_to_stars(40), "**");
+        assert_eq!(strength_to_stars(10), "*");
+        assert_eq!(strength_to_stars(0), "");
+    }
+
+    #[test]
+    fn test_wifi_connection_settings() {
+        let settings = wifi_connection_settings("Cafe", "WPA3", Some("secret"), true).unwrap();
+        let security = &settings["802-11-wireless-security"];
+        assert_eq!(prop_cast::<String>(security, "key-mgmt").unwrap(), "sae");
+        assert_eq!(prop_cast::<String>(security, "psk").unwrap(), "secret");
+        assert!(*prop_cast::<bool>(&settings["802-11-wireless"], "hidden").unwrap());
+
+        let settings = wifi_connection_settings("Open", "", None, false).unwrap();
+        assert!(!settings.contains_key("802-11-wireless-security"));
+        assert_eq!(
+            prop_cast::<Vec<u8>>(&settings["802-11-wireless"], "ssid").unwrap(),
+            b"Open"
+        );
+    }
+
+    #[test]
+    fn test_wifi_connection_settings_wep_owe_and_enterprise() {
+        let settings = wifi_connection_settings("Old", "WEP", Some("abcde"), false).unwrap();
+        let security = &settings["802-11-wireless-security"];
+        assert_eq!(prop_cast::<String>(security, "key-mgmt").unwrap(), "none");
+        assert_eq!(prop_cast::<String>(security, "wep-key0").unwrap(), "abcde");
+        assert_eq!(*prop_cast::<u32>(security, "wep-key-type").unwrap(), 1);
+        assert!(prop_cast::<String>(security, "psk").is_none());
+
+        let settings =
+            wifi_connection_settings("Old", "WEP", Some("long passphrase"), false).unwrap();
+        let security = &settings["802-11-wireless-security"];
+        assert_eq!(*prop_cast::<u32>(security, "wep-key-type").unwrap(), 2);
+
+        let settings = wifi_connection_settings("Airport", "OWE", None, false).unwrap();
+        let security = &settings["802-11-wireless-security"];
+        assert_eq!(prop_cast::<String>(security, "key-mgmt").unwrap(), "owe");
+        assert!(is_open_security("OWE"));
+
+        assert!(wifi_connection_settings("Corp", "WPA2 802.1X", Some("x"), false).is_err());
+        assert!(wifi_connection_settings("Cafe", "WPA2", None, false).is_err());
+        assert!(!supports_security("WPA2 802.1X"));
+        assert!(supports_security("WPA1 WPA2"));
+    }
+
+    #[derive(Default)]
+    struct FakeObject {
+        properties: HashMap<&'static str, u32>,
+        strings: HashMap<&'static str, String>,
+        paths: HashMap<&'static str, Vec<Path<'static>>>,
+        ssid: Vec<u8>,
+        active_ap: Option<Path<'static>>,
+        settings: HashMap<String, PropMap>,
+    }
+
+    fn fake_settings(
+        id: &str,
+        uuid: &str,
+        typ: &str,
+        ssid: Option<&str>,
+    ) -> HashMap<String, PropMap> {
+        let mut settings = HashMap::new();
+        let mut connection: PropMap = HashMap::new();
+        connection.insert("id".into(), variant(id.to_string()));
+        connection.insert("uuid".into(), variant(uuid.to_string()));
+        connection.insert("type".into(), variant(typ.to_string()));
+        settings.insert("connection".to_string(), connection);
+        if let Some(ssid) = ssid {
+            let mut wireless: PropMap = HashMap::new();
+            wireless.insert("ssid".into(), variant(ssid.as_bytes().to_vec()));
+            settings.insert("802-11-wireless".to_string(), wireless);
+        }
+        settings
+    }
+
+    /// Serves a minimal NetworkManager with one Wi-Fi device, two access points
+    /// and one VPN profile.
+    fn serve_fake_network_manager(address: String) {
+        std::thread::spawn(move || {
+            let connection = open_private_connection(&address).unwrap();
+            connection
+                .request_name(NM_BUS_NAME, false, true, false)
+                .unwrap();
+
+            let mut cr = Crossroads::new();
+
+            let nm = cr.register(NM_IFACE, |b| {
+                b.property::<Vec<Path<'static>>, _>("Devices")
+                    .get(|_, o: &mut FakeObject| Ok(o.paths["Devices"].clone()));
+                b.property::<Vec<Path<'static>>, _>("ActiveConnections")
+                    .get(|_, o: &mut FakeObject| Ok(o.paths["ActiveConnections"].clone()));
+                b.method(
+                    "ActivateConnection",
+                    ("connection", "device", "specific_object"),
+                    ("active_connection",),
+                    |_: &mut Context,
+                     _: &mut FakeObject,
+                     (conn, _, _): (Path<'static>, Path<'static>, Path<'static>)| {
+                        if &*conn == "/org/freedesktop/NetworkManager/Settings/2" {
+                            Ok((path("/org/freedesktop/NetworkManager/ActiveConnection/1"),))
+                        } else {
+                            Err(MethodErr::failed("unknown connection"))
+                        }
+                    },
+                );
+                b.method(
+                    "DeactivateConnection",
+                    ("active_connection",),
+                    (),
+                    |_: &mut Context, _: &mut FakeObject, (_,): (Path<'static>,)| Ok(()),
+                );
+            });
+
+            let device = cr.register(DEVICE_IFACE, |b| {
+                b.property::<u32, _>("DeviceType")
+                    .get(|_, o: &mut FakeObject| Ok(o.properties["DeviceType"]));
+                b.property::<String, _>("Interface")
+                    .get(|_, o: &mut FakeObject| Ok(o.strings["Interface"].clone()));
+            });
+
+            let wireless = cr.register(WIRELESS_IFACE, |b| {
+                b.property::<Vec<Path<'static>>, _>("AccessPoints")
+                    .get(|_, o: &mut FakeObject| Ok(o.paths["AccessPoints"].clone()));
+                b.property::<Path<'static>, _>("ActiveAccessPoint")
+                    .get(|_, o: &mut FakeObject| Ok(o.active_ap.clone().unwrap()));
+                b.method(
+                    "RequestScan",
+                    ("options",),
+                    (),
+                    |_: &mut Context, _: &mut FakeObject, (_,): (PropMap,)| Ok(()),
+                );
+            });
+
+            let ap = cr.register(AP_IFACE, |b| {
+                b.property::<Vec<u8>, _>("Ssid")
+                    .get(|_, o: &mut FakeObject| Ok(o.ssid.clone()));
+                for name in ["Flags", "WpaFlags", "RsnFlags", "Frequency", "MaxBitrate"] {
+                    b.property::<u32, _>(name)
+                        .get(move |_, o: &mut FakeObject| Ok(o.properties[name]));
+                }
+                b.property::<u8, _>("Strength")
+                    .get(|_, o: &mut FakeObject| Ok(o.properties["Strength"] as u8));
+                b.property::<String, _>("HwAddress")
+                    .get(|_, o: &mut FakeObject| Ok(o.strings["HwAddress"].clone()));
+            });
+
+            let settings = cr.register(SETTINGS_IFACE, |b| {
+                b.method(
+                    "ListConnections",
+                    (),
+                    ("connections",),
+                    |_: &mut Context, o: &mut FakeObject, _: ()| {
+                        Ok((o.paths["Connections"].clone(),))
+                    },
+                );
+            });
+
+            let settings_connection = cr.register(SETTINGS_CONNECTION_IFACE, |b| {
+                b.method(
+                    "GetSettings",
+                    (),
+                    ("settings",),
+                    |_: &mut Context, o: &mut FakeObject, _: ()| {
+                        let settings: HashMap<String, PropMap> = o
+                            .settings
+                            .iter()
+                            .map(|(k, v)| {
+                                (
+                                    k.clone(),
+                                    v.iter()
+                                        .map(|(key, value)| {
+                                            (key.clone(), Variant(value.0.box_clone()))
+                                        })
+                                        .collect(),
+                                )
+                            })
+                            .collect();
+                        Ok((settings,))
+                    },
+                );
+            });
+
+            let active = cr.register(ACTIVE_IFACE, |b| {
+                for name in ["Id", "Uuid", "Type"] {
+                    b.property::<String, _>(name)
+                        .get(move |_, o: &mut FakeObject| Ok(o.strings[name].clone()));
+                }
+                b.property::<u32, _>("State")
+                    .get(|_, o: &mut FakeObject| Ok(o.properties["State"]));
+            });
+
+            let mut root = FakeObject::default();
+            root.paths.insert(
+                "Devices",
+                vec![path("/org/freedesktop/NetworkManager/Devices/1")],
+            );
+            root.paths.insert(
+                "ActiveConnections",
+                vec![path("/org/freedesktop/NetworkManager/ActiveConnection/1")],
+            );
+            cr.insert(NM_PATH, &[nm], root);
+
+            let mut wifi = FakeObject::default();
+            wifi.properties.insert("DeviceType", DEVICE_TYPE_WIFI);
+            wifi.strings.insert("Interface", "wlan0".to_string());
+            wifi.paths.insert(
+                "AccessPoints",
+                vec![
+                    path("/org/freedesktop/NetworkManager/AccessPoint/1"),
+                    path("/org/freedesktop/NetworkManager/AccessPoint/2"),
+                ],
+            );
+            wifi.active_ap = Some(path("/org/freedesktop/NetworkManager/AccessPoint/1"));
+            cr.insert(
+                "/org/freedesktop/NetworkManager/Devices/1",
+                &[device, wireless],
+                wifi,
+            );
+
+            for (i, ssid, strength, rsn) in [
+                (1, "Home:Net", 90, AP_SEC_KEY_MGMT_PSK),
+                (2, "Coffee Shop", 40, 0),
+            ] {
+                let mut obj = FakeObject {
+                    ssid: ssid.as_bytes().to_vec(),
+                    ..Default::default()
+                };
+                obj.properties
+                    .insert("Flags", if rsn != 0 { AP_FLAGS_PRIVACY } else { 0 });
+                obj.properties.insert("WpaFlags", 0);
+                obj.properties.insert("RsnFlags", rsn);
+                obj.properties.insert("Frequency", 5180);
+                obj.properties.insert("MaxBitrate", 540000);
+                obj.properties.insert("Strength", strength);
+                obj.strings
+                    .insert("HwAddress", format!("AA:BB:CC:DD:EE:0{}", i));
+                cr.insert(
+                    format!("/org/freedesktop/NetworkManager/AccessPoint/{}", i),
+                    &[ap],
+                    obj,
+                );
+            }
+
+            let mut settings_root = FakeObject::default();
+            settings_root.paths.insert(
+                "Connections",
+                vec![
+                    path("/org/freedesktop/NetworkManager/Settings/1"),
+                    path("/org/freedesktop/NetworkManager/Settings/2"),
+                ],
+            );
+            cr.insert(NM_SETTINGS_PATH, &[settings], settings_root);
+
+            cr.insert(
+                "/org/freedesktop/NetworkManager/Settings/1",
+                &[settings_connection],
+                FakeObject {
+                    settings: fake_settings(
+                        "Home:Net",
+                        "uuid-home",
+                        "802-11-wireless",
+                        Some("Home:Net"),
+                    ),
+                    ..Default::default()
+                },
+            );
+            cr.insert(
+                "/org/freedesktop/NetworkManager/Settings/2",
+                &[settings_connection],
+                FakeObject {
+                    settings: fake_settings("Work VPN", "uuid-vpn", "vpn", None),
+                    ..Default::default()
+                },
+            );
+
+            let mut active_vpn = FakeObject::default();
+            active_vpn.strings.insert("Id", "Work VPN".to_string());
+            active_vpn.strings.insert("Uuid", "uuid-vpn".to_string());
+            active_vpn.strings.insert("Type", "vpn".to_string());
+            active_vpn
+                .properties
+                .insert("State", ACTIVE_STATE_ACTIVATED);
+            cr.insert(
+                "/org/freedesktop/NetworkManager/ActiveConnection/1",
+                &[active],
+                active_vpn,
+            );
+
+            // Returns an error once the test bus goes away
+            let _ = cr.serve(&connection);
+        });
+    }
+
+    fn connect_fake_client(bus: &TestBus) -> NmDbusClient {
+        wait_for_service(
+            bus,
+            NmDbusClient::from_connection,
+            NmDbusClient::is_available,
+        )
+    }
+
+    #[test]
+    fn test_fake_bus_wifi_and_vpn_listing() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_network_manager(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        let devices = client.wifi_devices().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].1, "wlan0");
+
+        let access_points = client.access_points().unwrap();
+        assert_eq!(access_points.len(), 2);
+        let home = access_points.iter().find(|ap| ap.in_use).unwrap();
+        assert_eq!(home.ssid, "Home:Net");
+        assert_eq!(home.security, "WPA2");
+        assert_eq!(home.bssid, "AA:BB:CC:DD:EE:01");
+
+        let wifi = client.wifi_networks().unwrap();
+        assert_eq!(wifi.len(), 2);
+        match &wifi[0] {
+            WifiAction::Network(display) => {
+                let (ssid, security) = parse_wifi_action(display).unwrap();
+                assert_eq!(ssid, "Home:Net");
+                assert_eq!(security, "WPA2");
+            }
+            other => panic!("unexpected action {:?}", other),
+        }
+
+        let vpn = client.vpn_networks().unwrap();
+        assert_eq!(vpn.len(), 1);
+
+        let profiles = client.connections().unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert!(profiles.iter().all(|profile| profile.autoconnect));
+        assert_eq!(profiles[0].ssid.as_deref(), Some("Home:Net"));
+        assert!(matches!(&vpn[0], VpnAction::Disconnect(name) if name.ends_with("Work VPN")));
+    }
+
+    #[test]
+    fn test_fake_bus_vpn_activation() {
+        let Some(bus) = start_test_bus() else {
+            return;
+        };
+        serve_fake_network_manager(bus.address.clone());
+        let client = connect_fake_client(&bus);
+
+        assert!(client.connect_vpn("📶 Work VPN").unwrap());
+        assert!(client.disconnect_vpn("✅ Work VPN").unwrap());
+        assert!(client.connect_vpn("📶 Missing VPN").is_err());
+    }
+}
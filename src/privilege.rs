@@ -3,6 +3,12 @@
 //! This module provides utilities to detect and use the appropriate privilege
 //! escalation method (pkexec for GUI or sudo for terminal).
 
+use crate::command::CommandRunner;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
 use which::which;
 
 /// Determines the best available privilege escalation command
@@ -105,10 +111,73 @@ pub fn get_privilege_method_description() -> &'static str {
     }
 }
 
+/// Installs `content` at `target` as root, with the given permissions.
+///
+/// The content is staged in a private temporary file first, so secrets never
+/// show up in the process list.
+pub fn install_file_privileged(
+    target: &Path,
+    content: &str,
+    mode: u32,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let staging = std::env::temp_dir().join(format!(
+        "network-dmenu-{}-{}",
+        std::process::id(),
+        file_name
+    ));
+    write_private_file(&staging, content)?;
+
+    let command = format!(
+        "install -D -m {:o} '{}' '{}'",
+        mode,
+        staging.display().to_string().replace('\'', r"'\''"),
+        target.display().to_string().replace('\'', r"'\''")
+    );
+    let privileged_cmd = wrap_privileged_command(&command, true);
+    let result = command_runner.run_command("sh", &["-c", &privileged_cmd]);
+    let _ = fs::remove_file(&staging);
+
+    if !result?.status.success() {
+        return Err(format!("Failed to install {}", target.display()).into());
+    }
+    Ok(())
+}
+
+pub(crate) fn write_private_file(
+    path: &Path,
+    content: impl AsRef<[u8]>,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content.as_ref())?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_write_private_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("staged.conf");
+        write_private_file(&path, "secret").unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(write_private_file(&path, "again").is_err());
+    }
+
     #[test]
     fn test_get_privilege_command() {
         let cmd = get_privilege_command();
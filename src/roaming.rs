@@ -0,0 +1,466 @@
+//! Roaming watcher.
+//!
+//! Periodically compares the connected access point with the known networks
+//! in range and switches to a clearly better one. A candidate has to beat the
+//! current access point by a signal margin in several consecutive scans, and
+//! roams are spaced by a cooldown, so the connection does not flap between
+//! two access points of similar strength.
+
+use crate::command::{is_command_installed, CommandRunner, CONNECTION_COMMAND_TIMEOUT};
+use crate::iwd;
+use crate::iwd_dbus;
+use crate::networkmanager;
+use crate::wifi_details::{self, BssInfo, WifiDetailAction};
+use crate::{parse_wifi_action, WifiAction};
+use log::{debug, error, info};
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Roaming watcher settings, the `[roaming]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoamingConfig {
+    /// Seconds between two scans
+    pub interval_secs: u64,
+    /// Only look for another access point below this signal (percent)
+    pub trigger_signal: u8,
+    /// How many points of signal a candidate must gain over the current one
+    pub min_signal_gain: u8,
+    /// Consecutive scans a candidate must win before roaming
+    pub confirm_scans: u32,
+    /// Seconds to stay put after a roam
+    pub cooldown_secs: u64,
+    /// Show a notification when roaming
+    pub notify: bool,
+}
+
+impl Default for RoamingConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            trigger_signal: 60,
+            min_signal_gain: 15,
+            confirm_scans: 2,
+            cooldown_secs: 300,
+            notify: true,
+        }
+    }
+}
+
+/// Hysteresis state carried between scans.
+#[derive(Debug, Default)]
+pub struct RoamingState {
+    candidate: Option<String>,
+    wins: u32,
+    last_roam: Option<Instant>,
+}
+
+impl RoamingState {
+    /// Records the best candidate of a scan and returns it once it has won
+    /// enough consecutive scans outside the cooldown.
+    pub fn observe(
+        &mut self,
+        candidate: Option<&BssInfo>,
+        now: Instant,
+        config: &RoamingConfig,
+    ) -> Option<BssInfo> {
+        let Some(candidate) = candidate else {
+            self.candidate = None;
+            self.wins = 0;
+            return None;
+        };
+
+        let key = roam_key(candidate);
+        if self.candidate.as_deref() == Some(key) {
+            self.wins += 1;
+        } else {
+            self.candidate = Some(key.to_string());
+            self.wins = 1;
+        }
+
+        let cooling_down = self.last_roam.is_some_and(|last| {
+            now.duration_since(last) < Duration::from_secs(config.cooldown_secs)
+        });
+        if cooling_down || self.wins < config.confirm_scans.max(1) {
+            return None;
+        }
+
+        self.candidate = None;
+        self.wins = 0;
+        self.last_roam = Some(now);
+        Some(candidate.clone())
+    }
+}
+
+/// Access points are told apart by BSSID; iwd only reports networks.
+fn roam_key(bss: &BssInfo) -> &str {
+    if bss.bssid.is_empty() {
+        &bss.ssid
+    } else {
+        &bss.bssid
+    }
+}
+
+/// Picks a known access point clearly better than the connected one.
+///
+/// A candidate needs `min_signal_gain` more signal and at least half the
+/// bitrate of the current access point, so a strong but slow 2.4 GHz access
+/// point does not replace a decent 5 GHz one.
+pub fn pick_candidate<'a>(
+    access_points: &'a [BssInfo],
+    is_known: &dyn Fn(&str) -> bool,
+    config: &RoamingConfig,
+) -> Option<&'a BssInfo> {
+    let current = access_points.iter().find(|ap| ap.in_use)?;
+    if current.signal >= config.trigger_signal {
+        return None;
+    }
+
+    access_points
+        .iter()
+        .filter(|ap| !ap.in_use && roam_key(ap) != roam_key(current))
+        .filter(|ap| ap.signal >= current.signal.saturating_add(config.min_signal_gain))
+        .filter(|ap| ap.max_rate.saturating_mul(2) >= current.max_rate)
+        .filter(|ap| ap.ssid == current.ssid || is_known(&ap.ssid))
+        .max_by_key(|ap| (ap.signal, ap.max_rate))
+}
+
+/// Converts the signal bars of a menu entry, e.g. `▂▄▆_`, to percent.
+fn bars_to_percent(bars: &str) -> u8 {
+    let filled = bars.chars().filter(|c| "▂▄▆█".contains(*c)).count();
+    (filled.min(4) * 25) as u8
+}
+
+/// Maps iwd's signal strength (100 * dBm) to a percentage, -100 dBm being 0%
+/// and -50 dBm or better 100%.
+fn signal_to_percent(signal: i16) -> u8 {
+    ((i32::from(signal) / 100 + 100) * 2).clamp(0, 100) as u8
+}
+
+/// Scans for access points on `interface`.
+///
+/// iwd roams between the access points of a network by itself and only
+/// reports networks, so those come without BSSID or bitrate.
+pub async fn scan_access_points(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<BssInfo>, Box<dyn Error>> {
+    if is_command_installed("nmcli") {
+        return wifi_details::get_access_points(command_runner).await;
+    }
+
+    let dbus_interface = interface.to_string();
+    if let Some(Ok(networks)) =
+        iwd_dbus::with_client(move |client| client.networks(Some(&dbus_interface))).await
+    {
+        return Ok(networks
+            .into_iter()
+            .map(|network| BssInfo {
+                ssid: network.name,
+                bssid: String::new(),
+                frequency: 0,
+                signal: signal_to_percent(network.signal),
+                max_rate: 0,
+                security: network.security.to_uppercase(),
+                in_use: network.connected,
+            })
+            .collect());
+    }
+
+    let networks = iwd::get_iwd_networks(interface, command_runner).await?;
+    Ok(networks
+        .iter()
+        .filter_map(|action| match action {
+            WifiAction::Network(display) => {
+                let (ssid, security) = parse_wifi_action(display).ok()?;
+                Some(BssInfo {
+                    ssid: ssid.to_string(),
+                    bssid: String::new(),
+                    frequency: 0,
+                    signal: bars_to_percent(display.rsplit('\t').next().unwrap_or_default()),
+                    max_rate: 0,
+                    security: security.to_string(),
+                    in_use: display.starts_with(crate::constants::ICON_CHECK),
+                })
+            }
+            _ => None,
+        })
+        .collect())
+}
+
+/// Switches to `target`.
+pub async fn roam_to(
+    target: &BssInfo,
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if target.bssid.is_empty() {
+        if let Some(result) = iwd_dbus::with_client({
+            let ssid = target.ssid.clone();
+            let interface = interface.to_string();
+            move |client| client.connect_network(&ssid, Some(&interface), None)
+        })
+        .await
+        {
+            return Ok(result?);
+        }
+
+        let status = command_runner
+            .run_command_with_timeout(
+                "iwctl",
+                &["station", interface, "connect", &target.ssid],
+                CONNECTION_COMMAND_TIMEOUT,
+            )
+            .await?
+            .status;
+        return Ok(status.success());
+    }
+
+    wifi_details::handle_wifi_detail_action(
+        &WifiDetailAction::ConnectBssid(target.clone()),
+        command_runner,
+    )
+    .await
+}
+
+async fn is_known_network(ssid: &str, command_runner: &dyn CommandRunner) -> bool {
+    let known = if is_command_installed("nmcli") {
+        networkmanager::is_known_network(ssid, command_runner).await
+    } else if let Some(result) = iwd_dbus::with_client({
+        let ssid = ssid.to_string();
+        move |client| client.is_known_network(&ssid)
+    })
+    .await
+    {
+        result.map_err(Into::into)
+    } else {
+        iwd::is_known_network(ssid, command_runner).await
+    };
+    known.unwrap_or(false)
+}
+
+/// Bytes received and sent so far on `interface`.
+fn traffic_bytes(interface: &str) -> Option<u64> {
+    let read = |counter: &str| -> Option<u64> {
+        fs::read_to_string(format!("/sys/class/net/{interface}/statistics/{counter}"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some(read("rx_bytes")? + read("tx_bytes")?)
+}
+
+/// Runs the watcher until the process is stopped.
+pub async fn run_watcher(
+    interface: &str,
+    config: &RoamingConfig,
+    command_runner: &dyn CommandRunner,
+) -> Result<(), Box<dyn Error>> {
+    info!(
+        "Roaming watcher on {interface}: below {}% signal, roam for +{} points",
+        config.trigger_signal, config.min_signal_gain
+    );
+
+    let interval = Duration::from_secs(config.interval_secs.max(5));
+    let mut state = RoamingState::default();
+    let mut last_traffic = traffic_bytes(interface);
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let traffic = traffic_bytes(interface);
+        if let (Some(before), Some(after)) = (last_traffic, traffic) {
+            debug!(
+                "Throughput on {interface}: {} kB/s",
+                after.saturating_sub(before) / 1024 / interval.as_secs()
+            );
+        }
+        last_traffic = traffic;
+
+        let access_points = match scan_access_points(interface, command_runner).await {
+            Ok(access_points) => access_points,
+            Err(e) => {
+                error!("Roaming scan failed: {e}");
+                continue;
+            }
+        };
+        let Some(current) = access_points.iter().find(|ap| ap.in_use).cloned() else {
+            // Not connected: autoconnect is the backend's job
+            state.observe(None, Instant::now(), config);
+            continue;
+        };
+
+        // Only look the other SSIDs up when the signal is weak enough to roam
+        let mut known = HashSet::new();
+        if current.signal < config.trigger_signal {
+            let others: HashSet<&str> = access_points
+                .iter()
+                .map(|ap| ap.ssid.as_str())
+                .filter(|ssid| *ssid != current.ssid)
+                .collect();
+            for ssid in others {
+                if is_known_network(ssid, command_runner).await {
+                    known.insert(ssid);
+                }
+            }
+        }
+        let is_known = |ssid: &str| known.contains(ssid);
+        let candidate = pick_candidate(&access_points, &is_known, config);
+        debug!(
+            "Connected to {} ({}%), candidate: {:?}",
+            current.ssid,
+            current.signal,
+            candidate.map(|ap| (&ap.ssid, &ap.bssid, ap.signal))
+        );
+
+        let Some(target) = state.observe(candidate, Instant::now(), config) else {
+            continue;
+        };
+
+        info!(
+            "Roaming from {} {} ({}%) to {} {} ({}%)",
+            current.ssid, current.bssid, current.signal, target.ssid, target.bssid, target.signal
+        );
+        let roamed = roam_to(&target, interface, command_runner)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Roaming to {} failed: {e}", target.ssid);
+                false
+            });
+        if roamed && config.notify {
+            let _ = Notification::new()
+                .summary("Wi-Fi roaming")
+                .body(&format!(
+                    "Switched from {} ({}%) to {} ({}%)",
+                    current.ssid, current.signal, target.ssid, target.signal
+                ))
+                .show();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bss(ssid: &str, bssid: &str, signal: u8, max_rate: u32, in_use: bool) -> BssInfo {
+        BssInfo {
+            ssid: ssid.to_string(),
+            bssid: bssid.to_string(),
+            frequency: 5180,
+            signal,
+            max_rate,
+            security: "WPA2".to_string(),
+            in_use,
+        }
+    }
+
+    #[test]
+    fn test_pick_candidate_prefers_known_stronger_access_point() {
+        let config = RoamingConfig::default();
+        let access_points = vec![
+            bss("Office", "AA:00:00:00:00:01", 35, 540, true),
+            bss("Office", "AA:00:00:00:00:02", 80, 540, false),
+            bss("Office-Guest", "AA:00:00:00:00:03", 95, 540, false),
+            bss("Lab", "AA:00:00:00:00:04", 45, 540, false),
+        ];
+
+        let candidate = pick_candidate(&access_points, &|_| false, &config).unwrap();
+        assert_eq!(candidate.bssid, "AA:00:00:00:00:02");
+
+        let candidate = pick_candidate(&access_points, &|ssid| ssid == "Office-Guest", &config);
+        assert_eq!(candidate.unwrap().bssid, "AA:00:00:00:00:03");
+    }
+
+    #[test]
+    fn test_pick_candidate_respects_trigger_margin_and_rate() {
+        let config = RoamingConfig::default();
+
+        let good_signal = vec![
+            bss("Office", "AA:00:00:00:00:01", 70, 540, true),
+            bss("Office", "AA:00:00:00:00:02", 99, 540, false),
+        ];
+        assert_eq!(pick_candidate(&good_signal, &|_| true, &config), None);
+
+        let small_gain = vec![
+            bss("Office", "AA:00:00:00:00:01", 40, 540, true),
+            bss("Office", "AA:00:00:00:00:02", 50, 540, false),
+        ];
+        assert_eq!(pick_candidate(&small_gain, &|_| true, &config), None);
+
+        let slow = vec![
+            bss("Office", "AA:00:00:00:00:01", 40, 540, true),
+            bss("Office", "AA:00:00:00:00:02", 90, 130, false),
+        ];
+        assert_eq!(pick_candidate(&slow, &|_| true, &config), None);
+
+        let disconnected = vec![bss("Office", "AA:00:00:00:00:02", 90, 540, false)];
+        assert_eq!(pick_candidate(&disconnected, &|_| true, &config), None);
+    }
+
+    #[test]
+    fn test_state_requires_consecutive_wins() {
+        let config = RoamingConfig::default();
+        let mut state = RoamingState::default();
+        let now = Instant::now();
+        let a = bss("Office", "AA:00:00:00:00:02", 80, 540, false);
+        let b = bss("Office", "AA:00:00:00:00:03", 85, 540, false);
+
+        assert_eq!(state.observe(Some(&a), now, &config), None);
+        // A different winner restarts the count
+        assert_eq!(state.observe(Some(&b), now, &config), None);
+        assert_eq!(state.observe(Some(&b), now, &config), Some(b.clone()));
+
+        // No candidate in between resets as well
+        assert_eq!(state.observe(Some(&a), now, &config), None);
+        assert_eq!(state.observe(None, now, &config), None);
+        assert_eq!(state.observe(Some(&a), now, &config), None);
+    }
+
+    #[test]
+    fn test_state_cooldown_after_roam() {
+        let config = RoamingConfig {
+            confirm_scans: 1,
+            ..RoamingConfig::default()
+        };
+        let mut state = RoamingState::default();
+        let start = Instant::now();
+        let a = bss("Office", "AA:00:00:00:00:02", 80, 540, false);
+
+        assert!(state.observe(Some(&a), start, &config).is_some());
+        assert_eq!(
+            state.observe(Some(&a), start + Duration::from_secs(60), &config),
+            None
+        );
+        assert!(state
+            .observe(Some(&a), start + Duration::from_secs(301), &config)
+            .is_some());
+    }
+
+    #[test]
+    fn test_bars_to_percent() {
+        assert_eq!(bars_to_percent("▂▄▆█"), 100);
+        assert_eq!(bars_to_percent("▂▄__"), 50);
+        assert_eq!(bars_to_percent("____"), 0);
+    }
+
+    #[test]
+    fn test_signal_to_percent() {
+        assert_eq!(signal_to_percent(-4000), 100);
+        assert_eq!(signal_to_percent(-7000), 60);
+        assert_eq!(signal_to_percent(-11000), 0);
+    }
+
+    #[test]
+    fn test_config_defaults_from_partial_table() {
+        let config: RoamingConfig = toml::from_str("trigger_signal = 50").unwrap();
+        assert_eq!(config.trigger_signal, 50);
+        assert_eq!(config.confirm_scans, 2);
+        assert!(config.notify);
+    }
+}
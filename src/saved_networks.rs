@@ -0,0 +1,653 @@
+//! Saved connection management for NetworkManager and iwd.
+//!
+//! Lists saved Wi-Fi, VPN and wired profiles and lets the user forget them,
+//! toggle autoconnect, change the autoconnect priority or rename them. iwd only
+//! knows Wi-Fi networks and has neither priorities nor custom names.
+
+use crate::command::{is_command_installed, read_output_lines, CommandRunner};
+use crate::constants::{
+    ACTION_TYPE_SYSTEM, ICON_CHECK, ICON_CROSS, ICON_ETHERNET, ICON_KEY, ICON_LOCK, ICON_MASK,
+    ICON_METERED, ICON_SIGNAL,
+};
+use crate::format_entry;
+use crate::iwd_dbus;
+use crate::networkmanager::split_nmcli_fields;
+use crate::nm_dbus;
+use regex::Regex;
+use std::error::Error;
+use std::fmt;
+
+/// Backend a saved connection belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SavedBackend {
+    NetworkManager,
+    Iwd,
+}
+
+/// Kind of saved connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SavedConnectionKind {
+    Wifi,
+    Vpn,
+    Wired,
+}
+
+impl SavedConnectionKind {
+    /// Maps a NetworkManager connection type, ignoring types we do not manage.
+    fn from_nm_type(connection_type: &str) -> Option<Self> {
+        match connection_type {
+            "802-11-wireless" | "wifi" => Some(Self::Wifi),
+            "vpn" | "wireguard" => Some(Self::Vpn),
+            "802-3-ethernet" | "ethernet" => Some(Self::Wired),
+            _ => None,
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Self::Wifi => ICON_SIGNAL,
+            Self::Vpn => ICON_LOCK,
+            Self::Wired => ICON_ETHERNET,
+        }
+    }
+}
+
+impl fmt::Display for SavedConnectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wifi => write!(f, "wifi"),
+            Self::Vpn => write!(f, "vpn"),
+            Self::Wired => write!(f, "wired"),
+        }
+    }
+}
+
+/// A saved connection profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedConnection {
+    /// UUID for NetworkManager, network name for iwd.
+    pub id: String,
+    pub name: String,
+    pub kind: SavedConnectionKind,
+    pub backend: SavedBackend,
+    pub autoconnect: bool,
+    /// Autoconnect priority, `None` when the backend has no priorities.
+    pub priority: Option<i32>,
+}
+
+impl SavedConnection {
+    pub fn to_display_string(&self) -> String {
+        let mut details = vec![self.kind.to_string()];
+        details.push(if self.autoconnect { "auto" } else { "manual" }.to_string());
+        if let Some(priority) = self.priority {
+            details.push(format!("priority {priority}"));
+        }
+        if self.backend == SavedBackend::Iwd {
+            details.push("iwd".to_string());
+        }
+
+        format_entry(
+            ACTION_TYPE_SYSTEM,
+            self.kind.icon(),
+            &format!("{:<25}\t{}", self.name, details.join(", ")),
+        )
+    }
+}
+
+/// Actions available for a saved connection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SavedConnectionAction {
+    Forget,
+    SetAutoconnect(bool),
+    SetPriority,
+    Rename,
+    SetMacPolicy,
+    SetMetered,
+    ShowPassword,
+}
+
+impl SavedConnectionAction {
+    pub fn to_display_string(&self) -> String {
+        match self {
+            Self::Forget => format_entry(ACTION_TYPE_SYSTEM, ICON_CROSS, "Forget"),
+            Self::SetAutoconnect(true) => {
+                format_entry(ACTION_TYPE_SYSTEM, ICON_CHECK, "Enable autoconnect")
+            }
+            Self::SetAutoconnect(false) => {
+                format_entry(ACTION_TYPE_SYSTEM, ICON_CROSS, "Disable autoconnect")
+            }
+            Self::SetPriority => format_entry(ACTION_TYPE_SYSTEM, "🔢", "Set autoconnect priority"),
+            Self::Rename => format_entry(ACTION_TYPE_SYSTEM, "✏️", "Rename"),
+            Self::SetMacPolicy => format_entry(ACTION_TYPE_SYSTEM, ICON_MASK, "MAC address policy"),
+            Self::SetMetered => {
+                format_entry(ACTION_TYPE_SYSTEM, ICON_METERED, "Metered connection")
+            }
+            Self::ShowPassword => format_entry(ACTION_TYPE_SYSTEM, ICON_KEY, "Show password"),
+        }
+    }
+}
+
+/// Returns the actions supported for a saved connection.
+pub fn get_saved_connection_actions(connection: &SavedConnection) -> Vec<SavedConnectionAction> {
+    let mut actions = vec![
+        SavedConnectionAction::SetAutoconnect(!connection.autoconnect),
+        SavedConnectionAction::Forget,
+    ];
+
+    if connection.backend == SavedBackend::NetworkManager {
+        actions.insert(1, SavedConnectionAction::SetPriority);
+        actions.insert(2, SavedConnectionAction::Rename);
+        actions.insert(actions.len() - 1, SavedConnectionAction::SetMetered);
+    }
+    if connection.kind == SavedConnectionKind::Wifi {
+        let position = if connection.backend == SavedBackend::NetworkManager {
+            3
+        } else {
+            1
+        };
+        actions.insert(position, SavedConnectionAction::SetMacPolicy);
+        actions.insert(actions.len() - 1, SavedConnectionAction::ShowPassword);
+    }
+
+    actions
+}
+
+/// Lists saved connections from NetworkManager (D-Bus, then nmcli) and iwd.
+pub async fn get_saved_connections(
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<SavedConnection>, Box<dyn Error>> {
+    let mut connections = match nm_dbus::with_client(|client| client.connections()).await {
+        Some(Ok(profiles)) => profiles
+            .into_iter()
+            .filter_map(|profile| {
+                Some(SavedConnection {
+                    kind: SavedConnectionKind::from_nm_type(&profile.connection_type)?,
+                    id: profile.uuid,
+                    name: profile.id,
+                    backend: SavedBackend::NetworkManager,
+                    autoconnect: profile.autoconnect,
+                    priority: Some(profile.autoconnect_priority),
+                })
+            })
+            .collect(),
+        _ if is_command_installed("nmcli") => get_nmcli_connections(command_runner).await?,
+        _ => Vec::new(),
+    };
+
+    if is_command_installed("iwctl") {
+        match iwd_dbus::with_client(|client| client.known_networks()).await {
+            Some(Ok(known)) => {
+                connections.extend(known.into_iter().map(|network| SavedConnection {
+                    id: network.name.clone(),
+                    name: network.name,
+                    kind: SavedConnectionKind::Wifi,
+                    backend: SavedBackend::Iwd,
+                    autoconnect: network.autoconnect,
+                    priority: None,
+                }))
+            }
+            _ => connections.extend(get_iwd_known_networks(command_runner).await?),
+        }
+    }
+
+    connections.sort_by(|a, b| {
+        b.autoconnect
+            .cmp(&a.autoconnect)
+            .then(b.priority.cmp(&a.priority))
+            .then(a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(connections)
+}
+
+async fn get_nmcli_connections(
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<SavedConnection>, Box<dyn Error>> {
+    let output = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "--colors",
+                "no",
+                "-t",
+                "-f",
+                "NAME,UUID,TYPE,AUTOCONNECT,AUTOCONNECT-PRIORITY",
+                "connection",
+                "show",
+            ],
+        )
+        .await?;
+
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    Ok(parse_nmcli_connections(read_output_lines(&output)?))
+}
+
+/// Parses `nmcli -t -f NAME,UUID,TYPE,AUTOCONNECT,AUTOCONNECT-PRIORITY connection show`.
+fn parse_nmcli_connections(lines: Vec<String>) -> Vec<SavedConnection> {
+    lines
+        .iter()
+        .map(|line| split_nmcli_fields(line))
+        .filter(|fields| fields.len() == 5)
+        .filter_map(|fields| {
+            Some(SavedConnection {
+                kind: SavedConnectionKind::from_nm_type(fields[2].trim())?,
+                name: fields[0].trim().to_string(),
+                id: fields[1].trim().to_string(),
+                backend: SavedBackend::NetworkManager,
+                autoconnect: fields[3].trim() == "yes",
+                priority: Some(fields[4].trim().parse().unwrap_or_default()),
+            })
+        })
+        .collect()
+}
+
+async fn get_iwd_known_networks(
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<SavedConnection>, Box<dyn Error>> {
+    let output = command_runner
+        .run_command_async("iwctl", &["known-networks", "list"])
+        .await?;
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    let mut networks = Vec::new();
+    for name in parse_iwd_known_networks(read_output_lines(&output)?)? {
+        let show = command_runner
+            .run_command_async("iwctl", &["known-networks", &name, "show"])
+            .await?;
+        let autoconnect = read_output_lines(&show)
+            .map(|lines| parse_iwd_autoconnect(&lines))
+            .unwrap_or(true);
+
+        networks.push(SavedConnection {
+            id: name.clone(),
+            name,
+            kind: SavedConnectionKind::Wifi,
+            backend: SavedBackend::Iwd,
+            autoconnect,
+            priority: None,
+        });
+    }
+
+    Ok(networks)
+}
+
+/// Extracts network names from `iwctl known-networks list`.
+fn parse_iwd_known_networks(lines: Vec<String>) -> Result<Vec<String>, Box<dyn Error>> {
+    let ansi_escape = Regex::new(r"\x1B\[[0-?]*[ -/]*[@-~]")?;
+    let columns = Regex::new(r"\s{2,}")?;
+
+    Ok(lines
+        .iter()
+        .map(|line| ansi_escape.replace_all(line, "").to_string())
+        .skip_while(|line| !(line.contains("Name") && line.contains("Security")))
+        .skip(1)
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('-'))
+        .filter_map(|line| {
+            columns
+                .split(line.trim())
+                .next()
+                .map(|name| name.to_string())
+        })
+        .collect())
+}
+
+/// Reads the AutoConnect property from `iwctl known-networks <name> show`.
+fn parse_iwd_autoconnect(lines: &[String]) -> bool {
+    lines
+        .iter()
+        .find(|line| line.contains("AutoConnect"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(|value| value != "no")
+        .unwrap_or(true)
+}
+
+/// Removes a saved connection.
+pub async fn forget_connection(
+    connection: &SavedConnection,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let status = match connection.backend {
+        SavedBackend::Iwd => {
+            let id = connection.id.clone();
+            if let Some(result) =
+                iwd_dbus::with_client(move |client| client.forget_known_network(&id)).await
+            {
+                result?;
+                return Ok(true);
+            }
+
+            command_runner
+                .run_command_async("iwctl", &["known-networks", &connection.id, "forget"])
+                .await?
+                .status
+        }
+        SavedBackend::NetworkManager => {
+            let (uuid, name) = (connection.id.clone(), connection.name.clone());
+            if let Some(result) = nm_dbus::with_client(move |client| {
+                let profile = client
+                    .connections()?
+                    .into_iter()
+                    .find(|profile| profile.uuid == uuid)
+                    .ok_or_else(|| format!("Connection {name} not found"))?;
+                client.delete_connection(&profile.path)
+            })
+            .await
+            {
+                result?;
+                return Ok(true);
+            }
+
+            command_runner
+                .run_command_async("nmcli", &["connection", "delete", "uuid", &connection.id])
+                .await?
+                .status
+        }
+    };
+
+    Ok(status.success())
+}
+
+/// Enables or disables autoconnect for a saved connection.
+pub async fn set_autoconnect(
+    connection: &SavedConnection,
+    enabled: bool,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let value = if enabled { "yes" } else { "no" };
+
+    match connection.backend {
+        SavedBackend::Iwd => {
+            let id = connection.id.clone();
+            if let Some(result) =
+                iwd_dbus::with_client(move |client| client.set_autoconnect(&id, enabled)).await
+            {
+                result?;
+                return Ok(true);
+            }
+
+            Ok(command_runner
+                .run_command_async(
+                    "iwctl",
+                    &[
+                        "known-networks",
+                        &connection.id,
+                        "set-property",
+                        "AutoConnect",
+                        value,
+                    ],
+                )
+                .await?
+                .status
+                .success())
+        }
+        SavedBackend::NetworkManager => {
+            modify_nm_connection(connection, "connection.autoconnect", value, command_runner)
+        }
+    }
+}
+
+/// Sets the autoconnect priority of a NetworkManager connection.
+pub fn set_priority(
+    connection: &SavedConnection,
+    priority: i32,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if connection.backend != SavedBackend::NetworkManager {
+        return Err("iwd does not support autoconnect priorities".into());
+    }
+
+    modify_nm_connection(
+        connection,
+        "connection.autoconnect-priority",
+        &priority.to_string(),
+        command_runner,
+    )
+}
+
+/// Renames a NetworkManager connection.
+pub fn rename_connection(
+    connection: &SavedConnection,
+    new_name: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if connection.backend != SavedBackend::NetworkManager {
+        return Err("iwd networks cannot be renamed".into());
+    }
+    if new_name.trim().is_empty() {
+        return Err("Connection name cannot be empty".into());
+    }
+
+    modify_nm_connection(connection, "connection.id", new_name.trim(), command_runner)
+}
+
+fn modify_nm_connection(
+    connection: &SavedConnection,
+    setting: &str,
+    value: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let status = command_runner
+        .run_command(
+            "nmcli",
+            &[
+                "connection",
+                "modify",
+                "uuid",
+                &connection.id,
+                setting,
+                value,
+            ],
+        )?
+        .status;
+    Ok(status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct RecordingCommandRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl RecordingCommandRunner {
+        fn new() -> Self {
+            Self {
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn last_call(&self) -> Vec<String> {
+            self.calls
+                .lock()
+                .unwrap()
+                .last()
+                .cloned()
+                .unwrap_or_default()
+        }
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call);
+            Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout: vec![],
+                stderr: vec![],
+            })
+        }
+    }
+
+    fn saved(backend: SavedBackend, autoconnect: bool) -> SavedConnection {
+        SavedConnection {
+            id: "3f1c-uuid".to_string(),
+            name: "Hotel Lobby".to_string(),
+            kind: SavedConnectionKind::Wifi,
+            backend,
+            autoconnect,
+            priority: (backend == SavedBackend::NetworkManager).then_some(0),
+        }
+    }
+
+    #[test]
+    fn test_parse_nmcli_connections() {
+        let connections = parse_nmcli_connections(vec![
+            "Home:1111:802-11-wireless:yes:10".to_string(),
+            r"Office\:VPN:2222:vpn:no:0".to_string(),
+            "Wired connection 1:3333:802-3-ethernet:yes:-5".to_string(),
+            "lo:4444:loopback:yes:0".to_string(),
+        ]);
+
+        assert_eq!(connections.len(), 3);
+        assert_eq!(connections[0].priority, Some(10));
+        assert_eq!(connections[1].name, "Office:VPN");
+        assert_eq!(connections[1].kind, SavedConnectionKind::Vpn);
+        assert!(!connections[1].autoconnect);
+        assert_eq!(connections[2].kind, SavedConnectionKind::Wired);
+        assert_eq!(connections[2].priority, Some(-5));
+    }
+
+    #[test]
+    fn test_parse_iwd_known_networks() {
+        let lines = vec![
+            "                               Known Networks".to_string(),
+            "-".repeat(80),
+            "  Name                              Security     Hidden   Last connected".to_string(),
+            "-".repeat(80),
+            "  HomeNet                           psk                   Jan 12,  3:14 PM"
+                .to_string(),
+            "  Cafe Guest                        open         *        Dec  3,  9:00 AM"
+                .to_string(),
+            "".to_string(),
+        ];
+
+        let names = parse_iwd_known_networks(lines).unwrap();
+        assert_eq!(names, vec!["HomeNet", "Cafe Guest"]);
+    }
+
+    #[test]
+    fn test_parse_iwd_autoconnect() {
+        let show = |value: &str| {
+            vec![
+                "            Name                  HomeNet".to_string(),
+                format!("         *  AutoConnect           {value}"),
+            ]
+        };
+        assert!(parse_iwd_autoconnect(&show("yes")));
+        assert!(!parse_iwd_autoconnect(&show("no")));
+        assert!(parse_iwd_autoconnect(&[]));
+    }
+
+    #[test]
+    fn test_actions_per_backend() {
+        let nm = get_saved_connection_actions(&saved(SavedBackend::NetworkManager, true));
+        assert_eq!(
+            nm,
+            vec![
+                SavedConnectionAction::SetAutoconnect(false),
+                SavedConnectionAction::SetPriority,
+                SavedConnectionAction::Rename,
+                SavedConnectionAction::SetMacPolicy,
+                SavedConnectionAction::SetMetered,
+                SavedConnectionAction::ShowPassword,
+                SavedConnectionAction::Forget,
+            ]
+        );
+
+        let wired = SavedConnection {
+            kind: SavedConnectionKind::Wired,
+            ..saved(SavedBackend::NetworkManager, true)
+        };
+        assert!(
+            !get_saved_connection_actions(&wired).contains(&SavedConnectionAction::SetMacPolicy)
+        );
+        assert!(
+            !get_saved_connection_actions(&wired).contains(&SavedConnectionAction::ShowPassword)
+        );
+
+        let iwd = get_saved_connection_actions(&saved(SavedBackend::Iwd, false));
+        assert_eq!(
+            iwd,
+            vec![
+                SavedConnectionAction::SetAutoconnect(true),
+                SavedConnectionAction::SetMacPolicy,
+                SavedConnectionAction::ShowPassword,
+                SavedConnectionAction::Forget
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_string() {
+        let display = saved(SavedBackend::NetworkManager, true).to_display_string();
+        assert!(display.starts_with("system    - 📶 Hotel Lobby"));
+        assert!(display.ends_with("wifi, auto, priority 0"));
+
+        let display = saved(SavedBackend::Iwd, false).to_display_string();
+        assert!(display.ends_with("wifi, manual, iwd"));
+    }
+
+    #[tokio::test]
+    async fn test_nm_modifications_use_uuid() {
+        let runner = RecordingCommandRunner::new();
+        let connection = saved(SavedBackend::NetworkManager, true);
+
+        assert!(set_autoconnect(&connection, false, &runner).await.unwrap());
+        assert_eq!(
+            runner.last_call(),
+            vec![
+                "nmcli",
+                "connection",
+                "modify",
+                "uuid",
+                "3f1c-uuid",
+                "connection.autoconnect",
+                "no"
+            ]
+        );
+
+        assert!(set_priority(&connection, 20, &runner).unwrap());
+        assert_eq!(
+            runner.last_call()[5..],
+            ["connection.autoconnect-priority", "20"]
+        );
+
+        assert!(rename_connection(&connection, " Old hotel ", &runner).unwrap());
+        assert_eq!(runner.last_call()[5..], ["connection.id", "Old hotel"]);
+        assert!(rename_connection(&connection, "  ", &runner).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_iwd_operations() {
+        let runner = RecordingCommandRunner::new();
+        let connection = saved(SavedBackend::Iwd, true);
+
+        assert!(set_autoconnect(&connection, false, &runner).await.unwrap());
+        assert_eq!(
+            runner.last_call(),
+            vec![
+                "iwctl",
+                "known-networks",
+                "3f1c-uuid",
+                "set-property",
+                "AutoConnect",
+                "no"
+            ]
+        );
+
+        assert!(forget_connection(&connection, &runner).await.unwrap());
+        assert_eq!(runner.last_call()[3], "forget");
+
+        assert!(set_priority(&connection, 1, &runner).is_err());
+        assert!(rename_connection(&connection, "x", &runner).is_err());
+    }
+}
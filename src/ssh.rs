@@ -1,4 +1,4 @@
-use crate::command::CommandRunner;
+use crate::command::{CommandRunner, CONNECTION_COMMAND_TIMEOUT};
 use crate::constants::ICON_CHECK;
 use crate::format_entry;
 use crate::port_utils::is_port_listening;
@@ -53,7 +53,7 @@ impl SshProxyConfig {
     }
 
     /// Start the SSH SOCKS proxy
-    pub fn start(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
+    pub async fn start(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
         if self.is_active() {
             return Ok(()); // Already active
         }
@@ -70,7 +70,10 @@ impl SshProxyConfig {
         debug!("Starting SSH SOCKS proxy: ssh {}", cmd_args.join(" "));
 
         let cmd_args_refs: Vec<&str> = cmd_args.iter().map(|s| s.as_str()).collect();
-        match command_runner.run_command("ssh", &cmd_args_refs) {
+        match command_runner
+            .run_command_with_timeout("ssh", &cmd_args_refs, CONNECTION_COMMAND_TIMEOUT)
+            .await
+        {
             Ok(output) => {
                 if output.status.success() {
                     debug!("SSH SOCKS proxy started successfully for {}", self.name);
@@ -93,7 +96,7 @@ impl SshProxyConfig {
     }
 
     /// Stop the SSH SOCKS proxy
-    pub fn stop(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
+    pub async fn stop(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
         if !self.is_active() {
             return Ok(()); // Already stopped
         }
@@ -110,7 +113,10 @@ impl SshProxyConfig {
         debug!("Stopping SSH SOCKS proxy: ssh {}", kill_args.join(" "));
 
         let kill_args_refs: Vec<&str> = kill_args.iter().map(|s| s.as_str()).collect();
-        match command_runner.run_command("ssh", &kill_args_refs) {
+        match command_runner
+            .run_command_async("ssh", &kill_args_refs)
+            .await
+        {
             Ok(output) => {
                 if output.status.success() || output.status.code() == Some(255) {
                     // SSH returns 255 when connection is terminated, which is expected
@@ -180,18 +186,18 @@ pub fn ssh_action_to_string(action: &SshAction) -> String {
 }
 
 /// Handle SSH SOCKS proxy action
-pub fn handle_ssh_action(
+pub async fn handle_ssh_action(
     action: &SshAction,
     command_runner: &dyn CommandRunner,
 ) -> Result<(), String> {
     match action {
         SshAction::StartProxy(config) => {
             debug!("Starting SSH SOCKS proxy: {}", config.name);
-            config.start(command_runner)
+            config.start(command_runner).await
         }
         SshAction::StopProxy(config) => {
             debug!("Stopping SSH SOCKS proxy: {}", config.name);
-            config.stop(command_runner)
+            config.stop(command_runner).await
         }
     }
 }
@@ -2,15 +2,19 @@
 use crate::TailscaleAction;
 use crate::{
     format_entry, ActionType, Args, Config, CustomAction, SystemAction, VpnAction, WifiAction,
-    ACTION_TYPE_SYSTEM, ICON_CROSS, ICON_SIGNAL,
+    ACTION_TYPE_SYSTEM, ICON_CHECK, ICON_CROSS, ICON_MASK, ICON_METERED, ICON_SIGNAL, ICON_WARNING,
+    VPN_STALE_HANDSHAKE,
 };
 use network_dmenu::{
     bluetooth::get_paired_bluetooth_devices,
     command::{is_command_installed, CommandRunner, RealCommandRunner},
-    diagnostics, dns_cache,
+    connman, diagnostics, dns_cache,
     iwd::get_iwd_networks,
-    networkmanager::{get_nm_vpn_networks, get_nm_wifi_networks},
-    nextdns, rfkill, tor,
+    iwd_dbus, kill_switch, mac_policy, metered,
+    networkmanager::{get_nm_vpn_networks, get_nm_wifi_networks_on},
+    nextdns, nm_dbus, rfkill, tor, wg_quick, wifi_security, wireguard,
+    wpa_supplicant::get_wpa_networks,
+    SecurityWarning, WifiBackend,
 };
 #[cfg(feature = "tailscale")]
 use network_dmenu::{
@@ -112,8 +116,8 @@ async fn stream_actions_simple(
     // Send fastest actions first for immediate response
 
     // 1. Custom actions (already available, no computation needed)
-    for action in &config.actions {
-        let _ = tx.send(ActionType::Custom(action.clone()));
+    for action in filter_metered_actions(config.actions.clone(), &RealCommandRunner).await {
+        let _ = tx.send(ActionType::Custom(action));
     }
 
     // 2. System actions (very fast)
@@ -124,9 +128,16 @@ async fn stream_actions_simple(
         let _ = tx.send(ActionType::System(SystemAction::EditConnections));
     }
 
+    if !args.no_wifi && (is_command_installed("nmcli") || is_command_installed("iwctl")) {
+        let _ = tx.send(ActionType::System(SystemAction::SavedNetworks));
+        let _ = tx.send(ActionType::System(SystemAction::MacPolicy));
+    }
+
     // 3. Diagnostic actions (instant, no external commands)
     if !args.no_diagnostics {
-        send_diagnostic_actions(&tx);
+        let hide_speedtests =
+            config.hide_speedtest_when_metered && metered::is_metered(&RealCommandRunner).await;
+        send_diagnostic_actions(&tx, hide_speedtests);
     }
 
     // 4. SSH proxy actions (fast, just config-based)
@@ -149,7 +160,7 @@ async fn stream_actions_simple(
     }
 
     // Priority 2: VPN (usually fast, few connections)
-    if !args.no_vpn && is_command_installed("nmcli") {
+    if !args.no_vpn {
         let tx_clone = tx.clone();
         handles.push(tokio::spawn(async move {
             send_vpn_actions(&tx_clone).await;
@@ -175,6 +186,40 @@ async fn stream_actions_simple(
         }));
     }
 
+    if !args.no_wifi
+        && !args.no_hotspot
+        && (is_command_installed("nmcli")
+            || is_command_installed("iwctl")
+            || is_command_installed("connmanctl"))
+    {
+        let tx_clone = tx.clone();
+        let wifi_interface = args.wifi_interface.clone();
+        handles.push(tokio::spawn(async move {
+            send_hotspot_actions(&tx_clone, wifi_interface.as_deref()).await;
+        }));
+    }
+
+    if !args.no_ethernet && (is_command_installed("nmcli") || is_command_installed("connmanctl")) {
+        let tx_clone = tx.clone();
+        handles.push(tokio::spawn(async move {
+            send_ethernet_actions(&tx_clone).await;
+        }));
+    }
+
+    if !args.no_networkd && is_command_installed("networkctl") {
+        let tx_clone = tx.clone();
+        handles.push(tokio::spawn(async move {
+            send_networkd_actions(&tx_clone).await;
+        }));
+    }
+
+    if is_command_installed("nmcli") {
+        let tx_clone = tx.clone();
+        handles.push(tokio::spawn(async move {
+            send_metered_actions(&tx_clone).await;
+        }));
+    }
+
     // Priority 5: Tailscale (can be slow due to exit node fetching)
     #[cfg(feature = "tailscale")]
     if !args.no_tailscale && is_command_installed("tailscale") {
@@ -354,6 +399,11 @@ async fn produce_actions_streaming(
         let _ = tx.send(ActionType::System(SystemAction::EditConnections));
     }
 
+    if !args.no_wifi && (is_command_installed("nmcli") || is_command_installed("iwctl")) {
+        let _ = tx.send(ActionType::System(SystemAction::SavedNetworks));
+        let _ = tx.send(ActionType::System(SystemAction::MacPolicy));
+    }
+
     // Start parallel tasks for slower operations
     let mut tasks = vec![];
 
@@ -366,7 +416,7 @@ async fn produce_actions_streaming(
     }
 
     // VPN networks (usually fast)
-    if !args.no_vpn && is_command_installed("nmcli") {
+    if !args.no_vpn {
         let tx_clone = tx.clone();
         tasks.push(tokio::spawn(async move {
             send_vpn_actions(&tx_clone).await;
@@ -382,6 +432,40 @@ async fn produce_actions_streaming(
         }));
     }
 
+    if !args.no_wifi
+        && !args.no_hotspot
+        && (is_command_installed("nmcli")
+            || is_command_installed("iwctl")
+            || is_command_installed("connmanctl"))
+    {
+        let tx_clone = tx.clone();
+        let wifi_interface = args.wifi_interface.clone();
+        tasks.push(tokio::spawn(async move {
+            send_hotspot_actions(&tx_clone, wifi_interface.as_deref()).await;
+        }));
+    }
+
+    if !args.no_ethernet && (is_command_installed("nmcli") || is_command_installed("connmanctl")) {
+        let tx_clone = tx.clone();
+        tasks.push(tokio::spawn(async move {
+            send_ethernet_actions(&tx_clone).await;
+        }));
+    }
+
+    if !args.no_networkd && is_command_installed("networkctl") {
+        let tx_clone = tx.clone();
+        tasks.push(tokio::spawn(async move {
+            send_networkd_actions(&tx_clone).await;
+        }));
+    }
+
+    if is_command_installed("nmcli") {
+        let tx_clone = tx.clone();
+        tasks.push(tokio::spawn(async move {
+            send_metered_actions(&tx_clone).await;
+        }));
+    }
+
     // Tailscale (can be slow)
     // Handle Tailscale
     #[cfg(feature = "tailscale")]
@@ -422,8 +506,10 @@ async fn produce_actions_streaming(
     // Diagnostics
     if !args.no_diagnostics {
         let tx_clone = tx.clone();
+        let hide_speedtests =
+            config.hide_speedtest_when_metered && metered::is_metered(command_runner).await;
         tasks.push(tokio::spawn(async move {
-            send_diagnostic_actions(&tx_clone);
+            send_diagnostic_actions(&tx_clone, hide_speedtests);
         }));
     }
 
@@ -540,7 +626,7 @@ async fn send_custom_actions(
     command_runner: &impl CommandRunner,
     tx: &mpsc::UnboundedSender<ActionType>,
 ) -> Result<(), Box<dyn Error>> {
-    let mut custom_actions = config.actions.clone();
+    let mut custom_actions = filter_metered_actions(config.actions.clone(), command_runner).await;
 
     // Add DNS cache actions if available
     if config.use_dns_cache {
@@ -555,6 +641,7 @@ async fn send_custom_actions(
                                 CustomAction {
                                     display: dns_action.display,
                                     cmd: dns_action.cmd,
+                                    metered: None,
                                 },
                             );
                         }
@@ -575,10 +662,27 @@ async fn send_custom_actions(
     Ok(())
 }
 
+/// Drops custom actions whose `metered` condition does not hold, only
+/// checking the connection when some action has one.
+async fn filter_metered_actions(
+    actions: Vec<CustomAction>,
+    command_runner: &dyn CommandRunner,
+) -> Vec<CustomAction> {
+    if actions.iter().all(|action| action.metered.is_none()) {
+        return actions;
+    }
+
+    let is_metered = metered::is_metered(command_runner).await;
+    actions
+        .into_iter()
+        .filter(|action| action.metered.is_none_or(|metered| metered == is_metered))
+        .collect()
+}
+
 async fn send_bluetooth_actions(tx: &mpsc::UnboundedSender<ActionType>) {
     let command_runner = RealCommandRunner;
 
-    if let Ok(devices) = get_paired_bluetooth_devices(&command_runner) {
+    if let Ok(devices) = get_paired_bluetooth_devices(&command_runner).await {
         for device in devices {
             let _ = tx.send(ActionType::Bluetooth(device));
         }
@@ -588,51 +692,245 @@ async fn send_bluetooth_actions(tx: &mpsc::UnboundedSender<ActionType>) {
 async fn send_vpn_actions(tx: &mpsc::UnboundedSender<ActionType>) {
     let command_runner = RealCommandRunner;
 
-    if let Ok(actions) = get_nm_vpn_networks(&command_runner) {
-        for action in actions {
-            // Convert library VpnAction to main VpnAction
-            let main_action = match action {
-                network_dmenu::VpnAction::Connect(name) => VpnAction::Connect(name),
-                network_dmenu::VpnAction::Disconnect(name) => VpnAction::Disconnect(name),
-            };
-            let _ = tx.send(ActionType::Vpn(main_action));
+    let mut actions = match nm_dbus::with_client(|client| client.vpn_networks()).await {
+        Some(Ok(actions)) => actions,
+        _ if is_command_installed("nmcli") => get_nm_vpn_networks(&command_runner)
+            .await
+            .unwrap_or_default(),
+        _ => match connman::with_client(|client| client.vpn_networks()).await {
+            Some(Ok(actions)) => actions,
+            _ => Vec::new(),
+        },
+    };
+
+    // Tunnels imported into NetworkManager keep their wg-quick name, so only
+    // list the ones it does not know about.
+    if is_command_installed("wg-quick") {
+        let known: Vec<String> = actions
+            .iter()
+            .filter_map(|action| match action {
+                network_dmenu::VpnAction::Connect(name)
+                | network_dmenu::VpnAction::Disconnect(name) => {
+                    network_dmenu::parse_vpn_action(name)
+                        .ok()
+                        .map(str::to_string)
+                }
+            })
+            .collect();
+        actions.extend(
+            wg_quick::get_wg_quick_vpn_networks(&command_runner)
+                .await
+                .into_iter()
+                .filter(|action| match action {
+                    network_dmenu::VpnAction::Connect(name)
+                    | network_dmenu::VpnAction::Disconnect(name) => {
+                        network_dmenu::parse_vpn_action(name)
+                            .is_ok_and(|name| !known.iter().any(|known| known == name))
+                    }
+                }),
+        );
+    }
+
+    let mut stale = wireguard::stale_interfaces(&command_runner).await;
+    stale.extend(wireguard::stale_connections(&stale, &command_runner).await);
+    for action in actions {
+        // Convert library VpnAction to main VpnAction
+        let main_action = match action {
+            network_dmenu::VpnAction::Connect(name) => VpnAction::Connect(name),
+            network_dmenu::VpnAction::Disconnect(name) => {
+                match network_dmenu::parse_vpn_action(&name) {
+                    Ok(id) if stale.iter().any(|s| s == id) => VpnAction::Disconnect(format!(
+                        "{name}\t{ICON_WARNING} {VPN_STALE_HANDSHAKE}"
+                    )),
+                    _ => VpnAction::Disconnect(name),
+                }
+            }
+        };
+        let _ = tx.send(ActionType::Vpn(main_action));
+    }
+
+    if is_command_installed("nmcli") {
+        let _ = tx.send(ActionType::Vpn(VpnAction::ImportWireguard));
+        let _ = tx.send(ActionType::Vpn(VpnAction::ImportOpenvpn));
+        if is_command_installed("openconnect") {
+            let _ = tx.send(ActionType::Vpn(VpnAction::AddOpenconnect));
         }
     }
+    if is_command_installed("wg") && !wireguard::list_wireguard_links().is_empty() {
+        let _ = tx.send(ActionType::Vpn(VpnAction::WireguardDetails));
+    }
+    if is_command_installed("nft") {
+        let _ = tx.send(ActionType::Vpn(VpnAction::KillSwitch(
+            kill_switch::get_status(&RealCommandRunner).await,
+        )));
+    }
+}
+
+async fn send_networkd_actions(tx: &mpsc::UnboundedSender<ActionType>) {
+    for action in network_dmenu::get_networkd_actions(&RealCommandRunner).await {
+        let _ = tx.send(ActionType::Networkd(action));
+    }
+}
+
+async fn send_ethernet_actions(tx: &mpsc::UnboundedSender<ActionType>) {
+    for action in network_dmenu::get_ethernet_actions(&RealCommandRunner).await {
+        let _ = tx.send(ActionType::Ethernet(action));
+    }
 }
 
+/// Sends the Wi-Fi networks, plus the access point details, QR share and
+/// import entries.
+///
+/// With several radios and none picked on the command line, each radio lists
+/// the networks it sees and its own connect and disconnect entries, tagged with
+/// its name so the action goes through it.
 async fn send_wifi_actions(tx: &mpsc::UnboundedSender<ActionType>, wifi_interface: Option<&str>) {
+    let radios = crate::utils::list_wifi_interfaces();
+    let scopes: Vec<Option<String>> = match wifi_interface {
+        Some(interface) => vec![Some(interface.to_string())],
+        None if radios.len() > 1 => radios.into_iter().map(Some).collect(),
+        None => vec![None],
+    };
+    let tagged = scopes.len() > 1;
+
+    let mut listed = false;
+    let mut connected = false;
+    let mut networkmanager = false;
+    for scope in scopes {
+        let interface = crate::utils::get_wifi_interface(scope.as_deref());
+        let Some((actions, is_networkmanager)) = fetch_wifi_networks(scope, &interface).await
+        else {
+            continue;
+        };
+        listed = true;
+        networkmanager |= is_networkmanager;
+        connected |= send_wifi_network_actions(tx, actions, &interface, tagged).await;
+    }
+
+    if !listed {
+        return;
+    }
+    if connected {
+        let _ = tx.send(ActionType::Wifi(WifiAction::Share));
+    }
+    let _ = tx.send(ActionType::Wifi(WifiAction::ImportQr));
+    if networkmanager {
+        let _ = tx.send(ActionType::Wifi(WifiAction::Details));
+    }
+}
+
+/// Lists the networks seen by `scope`, or by every radio when `None`, and
+/// whether NetworkManager provided them.
+async fn fetch_wifi_networks(
+    scope: Option<String>,
+    interface: &str,
+) -> Option<(Vec<network_dmenu::WifiAction>, bool)> {
     let command_runner = RealCommandRunner;
 
-    if is_command_installed("nmcli") {
-        if let Ok(actions) = get_nm_wifi_networks(&command_runner) {
-            for action in actions {
-                // Convert library WifiAction to main WifiAction
-                let main_action = match action {
-                    network_dmenu::WifiAction::Connect => WifiAction::Connect,
-                    network_dmenu::WifiAction::ConnectHidden => WifiAction::ConnectHidden,
-                    network_dmenu::WifiAction::Disconnect => WifiAction::Disconnect,
-                    network_dmenu::WifiAction::Network(name) => WifiAction::Network(name),
-                };
-                let _ = tx.send(ActionType::Wifi(main_action));
-            }
+    match WifiBackend::detect(interface, &command_runner).await? {
+        WifiBackend::NetworkManager => {
+            let dbus_scope = scope.clone();
+            let actions = match nm_dbus::with_client(move |client| {
+                client.wifi_networks_on(dbus_scope.as_deref())
+            })
+            .await
+            {
+                Some(Ok(actions)) => actions,
+                _ => get_nm_wifi_networks_on(scope.as_deref(), &command_runner)
+                    .await
+                    .ok()?,
+            };
+            Some((actions, true))
         }
-    } else if is_command_installed("iwctl") {
-        if let Ok(actions) = get_iwd_networks(
-            &crate::utils::get_wifi_interface(wifi_interface),
-            &command_runner,
-        ) {
-            for action in actions {
-                // Convert library WifiAction to main WifiAction
-                let main_action = match action {
-                    network_dmenu::WifiAction::Connect => WifiAction::Connect,
-                    network_dmenu::WifiAction::ConnectHidden => WifiAction::ConnectHidden,
-                    network_dmenu::WifiAction::Disconnect => WifiAction::Disconnect,
-                    network_dmenu::WifiAction::Network(name) => WifiAction::Network(name),
-                };
-                let _ = tx.send(ActionType::Wifi(main_action));
-            }
+        WifiBackend::Connman => match connman::with_client(|client| client.wifi_networks()).await {
+            Some(Ok(actions)) => Some((actions, false)),
+            _ => None,
+        },
+        WifiBackend::Iwd => {
+            let dbus_interface = interface.to_string();
+            let actions = match iwd_dbus::with_client(move |client| {
+                client.wifi_networks(Some(&dbus_interface))
+            })
+            .await
+            {
+                Some(Ok(actions)) => actions,
+                _ => get_iwd_networks(interface, &command_runner).await.ok()?,
+            };
+            Some((actions, false))
         }
+        WifiBackend::WpaSupplicant => get_wpa_networks(interface, &command_runner)
+            .await
+            .ok()
+            .map(|actions| (actions, false)),
+    }
+}
+
+/// Sends the Wi-Fi networks seen by `interface` and returns whether one of
+/// them is connected.
+///
+/// The connected network also shows the MAC address currently in use and
+/// whether it is metered. Weakly secured networks get a warning badge.
+async fn send_wifi_network_actions(
+    tx: &mpsc::UnboundedSender<ActionType>,
+    actions: Vec<network_dmenu::WifiAction>,
+    interface: &str,
+    tagged: bool,
+) -> bool {
+    let mac = mac_policy::interface_mac(interface);
+    let visible: Vec<&str> = actions
+        .iter()
+        .filter_map(|action| match action {
+            network_dmenu::WifiAction::Network(name) => network_dmenu::parse_wifi_action(name)
+                .ok()
+                .map(|(ssid, _)| ssid),
+            _ => None,
+        })
+        .collect();
+    let saved = wifi_security::saved_securities(&visible, &RealCommandRunner).await;
+    let warnings: Vec<Option<SecurityWarning>> = actions
+        .iter()
+        .map(|action| match action {
+            network_dmenu::WifiAction::Network(name) => {
+                let (ssid, security) = network_dmenu::parse_wifi_action(name).ok()?;
+                wifi_security::check_security(security, saved.get(ssid).map(String::as_str))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let radio = tagged.then(|| interface.to_string());
+    let mut connected = false;
+    for (action, warning) in actions.into_iter().zip(warnings) {
+        // Convert library WifiAction to main WifiAction
+        let main_action = match action {
+            network_dmenu::WifiAction::Connect => WifiAction::Connect(radio.clone()),
+            network_dmenu::WifiAction::ConnectHidden => WifiAction::ConnectHidden(radio.clone()),
+            network_dmenu::WifiAction::Disconnect => WifiAction::Disconnect(radio.clone()),
+            network_dmenu::WifiAction::Network(mut name) => {
+                if name.starts_with(ICON_CHECK) {
+                    connected = true;
+                    if let Some(mac) = &mac {
+                        name.push_str(&format!("\t{ICON_MASK} {mac}"));
+                    }
+                    if is_command_installed("nmcli")
+                        && metered::is_device_metered(interface, &RealCommandRunner).await
+                    {
+                        name.push_str(&format!("\t{ICON_METERED} metered"));
+                    }
+                }
+                if let Some(warning) = warning {
+                    name.push_str(&format!("\t{}", warning.badge()));
+                }
+                if tagged {
+                    name = network_dmenu::tag_wifi_interface(&name, interface);
+                }
+                WifiAction::Network(name)
+            }
+        };
+        let _ = tx.send(ActionType::Wifi(main_action));
     }
+
+    connected
 }
 
 // Simplified tailscale action sender
@@ -647,7 +945,7 @@ async fn send_tailscale_actions_simple(
     let command_runner = RealCommandRunner;
 
     // Get Tailscale preferences
-    if let Some(prefs) = parse_tailscale_prefs(&command_runner) {
+    if let Some(prefs) = parse_tailscale_prefs(&command_runner).await {
         // Send basic Tailscale actions first (these are simple and fast)
         let _ = tx.send(ActionType::Tailscale(TailscaleAction::SetShields(
             !prefs.ShieldsUp,
@@ -661,7 +959,7 @@ async fn send_tailscale_actions_simple(
         let _ = tx.send(ActionType::Tailscale(TailscaleAction::ShowLockStatus));
 
         // Create TailscaleState to get exit node information
-        let tailscale_state = TailscaleState::new(&command_runner);
+        let tailscale_state = TailscaleState::fetch(&command_runner).await;
 
         // Get and send Mullvad/exit node actions
         let mullvad_actions = get_mullvad_actions(
@@ -761,12 +1059,38 @@ async fn send_nextdns_actions(
     }
 }
 
-fn send_diagnostic_actions(tx: &mpsc::UnboundedSender<ActionType>) {
+fn send_diagnostic_actions(tx: &mpsc::UnboundedSender<ActionType>, hide_speedtests: bool) {
     for action in diagnostics::get_diagnostic_actions() {
+        if hide_speedtests
+            && matches!(
+                action,
+                diagnostics::DiagnosticAction::SpeedTest
+                    | diagnostics::DiagnosticAction::SpeedTestFast
+            )
+        {
+            continue;
+        }
         let _ = tx.send(ActionType::Diagnostic(action));
     }
 }
 
+async fn send_metered_actions(tx: &mpsc::UnboundedSender<ActionType>) {
+    if let Some(connection) = metered::get_primary_connection(&RealCommandRunner).await {
+        let _ = tx.send(ActionType::System(SystemAction::Metered(connection)));
+    }
+}
+
+async fn send_hotspot_actions(
+    tx: &mpsc::UnboundedSender<ActionType>,
+    wifi_interface: Option<&str>,
+) {
+    let command_runner = RealCommandRunner;
+    let interface = crate::utils::get_wifi_interface(wifi_interface);
+    for action in network_dmenu::get_hotspot_actions(&interface, &command_runner).await {
+        let _ = tx.send(ActionType::Hotspot(action));
+    }
+}
+
 async fn send_ssh_actions(
     tx: &mpsc::UnboundedSender<ActionType>,
     ssh_proxies: &std::collections::HashMap<String, network_dmenu::SshProxyConfig>,
@@ -784,31 +1108,36 @@ async fn send_rfkill_actions(
     no_wifi: bool,
     no_bluetooth: bool,
 ) {
-    // Check if all devices are blocked (airplane mode is on)
-    // Use get_device_type_summary to efficiently check device status
-    let device_summary = rfkill::get_device_type_summary().await.unwrap_or_default();
-
-    if !device_summary.is_empty() {
-        // Check if all radio devices are blocked
-        let radio_types = ["wlan", "bluetooth", "wwan", "fm", "nfc", "gps"];
-        let radio_devices: Vec<_> = device_summary
-            .iter()
-            .filter(|(device_type, _)| radio_types.contains(&device_type.as_str()))
-            .collect();
+    // ConnMan's offline mode is the airplane mode when ConnMan runs
+    if let Some(Ok(offline)) = connman::with_client(|client| client.offline_mode()).await {
+        let _ = tx.send(ActionType::System(SystemAction::AirplaneMode(!offline)));
+    } else {
+        // Check if all devices are blocked (airplane mode is on)
+        // Use get_device_type_summary to efficiently check device status
+        let device_summary = rfkill::get_device_type_summary().await.unwrap_or_default();
 
-        if !radio_devices.is_empty() {
+        if !device_summary.is_empty() {
             // Check if all radio devices are blocked
-            // (blocked_count, unblocked_count)
-            let all_blocked = radio_devices
+            let radio_types = ["wlan", "bluetooth", "wwan", "fm", "nfc", "gps"];
+            let radio_devices: Vec<_> = device_summary
                 .iter()
-                .all(|(_, (blocked, unblocked))| *blocked > 0 && *unblocked == 0);
-
-            if all_blocked {
-                // All devices are blocked, offer to disable airplane mode
-                let _ = tx.send(ActionType::System(SystemAction::AirplaneMode(false)));
-            } else {
-                // Not all devices are blocked, offer to enable airplane mode
-                let _ = tx.send(ActionType::System(SystemAction::AirplaneMode(true)));
+                .filter(|(device_type, _)| radio_types.contains(&device_type.as_str()))
+                .collect();
+
+            if !radio_devices.is_empty() {
+                // Check if all radio devices are blocked
+                // (blocked_count, unblocked_count)
+                let all_blocked = radio_devices
+                    .iter()
+                    .all(|(_, (blocked, unblocked))| *blocked > 0 && *unblocked == 0);
+
+                if all_blocked {
+                    // All devices are blocked, offer to disable airplane mode
+                    let _ = tx.send(ActionType::System(SystemAction::AirplaneMode(false)));
+                } else {
+                    // Not all devices are blocked, offer to enable airplane mode
+                    let _ = tx.send(ActionType::System(SystemAction::AirplaneMode(true)));
+                }
             }
         }
     }
@@ -8,6 +8,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
+use std::process::Output;
 
 use reqwest::Client;
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
@@ -194,97 +195,120 @@ pub struct TailscaleLocation {
 impl TailscaleState {
     /// Creates a new TailscaleState by fetching all necessary Tailscale information once
     pub fn new(command_runner: &dyn CommandRunner) -> Self {
-        // Default empty state
+        let Some(status) =
+            parse_status_output(command_runner.run_command("tailscale", &["status", "--json"]))
+        else {
+            return Self::default();
+        };
+        let suggested = parse_suggest_output(
+            command_runner.run_command("tailscale", &["exit-node", "suggest"]),
+        );
+        let lock_output = command_runner.run_command("tailscale", &["lock"]);
+        Self::from_outputs(status, suggested, lock_output)
+    }
+
+    /// Same as [`TailscaleState::new`], without blocking the async runtime
+    pub async fn fetch(command_runner: &dyn CommandRunner) -> Self {
+        let Some(status) = parse_status_output(
+            command_runner
+                .run_command_async("tailscale", &["status", "--json"])
+                .await,
+        ) else {
+            return Self::default();
+        };
+        let suggested = get_exit_node_suggested(command_runner).await;
+        let lock_output = command_runner
+            .run_command_async("tailscale", &["lock"])
+            .await;
+        Self::from_outputs(status, suggested, lock_output)
+    }
+
+    fn from_outputs(
+        status: TailscaleStatus,
+        suggested: Option<String>,
+        lock_output: Result<Output, std::io::Error>,
+    ) -> Self {
         let mut state = Self {
-            status: TailscaleStatus::default(),
-            active_exit_node: String::new(),
-            suggested_exit_node: String::new(),
-            lock_output: None,
-            can_sign_nodes: false,
-            node_signing_key: None,
+            status,
+            suggested_exit_node: suggested.unwrap_or_default(),
+            ..Self::default()
         };
 
-        // Fetch Tailscale status
-        let output = match command_runner.run_command("tailscale", &["status", "--json"]) {
-            Ok(out) => out,
-            Err(e) => {
-                error!("Failed to execute tailscale status command: {e}");
-                return state;
+        // Find active exit node
+        for peer in state.status.peer.values() {
+            if peer.active && peer.exit_node {
+                state.active_exit_node = peer.dns_name.trim_end_matches('.').to_string();
+                break;
             }
-        };
-
-        if !output.status.success() {
-            return state;
         }
 
-        // Parse the JSON output
-        match serde_json::from_slice(&output.stdout) {
-            Ok(status) => {
-                state.status = status;
-
-                // Find active exit node
-                for peer in state.status.peer.values() {
-                    if peer.active && peer.exit_node {
-                        state.active_exit_node = peer.dns_name.trim_end_matches('.').to_string();
-                        break;
-                    }
-                }
-
-                // Get suggested exit node
-                state.suggested_exit_node =
-                    get_exit_node_suggested(command_runner).unwrap_or_default();
-
-                // Get lock status and locked nodes in one call
-                if let Ok(lock_output) = command_runner.run_command("tailscale", &["lock"]) {
-                    if lock_output.status.success() {
-                        // Store the entire output to avoid repeated calls
-                        let stdout = String::from_utf8_lossy(&lock_output.stdout).to_string();
-                        state.lock_output = Some(stdout.clone());
-
-                        // Check if lock is enabled
-                        if stdout
-                            .to_ascii_lowercase()
-                            .contains("tailnet lock is enabled")
-                        {
-                            // Extract this node's signing key
-                            for line in stdout.lines() {
-                                if line.contains("This node's tailnet-lock key:") {
-                                    if let Some(key) = line.split_whitespace().last() {
-                                        state.node_signing_key = Some(key.to_string());
-                                    }
-                                }
+        // Get lock status and locked nodes in one call
+        if let Ok(lock_output) = lock_output {
+            if lock_output.status.success() {
+                // Store the entire output to avoid repeated calls
+                let stdout = String::from_utf8_lossy(&lock_output.stdout).to_string();
+                state.lock_output = Some(stdout.clone());
+
+                // Check if lock is enabled
+                if stdout
+                    .to_ascii_lowercase()
+                    .contains("tailnet lock is enabled")
+                {
+                    // Extract this node's signing key
+                    for line in stdout.lines() {
+                        if line.contains("This node's tailnet-lock key:") {
+                            if let Some(key) = line.split_whitespace().last() {
+                                state.node_signing_key = Some(key.to_string());
                             }
+                        }
+                    }
 
-                            // Check if the node's key is in the trusted keys list
-                            if let Some(key) = &state.node_signing_key {
-                                let trusted_keys_section =
-                                    stdout.split("Trusted signing keys:").nth(1);
-                                if let Some(trusted_section) = trusted_keys_section {
-                                    let locked_nodes_marker = "The following nodes are locked out";
-                                    let trusted_section = if let Some(pos) =
-                                        trusted_section.find(locked_nodes_marker)
-                                    {
-                                        &trusted_section[..pos]
-                                    } else {
-                                        trusted_section
-                                    };
-
-                                    state.can_sign_nodes = trusted_section.contains(key);
-                                }
-                            }
+                    // Check if the node's key is in the trusted keys list
+                    if let Some(key) = &state.node_signing_key {
+                        let trusted_keys_section = stdout.split("Trusted signing keys:").nth(1);
+                        if let Some(trusted_section) = trusted_keys_section {
+                            let locked_nodes_marker = "The following nodes are locked out";
+                            let trusted_section =
+                                if let Some(pos) = trusted_section.find(locked_nodes_marker) {
+                                    &trusted_section[..pos]
+                                } else {
+                                    trusted_section
+                                };
+
+                            state.can_sign_nodes = trusted_section.contains(key);
                         }
                     }
                 }
             }
-            Err(e) => {
-                error!("Failed to parse Tailscale status JSON: {e}");
-            }
-        };
+        }
 
         state
     }
 }
 
+/// Parses the output of `tailscale status --json`.
+fn parse_status_output(output: Result<Output, std::io::Error>) -> Option<TailscaleStatus> {
+    let output = match output {
+        Ok(out) => out,
+        Err(e) => {
+            error!("Failed to execute tailscale status command: {e}");
+            return None;
+        }
+    };
+
+    if !output.status.success() {
+        return None;
+    }
+
+    match serde_json::from_slice(&output.stdout) {
+        Ok(status) => Some(status),
+        Err(e) => {
+            error!("Failed to parse Tailscale status JSON: {e}");
+            None
+        }
+    }
+}
+
 // TailscalePeer and TailscaleLocation structs are defined earlier, duplicates removed
 
 /// Enum representing various Tailscale actions.
@@ -714,9 +738,18 @@ fn extract_short_name(node_name: &str) -> &str {
 }
 
 /// Get the suggested exit-node
-pub fn get_exit_node_suggested(command_runner: &dyn CommandRunner) -> Option<String> {
+pub async fn get_exit_node_suggested(command_runner: &dyn CommandRunner) -> Option<String> {
     debug!("Getting suggested exit node from tailscale");
-    let output = match command_runner.run_command("tailscale", &["exit-node", "suggest"]) {
+    parse_suggest_output(
+        command_runner
+            .run_command_async("tailscale", &["exit-node", "suggest"])
+            .await,
+    )
+}
+
+/// Parses the output of `tailscale exit-node suggest`.
+fn parse_suggest_output(output: Result<Output, std::io::Error>) -> Option<String> {
+    let output = match output {
         Ok(out) => out,
         Err(e) => {
             error!("Failed to get suggested exit node: {e}");
@@ -751,7 +784,9 @@ pub async fn set_exit_node_by_hostname(command_runner: &dyn CommandRunner, hostn
     debug!("Using node name: {}", node_name);
 
     // Try to get the IP address from tailscale status
-    let node_ip = if let Ok(output) = command_runner.run_command("tailscale", &["status", "--json"])
+    let node_ip = if let Ok(output) = command_runner
+        .run_command_async("tailscale", &["status", "--json"])
+        .await
     {
         if output.status.success() {
             if let Ok(status) = serde_json::from_slice::<TailscaleStatus>(&output.stdout) {
@@ -778,7 +813,7 @@ pub async fn set_exit_node_by_hostname(command_runner: &dyn CommandRunner, hostn
     debug!("Resolved IP for {}: {:?}", hostname, node_ip);
 
     // Run the "tailscale up" command
-    match command_runner.run_command("tailscale", &["up"]) {
+    match command_runner.run_command_async("tailscale", &["up"]).await {
         Ok(output) if output.status.success() => {
             debug!("Tailscale up command succeeded");
         }
@@ -788,14 +823,17 @@ pub async fn set_exit_node_by_hostname(command_runner: &dyn CommandRunner, hostn
     // Try with IP address first if we have it
     if let Some(ip) = &node_ip {
         debug!("Trying to set exit node using IP: {}", ip);
-        match command_runner.run_command(
-            "tailscale",
-            &[
-                "set",
-                &format!("--exit-node={}", ip),
-                "--exit-node-allow-lan-access=true",
-            ],
-        ) {
+        match command_runner
+            .run_command_async(
+                "tailscale",
+                &[
+                    "set",
+                    &format!("--exit-node={}", ip),
+                    "--exit-node-allow-lan-access=true",
+                ],
+            )
+            .await
+        {
             Ok(output) => {
                 if output.status.success() {
                     debug!("Successfully set exit node using IP: {}", ip);
@@ -809,14 +847,17 @@ pub async fn set_exit_node_by_hostname(command_runner: &dyn CommandRunner, hostn
     }
 
     // Fall back to trying with just the node name
-    match command_runner.run_command(
-        "tailscale",
-        &[
-            "set",
-            &format!("--exit-node={}", node_name),
-            "--exit-node-allow-lan-access=true",
-        ],
-    ) {
+    match command_runner
+        .run_command_async(
+            "tailscale",
+            &[
+                "set",
+                &format!("--exit-node={}", node_name),
+                "--exit-node-allow-lan-access=true",
+            ],
+        )
+        .await
+    {
         Ok(output) => {
             let success = output.status.success();
             if success {
@@ -826,14 +867,17 @@ pub async fn set_exit_node_by_hostname(command_runner: &dyn CommandRunner, hostn
                 // Try with the full hostname if the short name failed
                 if hostname != node_name {
                     debug!("Retrying with full hostname: {}", hostname);
-                    match command_runner.run_command(
-                        "tailscale",
-                        &[
-                            "set",
-                            &format!("--exit-node={}", hostname),
-                            "--exit-node-allow-lan-access=true",
-                        ],
-                    ) {
+                    match command_runner
+                        .run_command_async(
+                            "tailscale",
+                            &[
+                                "set",
+                                &format!("--exit-node={}", hostname),
+                                "--exit-node-allow-lan-access=true",
+                            ],
+                        )
+                        .await
+                    {
                         Ok(retry_output) => {
                             let retry_success = retry_output.status.success();
                             if retry_success {
@@ -866,7 +910,7 @@ pub async fn set_exit_node(command_runner: &dyn CommandRunner, action: &str) ->
     println!("Exit-node ip address: {node_ip}");
 
     // Run the "tailscale up" command
-    match command_runner.run_command("tailscale", &["up"]) {
+    match command_runner.run_command_async("tailscale", &["up"]).await {
         Ok(output) if output.status.success() => {
             #[cfg(debug_assertions)]
             println!("Tailscale up command succeeded");
@@ -875,14 +919,17 @@ pub async fn set_exit_node(command_runner: &dyn CommandRunner, action: &str) ->
     }
 
     // Run the "tailscale set" command with the exit node
-    match command_runner.run_command(
-        "tailscale",
-        &[
-            "set",
-            &format!("--exit-node={node_ip}"),
-            "--exit-node-allow-lan-access=true",
-        ],
-    ) {
+    match command_runner
+        .run_command_async(
+            "tailscale",
+            &[
+                "set",
+                &format!("--exit-node={node_ip}"),
+                "--exit-node-allow-lan-access=true",
+            ],
+        )
+        .await
+    {
         Ok(output) => {
             let success = output.status.success();
             #[cfg(debug_assertions)]
@@ -945,7 +992,7 @@ pub async fn handle_tailscale_action(
         if let Some(s) = tailscale_state {
             s
         } else {
-            owned_state = TailscaleState::new(command_runner);
+            owned_state = TailscaleState::fetch(command_runner).await;
             &owned_state
         }
     } else {
@@ -967,7 +1014,8 @@ pub async fn handle_tailscale_action(
         }
         TailscaleAction::DisableExitNode => {
             let status = command_runner
-                .run_command("tailscale", &["set", "--exit-node="])?
+                .run_command_async("tailscale", &["set", "--exit-node="])
+                .await?
                 .status;
             // Log errors from mullvad check in debug mode but continue execution
             if let Err(_e) = check_mullvad().await {
@@ -977,7 +1025,8 @@ pub async fn handle_tailscale_action(
         }
         TailscaleAction::SetEnable(enable) => {
             let status = command_runner
-                .run_command("tailscale", &[if *enable { "up" } else { "down" }])?
+                .run_command_async("tailscale", &[if *enable { "up" } else { "down" }])
+                .await?
                 .status;
             Ok(status.success())
         }
@@ -1001,7 +1050,7 @@ pub async fn handle_tailscale_action(
                 // Fetch the current active exit node state for debugging
                 #[cfg(debug_assertions)]
                 {
-                    let new_state = TailscaleState::new(command_runner);
+                    let new_state = TailscaleState::fetch(command_runner).await;
                     println!(
                         "After setting exit node, active node is: {}",
                         if new_state.active_exit_node.is_empty() {
@@ -1017,7 +1066,7 @@ pub async fn handle_tailscale_action(
         }
         TailscaleAction::SetShields(enable) => {
             let status = command_runner
-                .run_command(
+                .run_command_async(
                     "tailscale",
                     &[
                         "set",
@@ -1027,13 +1076,14 @@ pub async fn handle_tailscale_action(
                             "--shields-up=false"
                         },
                     ],
-                )?
+                )
+                .await?
                 .status;
             Ok(status.success())
         }
         TailscaleAction::SetAcceptRoutes(enable) => {
             let status = command_runner
-                .run_command(
+                .run_command_async(
                     "tailscale",
                     &[
                         "set",
@@ -1043,13 +1093,14 @@ pub async fn handle_tailscale_action(
                             "--accept-routes=false"
                         },
                     ],
-                )?
+                )
+                .await?
                 .status;
             Ok(status.success())
         }
         TailscaleAction::SetAllowLanAccess(enable) => {
             let status = command_runner
-                .run_command(
+                .run_command_async(
                     "tailscale",
                     &[
                         "set",
@@ -1059,7 +1110,8 @@ pub async fn handle_tailscale_action(
                             "--exit-node-allow-lan-access=false"
                         },
                     ],
-                )?
+                )
+                .await?
                 .status;
             Ok(status.success())
         }
@@ -1069,14 +1121,18 @@ pub async fn handle_tailscale_action(
                 if let Some(lock_output) = &state.lock_output {
                     lock_output.clone()
                 } else {
-                    let output = command_runner.run_command("tailscale", &["lock"])?;
+                    let output = command_runner
+                        .run_command_async("tailscale", &["lock"])
+                        .await?;
                     if !output.status.success() {
                         return Ok(false);
                     }
                     String::from_utf8_lossy(&output.stdout).to_string()
                 }
             } else {
-                let output = command_runner.run_command("tailscale", &["lock"])?;
+                let output = command_runner
+                    .run_command_async("tailscale", &["lock"])
+                    .await?;
                 if !output.status.success() {
                     return Ok(false);
                 }
@@ -1236,7 +1292,7 @@ pub async fn handle_tailscale_action(
             if suggested_node.is_empty() {
                 debug!("No suggested exit node available in state, trying to fetch it again");
                 // Try to get it directly in case the state is stale
-                let fresh_suggested = get_exit_node_suggested(command_runner);
+                let fresh_suggested = get_exit_node_suggested(command_runner).await;
 
                 if let Some(fresh_node) = fresh_suggested {
                     debug!("Got fresh suggested node: {}", fresh_node);
@@ -1730,7 +1786,7 @@ mod tests {
     #[derive(Debug)]
     struct MockCommandRunner {
         responses: Vec<(String, Vec<String>, Output)>,
-        call_count: std::cell::RefCell<usize>,
+        call_count: std::sync::Mutex<usize>,
     }
 
     impl MockCommandRunner {
@@ -1741,7 +1797,7 @@ mod tests {
                     args.iter().map(|s| s.to_string()).collect(),
                     output,
                 )],
-                call_count: std::cell::RefCell::new(0),
+                call_count: std::sync::Mutex::new(0),
             }
         }
 
@@ -1757,14 +1813,14 @@ mod tests {
                         )
                     })
                     .collect(),
-                call_count: std::cell::RefCell::new(0),
+                call_count: std::sync::Mutex::new(0),
             }
         }
     }
 
     impl CommandRunner for MockCommandRunner {
         fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
-            let mut count = self.call_count.borrow_mut();
+            let mut count = self.call_count.lock().unwrap();
             if *count < self.responses.len() {
                 let (expected_cmd, expected_args, output) = &self.responses[*count];
                 assert_eq!(command, expected_cmd);
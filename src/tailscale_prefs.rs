@@ -27,9 +27,10 @@ pub struct TailscalePrefs {
 }
 
 /// Parse the output of `tailscale debug prefs` to get current state
-pub fn parse_tailscale_prefs(command_runner: &dyn CommandRunner) -> Option<TailscalePrefs> {
+pub async fn parse_tailscale_prefs(command_runner: &dyn CommandRunner) -> Option<TailscalePrefs> {
     let output = command_runner
-        .run_command("tailscale", &["debug", "prefs"])
+        .run_command_async("tailscale", &["debug", "prefs"])
+        .await
         .ok()?;
 
     if !output.status.success() {
@@ -81,8 +82,8 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_parse_tailscale_prefs() {
+    #[tokio::test]
+    async fn test_parse_tailscale_prefs() {
         let json = r#"{
             "ShieldsUp": false,
             "RouteAll": true,
@@ -101,7 +102,7 @@ mod tests {
 
         let mock_runner = MockCommandRunner::new("tailscale", &["debug", "prefs"], output);
 
-        let prefs = parse_tailscale_prefs(&mock_runner);
+        let prefs = parse_tailscale_prefs(&mock_runner).await;
         assert!(prefs.is_some());
 
         let prefs = prefs.unwrap();
@@ -111,8 +112,8 @@ mod tests {
         assert_eq!(prefs.ExitNodeIP, Some("100.101.102.103".to_string()));
     }
 
-    #[test]
-    fn test_parse_tailscale_prefs_command_failure() {
+    #[tokio::test]
+    async fn test_parse_tailscale_prefs_command_failure() {
         let output = Output {
             status: ExitStatus::from_raw(1),
             stdout: vec![],
@@ -121,12 +122,12 @@ mod tests {
 
         let mock_runner = MockCommandRunner::new("tailscale", &["debug", "prefs"], output);
 
-        let prefs = parse_tailscale_prefs(&mock_runner);
+        let prefs = parse_tailscale_prefs(&mock_runner).await;
         assert!(prefs.is_none());
     }
 
-    #[test]
-    fn test_parse_tailscale_prefs_invalid_json() {
+    #[tokio::test]
+    async fn test_parse_tailscale_prefs_invalid_json() {
         let json = r#"{ invalid json }"#;
 
         let output = Output {
@@ -137,7 +138,7 @@ mod tests {
 
         let mock_runner = MockCommandRunner::new("tailscale", &["debug", "prefs"], output);
 
-        let prefs = parse_tailscale_prefs(&mock_runner);
+        let prefs = parse_tailscale_prefs(&mock_runner).await;
         assert!(prefs.is_none());
     }
 }
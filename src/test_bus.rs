@@ -0,0 +1,78 @@
+//! Private D-Bus daemon for exercising the D-Bus backends against fake services.
+
+use crate::command::is_command_installed;
+use dbus::blocking::Connection;
+use dbus::Path;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Private dbus-daemon torn down when the test ends.
+pub struct TestBus {
+    daemon: Child,
+    pub address: String,
+    _dir: tempfile::TempDir,
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// Starts a bus, or returns `None` when dbus-daemon is not installed.
+pub fn start_test_bus() -> Option<TestBus> {
+    if !is_command_installed("dbus-daemon") {
+        return None;
+    }
+
+    let dir = tempfile::tempdir().ok()?;
+    let mut daemon = Command::new("dbus-daemon")
+        .arg("--session")
+        .arg("--nofork")
+        .arg("--print-address")
+        .arg(format!(
+            "--address=unix:path={}",
+            dir.path().join("bus").display()
+        ))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let mut address = String::new();
+    BufReader::new(daemon.stdout.take()?)
+        .read_line(&mut address)
+        .ok()?;
+
+    Some(TestBus {
+        daemon,
+        address: address.trim().to_string(),
+        _dir: dir,
+    })
+}
+
+pub fn path(p: &str) -> Path<'static> {
+    Path::new(p.to_string()).unwrap()
+}
+
+/// Opens connections until `is_available` accepts one, panicking after 5 seconds.
+pub fn wait_for_service<T>(
+    bus: &TestBus,
+    connect: impl Fn(Connection) -> T,
+    is_available: impl Fn(&T) -> bool,
+) -> T {
+    let started = Instant::now();
+    loop {
+        let client = connect(crate::nm_dbus::open_private_connection(&bus.address).unwrap());
+        if is_available(&client) {
+            return client;
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "fake service never started"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
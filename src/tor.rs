@@ -1,4 +1,4 @@
-use crate::command::{is_command_installed, CommandRunner};
+use crate::command::{is_command_installed, CommandRunner, CONNECTION_COMMAND_TIMEOUT};
 use crate::constants::{ICON_CHECK, ICON_CROSS};
 use crate::format_entry;
 use crate::port_utils::is_any_port_listening;
@@ -105,7 +105,7 @@ impl TorManager {
     }
 
     /// Start Tor daemon
-    pub fn start_tor(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
+    pub async fn start_tor(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
         if self.is_tor_running() {
             debug!("Tor is already running");
             return Ok(());
@@ -132,12 +132,15 @@ impl TorManager {
 
         debug!("Starting Tor: tor {}", tor_args.join(" "));
 
-        match command_runner.run_command("tor", &tor_args) {
+        match command_runner
+            .run_command_with_timeout("tor", &tor_args, CONNECTION_COMMAND_TIMEOUT)
+            .await
+        {
             Ok(output) => {
                 if output.status.success() {
                     debug!("Tor started successfully");
                     // Give Tor a moment to establish connections
-                    std::thread::sleep(std::time::Duration::from_secs(3));
+                    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
                     Ok(())
                 } else {
                     let error_msg = format!(
@@ -157,36 +160,45 @@ impl TorManager {
     }
 
     /// Stop Tor daemon
-    pub fn stop_tor(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
+    pub async fn stop_tor(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
         if !self.is_tor_running() {
             debug!("Tor is not running");
             return Ok(());
         }
 
         // Try graceful shutdown via control port first
-        if self.control_shutdown().is_err() {
+        if self.control_shutdown().await.is_err() {
             warn!("Graceful shutdown failed, attempting force kill");
 
             // Try multiple methods to kill Tor processes
             // Method 1: killall
-            let killall_result = command_runner.run_command("killall", &["tor"]);
+            let killall_result = command_runner.run_command_async("killall", &["tor"]).await;
             if let Err(e) = killall_result {
                 debug!("killall failed: {}", e);
 
                 // Method 2: pkill
-                let pkill_result = command_runner.run_command("pkill", &["-f", "^tor$"]);
+                let pkill_result = command_runner
+                    .run_command_async("pkill", &["-f", "^tor$"])
+                    .await;
                 if let Err(e2) = pkill_result {
                     debug!("pkill failed: {}", e2);
 
                     // Method 3: Find PIDs with pgrep and kill them
-                    if let Ok(pgrep_output) = command_runner.run_command("pgrep", &["-x", "tor"]) {
+                    if let Ok(pgrep_output) = command_runner
+                        .run_command_async("pgrep", &["-x", "tor"])
+                        .await
+                    {
                         let pids_str = String::from_utf8_lossy(&pgrep_output.stdout);
                         for pid in pids_str.lines() {
                             if !pid.is_empty() {
                                 debug!("Trying to kill Tor PID: {}", pid);
-                                let _ = command_runner.run_command("kill", &["-TERM", pid]);
-                                std::thread::sleep(std::time::Duration::from_millis(500));
-                                let _ = command_runner.run_command("kill", &["-KILL", pid]);
+                                let _ = command_runner
+                                    .run_command_async("kill", &["-TERM", pid])
+                                    .await;
+                                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                                let _ = command_runner
+                                    .run_command_async("kill", &["-KILL", pid])
+                                    .await;
                             }
                         }
                     }
@@ -208,7 +220,7 @@ impl TorManager {
         Ok(())
     }
 
-    fn control_shutdown(&self) -> Result<(), String> {
+    async fn control_shutdown(&self) -> Result<(), String> {
         // Send proper Tor control protocol commands
         // First authenticate (if no password set, authenticate with empty password)
         // Then send SIGNAL SHUTDOWN
@@ -218,9 +230,10 @@ impl TorManager {
         );
 
         debug!("Attempting graceful Tor shutdown via control port");
-        match std::process::Command::new("sh")
+        match tokio::process::Command::new("sh")
             .args(["-c", &shutdown_cmd])
             .output()
+            .await
         {
             Ok(output) => {
                 let output_str = String::from_utf8_lossy(&output.stdout);
@@ -230,7 +243,7 @@ impl TorManager {
                 if output_str.contains("250 OK") {
                     debug!("Tor graceful shutdown successful");
                     // Wait a bit for Tor to actually shut down
-                    std::thread::sleep(std::time::Duration::from_secs(2));
+                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
                     Ok(())
                 } else {
                     debug!(
@@ -507,11 +520,11 @@ impl TorManager {
     }
 
     /// Restart Tor daemon
-    pub fn restart_tor(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
+    pub async fn restart_tor(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
         debug!("Restarting Tor");
-        self.stop_tor(command_runner)?;
-        std::thread::sleep(std::time::Duration::from_secs(2));
-        self.start_tor(command_runner)
+        self.stop_tor(command_runner).await?;
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        self.start_tor(command_runner).await
     }
 }
 
@@ -580,7 +593,7 @@ impl TorsocksConfig {
     }
 
     /// Start application with torsocks
-    pub fn start(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
+    pub async fn start(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
         if self.is_running() {
             return Ok(());
         }
@@ -594,7 +607,10 @@ impl TorsocksConfig {
 
         debug!("Starting torsocks: {}", torsocks_args.join(" "));
 
-        match command_runner.run_command("sh", &["-c", &format!("{} &", torsocks_args.join(" "))]) {
+        match command_runner
+            .run_command_async("sh", &["-c", &format!("{} &", torsocks_args.join(" "))])
+            .await
+        {
             Ok(_) => {
                 debug!("Started {} with torsocks", self.name);
                 Ok(())
@@ -608,7 +624,7 @@ impl TorsocksConfig {
     }
 
     /// Stop application running with torsocks
-    pub fn stop(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
+    pub async fn stop(&self, command_runner: &dyn CommandRunner) -> Result<(), String> {
         if !self.is_running() {
             return Ok(());
         }
@@ -616,7 +632,7 @@ impl TorsocksConfig {
         // Kill processes matching our torsocks command
         let pkill_args = ["-f", &format!("torsocks {}", self.command)];
 
-        match command_runner.run_command("pkill", &pkill_args) {
+        match command_runner.run_command_async("pkill", &pkill_args).await {
             Ok(_) => {
                 debug!("Stopped {} with torsocks", self.name);
                 Ok(())
@@ -770,7 +786,7 @@ pub fn tor_action_to_string(action: &TorAction) -> String {
 }
 
 /// Handle Tor action
-pub fn handle_tor_action(
+pub async fn handle_tor_action(
     action: &TorAction,
     command_runner: &dyn CommandRunner,
 ) -> Result<String, String> {
@@ -781,16 +797,21 @@ pub fn handle_tor_action(
             debug!("Starting Tor daemon");
             tor_manager
                 .start_tor(command_runner)
+                .await
                 .map(|_| "".to_string())
         }
         TorAction::StopTor => {
             debug!("Stopping Tor daemon");
-            tor_manager.stop_tor(command_runner).map(|_| "".to_string())
+            tor_manager
+                .stop_tor(command_runner)
+                .await
+                .map(|_| "".to_string())
         }
         TorAction::RestartTor => {
             debug!("Restarting Tor daemon");
             tor_manager
                 .restart_tor(command_runner)
+                .await
                 .map(|_| "".to_string())
         }
         TorAction::RefreshCircuit => {
@@ -819,11 +840,11 @@ pub fn handle_tor_action(
                     "torsocks command not found. Please install torsocks package".to_string(),
                 );
             }
-            config.start(command_runner).map(|_| "".to_string())
+            config.start(command_runner).await.map(|_| "".to_string())
         }
         TorAction::StopTorsocks(config) => {
             debug!("Stopping {} with torsocks", config.name);
-            config.stop(command_runner).map(|_| "".to_string())
+            config.stop(command_runner).await.map(|_| "".to_string())
         }
         TorAction::DebugControlPort => {
             debug!("Debugging Tor control port");
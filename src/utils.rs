@@ -1,88 +1,23 @@
 use country_emoji::flag;
 use log::debug;
 
-use notify_rust::Notification;
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use crate::captive_portal::{handle_captive_portal, CaptivePortalConfig};
+use crate::command::RealCommandRunner;
+use crate::ethernet::SYS_CLASS_NET;
 use std::error::Error;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use tokio::time::{timeout, Duration};
-
-const DETECT_CAPTIVE_PORTAL_URL: &str = "http://detectportal.firefox.com/";
-const EXPECTED_RESPONSE: &str = "success";
-const TIMEOUT_DURATION: Duration = Duration::from_secs(5);
+use tokio::time::Duration;
 
 /// Returns the flag emoji for a given country.
 pub fn get_flag(name_or_code: &str) -> String {
     flag(name_or_code).unwrap_or_else(|| "❓".into())
 }
-/// Detects a captive portal by making an HTTP request to a known URL.
-/// If a captive portal is detected, it notifies the user and opens the portal in a web browser.
+/// Checks for a captive portal with the default settings and helps the user
+/// sign in, see [`crate::captive_portal::handle_captive_portal`].
 pub async fn check_captive_portal() -> Result<(), Box<dyn Error>> {
-    // Wait for the connection to stabilize before checking for captive portal
-    tokio::time::sleep(Duration::from_secs(2)).await;
-
-    // Create a retry policy with exponential backoff
-    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-
-    // Build a client with retry middleware
-    let client: ClientWithMiddleware = ClientBuilder::new(Client::new())
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-        .build();
-
-    // Make a request and handle retries automatically, with a timeout
-    let response_result = timeout(
-        TIMEOUT_DURATION,
-        client.get(DETECT_CAPTIVE_PORTAL_URL).send(),
-    )
-    .await;
-
-    // Handle connection errors gracefully
-    let response = match response_result {
-        Ok(Ok(resp)) => resp,
-        Ok(Err(_e)) => {
-            debug!("Captive portal check error: {}", _e);
-            // Return Ok instead of propagating the error
-            return Ok(());
-        }
-        Err(_e) => {
-            #[cfg(debug_assertions)]
-            eprintln!("Captive portal check timeout: {}", _e);
-            // Return Ok instead of propagating the error
-            return Ok(());
-        }
-    };
-
-    // Try to get response text, but handle errors gracefully
-    let response_text = match response.text().await {
-        Ok(text) => text,
-        Err(_e) => {
-            #[cfg(debug_assertions)]
-            eprintln!("Failed to read captive portal response: {}", _e);
-            return Ok(());
-        }
-    };
-
-    if response_text.trim() != EXPECTED_RESPONSE {
-        // Show notification
-        if let Err(_e) = Notification::new()
-            .summary("Captive Portal Detected")
-            .body("Opening captive portal in your default browser.")
-            .show()
-        {
-            #[cfg(debug_assertions)]
-            eprintln!("Failed to show notification: {}", _e);
-        }
-
-        // Open web browser
-        if let Err(_e) = webbrowser::open(DETECT_CAPTIVE_PORTAL_URL) {
-            #[cfg(debug_assertions)]
-            eprintln!("Failed to open browser: {}", _e);
-        }
-    }
-
+    handle_captive_portal(None, &CaptivePortalConfig::default(), &RealCommandRunner).await?;
     Ok(())
 }
 
@@ -129,6 +64,14 @@ pub fn prompt_for_text(description: &str) -> Result<String, Box<dyn std::error::
     Ok(value)
 }
 
+/// Expands a leading `~/` to the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
 pub fn prompt_for_ssid() -> Result<String, Box<dyn std::error::Error>> {
     prompt_for_text("Enter SSID")
 }
@@ -174,6 +117,113 @@ pub fn prompt_for_visible_text(description: &str) -> Result<String, Box<dyn std:
     Ok(value.trim().to_string())
 }
 
+/// Shows `items` in the menu program and returns the selected line, if any.
+pub fn select_from_menu(
+    dmenu_cmd: &str,
+    dmenu_args: &str,
+    items: &[String],
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut child = Command::new(dmenu_cmd)
+        .args(dmenu_args.split_whitespace())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    {
+        let stdin = child.stdin.as_mut().ok_or("Failed to open stdin")?;
+        for item in items {
+            writeln!(stdin, "{item}")?;
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    let selected = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    Ok((!selected.is_empty()).then_some(selected))
+}
+
+/// A clipboard program as `(copy, paste)` command lines.
+type ClipboardTool = (&'static [&'static str], &'static [&'static str]);
+
+/// Clipboard programs in order of preference.
+const CLIPBOARD_TOOLS: [ClipboardTool; 3] = [
+    (&["wl-copy"], &["wl-paste", "--no-newline"]),
+    (
+        &["xclip", "-selection", "clipboard"],
+        &["xclip", "-selection", "clipboard", "-o"],
+    ),
+    (
+        &["xsel", "--clipboard", "--input"],
+        &["xsel", "--clipboard", "--output"],
+    ),
+];
+
+/// Picks the clipboard program for the current session.
+fn clipboard_tool() -> Result<ClipboardTool, Box<dyn Error>> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    CLIPBOARD_TOOLS
+        .into_iter()
+        .filter(|(copy, _)| wayland || copy[0] != "wl-copy")
+        .find(|(copy, _)| crate::command::is_command_installed(copy[0]))
+        .ok_or_else(|| "No clipboard tool found (wl-copy, xclip or xsel)".into())
+}
+
+/// Copies `text` to the clipboard, passing it on stdin so it never shows up
+/// in the process list.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn Error>> {
+    let (copy, _) = clipboard_tool()?;
+    let mut child = Command::new(copy[0])
+        .args(&copy[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    {
+        let stdin = child.stdin.as_mut().ok_or("Failed to open stdin")?;
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed", copy[0]).into())
+    }
+}
+
+/// Clears the clipboard after `delay` if it still holds `text`.
+///
+/// A detached shell does the waiting so the menu can exit right away; it gets
+/// `text` on stdin so it never shows up in the process list.
+pub fn clear_clipboard_after(text: &str, delay: Duration) -> Result<(), Box<dyn Error>> {
+    let (copy, paste) = clipboard_tool()?;
+    let script = format!(
+        "IFS= read -r secret; sleep {}; [ \"$({})\" = \"$secret\" ] && printf '' | {}",
+        delay.as_secs(),
+        paste.join(" "),
+        copy.join(" ")
+    );
+    let mut child = Command::new("sh")
+        .args(["-c", &script])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let stdin = child.stdin.as_mut().ok_or("Failed to open stdin")?;
+    writeln!(stdin, "{text}")?;
+    Ok(())
+}
+
+/// Returns the current clipboard text.
+pub fn read_clipboard() -> Result<String, Box<dyn Error>> {
+    let (_, paste) = clipboard_tool()?;
+    let output = Command::new(paste[0]).args(&paste[1..]).output()?;
+    if !output.status.success() {
+        return Err(format!("{} failed", paste[0]).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -248,6 +298,30 @@ mod tests {
         assert_eq!(result, "▂▄__");
     }
 
+    #[test]
+    fn test_list_wifi_interfaces() {
+        let root = tempfile::tempdir().unwrap();
+        for (name, marker, state) in [
+            ("wlp2s0", "wireless", "down"),
+            ("wlx00c0ca", "phy80211", "up"),
+            ("enp0s31f6", "device", "up"),
+        ] {
+            let dir = root.path().join(name);
+            std::fs::create_dir_all(dir.join(marker)).unwrap();
+            std::fs::write(dir.join("operstate"), format!("{state}\n")).unwrap();
+        }
+
+        assert_eq!(
+            list_wifi_interfaces_in(root.path()),
+            vec!["wlp2s0".to_string(), "wlx00c0ca".to_string()]
+        );
+        assert_eq!(
+            preferred_wifi_interface_in(root.path()),
+            Some("wlx00c0ca".to_string())
+        );
+        assert_eq!(preferred_wifi_interface_in(&root.path().join("none")), None);
+    }
+
     #[tokio::test]
     async fn test_check_captive_portal_success_response() {
         // This test would require mocking the HTTP client
@@ -282,35 +356,65 @@ mod tests {
     }
 }
 
-/// Get WiFi interface name with fallback detection
+/// Get WiFi interface name, preferring the radio that is up
 pub fn get_wifi_interface(provided: Option<&str>) -> String {
     if let Some(interface) = provided {
         debug!("Using provided WiFi interface: {}", interface);
         return interface.to_string();
     }
 
-    // Fallback to common names
-    let fallback_interfaces = ["wlan0", "wlp3s0", "wlo1"];
-    for interface in &fallback_interfaces {
-        if std::path::Path::new(&format!("/sys/class/net/{}", interface)).exists() {
-            debug!("Found fallback WiFi interface: {}", interface);
-            return interface.to_string();
-        }
+    if let Some(interface) = preferred_wifi_interface_in(Path::new(SYS_CLASS_NET)) {
+        debug!("Found WiFi interface: {}", interface);
+        return interface;
     }
 
     debug!("No WiFi interface found, using default: wlan0");
     "wlan0".to_string()
 }
 
-/// Get Ethernet interface name with fallback detection
+/// Lists all wireless interfaces, e.g. the built-in card and a USB dongle.
+pub fn list_wifi_interfaces() -> Vec<String> {
+    list_wifi_interfaces_in(Path::new(SYS_CLASS_NET))
+}
+
+/// Lists the interfaces with a `wireless` or `phy80211` entry under a
+/// `/sys/class/net`-like directory.
+pub fn list_wifi_interfaces_in(root: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut interfaces: Vec<String> = entries
+        .flatten()
+        .filter(|entry| {
+            let dir = entry.path();
+            dir.join("wireless").exists() || dir.join("phy80211").exists()
+        })
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    interfaces.sort();
+    interfaces
+}
+
+/// Picks the first wireless interface that is up, then the first one at all.
+fn preferred_wifi_interface_in(root: &Path) -> Option<String> {
+    let interfaces = list_wifi_interfaces_in(root);
+    interfaces
+        .iter()
+        .find(|interface| {
+            std::fs::read_to_string(root.join(interface).join("operstate"))
+                .is_ok_and(|state| state.trim() == "up")
+        })
+        .or_else(|| interfaces.first())
+        .cloned()
+}
+
+/// Get Ethernet interface name, preferring one with a cable plugged in
 pub fn get_ethernet_interface() -> String {
-    // Fallback to common names
-    let fallback_interfaces = ["eth0", "enp0s3", "eno1", "ens3"];
-    for interface in &fallback_interfaces {
-        if std::path::Path::new(&format!("/sys/class/net/{}", interface)).exists() {
-            debug!("Found fallback Ethernet interface: {}", interface);
-            return interface.to_string();
-        }
+    let interfaces = crate::ethernet::list_ethernet_interfaces();
+    if let Some(interface) = crate::ethernet::preferred_interface(&interfaces) {
+        debug!("Found Ethernet interface: {}", interface.name);
+        return interface.name.clone();
     }
 
     debug!("No Ethernet interface found, using default: eth0");
@@ -0,0 +1,616 @@
+//! OpenVPN and OpenConnect profiles in NetworkManager.
+//!
+//! `.ovpn` files are imported with nmcli, OpenConnect endpoints are added as
+//! `vpn-type openconnect` profiles. Without a secret agent NetworkManager
+//! cannot ask for passwords or one-time codes, so they are prompted here and
+//! handed to `nmcli connection up` through a private `passwd-file`.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::constants::ICON_LOCK;
+use crate::networkmanager;
+use crate::utils::expand_home;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const OPENVPN_SERVICE: &str = "org.freedesktop.NetworkManager.openvpn";
+const OPENCONNECT_SERVICE: &str = "org.freedesktop.NetworkManager.openconnect";
+
+/// `NM_SETTING_SECRET_FLAG_NOT_SAVED`: asked for on every connection.
+const SECRET_FLAG_NOT_SAVED: u32 = 2;
+
+/// Protocols spoken by OpenConnect, as named in the NetworkManager plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenconnectProtocol {
+    AnyConnect,
+    GlobalProtect,
+    Pulse,
+    Fortinet,
+}
+
+impl OpenconnectProtocol {
+    pub const ALL: [OpenconnectProtocol; 4] = [
+        Self::AnyConnect,
+        Self::GlobalProtect,
+        Self::Pulse,
+        Self::Fortinet,
+    ];
+
+    /// Value of the plugin's `protocol` key and of `openconnect --protocol`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AnyConnect => "anyconnect",
+            Self::GlobalProtect => "gp",
+            Self::Pulse => "pulse",
+            Self::Fortinet => "fortinet",
+        }
+    }
+
+    pub fn to_display_string(&self) -> String {
+        let name = match self {
+            Self::AnyConnect => "Cisco AnyConnect / OpenConnect",
+            Self::GlobalProtect => "Palo Alto GlobalProtect",
+            Self::Pulse => "Pulse / Ivanti Connect Secure",
+            Self::Fortinet => "Fortinet SSL VPN",
+        };
+        format!("{ICON_LOCK} {name}")
+    }
+}
+
+/// Asks the user for a secret, given its description.
+pub type SecretPrompter = dyn Fn(&str) -> Result<String, Box<dyn Error>>;
+
+/// A secret the user is asked for before connecting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretPrompt {
+    /// Key under `vpn.secrets`
+    pub key: String,
+    pub description: String,
+}
+
+/// The VPN settings of a NetworkManager profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VpnProfile {
+    pub name: String,
+    pub service_type: String,
+    pub user_name: Option<String>,
+    pub data: HashMap<String, String>,
+}
+
+impl VpnProfile {
+    fn is_openconnect(&self) -> bool {
+        self.service_type == OPENCONNECT_SERVICE
+    }
+
+    fn secret_flags(&self, key: &str) -> u32 {
+        self.data
+            .get(&format!("{key}-flags"))
+            .and_then(|flags| flags.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Whether connecting always needs input, so a plain activation is bound
+    /// to fail without a secret agent.
+    pub fn needs_interactive_secrets(&self) -> bool {
+        self.is_openconnect()
+            || self.data.contains_key("static-challenge")
+            || self.secret_flags("password") & SECRET_FLAG_NOT_SAVED != 0
+    }
+
+    /// The secrets to ask for, in order.
+    pub fn secret_prompts(&self) -> Vec<SecretPrompt> {
+        let mut prompts = vec![SecretPrompt {
+            key: "password".to_string(),
+            description: match &self.user_name {
+                Some(user) => format!("{} password for {user}", self.name),
+                None => format!("{} password", self.name),
+            },
+        }];
+
+        if self.is_openconnect() {
+            prompts.push(SecretPrompt {
+                key: "otp".to_string(),
+                description: format!("{} one-time code (leave empty if none)", self.name),
+            });
+        } else if let Some(challenge) = self.data.get("static-challenge") {
+            // "<text>, <echo>" where echo tells whether the answer is visible
+            let text = challenge
+                .rsplit_once(',')
+                .map_or(challenge.as_str(), |(text, _)| text)
+                .trim();
+            prompts.push(SecretPrompt {
+                key: "challenge-response".to_string(),
+                description: format!("{}: {text}", self.name),
+            });
+        } else if self.service_type == OPENVPN_SERVICE
+            && self
+                .data
+                .get("connection-type")
+                .is_some_and(|kind| kind == "tls")
+        {
+            // Certificate-only profiles only need the key passphrase
+            prompts[0] = SecretPrompt {
+                key: "cert-pass".to_string(),
+                description: format!("{} private key passphrase", self.name),
+            };
+        }
+        prompts
+    }
+
+    /// Asks for the secrets and returns them as `vpn.secrets` entries.
+    ///
+    /// OpenConnect logs in first and passes the session cookie instead.
+    pub fn collect_secrets(
+        &self,
+        prompt: &SecretPrompter,
+    ) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let mut answers = Vec::new();
+        for secret in self.secret_prompts() {
+            answers.push((secret.key, prompt(&secret.description)?));
+        }
+
+        if self.is_openconnect() {
+            let answer = |key: &str| {
+                answers
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, value)| value.as_str())
+                    .unwrap_or_default()
+            };
+            return openconnect_authenticate(self, answer("password"), answer("otp"));
+        }
+        Ok(answers)
+    }
+}
+
+/// Parses `vpn.data` as printed by nmcli: `key = value, key = value`, with
+/// commas inside values escaped by a backslash.
+pub fn parse_vpn_data(value: &str) -> HashMap<String, String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&',') => {
+                current.push(',');
+                chars.next();
+            }
+            ',' => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+
+    items
+        .iter()
+        .filter_map(|item| item.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Parses `nmcli -t -f vpn.service-type,vpn.user-name,vpn.data connection show`.
+pub fn parse_vpn_profile(name: &str, output: &str) -> VpnProfile {
+    let mut profile = VpnProfile {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    for line in output.lines() {
+        match line.split_once(':') {
+            Some(("vpn.service-type", value)) => profile.service_type = value.to_string(),
+            Some(("vpn.user-name", value)) if !value.is_empty() && value != "--" => {
+                profile.user_name = Some(value.to_string())
+            }
+            Some(("vpn.data", value)) => profile.data = parse_vpn_data(value),
+            _ => {}
+        }
+    }
+    profile
+}
+
+/// Loads the VPN settings of the profile called `name`.
+pub fn load_vpn_profile(
+    name: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<VpnProfile, Box<dyn Error>> {
+    let output = command_runner.run_command(
+        "nmcli",
+        &[
+            "-t",
+            "-f",
+            "vpn.service-type,vpn.user-name,vpn.data",
+            "connection",
+            "show",
+            name,
+        ],
+    )?;
+    if !output.status.success() {
+        return Err(format!("VPN connection {name} not found").into());
+    }
+    Ok(parse_vpn_profile(
+        name,
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+/// Whether nmcli failed because secrets were missing.
+pub fn is_missing_secrets(stderr: &str) -> bool {
+    stderr.to_lowercase().contains("secrets")
+}
+
+/// Parses the `KEY='value'` lines of `openconnect --authenticate`.
+pub fn parse_openconnect_auth(output: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let values: HashMap<&str, &str> = output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches('\'')))
+        .collect();
+
+    let cookie = values.get("COOKIE").ok_or("OpenConnect login failed")?;
+    let gateway = values
+        .get("HOST")
+        .or_else(|| values.get("CONNECT_URL"))
+        .ok_or("OpenConnect did not report the gateway")?;
+
+    let mut secrets = vec![
+        ("cookie".to_string(), cookie.to_string()),
+        ("gateway".to_string(), gateway.to_string()),
+    ];
+    if let Some(fingerprint) = values.get("FINGERPRINT") {
+        secrets.push(("gwcert".to_string(), fingerprint.to_string()));
+    }
+    Ok(secrets)
+}
+
+/// Logs in with `openconnect --authenticate` and returns the session secrets.
+///
+/// The password and one-time code go through stdin, never the command line.
+fn openconnect_authenticate(
+    profile: &VpnProfile,
+    password: &str,
+    otp: &str,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    if !is_command_installed("openconnect") {
+        return Err("openconnect is not installed".into());
+    }
+    let gateway = profile
+        .data
+        .get("gateway")
+        .ok_or_else(|| format!("{} has no gateway", profile.name))?;
+    let protocol = profile
+        .data
+        .get("protocol")
+        .map_or("anyconnect", String::as_str);
+
+    let mut command = Command::new("openconnect");
+    command
+        .arg("--authenticate")
+        .arg(format!("--protocol={protocol}"))
+        .arg("--passwd-on-stdin");
+    if let Some(user) = &profile.user_name {
+        command.arg(format!("--user={user}"));
+    }
+    let mut child = command
+        .arg(gateway)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    {
+        let stdin = child.stdin.as_mut().ok_or("Failed to open stdin")?;
+        writeln!(stdin, "{password}")?;
+        if !otp.is_empty() {
+            writeln!(stdin, "{otp}")?;
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "OpenConnect login failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    parse_openconnect_auth(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Builds the content of an nmcli `passwd-file`.
+pub fn passwd_file_content(secrets: &[(String, String)]) -> String {
+    secrets
+        .iter()
+        .map(|(key, value)| format!("vpn.secrets.{key}:{value}\n"))
+        .collect()
+}
+
+/// Brings the profile up with the given secrets.
+pub async fn connect_with_secrets(
+    name: &str,
+    secrets: &[(String, String)],
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    networkmanager::connection_up_with_passwd_file(
+        name,
+        &passwd_file_content(secrets),
+        command_runner,
+    )
+    .await
+}
+
+/// Imports an OpenVPN `.ovpn` file as a NetworkManager connection.
+pub fn import_openvpn_config(
+    path: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let path = expand_home(path.trim());
+    if !matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("ovpn" | "conf")
+    ) {
+        return Err("OpenVPN configs must be .ovpn or .conf files".into());
+    }
+    if !path.is_file() {
+        return Err(format!("{} not found", path.display()).into());
+    }
+
+    let output = command_runner.run_command(
+        "nmcli",
+        &[
+            "connection",
+            "import",
+            "type",
+            "openvpn",
+            "file",
+            &path.to_string_lossy(),
+        ],
+    )?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    Ok(true)
+}
+
+/// Adds an OpenConnect profile for `gateway`.
+///
+/// The session cookie is never stored, so every connection logs in again.
+pub fn add_openconnect_profile(
+    name: &str,
+    protocol: OpenconnectProtocol,
+    gateway: &str,
+    user: Option<&str>,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if name.is_empty() || gateway.is_empty() {
+        return Err("A name and a gateway are required".into());
+    }
+
+    let data = format!(
+        "gateway={},protocol={},cookie-flags={SECRET_FLAG_NOT_SAVED}",
+        gateway.replace(',', "\\,"),
+        protocol.as_str()
+    );
+    let mut args = vec![
+        "connection",
+        "add",
+        "type",
+        "vpn",
+        "con-name",
+        name,
+        "vpn-type",
+        "openconnect",
+        "vpn.data",
+        &data,
+    ];
+    if let Some(user) = user.filter(|user| !user.is_empty()) {
+        args.extend(["vpn.user-name", user]);
+    }
+
+    let output = command_runner.run_command("nmcli", &args)?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct RecordingCommandRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl RecordingCommandRunner {
+        fn new() -> Self {
+            Self {
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            // The secrets file is gone once the call returns, so record it too
+            if let Some(position) = args.iter().position(|arg| *arg == "passwd-file") {
+                call.push(fs::read_to_string(args[position + 1]).unwrap());
+            }
+            self.calls.lock().unwrap().push(call);
+            Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            })
+        }
+    }
+
+    const OPENVPN_SHOW: &str = "vpn.service-type:org.freedesktop.NetworkManager.openvpn
+vpn.user-name:--
+vpn.data:connection-type = password, password-flags = 2, remote = vpn.example.com:1194, static-challenge = Enter TOTP\\, 1, username = alice
+";
+
+    #[test]
+    fn test_parse_vpn_profile() {
+        let profile = parse_vpn_profile("corp", OPENVPN_SHOW);
+        assert_eq!(profile.service_type, OPENVPN_SERVICE);
+        assert_eq!(profile.user_name, None);
+        assert_eq!(profile.data["remote"], "vpn.example.com:1194");
+        assert_eq!(profile.data["static-challenge"], "Enter TOTP, 1");
+        assert_eq!(profile.data["username"], "alice");
+        assert!(profile.needs_interactive_secrets());
+    }
+
+    #[test]
+    fn test_openvpn_secret_prompts() {
+        let profile = parse_vpn_profile("corp", OPENVPN_SHOW);
+        let keys: Vec<_> = profile
+            .secret_prompts()
+            .into_iter()
+            .map(|prompt| (prompt.key, prompt.description))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("password".to_string(), "corp password".to_string()),
+                (
+                    "challenge-response".to_string(),
+                    "corp: Enter TOTP".to_string()
+                ),
+            ]
+        );
+
+        let secrets = profile
+            .collect_secrets(&|description| {
+                Ok(if description.contains("TOTP") {
+                    "123456".to_string()
+                } else {
+                    "hunter2".to_string()
+                })
+            })
+            .unwrap();
+        assert_eq!(
+            passwd_file_content(&secrets),
+            "vpn.secrets.password:hunter2\nvpn.secrets.challenge-response:123456\n"
+        );
+
+        let saved = VpnProfile {
+            service_type: OPENVPN_SERVICE.to_string(),
+            data: parse_vpn_data("connection-type = password, password-flags = 1"),
+            ..Default::default()
+        };
+        assert!(!saved.needs_interactive_secrets());
+    }
+
+    #[test]
+    fn test_openconnect_secret_prompts() {
+        let profile = VpnProfile {
+            name: "office".to_string(),
+            service_type: OPENCONNECT_SERVICE.to_string(),
+            user_name: Some("bob".to_string()),
+            data: parse_vpn_data("gateway = vpn.example.com, protocol = gp"),
+        };
+        assert!(profile.needs_interactive_secrets());
+        let keys: Vec<_> = profile
+            .secret_prompts()
+            .into_iter()
+            .map(|prompt| prompt.key)
+            .collect();
+        assert_eq!(keys, vec!["password", "otp"]);
+        assert_eq!(
+            profile.secret_prompts()[0].description,
+            "office password for bob"
+        );
+    }
+
+    #[test]
+    fn test_parse_openconnect_auth() {
+        let output = "COOKIE='abc123@xyz'\nHOST='203.0.113.7'\nCONNECT_URL='https://vpn.example.com'\nFINGERPRINT='pin-sha256:AAAA'\n";
+        assert_eq!(
+            parse_openconnect_auth(output).unwrap(),
+            vec![
+                ("cookie".to_string(), "abc123@xyz".to_string()),
+                ("gateway".to_string(), "203.0.113.7".to_string()),
+                ("gwcert".to_string(), "pin-sha256:AAAA".to_string()),
+            ]
+        );
+        assert!(parse_openconnect_auth("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_secrets() {
+        let runner = RecordingCommandRunner::new();
+        let secrets = vec![("password".to_string(), "hunter2".to_string())];
+        assert!(connect_with_secrets("corp", &secrets, &runner)
+            .await
+            .unwrap());
+
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(
+            calls[0][..5],
+            ["nmcli", "connection", "up", "corp", "passwd-file"]
+        );
+        assert_eq!(calls[0][6], "vpn.secrets.password:hunter2\n");
+        assert!(!std::path::Path::new(&calls[0][5]).exists());
+        assert!(is_missing_secrets(
+            "Error: Connection activation failed: Secrets were required, but not provided"
+        ));
+    }
+
+    #[test]
+    fn test_import_and_add_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("corp.ovpn");
+        fs::write(&config, "client\n").unwrap();
+        let runner = RecordingCommandRunner::new();
+
+        assert!(import_openvpn_config(&config.to_string_lossy(), &runner).unwrap());
+        assert!(import_openvpn_config("/nonexistent/corp.ovpn", &runner).is_err());
+        assert!(
+            import_openvpn_config(&dir.path().join("x.txt").to_string_lossy(), &runner).is_err()
+        );
+        assert!(add_openconnect_profile(
+            "office",
+            OpenconnectProtocol::GlobalProtect,
+            "vpn.example.com",
+            Some("bob"),
+            &runner
+        )
+        .unwrap());
+
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[0][..5],
+            ["nmcli", "connection", "import", "type", "openvpn"]
+        );
+        assert_eq!(
+            calls[1][1..],
+            [
+                "connection",
+                "add",
+                "type",
+                "vpn",
+                "con-name",
+                "office",
+                "vpn-type",
+                "openconnect",
+                "vpn.data",
+                "gateway=vpn.example.com,protocol=gp,cookie-flags=2",
+                "vpn.user-name",
+                "bob"
+            ]
+        );
+    }
+}
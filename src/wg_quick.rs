@@ -0,0 +1,269 @@
+//! WireGuard tunnels configured for `wg-quick` in `/etc/wireguard`.
+//!
+//! These tunnels live outside NetworkManager. They are listed as ordinary VPN
+//! entries tagged with [`WG_QUICK_TAG`], which the VPN handler uses to route
+//! the connect or disconnect to `wg-quick up/down`.
+//!
+//! `/etc/wireguard` is usually readable by root only. Building the menu never
+//! asks for privileges, so the tunnel names are then taken from a list saved
+//! whenever a wg-quick tunnel is brought up or down.
+
+use crate::command::{CommandRunner, DEFAULT_COMMAND_TIMEOUT, PRIVILEGED_COMMAND_TIMEOUT};
+use crate::networkmanager::vpn_network_action;
+use crate::parse_vpn_action;
+use crate::privilege::wrap_privileged_command;
+use crate::wireguard::list_wireguard_links;
+use crate::VpnAction;
+use log::debug;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+pub const WG_QUICK_DIR: &str = "/etc/wireguard";
+
+const CACHE_FILE_NAME: &str = "wg-quick-tunnels";
+
+/// Marks a VPN entry as a wg-quick tunnel; it follows the name after a tab.
+pub const WG_QUICK_TAG: &str = "wg-quick";
+
+/// Longest Linux interface name, which wg-quick takes from the file name.
+const IFNAMSIZ: usize = 15;
+
+/// Tunnel names from a listing of the config directory.
+///
+/// Files wg-quick would refuse (the name must be a valid interface name) are
+/// skipped.
+pub fn parse_config_names<I, S>(entries: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut names: Vec<String> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let name = entry.as_ref().trim().strip_suffix(".conf")?;
+            let valid = !name.is_empty()
+                && name.len() <= IFNAMSIZ
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '=' | '+' | '.' | '-'));
+            valid.then(|| name.to_string())
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("network-dmenu").join(CACHE_FILE_NAME))
+}
+
+fn read_config_dir() -> Option<Vec<String>> {
+    let entries = fs::read_dir(WG_QUICK_DIR).ok()?;
+    Some(parse_config_names(entries.flatten().map(|entry| {
+        entry.file_name().to_string_lossy().to_string()
+    })))
+}
+
+/// Lists the wg-quick tunnels without asking for privileges, from the
+/// directory when readable and from the saved list otherwise.
+pub fn list_configs() -> Vec<String> {
+    read_config_dir().unwrap_or_else(|| {
+        cache_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|cached| parse_config_names(cached.lines()))
+            .unwrap_or_default()
+    })
+}
+
+/// Lists the wg-quick tunnels, with a passwordless `sudo -n` when the
+/// directory is not readable, and saves the names for [`list_configs`].
+pub async fn refresh_configs(command_runner: &dyn CommandRunner) -> Vec<String> {
+    if let Some(configs) = read_config_dir() {
+        return configs;
+    }
+
+    let output = command_runner
+        .run_command_with_timeout(
+            "sudo",
+            &["-n", "ls", "-1", WG_QUICK_DIR],
+            DEFAULT_COMMAND_TIMEOUT,
+        )
+        .await;
+    let configs = match output {
+        Ok(output) if output.status.success() => {
+            parse_config_names(String::from_utf8_lossy(&output.stdout).lines())
+        }
+        _ => return list_configs(),
+    };
+
+    if let Some(path) = cache_path() {
+        let cached: String = configs
+            .iter()
+            .map(|name| format!("{name}.conf\n"))
+            .collect();
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, cached));
+        if let Err(e) = saved {
+            debug!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+    configs
+}
+
+/// WireGuard interfaces that are up, from `wg show interfaces` or sysfs.
+pub async fn active_interfaces(command_runner: &dyn CommandRunner) -> Vec<String> {
+    match command_runner
+        .run_command_async("wg", &["show", "interfaces"])
+        .await
+    {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        _ => list_wireguard_links(),
+    }
+}
+
+/// Builds the VPN entries for the wg-quick tunnels.
+pub fn vpn_actions(configs: &[String], active: &[String]) -> Vec<VpnAction> {
+    configs
+        .iter()
+        .map(|name| vpn_network_action(active.contains(name), &format!("{name}\t{WG_QUICK_TAG}")))
+        .collect()
+}
+
+/// Lists the wg-quick tunnels as VPN entries.
+pub async fn get_wg_quick_vpn_networks(command_runner: &dyn CommandRunner) -> Vec<VpnAction> {
+    let configs = list_configs();
+    if configs.is_empty() {
+        return Vec::new();
+    }
+    vpn_actions(&configs, &active_interfaces(command_runner).await)
+}
+
+/// Whether a VPN entry is a wg-quick tunnel.
+pub fn is_wg_quick_action(action: &str) -> bool {
+    action
+        .split('\t')
+        .skip(1)
+        .any(|field| field == WG_QUICK_TAG)
+}
+
+/// Brings the tunnel selected in the menu up or down, then saves the tunnel
+/// list while the privileges are fresh.
+pub async fn set_tunnel(
+    action: &str,
+    up: bool,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let name = parse_vpn_action(action)?;
+    if parse_config_names([format!("{name}.conf")]).is_empty() {
+        return Err(format!("{name} is not a valid wg-quick tunnel name").into());
+    }
+
+    let command = format!("wg-quick {} {}", if up { "up" } else { "down" }, name);
+    let output = command_runner
+        .run_command_with_timeout(
+            "sh",
+            &["-c", &wrap_privileged_command(&command, false)],
+            PRIVILEGED_COMMAND_TIMEOUT,
+        )
+        .await?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    refresh_configs(command_runner).await;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    struct RecordingCommandRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call);
+            // Listing fails, so the tests never save a tunnel list
+            let code = if command == "sudo" { 1 << 8 } else { 0 };
+            Ok(Output {
+                status: ExitStatus::from_raw(code),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_parse_config_names() {
+        let names = parse_config_names([
+            "wg0.conf",
+            "office.conf",
+            "notes.txt",
+            "bad name.conf",
+            "a-very-long-tunnel-name.conf",
+            ".conf",
+            "keys",
+        ]);
+        assert_eq!(names, vec!["office", "wg0"]);
+    }
+
+    #[test]
+    fn test_vpn_actions() {
+        let configs = vec!["office".to_string(), "wg0".to_string()];
+        let actions = vpn_actions(&configs, &["wg0".to_string()]);
+
+        let VpnAction::Connect(office) = &actions[0] else {
+            panic!("expected a connect entry");
+        };
+        let VpnAction::Disconnect(wg0) = &actions[1] else {
+            panic!("expected a disconnect entry");
+        };
+        assert!(is_wg_quick_action(office));
+        assert!(is_wg_quick_action(wg0));
+        assert_eq!(parse_vpn_action(office).unwrap(), "office");
+        assert_eq!(parse_vpn_action(wg0).unwrap(), "wg0");
+        assert!(!is_wg_quick_action("📶 wg-quick"));
+    }
+
+    #[tokio::test]
+    async fn test_set_tunnel() {
+        let runner = RecordingCommandRunner {
+            calls: Mutex::new(Vec::new()),
+        };
+        let VpnAction::Connect(action) = &vpn_actions(&["office".to_string()], &[])[0] else {
+            panic!("expected a connect entry");
+        };
+
+        assert!(set_tunnel(action, true, &runner).await.unwrap());
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(calls[0][..2], ["sh", "-c"]);
+        assert!(calls[0][2].ends_with("wg-quick up office"));
+    }
+
+    #[tokio::test]
+    async fn test_set_tunnel_rejects_unsafe_names() {
+        let runner = RecordingCommandRunner {
+            calls: Mutex::new(Vec::new()),
+        };
+        assert!(set_tunnel("📶 wg0;reboot\twg-quick", false, &runner)
+            .await
+            .is_err());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+}
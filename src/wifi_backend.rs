@@ -0,0 +1,36 @@
+//! Picks the backend that manages Wi-Fi on an interface.
+//!
+//! NetworkManager and ConnMan manage Wi-Fi themselves. Otherwise iwd comes
+//! first, and bare wpa_supplicant is only used when iwd is not running, so
+//! listing, connecting and disconnecting all go through the same daemon.
+//! Each daemon is checked on the bus at runtime, as their command line tools
+//! are often installed while the daemon is stopped.
+
+use crate::command::CommandRunner;
+use crate::{connman, iwd_dbus, nm_dbus, wpa_supplicant};
+
+/// Daemon managing Wi-Fi.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WifiBackend {
+    NetworkManager,
+    Connman,
+    Iwd,
+    WpaSupplicant,
+}
+
+impl WifiBackend {
+    /// Finds the backend for `interface`, `None` when no Wi-Fi daemon is usable.
+    pub async fn detect(interface: &str, command_runner: &dyn CommandRunner) -> Option<Self> {
+        if nm_dbus::with_client(|_| Ok(())).await.is_some() {
+            Some(Self::NetworkManager)
+        } else if connman::with_client(|_| Ok(())).await.is_some() {
+            Some(Self::Connman)
+        } else if iwd_dbus::with_client(|_| Ok(())).await.is_some() {
+            Some(Self::Iwd)
+        } else if wpa_supplicant::is_wpa_supplicant_active(interface, command_runner).await {
+            Some(Self::WpaSupplicant)
+        } else {
+            None
+        }
+    }
+}
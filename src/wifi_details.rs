@@ -0,0 +1,572 @@
+//! Per-access-point Wi-Fi details.
+//!
+//! The main menu shows one entry per SSID. This module lists every BSSID behind
+//! an SSID with its band, channel, signal, rate and security, and lets the user
+//! connect to one specific access point or lock a profile to a band.
+
+use crate::command::{
+    is_command_installed, read_output_lines, CommandRunner, CONNECTION_COMMAND_TIMEOUT,
+};
+use crate::constants::{ACTION_TYPE_WIFI, ICON_CHECK, ICON_CROSS, ICON_LOCK, ICON_SIGNAL};
+use crate::format_entry;
+use crate::networkmanager::{is_known_network, split_nmcli_fields};
+use crate::nm_dbus;
+use crate::utils::prompt_for_password;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Radio band of an access point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WifiBand {
+    Band2_4GHz,
+    Band5GHz,
+    Band6GHz,
+}
+
+impl WifiBand {
+    /// Returns the band a frequency (in MHz) belongs to.
+    pub fn from_frequency(frequency: u32) -> Option<Self> {
+        match frequency {
+            2400..=2500 => Some(Self::Band2_4GHz),
+            5150..=5925 => Some(Self::Band5GHz),
+            5926..=7125 => Some(Self::Band6GHz),
+            _ => None,
+        }
+    }
+
+    /// Value of the NetworkManager `802-11-wireless.band` setting, if the band can be locked.
+    pub fn nm_band(&self) -> Option<&'static str> {
+        match self {
+            Self::Band2_4GHz => Some("bg"),
+            Self::Band5GHz => Some("a"),
+            // NetworkManager has no band value for 6 GHz
+            Self::Band6GHz => None,
+        }
+    }
+}
+
+impl fmt::Display for WifiBand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Band2_4GHz => write!(f, "2.4 GHz"),
+            Self::Band5GHz => write!(f, "5 GHz"),
+            Self::Band6GHz => write!(f, "6 GHz"),
+        }
+    }
+}
+
+/// Converts a frequency in MHz to its 802.11 channel number.
+pub fn channel_from_frequency(frequency: u32) -> Option<u32> {
+    match WifiBand::from_frequency(frequency)? {
+        WifiBand::Band2_4GHz if frequency == 2484 => Some(14),
+        WifiBand::Band2_4GHz => Some(frequency.checked_sub(2407)? / 5),
+        WifiBand::Band5GHz => Some(frequency.checked_sub(5000)? / 5),
+        WifiBand::Band6GHz => Some(frequency.checked_sub(5950)? / 5),
+    }
+}
+
+/// One access point (BSSID) of a network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BssInfo {
+    pub ssid: String,
+    pub bssid: String,
+    /// Frequency in MHz.
+    pub frequency: u32,
+    /// Signal strength in percent.
+    pub signal: u8,
+    /// Maximum bitrate in Mbit/s.
+    pub max_rate: u32,
+    pub security: String,
+    pub in_use: bool,
+}
+
+impl BssInfo {
+    pub fn band(&self) -> Option<WifiBand> {
+        WifiBand::from_frequency(self.frequency)
+    }
+
+    pub fn channel(&self) -> Option<u32> {
+        channel_from_frequency(self.frequency)
+    }
+}
+
+impl From<nm_dbus::AccessPoint> for BssInfo {
+    fn from(ap: nm_dbus::AccessPoint) -> Self {
+        Self {
+            ssid: ap.ssid,
+            bssid: ap.bssid,
+            frequency: ap.frequency,
+            signal: ap.strength,
+            // NetworkManager reports kbit/s
+            max_rate: ap.max_bitrate / 1000,
+            security: ap.security,
+            in_use: ap.in_use,
+        }
+    }
+}
+
+/// Actions available in the detail view of a network.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WifiDetailAction {
+    /// Connect to this access point only.
+    ConnectBssid(BssInfo),
+    /// Lock the network's profile to a band, `None` removes the lock.
+    LockBand(String, Option<WifiBand>),
+}
+
+impl WifiDetailAction {
+    pub fn to_display_string(&self) -> String {
+        match self {
+            Self::ConnectBssid(bss) => format_entry(
+                ACTION_TYPE_WIFI,
+                if bss.in_use { ICON_CHECK } else { ICON_SIGNAL },
+                &format_bss(bss),
+            ),
+            Self::LockBand(_, Some(band)) => {
+                format_entry(ACTION_TYPE_WIFI, ICON_LOCK, &format!("Lock to {band}"))
+            }
+            Self::LockBand(_, None) => {
+                format_entry(ACTION_TYPE_WIFI, ICON_CROSS, "Remove band lock")
+            }
+        }
+    }
+}
+
+/// Formats one access point line of the detail view.
+fn format_bss(bss: &BssInfo) -> String {
+    let band = bss
+        .band()
+        .map(|band| band.to_string())
+        .unwrap_or_else(|| format!("{} MHz", bss.frequency));
+    let channel = bss
+        .channel()
+        .map(|channel| channel.to_string())
+        .unwrap_or_else(|| "?".to_string());
+    let security = if bss.security.is_empty() {
+        "open"
+    } else {
+        bss.security.as_str()
+    };
+
+    format!(
+        "{}\t{:<7} ch {:<3}\t{:>3}%\t{:>4} Mbit/s\t{}",
+        bss.bssid, band, channel, bss.signal, bss.max_rate, security
+    )
+}
+
+/// Groups access points by SSID, strongest network first and strongest BSSID first.
+pub fn group_by_ssid(access_points: &[BssInfo]) -> Vec<(String, Vec<BssInfo>)> {
+    let mut groups: HashMap<&str, Vec<BssInfo>> = HashMap::new();
+    for ap in access_points.iter().filter(|ap| !ap.ssid.is_empty()) {
+        groups.entry(&ap.ssid).or_default().push(ap.clone());
+    }
+
+    let mut groups: Vec<(String, Vec<BssInfo>)> = groups
+        .into_iter()
+        .map(|(ssid, mut bsses)| {
+            bsses.sort_by(|a, b| b.in_use.cmp(&a.in_use).then(b.signal.cmp(&a.signal)));
+            (ssid.to_string(), bsses)
+        })
+        .collect();
+
+    groups.sort_by(|(a_ssid, a), (b_ssid, b)| {
+        b[0].in_use
+            .cmp(&a[0].in_use)
+            .then(b[0].signal.cmp(&a[0].signal))
+            .then(a_ssid.cmp(b_ssid))
+    });
+    groups
+}
+
+/// Formats the entry of a network in the list of networks with details.
+pub fn format_network_summary(ssid: &str, bsses: &[BssInfo]) -> String {
+    let mut bands: Vec<WifiBand> = bsses.iter().filter_map(BssInfo::band).collect();
+    bands.sort();
+    bands.dedup();
+    let bands = bands
+        .iter()
+        .map(|band| band.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let count = if bsses.len() == 1 {
+        "1 AP".to_string()
+    } else {
+        format!("{} APs", bsses.len())
+    };
+
+    format_entry(
+        ACTION_TYPE_WIFI,
+        if bsses.iter().any(|bss| bss.in_use) {
+            ICON_CHECK
+        } else {
+            ICON_SIGNAL
+        },
+        &format!("{:<25}\t{}\t{}", ssid, count, bands),
+    )
+}
+
+/// Builds the detail view of a network: one entry per BSSID, then band locks.
+pub fn get_detail_actions(ssid: &str, bsses: &[BssInfo]) -> Vec<WifiDetailAction> {
+    let mut actions: Vec<WifiDetailAction> = bsses
+        .iter()
+        .cloned()
+        .map(WifiDetailAction::ConnectBssid)
+        .collect();
+
+    let mut bands: Vec<WifiBand> = bsses
+        .iter()
+        .filter_map(BssInfo::band)
+        .filter(|band| band.nm_band().is_some())
+        .collect();
+    bands.sort();
+    bands.dedup();
+
+    for band in bands {
+        actions.push(WifiDetailAction::LockBand(ssid.to_string(), Some(band)));
+    }
+    actions.push(WifiDetailAction::LockBand(ssid.to_string(), None));
+
+    actions
+}
+
+/// Lists every access point in range, using D-Bus when available and nmcli otherwise.
+pub async fn get_access_points(
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<BssInfo>, Box<dyn Error>> {
+    let dbus_access_points = nm_dbus::with_client(|client| client.access_points()).await;
+    if let Some(Ok(access_points)) = dbus_access_points {
+        return Ok(access_points.into_iter().map(BssInfo::from).collect());
+    }
+
+    let output = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "--colors",
+                "no",
+                "-t",
+                "-f",
+                "IN-USE,BSSID,SSID,FREQ,RATE,SIGNAL,SECURITY",
+                "device",
+                "wifi",
+                "list",
+            ],
+        )
+        .await?;
+
+    if !output.status.success() {
+        return Err("Failed to list access points".into());
+    }
+
+    Ok(parse_nmcli_bss_lines(read_output_lines(&output)?))
+}
+
+/// Parses `nmcli -t -f IN-USE,BSSID,SSID,FREQ,RATE,SIGNAL,SECURITY device wifi list`.
+fn parse_nmcli_bss_lines(lines: Vec<String>) -> Vec<BssInfo> {
+    // "5180 MHz" and "540 Mbit/s"
+    let leading_number = |field: &str| {
+        field
+            .split_whitespace()
+            .next()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    };
+
+    lines
+        .iter()
+        .map(|line| split_nmcli_fields(line))
+        .filter(|fields| fields.len() == 7)
+        .map(|fields| BssInfo {
+            in_use: fields[0].trim() == "*",
+            bssid: fields[1].trim().to_string(),
+            ssid: fields[2].trim().to_string(),
+            frequency: leading_number(&fields[3]),
+            max_rate: leading_number(&fields[4]),
+            signal: fields[5].trim().parse().unwrap_or_default(),
+            security: fields[6].trim().to_string(),
+        })
+        .collect()
+}
+
+/// Handles an action picked in the detail view.
+pub async fn handle_wifi_detail_action(
+    action: &WifiDetailAction,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    match action {
+        WifiDetailAction::ConnectBssid(bss) => connect_to_bssid(bss, command_runner).await,
+        WifiDetailAction::LockBand(ssid, band) => lock_band(ssid, *band, command_runner).await,
+    }
+}
+
+async fn connect_to_bssid(
+    bss: &BssInfo,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let bssid = bss.bssid.clone();
+    let dbus_result = if !nm_dbus::supports_security(&bss.security) {
+        // nmcli builds the profiles the D-Bus settings builder cannot
+        None
+    } else {
+        nm_dbus::with_client(move |client| {
+            let ap = client
+                .access_points()?
+                .into_iter()
+                .find(|ap| ap.bssid.eq_ignore_ascii_case(&bssid))
+                .ok_or_else(|| format!("Access point {bssid} is no longer visible"))?;
+            client.connect_access_point(&ap)
+        })
+        .await
+    };
+    if let Some(result) = dbus_result {
+        return Ok(result?);
+    }
+
+    let status = if is_known_network(&bss.ssid, command_runner).await? {
+        command_runner
+            .run_command_with_timeout(
+                "nmcli",
+                &["connection", "up", &bss.ssid, "ap", &bss.bssid],
+                CONNECTION_COMMAND_TIMEOUT,
+            )
+            .await?
+            .status
+    } else if bss.security.is_empty() {
+        command_runner
+            .run_command_with_timeout(
+                "nmcli",
+                &["device", "wifi", "connect", &bss.bssid],
+                CONNECTION_COMMAND_TIMEOUT,
+            )
+            .await?
+            .status
+    } else {
+        let password = prompt_for_password(&bss.ssid)?;
+        command_runner
+            .run_command_with_timeout(
+                "nmcli",
+                &[
+                    "device", "wifi", "connect", &bss.bssid, "password", &password,
+                ],
+                CONNECTION_COMMAND_TIMEOUT,
+            )
+            .await?
+            .status
+    };
+
+    Ok(status.success())
+}
+
+async fn lock_band(
+    ssid: &str,
+    band: Option<WifiBand>,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if !is_command_installed("nmcli") {
+        return Err("Locking a band requires nmcli".into());
+    }
+
+    let nm_band = match band {
+        Some(band) => band
+            .nm_band()
+            .ok_or_else(|| format!("NetworkManager cannot lock to {band}"))?,
+        None => "",
+    };
+
+    // The profile name may differ from the SSID
+    let profile = nm_dbus::with_client(|client| client.connections())
+        .await
+        .and_then(Result::ok)
+        .and_then(|profiles| {
+            profiles
+                .into_iter()
+                .find(|profile| profile.ssid.as_deref() == Some(ssid))
+        })
+        .map(|profile| profile.id)
+        .unwrap_or_else(|| ssid.to_string());
+
+    let modified = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "connection",
+                "modify",
+                &profile,
+                "802-11-wireless.band",
+                nm_band,
+            ],
+        )
+        .await?;
+    if !modified.status.success() {
+        return Ok(false);
+    }
+
+    // Reapply so the lock takes effect right away
+    let status = command_runner
+        .run_command_with_timeout(
+            "nmcli",
+            &["connection", "up", &profile],
+            CONNECTION_COMMAND_TIMEOUT,
+        )
+        .await?
+        .status;
+    Ok(status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    fn bss(ssid: &str, bssid: &str, frequency: u32, signal: u8, in_use: bool) -> BssInfo {
+        BssInfo {
+            ssid: ssid.to_string(),
+            bssid: bssid.to_string(),
+            frequency,
+            signal,
+            max_rate: 540,
+            security: "WPA2".to_string(),
+            in_use,
+        }
+    }
+
+    struct RecordingCommandRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+        stdout: String,
+    }
+
+    impl RecordingCommandRunner {
+        fn new(stdout: &str) -> Self {
+            Self {
+                calls: Mutex::new(Vec::new()),
+                stdout: stdout.to_string(),
+            }
+        }
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call);
+            Ok(Output {
+                status: ExitStatus::from_raw(0),
+                stdout: self.stdout.as_bytes().to_vec(),
+                stderr: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn test_band_and_channel_from_frequency() {
+        assert_eq!(WifiBand::from_frequency(2412), Some(WifiBand::Band2_4GHz));
+        assert_eq!(channel_from_frequency(2412), Some(1));
+        assert_eq!(channel_from_frequency(2484), Some(14));
+        assert_eq!(WifiBand::from_frequency(5180), Some(WifiBand::Band5GHz));
+        assert_eq!(channel_from_frequency(5180), Some(36));
+        assert_eq!(WifiBand::from_frequency(5955), Some(WifiBand::Band6GHz));
+        assert_eq!(channel_from_frequency(5955), Some(1));
+        assert_eq!(WifiBand::from_frequency(900), None);
+        assert_eq!(channel_from_frequency(900), None);
+    }
+
+    #[test]
+    fn test_parse_nmcli_bss_lines() {
+        let lines = vec![
+            r"*:AA\:BB\:CC\:DD\:EE\:01:Office:5180 MHz:540 Mbit/s:82:WPA2".to_string(),
+            r" :AA\:BB\:CC\:DD\:EE\:02:Office:2437 MHz:130 Mbit/s:91:WPA2".to_string(),
+            r" :AA\:BB\:CC\:DD\:EE\:03:Guest\:Lobby:2412 MHz:54 Mbit/s:40:".to_string(),
+            "garbage".to_string(),
+        ];
+
+        let bsses = parse_nmcli_bss_lines(lines);
+        assert_eq!(bsses.len(), 3);
+        assert!(bsses[0].in_use);
+        assert_eq!(bsses[0].bssid, "AA:BB:CC:DD:EE:01");
+        assert_eq!(bsses[0].frequency, 5180);
+        assert_eq!(bsses[0].max_rate, 540);
+        assert_eq!(bsses[0].signal, 82);
+        assert_eq!(bsses[2].ssid, "Guest:Lobby");
+        assert!(bsses[2].security.is_empty());
+    }
+
+    #[test]
+    fn test_group_by_ssid_orders_in_use_then_signal() {
+        let aps = vec![
+            bss("Cafe", "01", 2412, 95, false),
+            bss("Office", "02", 2437, 91, false),
+            bss("Office", "03", 5180, 60, true),
+            bss("", "04", 5180, 99, false),
+        ];
+
+        let groups = group_by_ssid(&aps);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "Office");
+        assert_eq!(groups[0].1[0].bssid, "03");
+        assert_eq!(groups[0].1[1].bssid, "02");
+        assert_eq!(groups[1].0, "Cafe");
+    }
+
+    #[test]
+    fn test_detail_actions_and_display() {
+        let bsses = vec![
+            bss("Office", "AA:BB:CC:DD:EE:01", 5180, 82, true),
+            bss("Office", "AA:BB:CC:DD:EE:02", 2437, 91, false),
+            bss("Office", "AA:BB:CC:DD:EE:03", 5955, 70, false),
+        ];
+
+        let actions = get_detail_actions("Office", &bsses);
+        assert_eq!(actions.len(), 6);
+        assert_eq!(
+            actions[3],
+            WifiDetailAction::LockBand("Office".to_string(), Some(WifiBand::Band2_4GHz))
+        );
+        assert_eq!(
+            actions[4],
+            WifiDetailAction::LockBand("Office".to_string(), Some(WifiBand::Band5GHz))
+        );
+        assert_eq!(
+            actions[5],
+            WifiDetailAction::LockBand("Office".to_string(), None)
+        );
+
+        let display = actions[0].to_display_string();
+        assert!(display.starts_with("wifi      - ✅ AA:BB:CC:DD:EE:01"));
+        assert!(display.contains("5 GHz"));
+        assert!(display.contains("ch 36"));
+        assert!(display.contains("82%"));
+        assert!(display.contains("540 Mbit/s"));
+        assert!(actions[4].to_display_string().ends_with("Lock to 5 GHz"));
+
+        let summary = format_network_summary("Office", &bsses);
+        assert!(summary.contains("3 APs"));
+        assert!(summary.contains("2.4 GHz, 5 GHz, 6 GHz"));
+    }
+
+    #[tokio::test]
+    async fn test_lock_band_runs_nmcli_modify() {
+        if !is_command_installed("nmcli") {
+            return;
+        }
+
+        let runner = RecordingCommandRunner::new("");
+        let result = lock_band("Office", Some(WifiBand::Band5GHz), &runner).await;
+        assert!(result.unwrap());
+
+        let calls = runner.calls.lock().unwrap();
+        assert!(calls[0].ends_with(&["802-11-wireless.band".to_string(), "a".to_string()]));
+        assert_eq!(calls[1][1..3], ["connection".to_string(), "up".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_lock_band_rejects_6ghz() {
+        let runner = RecordingCommandRunner::new("");
+        assert!(lock_band("Office", Some(WifiBand::Band6GHz), &runner)
+            .await
+            .is_err());
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+}
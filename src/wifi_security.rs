@@ -0,0 +1,358 @@
+//! Warnings for weakly secured Wi-Fi networks.
+//!
+//! Open, WEP and WPA1-only networks are flagged from the security field of the
+//! scan. A known network advertising weaker security than its saved profile
+//! is flagged too, as it may be an access point impersonating it.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::constants::ICON_WARNING;
+use crate::networkmanager::split_nmcli_fields;
+use crate::nm_dbus;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// What to do around weakly secured networks, the `[insecure_wifi]` section
+/// of the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InsecureWifiConfig {
+    /// Ask before connecting
+    pub confirm: bool,
+    /// NetworkManager VPN connection to bring up once connected
+    pub vpn: Option<String>,
+    /// Tailscale exit node to switch to once connected
+    pub exit_node: Option<String>,
+}
+
+impl Default for InsecureWifiConfig {
+    fn default() -> Self {
+        Self {
+            confirm: true,
+            vpn: None,
+            exit_node: None,
+        }
+    }
+}
+
+/// Why a network is considered weakly secured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityWarning {
+    Open,
+    Wep,
+    Wpa1,
+    /// Advertises weaker security than the saved profile
+    Downgrade {
+        saved: String,
+    },
+}
+
+impl SecurityWarning {
+    /// Short badge appended to the network entry.
+    pub fn badge(&self) -> String {
+        let text = match self {
+            Self::Open => "open",
+            Self::Wep => "WEP",
+            Self::Wpa1 => "WPA1",
+            Self::Downgrade { .. } => "weaker than saved",
+        };
+        format!("{ICON_WARNING} {text}")
+    }
+}
+
+impl fmt::Display for SecurityWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open => write!(f, "traffic is not encrypted"),
+            Self::Wep => write!(f, "WEP encryption is broken"),
+            Self::Wpa1 => write!(f, "WPA1/TKIP encryption is outdated"),
+            Self::Downgrade { saved } => write!(
+                f,
+                "saved as {saved} but now advertises weaker security, possibly an impostor"
+            ),
+        }
+    }
+}
+
+/// Ranks the security advertised in a scan, `None` when it is unknown.
+///
+/// Handles NetworkManager (`WPA1 WPA2`, `WPA3`, `OWE`, `--`) and iwd (`PSK`,
+/// `OPEN`, `8021X`) values; iwd's `PSK` covers WPA1 through WPA3.
+pub fn advertised_rank(security: &str) -> Option<u8> {
+    let security = security.trim().to_uppercase();
+    let rank = if security.is_empty() || security == "--" || security == "OPEN" {
+        0
+    } else if security == "UNKNOWN" {
+        return None;
+    } else if security.contains("WPA3") || security.contains("SAE") {
+        4
+    } else if security.contains("802.1X") || security.contains("8021X") || security.contains("WPA2")
+    {
+        3
+    } else if security.contains("WPA") || security.contains("PSK") {
+        2
+    } else if security.contains("WEP") || security.contains("OWE") {
+        1
+    } else {
+        return None;
+    };
+    Some(rank)
+}
+
+/// Ranks a saved profile's security, named after NetworkManager's key-mgmt.
+fn saved_rank(saved: &str) -> u8 {
+    match saved {
+        "sae" => 4,
+        "wpa-eap" => 3,
+        "wpa-psk" => 2,
+        "wep" | "owe" => 1,
+        _ => 0,
+    }
+}
+
+/// Checks a scanned network against its saved security, if any.
+pub fn check_security(security: &str, saved: Option<&str>) -> Option<SecurityWarning> {
+    let rank = advertised_rank(security)?;
+    if let Some(saved) = saved.filter(|saved| rank < saved_rank(saved)) {
+        return Some(SecurityWarning::Downgrade {
+            saved: saved.to_string(),
+        });
+    }
+
+    let security = security.trim().to_uppercase();
+    match rank {
+        0 => Some(SecurityWarning::Open),
+        1 if security.contains("WEP") => Some(SecurityWarning::Wep),
+        2 if security.contains("WPA1") => Some(SecurityWarning::Wpa1),
+        _ => None,
+    }
+}
+
+/// Maps a NetworkManager `wifi-sec.key-mgmt` value to a saved security name.
+fn nm_saved_security(key_mgmt: &str) -> String {
+    match key_mgmt.trim() {
+        "" => "open",
+        "none" | "ieee8021x" => "wep",
+        key_mgmt if key_mgmt.starts_with("wpa-eap") => "wpa-eap",
+        key_mgmt => key_mgmt,
+    }
+    .to_string()
+}
+
+/// Parses `iwctl known-networks list` into name and saved security.
+fn parse_iwd_known_networks(output: &str) -> HashMap<String, String> {
+    let ansi = Regex::new(r"\x1B\[[0-?]*[ -/]*[@-~]").expect("valid regex");
+    let re = Regex::new(r"^\s*(.+?)\s{2,}(psk|open|8021x)\b").expect("valid regex");
+    ansi.replace_all(output, "")
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .map(|caps| {
+            let saved = match &caps[2] {
+                "psk" => "wpa-psk",
+                "8021x" => "wpa-eap",
+                _ => "open",
+            };
+            (caps[1].to_string(), saved.to_string())
+        })
+        .collect()
+}
+
+/// Parses `nmcli -t -f 802-11-wireless.ssid,802-11-wireless-security.key-mgmt
+/// connection show <uuid>...` into SSID and saved security.
+///
+/// Each profile starts with its SSID line; open profiles have no key-mgmt line.
+fn parse_nm_saved_securities(output: &str) -> Vec<(String, String)> {
+    let mut securities: Vec<(String, String)> = Vec::new();
+    for fields in output.lines().map(split_nmcli_fields) {
+        match fields.as_slice() {
+            [field, ssid] if field == "802-11-wireless.ssid" => {
+                securities.push((ssid.clone(), nm_saved_security("")));
+            }
+            [field, key_mgmt] if field == "802-11-wireless-security.key-mgmt" => {
+                if let Some((_, saved)) = securities.last_mut() {
+                    *saved = nm_saved_security(key_mgmt);
+                }
+            }
+            _ => {}
+        }
+    }
+    securities
+}
+
+/// Reads the SSID and saved security of every NetworkManager Wi-Fi profile.
+async fn nm_saved_securities(command_runner: &dyn CommandRunner) -> Vec<(String, String)> {
+    if let Some(Ok(profiles)) = nm_dbus::with_client(|client| client.connections()).await {
+        return profiles
+            .into_iter()
+            .filter_map(|profile| {
+                let saved = nm_saved_security(profile.key_mgmt.as_deref().unwrap_or_default());
+                profile.ssid.map(|ssid| (ssid, saved))
+            })
+            .collect();
+    }
+
+    let Ok(output) = command_runner
+        .run_command_async("nmcli", &["-t", "-f", "UUID,TYPE", "connection", "show"])
+        .await
+    else {
+        return Vec::new();
+    };
+    let uuids: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(split_nmcli_fields)
+        .filter_map(|fields| match fields.as_slice() {
+            [uuid, kind] if kind == "802-11-wireless" => Some(uuid.clone()),
+            _ => None,
+        })
+        .collect();
+    if uuids.is_empty() {
+        return Vec::new();
+    }
+
+    let mut args = vec![
+        "-t",
+        "-f",
+        "802-11-wireless.ssid,802-11-wireless-security.key-mgmt",
+        "connection",
+        "show",
+    ];
+    args.extend(uuids.iter().map(String::as_str));
+    command_runner
+        .run_command_async("nmcli", &args)
+        .await
+        .map(|output| parse_nm_saved_securities(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Looks up the saved security of the visible networks that have a profile,
+/// keyed by SSID.
+pub async fn saved_securities(
+    visible: &[&str],
+    command_runner: &dyn CommandRunner,
+) -> HashMap<String, String> {
+    let known: Vec<(String, String)> = if is_command_installed("nmcli") {
+        nm_saved_securities(command_runner).await
+    } else if is_command_installed("iwctl") {
+        command_runner
+            .run_command_async("iwctl", &["known-networks", "list"])
+            .await
+            .map(|output| parse_iwd_known_networks(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default()
+            .into_iter()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut saved = HashMap::new();
+    for (ssid, security) in known {
+        if visible.contains(&ssid.as_str()) {
+            saved.entry(ssid).or_insert(security);
+        }
+    }
+    saved
+}
+
+/// Checks one network, looking up its saved profile.
+pub async fn check_network(
+    ssid: &str,
+    security: &str,
+    command_runner: &dyn CommandRunner,
+) -> Option<SecurityWarning> {
+    let saved = saved_securities(&[ssid], command_runner).await;
+    check_security(security, saved.get(ssid).map(String::as_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advertised_rank() {
+        assert_eq!(advertised_rank(""), Some(0));
+        assert_eq!(advertised_rank("--"), Some(0));
+        assert_eq!(advertised_rank("OPEN"), Some(0));
+        assert_eq!(advertised_rank("WEP"), Some(1));
+        assert_eq!(advertised_rank("OWE"), Some(1));
+        assert_eq!(advertised_rank("WPA1"), Some(2));
+        assert_eq!(advertised_rank("PSK"), Some(2));
+        assert_eq!(advertised_rank("WPA1 WPA2"), Some(3));
+        assert_eq!(advertised_rank("WPA2 802.1X"), Some(3));
+        assert_eq!(advertised_rank("WPA2 WPA3"), Some(4));
+        assert_eq!(advertised_rank("UNKNOWN"), None);
+    }
+
+    #[test]
+    fn test_check_security_weak_networks() {
+        assert_eq!(check_security("", None), Some(SecurityWarning::Open));
+        assert_eq!(check_security("WEP", None), Some(SecurityWarning::Wep));
+        assert_eq!(check_security("WPA1", None), Some(SecurityWarning::Wpa1));
+        assert_eq!(check_security("OWE", None), None);
+        assert_eq!(check_security("WPA1 WPA2", None), None);
+        assert_eq!(check_security("UNKNOWN", None), None);
+    }
+
+    #[test]
+    fn test_check_security_downgrade() {
+        assert_eq!(
+            check_security("WPA2", Some("sae")),
+            Some(SecurityWarning::Downgrade {
+                saved: "sae".to_string()
+            })
+        );
+        assert_eq!(
+            check_security("", Some("wpa-psk")),
+            Some(SecurityWarning::Downgrade {
+                saved: "wpa-psk".to_string()
+            })
+        );
+        assert_eq!(check_security("WPA2 WPA3", Some("sae")), None);
+        assert_eq!(check_security("PSK", Some("wpa-psk")), None);
+        assert_eq!(
+            check_security("", Some("open")),
+            Some(SecurityWarning::Open)
+        );
+    }
+
+    #[test]
+    fn test_nm_saved_security() {
+        assert_eq!(nm_saved_security(""), "open");
+        assert_eq!(nm_saved_security("none"), "wep");
+        assert_eq!(nm_saved_security("wpa-eap-suite-b-192"), "wpa-eap");
+        assert_eq!(nm_saved_security("sae\n"), "sae");
+    }
+
+    #[test]
+    fn test_parse_nm_saved_securities() {
+        let output = "802-11-wireless.ssid:Home Net\n\
+            802-11-wireless-security.key-mgmt:sae\n\
+            802-11-wireless.ssid:Phone\\: hotspot\n\
+            802-11-wireless.ssid:eduroam\n\
+            802-11-wireless-security.key-mgmt:wpa-eap\n";
+        assert_eq!(
+            parse_nm_saved_securities(output),
+            vec![
+                ("Home Net".to_string(), "sae".to_string()),
+                ("Phone: hotspot".to_string(), "open".to_string()),
+                ("eduroam".to_string(), "wpa-eap".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_iwd_known_networks() {
+        let output = "                             Known Networks\n\
+            --------------------------------------------------------------------------------\n\
+              Name                              Security     Hidden  Last connected\n\
+            --------------------------------------------------------------------------------\n\
+              \x1b[0mHome Net                          psk                  Oct 10, 10:00 AM\n\
+              Cafe                              open                 Oct  9,  9:00 AM\n\
+              eduroam                           8021x                Oct  8,  8:00 AM\n";
+        let known = parse_iwd_known_networks(output);
+        assert_eq!(known.get("Home Net").map(String::as_str), Some("wpa-psk"));
+        assert_eq!(known.get("Cafe").map(String::as_str), Some("open"));
+        assert_eq!(known.get("eduroam").map(String::as_str), Some("wpa-eap"));
+        assert_eq!(known.len(), 3);
+    }
+}
@@ -0,0 +1,896 @@
+//! Sharing and importing Wi-Fi credentials as `WIFI:` QR payloads.
+//!
+//! The payload format is the one phone cameras understand:
+//! `WIFI:T:WPA;S:<ssid>;P:<password>;H:true;;`, with `\`, `;`, `,`, `:` and `"`
+//! backslash-escaped. Credentials of the active network are read from
+//! NetworkManager or iwd and rendered with `qrencode`, or copied as text.
+//! Scanned credentials are joined with whichever Wi-Fi backend is running.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::constants::{ICON_KEY, ICON_LIST, ICON_QR};
+use crate::networkmanager::{self, split_nmcli_fields};
+use crate::privilege::{wrap_privileged_command, write_private_file};
+use crate::saved_networks::{SavedBackend, SavedConnection};
+use crate::utils::{clear_clipboard_after, copy_to_clipboard};
+use crate::{connman, iwd, nm_dbus, wpa_supplicant, WifiBackend};
+use regex::Regex;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const PAYLOAD_PREFIX: &str = "WIFI:";
+
+/// Authentication type of a `WIFI:` payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WifiQrSecurity {
+    Wpa,
+    Sae,
+    Wep,
+    Open,
+}
+
+impl WifiQrSecurity {
+    /// Value of the `T:` field.
+    pub fn payload_type(&self) -> &'static str {
+        match self {
+            Self::Wpa => "WPA",
+            Self::Sae => "SAE",
+            Self::Wep => "WEP",
+            Self::Open => "nopass",
+        }
+    }
+
+    /// Security string in the form the Wi-Fi menu entries use.
+    pub fn menu_security(&self) -> &'static str {
+        match self {
+            Self::Wpa => "WPA2",
+            Self::Sae => "WPA3",
+            Self::Wep => "WEP",
+            Self::Open => "",
+        }
+    }
+
+    /// ConnMan service security.
+    fn connman_security(&self) -> &'static str {
+        match self {
+            Self::Wpa | Self::Sae => "psk",
+            Self::Wep => "wep",
+            Self::Open => "none",
+        }
+    }
+
+    fn from_payload_type(value: &str) -> Result<Self, Box<dyn Error>> {
+        match value.to_uppercase().as_str() {
+            "WPA" | "WPA2" => Ok(Self::Wpa),
+            "SAE" | "WPA3" => Ok(Self::Sae),
+            "WEP" => Ok(Self::Wep),
+            "" | "NOPASS" => Ok(Self::Open),
+            other => Err(format!("Unsupported WIFI: security type {other}").into()),
+        }
+    }
+
+    /// Maps a NetworkManager `802-11-wireless-security.key-mgmt` value.
+    fn from_key_mgmt(key_mgmt: &str) -> Result<Self, Box<dyn Error>> {
+        match key_mgmt {
+            "wpa-psk" => Ok(Self::Wpa),
+            "sae" => Ok(Self::Sae),
+            "none" => Ok(Self::Wep),
+            "" | "owe" => Ok(Self::Open),
+            // Dynamic WEP gets its keys from 802.1X
+            "ieee8021x" | "wpa-eap" | "wpa-eap-suite-b-192" => {
+                Err("Enterprise networks cannot be shared as a QR code".into())
+            }
+            other => Err(format!("Unsupported key management {other}").into()),
+        }
+    }
+}
+
+/// Credentials of one Wi-Fi network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WifiCredentials {
+    pub ssid: String,
+    pub security: WifiQrSecurity,
+    pub password: Option<String>,
+    pub hidden: bool,
+}
+
+impl WifiCredentials {
+    /// Builds the `WIFI:` payload.
+    pub fn to_qr_payload(&self) -> String {
+        let mut payload = format!(
+            "{PAYLOAD_PREFIX}T:{};S:{};",
+            self.security.payload_type(),
+            escape_field(&self.ssid)
+        );
+        if let Some(password) = self.password.as_deref() {
+            if self.security != WifiQrSecurity::Open {
+                payload.push_str(&format!("P:{};", escape_field(password)));
+            }
+        }
+        if self.hidden {
+            payload.push_str("H:true;");
+        }
+        payload.push(';');
+        payload
+    }
+
+    /// Parses a `WIFI:` payload.
+    pub fn from_qr_payload(payload: &str) -> Result<Self, Box<dyn Error>> {
+        let body = payload
+            .trim()
+            .strip_prefix(PAYLOAD_PREFIX)
+            .ok_or("Not a WIFI: code")?;
+
+        let mut ssid = None;
+        let mut security = None;
+        let mut password = None;
+        let mut hidden = false;
+
+        for field in split_unescaped(body, ';') {
+            let Some((key, value)) = field.split_once(':') else {
+                continue;
+            };
+            let value = unescape_field(value);
+            match key {
+                "S" => ssid = Some(value),
+                "T" => security = Some(WifiQrSecurity::from_payload_type(&value)?),
+                "P" => password = Some(value),
+                "H" => hidden = value.eq_ignore_ascii_case("true"),
+                _ => {}
+            }
+        }
+
+        let ssid = ssid
+            .filter(|ssid| !ssid.is_empty())
+            .ok_or("WIFI: code has no SSID")?;
+        let password = password.filter(|password| !password.is_empty());
+        let security = security.unwrap_or(if password.is_some() {
+            WifiQrSecurity::Wpa
+        } else {
+            WifiQrSecurity::Open
+        });
+
+        Ok(Self {
+            ssid,
+            security,
+            password: password.filter(|_| security != WifiQrSecurity::Open),
+            hidden,
+        })
+    }
+}
+
+/// Whether `text` looks like a `WIFI:` payload.
+pub fn is_qr_payload(text: &str) -> bool {
+    text.trim().starts_with(PAYLOAD_PREFIX)
+}
+
+fn escape_field(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn unescape_field(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                unescaped.push(next);
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+/// Splits on `separator`, keeping escaped separators (and escapes) in place.
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            current.push(c);
+            if let Some(next) = chars.next() {
+                current.push(next);
+            }
+        } else if c == separator {
+            fields.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        fields.push(current);
+    }
+    fields
+}
+
+/// Where a shared network should go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareTarget {
+    Terminal,
+    Png,
+    Clipboard,
+}
+
+impl ShareTarget {
+    pub const ALL: [ShareTarget; 3] = [Self::Terminal, Self::Png, Self::Clipboard];
+
+    pub fn to_display_string(&self) -> String {
+        match self {
+            Self::Terminal => format!("{ICON_QR} Show QR code in terminal"),
+            Self::Png => format!("{ICON_QR} Save QR code as PNG"),
+            Self::Clipboard => format!("{ICON_LIST} Copy WIFI: code to clipboard"),
+        }
+    }
+}
+
+/// Where a revealed saved password should go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordTarget {
+    Notification,
+    Clipboard,
+    Both,
+}
+
+impl PasswordTarget {
+    pub const ALL: [PasswordTarget; 3] = [Self::Notification, Self::Clipboard, Self::Both];
+
+    pub fn to_display_string(&self, clear_after: Duration) -> String {
+        let seconds = clear_after.as_secs();
+        match self {
+            Self::Notification => format!("{ICON_KEY} Show password in a notification"),
+            Self::Clipboard => {
+                format!("{ICON_LIST} Copy password to clipboard (cleared after {seconds}s)")
+            }
+            Self::Both => format!("{ICON_KEY} Show and copy password (cleared after {seconds}s)"),
+        }
+    }
+}
+
+/// Reads the credentials of the active Wi-Fi connection.
+///
+/// Saved secrets usually need elevated rights, so reading them may go through
+/// pkexec or sudo.
+pub async fn get_active_wifi_credentials(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<WifiCredentials, Box<dyn Error>> {
+    match WifiBackend::detect(interface, command_runner).await {
+        Some(WifiBackend::NetworkManager) => nm_active_wifi_credentials(command_runner),
+        Some(WifiBackend::Iwd) => iwd_active_wifi_credentials(interface, command_runner),
+        // Neither hands out saved passphrases
+        Some(WifiBackend::Connman | WifiBackend::WpaSupplicant) => {
+            Err("Sharing the password needs NetworkManager or iwd".into())
+        }
+        None => Err("No Wi-Fi backend is running".into()),
+    }
+}
+
+fn nm_active_wifi_credentials(
+    command_runner: &dyn CommandRunner,
+) -> Result<WifiCredentials, Box<dyn Error>> {
+    let output = command_runner.run_command(
+        "nmcli",
+        &["-t", "-f", "UUID,TYPE", "connection", "show", "--active"],
+    )?;
+    let uuid = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(split_nmcli_fields)
+        .find(|fields| fields.len() >= 2 && fields[1] == "802-11-wireless")
+        .map(|fields| fields[0].clone())
+        .ok_or("Not connected to a Wi-Fi network")?;
+
+    nm_connection_credentials(&uuid, command_runner)
+}
+
+/// Reads the SSID, security and secret of a NetworkManager Wi-Fi profile.
+pub(crate) fn nm_connection_credentials(
+    uuid: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<WifiCredentials, Box<dyn Error>> {
+    let ssid = nm_field(uuid, "802-11-wireless.ssid", command_runner)?;
+    let hidden = nm_field(uuid, "802-11-wireless.hidden", command_runner)? == "yes";
+    let key_mgmt =
+        nm_field(uuid, "802-11-wireless-security.key-mgmt", command_runner).unwrap_or_default();
+    let security = WifiQrSecurity::from_key_mgmt(&key_mgmt)?;
+
+    let password = match security {
+        WifiQrSecurity::Open => None,
+        WifiQrSecurity::Wep => Some(nm_secret(
+            uuid,
+            "802-11-wireless-security.wep-key0",
+            command_runner,
+        )?),
+        WifiQrSecurity::Wpa | WifiQrSecurity::Sae => Some(nm_secret(
+            uuid,
+            "802-11-wireless-security.psk",
+            command_runner,
+        )?),
+    };
+
+    Ok(WifiCredentials {
+        ssid,
+        security,
+        password,
+        hidden,
+    })
+}
+
+/// Reads one property of a NetworkManager connection.
+pub(crate) fn nm_field(
+    uuid: &str,
+    field: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<String, Box<dyn Error>> {
+    let output = command_runner.run_command(
+        "nmcli",
+        &["-s", "-g", field, "connection", "show", "uuid", uuid],
+    )?;
+    if !output.status.success() {
+        return Err(format!("Failed to read {field}").into());
+    }
+    Ok(unescape_nmcli_value(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Reads a secret, asking for elevated rights when the agent hides it.
+fn nm_secret(
+    uuid: &str,
+    field: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<String, Box<dyn Error>> {
+    let secret = nm_field(uuid, field, command_runner).unwrap_or_default();
+    if !secret.is_empty() {
+        return Ok(secret);
+    }
+
+    let command = format!("nmcli -s -g {field} connection show uuid '{uuid}'");
+    let privileged_cmd = wrap_privileged_command(&command, false);
+    let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    let secret = unescape_nmcli_value(&String::from_utf8_lossy(&output.stdout));
+    if output.status.success() && !secret.is_empty() {
+        Ok(secret)
+    } else {
+        Err("Could not read the saved password".into())
+    }
+}
+
+fn unescape_nmcli_value(output: &str) -> String {
+    let line = output.trim_end_matches('\n');
+    split_nmcli_fields(line).join(":")
+}
+
+fn iwd_active_wifi_credentials(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<WifiCredentials, Box<dyn Error>> {
+    let output = command_runner.run_command("iwctl", &["station", interface, "show"])?;
+    let ssid = parse_iwd_connected_network(&String::from_utf8_lossy(&output.stdout))?
+        .ok_or("Not connected to a Wi-Fi network")?;
+
+    iwd_network_credentials(ssid, command_runner)
+}
+
+/// Reads the credentials iwd saved for `ssid`.
+fn iwd_network_credentials(
+    ssid: String,
+    command_runner: &dyn CommandRunner,
+) -> Result<WifiCredentials, Box<dyn Error>> {
+    let dir = Path::new(iwd::IWD_STATE_DIR);
+    let psk_file = dir.join(iwd::network_file_name(&ssid, "psk"));
+    let open_file = dir.join(iwd::network_file_name(&ssid, "open"));
+    let command = format!(
+        "cat '{}' 2>/dev/null || test -e '{}'",
+        psk_file.display().to_string().replace('\'', r"'\''"),
+        open_file.display().to_string().replace('\'', r"'\''")
+    );
+    let privileged_cmd = wrap_privileged_command(&command, true);
+    let output = command_runner.run_command("sh", &["-c", &privileged_cmd])?;
+    if !output.status.success() {
+        return Err(format!("No saved iwd profile for {ssid}").into());
+    }
+
+    let contents = String::from_utf8_lossy(&output.stdout);
+    let (password, hidden) = parse_iwd_network_file(&contents);
+    if contents.trim().is_empty() {
+        return Ok(WifiCredentials {
+            ssid,
+            security: WifiQrSecurity::Open,
+            password: None,
+            hidden,
+        });
+    }
+
+    Ok(WifiCredentials {
+        ssid,
+        security: WifiQrSecurity::Wpa,
+        password: Some(password.ok_or("iwd only stores a derived key for this network")?),
+        hidden,
+    })
+}
+
+/// Reads the credentials of a saved Wi-Fi profile.
+pub fn get_saved_credentials(
+    connection: &SavedConnection,
+    command_runner: &dyn CommandRunner,
+) -> Result<WifiCredentials, Box<dyn Error>> {
+    match connection.backend {
+        SavedBackend::NetworkManager => nm_connection_credentials(&connection.id, command_runner),
+        SavedBackend::Iwd => iwd_network_credentials(connection.id.clone(), command_runner),
+    }
+}
+
+/// Sends the saved password to `target`, returning a message for the user.
+///
+/// A copied password is removed from the clipboard after `clear_after`, unless
+/// something else was copied in the meantime.
+pub fn reveal_password(
+    credentials: &WifiCredentials,
+    target: PasswordTarget,
+    clear_after: Duration,
+) -> Result<String, Box<dyn Error>> {
+    let password = credentials
+        .password
+        .as_deref()
+        .ok_or_else(|| format!("{} is an open network", credentials.ssid))?;
+
+    if target != PasswordTarget::Notification {
+        copy_to_clipboard(password)?;
+        clear_clipboard_after(password, clear_after)?;
+    }
+
+    let seconds = clear_after.as_secs();
+    Ok(match target {
+        PasswordTarget::Notification => format!("Password: {password}"),
+        PasswordTarget::Clipboard => format!("Password copied, cleared in {seconds}s"),
+        PasswordTarget::Both => {
+            format!("Password: {password}\nCopied, cleared in {seconds}s")
+        }
+    })
+}
+
+/// Extracts the SSID from `iwctl station <iface> show`.
+fn parse_iwd_connected_network(output: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let ansi = Regex::new(r"\x1B\[[0-?]*[ -/]*[@-~]")?;
+    let output = ansi.replace_all(output, "");
+    let re = Regex::new(r"^\s*Connected network\s+(.+?)\s*$")?;
+    Ok(output
+        .lines()
+        .find_map(|line| re.captures(line))
+        .map(|caps| caps[1].to_string()))
+}
+
+/// Returns the passphrase and hidden flag of an iwd network file.
+pub(crate) fn parse_iwd_network_file(contents: &str) -> (Option<String>, bool) {
+    let mut passphrase = None;
+    let mut hidden = false;
+    for line in contents.lines() {
+        match line.trim().split_once('=') {
+            Some(("Passphrase", value)) => passphrase = Some(value.to_string()),
+            Some(("Hidden", value)) => hidden = value.trim() == "true",
+            _ => {}
+        }
+    }
+    (passphrase, hidden)
+}
+
+/// Path of the PNG a network's QR code is saved to.
+pub fn qr_png_path(ssid: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let name: String = ssid
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Ok(dirs::cache_dir()
+        .ok_or("No cache directory")?
+        .join("network-dmenu")
+        .join(format!("wifi-{name}.png")))
+}
+
+/// Sends the credentials to `target`, returning a message for the user.
+pub fn share_credentials(
+    credentials: &WifiCredentials,
+    target: ShareTarget,
+) -> Result<String, Box<dyn Error>> {
+    let payload = credentials.to_qr_payload();
+    match target {
+        ShareTarget::Terminal => {
+            let output = run_qrencode(&["-t", "ANSIUTF8"], &payload)?;
+            std::io::stdout().write_all(&output)?;
+            Ok(format!("QR code for {} printed", credentials.ssid))
+        }
+        ShareTarget::Png => {
+            let path = qr_png_path(&credentials.ssid)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let image = run_qrencode(&["-t", "PNG", "-s", "8", "-o", "-"], &payload)?;
+            // The image holds the password, so it is never readable by others
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+            write_private_file(&path, image)?;
+            Ok(format!("QR code saved to {}", path.display()))
+        }
+        ShareTarget::Clipboard => {
+            copy_to_clipboard(&payload)?;
+            Ok(format!("WIFI: code for {} copied", credentials.ssid))
+        }
+    }
+}
+
+/// Runs qrencode with the payload on stdin, keeping it out of the process list.
+fn run_qrencode(args: &[&str], payload: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !is_command_installed("qrencode") {
+        return Err("qrencode is not installed".into());
+    }
+
+    let mut child = Command::new("qrencode")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    {
+        let stdin = child.stdin.as_mut().ok_or("Failed to open stdin")?;
+        stdin.write_all(payload.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err("qrencode failed".into())
+    }
+}
+
+/// Connects to a network from parsed `WIFI:` credentials.
+pub async fn connect_with_credentials(
+    credentials: &WifiCredentials,
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let ssid = credentials.ssid.as_str();
+    let password = credentials.password.as_deref();
+
+    match WifiBackend::detect(interface, command_runner).await {
+        Some(WifiBackend::NetworkManager) => {
+            // Schemes the D-Bus settings builder cannot express go through nmcli
+            if nm_dbus::supports_security(credentials.security.menu_security()) {
+                let dbus_result = nm_dbus::with_client({
+                    let credentials = credentials.clone();
+                    let interface = interface.to_string();
+                    move |client| {
+                        client.connect_wifi_with_credentials(
+                            &credentials.ssid,
+                            credentials.security.menu_security(),
+                            credentials.password.as_deref(),
+                            credentials.hidden,
+                            Some(&interface),
+                        )
+                    }
+                })
+                .await;
+                if let Some(result) = dbus_result {
+                    return Ok(result?);
+                }
+            }
+
+            networkmanager::attempt_wifi_connection(
+                ssid,
+                credentials.hidden,
+                password.map(String::from),
+                Some(interface),
+                command_runner,
+            )
+            .await
+        }
+        Some(WifiBackend::Connman) => {
+            let credentials = credentials.clone();
+            connman::with_client(move |client| {
+                let password = credentials.password.as_deref();
+                if credentials.hidden {
+                    return client.connect_hidden_wifi(
+                        &credentials.ssid,
+                        credentials.security.connman_security(),
+                        password,
+                    );
+                }
+                let service = client
+                    .services()?
+                    .into_iter()
+                    .find(|service| {
+                        service.service_type == "wifi" && service.name == credentials.ssid
+                    })
+                    .ok_or_else(|| format!("Network {} is not in range", credentials.ssid))?;
+                client.connect_service_with_agent(&service, password)
+            })
+            .await
+            .ok_or("ConnMan stopped answering")?
+            .map_err(Into::into)
+        }
+        Some(WifiBackend::Iwd) => {
+            if credentials.security == WifiQrSecurity::Wep {
+                return Err("iwd does not support WEP networks".into());
+            }
+            iwd::attempt_connection(
+                interface,
+                ssid,
+                credentials.hidden,
+                password,
+                command_runner,
+            )
+            .await
+        }
+        Some(WifiBackend::WpaSupplicant) => {
+            wpa_supplicant::connect_to_wpa_network(
+                interface,
+                ssid,
+                credentials.security.menu_security(),
+                password,
+                credentials.hidden,
+                command_runner,
+            )
+            .await
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    struct ScriptedCommandRunner {
+        responses: Vec<(Vec<&'static str>, &'static str)>,
+    }
+
+    impl CommandRunner for ScriptedCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command];
+            call.extend_from_slice(args);
+            let response = self
+                .responses
+                .iter()
+                .find(|(expected, _)| *expected == call)
+                .map(|(_, stdout)| *stdout);
+            Ok(Output {
+                status: ExitStatus::from_raw(if response.is_some() { 0 } else { 1 << 8 }),
+                stdout: response.unwrap_or_default().as_bytes().to_vec(),
+                stderr: vec![],
+            })
+        }
+    }
+
+    fn credentials(ssid: &str, password: Option<&str>) -> WifiCredentials {
+        WifiCredentials {
+            ssid: ssid.to_string(),
+            security: WifiQrSecurity::Wpa,
+            password: password.map(String::from),
+            hidden: false,
+        }
+    }
+
+    #[test]
+    fn test_payload_round_trip_with_special_characters() {
+        let creds = WifiCredentials {
+            hidden: true,
+            ..credentials(r#"Café;"Guest":5G"#, Some(r"p\a,s;s"))
+        };
+        let payload = creds.to_qr_payload();
+        assert_eq!(
+            payload,
+            r#"WIFI:T:WPA;S:Café\;\"Guest\"\:5G;P:p\\a\,s\;s;H:true;;"#
+        );
+        assert_eq!(WifiCredentials::from_qr_payload(&payload).unwrap(), creds);
+    }
+
+    #[test]
+    fn test_open_payload_has_no_password() {
+        let creds = WifiCredentials {
+            security: WifiQrSecurity::Open,
+            ..credentials("Library", Some("ignored"))
+        };
+        assert_eq!(creds.to_qr_payload(), "WIFI:T:nopass;S:Library;;");
+    }
+
+    #[test]
+    fn test_parse_payload_field_order_and_defaults() {
+        let creds = WifiCredentials::from_qr_payload("WIFI:P:secret123;S:Home;;").unwrap();
+        assert_eq!(creds, credentials("Home", Some("secret123")));
+
+        let creds = WifiCredentials::from_qr_payload("WIFI:S:Cafe;T:nopass;P:;;").unwrap();
+        assert_eq!(creds.security, WifiQrSecurity::Open);
+        assert_eq!(creds.password, None);
+
+        let creds = WifiCredentials::from_qr_payload("WIFI:T:SAE;S:Home6E;P:pw;;").unwrap();
+        assert_eq!(creds.security, WifiQrSecurity::Sae);
+    }
+
+    #[test]
+    fn test_parse_payload_rejects_invalid_input() {
+        assert!(WifiCredentials::from_qr_payload("https://example.com").is_err());
+        assert!(WifiCredentials::from_qr_payload("WIFI:T:WPA;P:pw;;").is_err());
+        assert!(WifiCredentials::from_qr_payload("WIFI:T:LEAP;S:x;;").is_err());
+    }
+
+    #[test]
+    fn test_security_from_key_mgmt() {
+        assert_eq!(
+            WifiQrSecurity::from_key_mgmt("wpa-psk").unwrap(),
+            WifiQrSecurity::Wpa
+        );
+        assert_eq!(
+            WifiQrSecurity::from_key_mgmt("").unwrap(),
+            WifiQrSecurity::Open
+        );
+        assert!(WifiQrSecurity::from_key_mgmt("wpa-eap").is_err());
+        let error = WifiQrSecurity::from_key_mgmt("ieee8021x").unwrap_err();
+        assert!(error.to_string().contains("Enterprise networks"));
+    }
+
+    #[test]
+    fn test_nm_active_wifi_credentials() {
+        let uuid = "5b1e-uuid";
+        let runner = ScriptedCommandRunner {
+            responses: vec![
+                (
+                    vec![
+                        "nmcli",
+                        "-t",
+                        "-f",
+                        "UUID,TYPE",
+                        "connection",
+                        "show",
+                        "--active",
+                    ],
+                    "aaaa-uuid:802-3-ethernet\n5b1e-uuid:802-11-wireless\n",
+                ),
+                (
+                    vec![
+                        "nmcli",
+                        "-s",
+                        "-g",
+                        "802-11-wireless.ssid",
+                        "connection",
+                        "show",
+                        "uuid",
+                        uuid,
+                    ],
+                    "Home\\:Net\n",
+                ),
+                (
+                    vec![
+                        "nmcli",
+                        "-s",
+                        "-g",
+                        "802-11-wireless.hidden",
+                        "connection",
+                        "show",
+                        "uuid",
+                        uuid,
+                    ],
+                    "no\n",
+                ),
+                (
+                    vec![
+                        "nmcli",
+                        "-s",
+                        "-g",
+                        "802-11-wireless-security.key-mgmt",
+                        "connection",
+                        "show",
+                        "uuid",
+                        uuid,
+                    ],
+                    "wpa-psk\n",
+                ),
+                (
+                    vec![
+                        "nmcli",
+                        "-s",
+                        "-g",
+                        "802-11-wireless-security.psk",
+                        "connection",
+                        "show",
+                        "uuid",
+                        uuid,
+                    ],
+                    "hunter22\n",
+                ),
+            ],
+        };
+
+        let creds = nm_active_wifi_credentials(&runner).unwrap();
+        assert_eq!(creds, credentials("Home:Net", Some("hunter22")));
+    }
+
+    #[test]
+    fn test_nm_active_wifi_credentials_not_connected() {
+        let runner = ScriptedCommandRunner {
+            responses: vec![(
+                vec![
+                    "nmcli",
+                    "-t",
+                    "-f",
+                    "UUID,TYPE",
+                    "connection",
+                    "show",
+                    "--active",
+                ],
+                "aaaa-uuid:802-3-ethernet\n",
+            )],
+        };
+        assert!(nm_active_wifi_credentials(&runner).is_err());
+    }
+
+    #[test]
+    fn test_parse_iwd_station_and_network_file() {
+        let output = "                                 Station: wlan0\n\
+            --------------------------------------------------------------------------------\n\
+              Settable  Property              Value\n\
+            --------------------------------------------------------------------------------\n\
+                        Scanning              no\n\
+                        State                 connected\n\
+                        Connected network     \x1b[1mMy Home\x1b[0m\n";
+        assert_eq!(
+            parse_iwd_connected_network(output).unwrap(),
+            Some("My Home".to_string())
+        );
+
+        let file = "[Security]\nPreSharedKey=abcd\nPassphrase=s3cr=t\n\n[Settings]\nHidden=true\n";
+        assert_eq!(
+            parse_iwd_network_file(file),
+            (Some("s3cr=t".to_string()), true)
+        );
+    }
+
+    #[test]
+    fn test_reveal_password_in_notification() {
+        let clear_after = Duration::from_secs(30);
+        let message = reveal_password(
+            &credentials("Home", Some("hunter22")),
+            PasswordTarget::Notification,
+            clear_after,
+        )
+        .unwrap();
+        assert_eq!(message, "Password: hunter22");
+
+        assert!(reveal_password(
+            &credentials("Cafe", None),
+            PasswordTarget::Notification,
+            clear_after
+        )
+        .is_err());
+        assert!(PasswordTarget::Clipboard
+            .to_display_string(clear_after)
+            .contains("cleared after 30s"));
+    }
+
+    #[test]
+    fn test_qr_png_path_sanitizes_ssid() {
+        let path = qr_png_path("a/b c").unwrap();
+        assert!(path.ends_with("network-dmenu/wifi-a_b_c.png"));
+    }
+}
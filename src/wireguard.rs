@@ -0,0 +1,504 @@
+//! WireGuard tunnels managed by NetworkManager.
+//!
+//! `.conf` files are imported as NetworkManager profiles. Peer state comes
+//! from `wg show all dump`, which needs `CAP_NET_ADMIN`: the detail view asks
+//! for privileges, while the menu only flags stale handshakes when `wg` works
+//! without a prompt (as root or through a passwordless sudo rule).
+
+use crate::command::{
+    is_command_installed, CommandRunner, DEFAULT_COMMAND_TIMEOUT, PRIVILEGED_COMMAND_TIMEOUT,
+};
+use crate::constants::{ICON_KEY, ICON_WARNING};
+use crate::ethernet::SYS_CLASS_NET;
+use crate::networkmanager::split_nmcli_fields;
+use crate::privilege::wrap_privileged_command;
+use crate::utils::expand_home;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// WireGuard rekeys every two minutes while traffic flows, so a handshake
+/// older than this means the peer is unreachable or the tunnel is idle.
+pub const STALE_HANDSHAKE: Duration = Duration::from_secs(180);
+
+/// Longest Linux interface name, which nmcli derives from the file name.
+const IFNAMSIZ: usize = 15;
+
+/// A peer of a WireGuard interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireguardPeer {
+    pub public_key: String,
+    pub endpoint: Option<String>,
+    pub allowed_ips: Vec<String>,
+    /// Seconds since the epoch, `None` before the first handshake
+    pub latest_handshake: Option<u64>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl WireguardPeer {
+    /// Time since the latest handshake.
+    pub fn handshake_age(&self, now: SystemTime) -> Option<Duration> {
+        let handshake = UNIX_EPOCH + Duration::from_secs(self.latest_handshake?);
+        Some(now.duration_since(handshake).unwrap_or_default())
+    }
+
+    pub fn is_stale(&self, now: SystemTime) -> bool {
+        self.handshake_age(now)
+            .is_none_or(|age| age > STALE_HANDSHAKE)
+    }
+
+    /// One-line summary for the detail view.
+    pub fn to_display_string(&self, now: SystemTime) -> String {
+        let handshake = match self.handshake_age(now) {
+            Some(age) => format!("handshake {} ago", format_age(age)),
+            None => "no handshake".to_string(),
+        };
+        format!(
+            "{} {}…\t{}\t{}{}\t↓ {} ↑ {}\t{}",
+            if self.is_stale(now) {
+                ICON_WARNING
+            } else {
+                ICON_KEY
+            },
+            self.public_key.chars().take(8).collect::<String>(),
+            self.endpoint.as_deref().unwrap_or("no endpoint"),
+            handshake,
+            if self.is_stale(now) { " (stale)" } else { "" },
+            format_bytes(self.rx_bytes),
+            format_bytes(self.tx_bytes),
+            self.allowed_ips.join(", "),
+        )
+    }
+}
+
+/// A WireGuard interface and its peers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireguardInterface {
+    pub name: String,
+    pub public_key: String,
+    pub listen_port: Option<u16>,
+    pub peers: Vec<WireguardPeer>,
+}
+
+impl WireguardInterface {
+    pub fn has_stale_peer(&self, now: SystemTime) -> bool {
+        self.peers.iter().any(|peer| peer.is_stale(now))
+    }
+}
+
+/// Formats a duration the way `wg show` does, coarsely.
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+/// Formats a byte count with binary units.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn dump_field(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.is_empty() && value != "(none)")
+}
+
+/// Parses `wg show all dump`.
+///
+/// Interface lines have five tab-separated fields (name, private key,
+/// public key, listen port, fwmark); peer lines have nine.
+pub fn parse_wg_dump(output: &str) -> Vec<WireguardInterface> {
+    let mut interfaces: Vec<WireguardInterface> = Vec::new();
+    for line in output.lines() {
+        match line.split('\t').collect::<Vec<_>>()[..] {
+            [name, _private_key, public_key, listen_port, _fwmark] => {
+                interfaces.push(WireguardInterface {
+                    name: name.to_string(),
+                    public_key: public_key.to_string(),
+                    listen_port: listen_port.parse().ok().filter(|port| *port != 0),
+                    peers: Vec::new(),
+                });
+            }
+            [name, public_key, _preshared_key, endpoint, allowed_ips, handshake, rx, tx, _keepalive] =>
+            {
+                let Some(interface) = interfaces.iter_mut().find(|i| i.name == name) else {
+                    continue;
+                };
+                interface.peers.push(WireguardPeer {
+                    public_key: public_key.to_string(),
+                    endpoint: dump_field(endpoint),
+                    allowed_ips: dump_field(allowed_ips)
+                        .map(|ips| ips.split(',').map(str::to_string).collect())
+                        .unwrap_or_default(),
+                    latest_handshake: handshake.parse().ok().filter(|secs| *secs != 0),
+                    rx_bytes: rx.parse().unwrap_or(0),
+                    tx_bytes: tx.parse().unwrap_or(0),
+                });
+            }
+            _ => {}
+        }
+    }
+    interfaces
+}
+
+/// Lists WireGuard links from sysfs, which needs no privileges.
+pub fn list_wireguard_links() -> Vec<String> {
+    list_wireguard_links_in(Path::new(SYS_CLASS_NET))
+}
+
+pub fn list_wireguard_links_in(root: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut links: Vec<String> = entries
+        .flatten()
+        .filter(|entry| {
+            fs::read_to_string(entry.path().join("uevent"))
+                .is_ok_and(|uevent| uevent.lines().any(|line| line == "DEVTYPE=wireguard"))
+        })
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    links.sort();
+    links
+}
+
+/// Reads the state of all WireGuard interfaces.
+///
+/// With `privileged`, pkexec or sudo may prompt; otherwise `wg` is tried
+/// directly and then through `sudo -n`, which fails instead of prompting.
+pub async fn get_wireguard_interfaces(
+    privileged: bool,
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<WireguardInterface>, Box<dyn Error>> {
+    if !is_command_installed("wg") {
+        return Err("wg is not installed".into());
+    }
+
+    let attempts: Vec<(&str, Vec<String>)> = if privileged {
+        vec![(
+            "sh",
+            vec![
+                "-c".to_string(),
+                wrap_privileged_command("wg show all dump", false),
+            ],
+        )]
+    } else {
+        vec![
+            ("wg", vec!["show".into(), "all".into(), "dump".into()]),
+            (
+                "sudo",
+                vec![
+                    "-n".into(),
+                    "wg".into(),
+                    "show".into(),
+                    "all".into(),
+                    "dump".into(),
+                ],
+            ),
+        ]
+    };
+
+    // A pkexec or sudo prompt may wait for the user
+    let timeout = if privileged {
+        PRIVILEGED_COMMAND_TIMEOUT
+    } else {
+        DEFAULT_COMMAND_TIMEOUT
+    };
+    for (command, args) in attempts {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        if let Ok(output) = command_runner
+            .run_command_with_timeout(command, &args, timeout)
+            .await
+        {
+            if output.status.success() {
+                return Ok(parse_wg_dump(&String::from_utf8_lossy(&output.stdout)));
+            }
+        }
+    }
+    Err("Failed to read the WireGuard state".into())
+}
+
+/// Names of active NetworkManager WireGuard connections running on one of the
+/// `stale` interfaces.
+pub async fn stale_connections(
+    stale: &[String],
+    command_runner: &dyn CommandRunner,
+) -> Vec<String> {
+    if stale.is_empty() {
+        return Vec::new();
+    }
+    let Ok(output) = command_runner
+        .run_command_async(
+            "nmcli",
+            &[
+                "-t",
+                "-f",
+                "NAME,TYPE,DEVICE",
+                "connection",
+                "show",
+                "--active",
+            ],
+        )
+        .await
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| match split_nmcli_fields(line).as_slice() {
+            [name, kind, device] if kind == "wireguard" && stale.contains(device) => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Names of WireGuard interfaces with a stale peer, empty when the peer
+/// state cannot be read without a prompt.
+pub async fn stale_interfaces(command_runner: &dyn CommandRunner) -> Vec<String> {
+    if list_wireguard_links().is_empty() {
+        return Vec::new();
+    }
+    let Ok(interfaces) = get_wireguard_interfaces(false, command_runner).await else {
+        return Vec::new();
+    };
+
+    let now = SystemTime::now();
+    interfaces
+        .into_iter()
+        .filter(|interface| interface.has_stale_peer(now))
+        .map(|interface| interface.name)
+        .collect()
+}
+
+/// Checks that nmcli can turn `path` into an interface name.
+fn validate_config_path(path: &Path) -> Result<(), Box<dyn Error>> {
+    if path.extension().and_then(|ext| ext.to_str()) != Some("conf") {
+        return Err("WireGuard configs must be .conf files".into());
+    }
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    if name.is_empty()
+        || name.len() > IFNAMSIZ
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '='))
+    {
+        return Err(format!(
+            "{name} is not a valid interface name; rename the file to at most {IFNAMSIZ} letters, digits, '-' or '_'"
+        )
+        .into());
+    }
+    if !path.is_file() {
+        return Err(format!("{} not found", path.display()).into());
+    }
+    Ok(())
+}
+
+/// Imports a wg-quick style `.conf` file as a NetworkManager connection.
+pub fn import_wireguard_config(
+    path: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let path = expand_home(path.trim());
+    validate_config_path(&path)?;
+
+    let output = command_runner.run_command(
+        "nmcli",
+        &[
+            "connection",
+            "import",
+            "type",
+            "wireguard",
+            "file",
+            &path.to_string_lossy(),
+        ],
+    )?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    const DUMP: &str = "wg0\tcHJpdmF0ZQ==\tSERVERPUBKEY=\t51820\toff
+wg0\tPEERONEKEY1234=\t(none)\t203.0.113.5:51820\t10.0.0.0/24,fd00::/64\t1700000000\t1048576\t2048\t25
+wg0\tPEERTWOKEY5678=\t(none)\t(none)\t10.0.1.2/32\t0\t0\t0\toff
+wg1\tcHJpdmF0ZQ==\tCLIENTPUBKEY=\t0\toff
+";
+
+    struct RecordingCommandRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+        stderr: &'static str,
+        success: bool,
+    }
+
+    impl CommandRunner for RecordingCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let mut call = vec![command.to_string()];
+            call.extend(args.iter().map(|arg| arg.to_string()));
+            self.calls.lock().unwrap().push(call);
+            Ok(Output {
+                status: ExitStatus::from_raw(if self.success { 0 } else { 1 << 8 }),
+                stdout: Vec::new(),
+                stderr: self.stderr.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_parse_wg_dump() {
+        let interfaces = parse_wg_dump(DUMP);
+        assert_eq!(interfaces.len(), 2);
+
+        let wg0 = &interfaces[0];
+        assert_eq!(wg0.public_key, "SERVERPUBKEY=");
+        assert_eq!(wg0.listen_port, Some(51820));
+        assert_eq!(
+            wg0.peers[0],
+            WireguardPeer {
+                public_key: "PEERONEKEY1234=".to_string(),
+                endpoint: Some("203.0.113.5:51820".to_string()),
+                allowed_ips: vec!["10.0.0.0/24".to_string(), "fd00::/64".to_string()],
+                latest_handshake: Some(1_700_000_000),
+                rx_bytes: 1_048_576,
+                tx_bytes: 2048,
+            }
+        );
+        assert_eq!(wg0.peers[1].endpoint, None);
+        assert_eq!(wg0.peers[1].latest_handshake, None);
+        assert_eq!(interfaces[1].listen_port, None);
+        assert!(interfaces[1].peers.is_empty());
+    }
+
+    #[test]
+    fn test_stale_handshakes() {
+        let interfaces = parse_wg_dump(DUMP);
+        let peer = &interfaces[0].peers[0];
+
+        let fresh = UNIX_EPOCH + Duration::from_secs(1_700_000_042);
+        assert_eq!(peer.handshake_age(fresh), Some(Duration::from_secs(42)));
+        assert!(!peer.is_stale(fresh));
+        assert_eq!(
+            peer.to_display_string(fresh),
+            format!(
+                "{ICON_KEY} PEERONEK…\t203.0.113.5:51820\thandshake 42s ago\t↓ 1.0 MiB ↑ 2.0 KiB\t10.0.0.0/24, fd00::/64"
+            )
+        );
+
+        let later = UNIX_EPOCH + Duration::from_secs(1_700_000_600);
+        assert!(peer.is_stale(later));
+        assert!(peer.to_display_string(later).contains("10m 0s ago (stale)"));
+
+        // Never completed a handshake
+        assert!(interfaces[0].peers[1].is_stale(fresh));
+        assert!(interfaces[0].has_stale_peer(fresh));
+        assert!(!interfaces[1].has_stale_peer(fresh));
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format_age(Duration::from_secs(5)), "5s");
+        assert_eq!(format_age(Duration::from_secs(3725)), "1h 2m");
+        assert_eq!(format_age(Duration::from_secs(90000)), "1d 1h");
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn test_list_wireguard_links() {
+        let root = tempfile::tempdir().unwrap();
+        for (name, uevent) in [
+            ("wg0", "DEVTYPE=wireguard\nINTERFACE=wg0\n"),
+            ("eth0", "INTERFACE=eth0\n"),
+        ] {
+            fs::create_dir(root.path().join(name)).unwrap();
+            fs::write(root.path().join(name).join("uevent"), uevent).unwrap();
+        }
+        assert_eq!(list_wireguard_links_in(root.path()), vec!["wg0"]);
+    }
+
+    #[test]
+    fn test_import_wireguard_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("office.conf");
+        fs::write(&config, "[Interface]\n").unwrap();
+        let runner = RecordingCommandRunner {
+            calls: Mutex::new(Vec::new()),
+            stderr: "",
+            success: true,
+        };
+
+        assert!(import_wireguard_config(&config.to_string_lossy(), &runner).unwrap());
+        assert_eq!(
+            runner.calls.lock().unwrap()[0],
+            vec![
+                "nmcli",
+                "connection",
+                "import",
+                "type",
+                "wireguard",
+                "file",
+                &config.to_string_lossy()
+            ]
+        );
+
+        let failing = RecordingCommandRunner {
+            calls: Mutex::new(Vec::new()),
+            stderr: "Error: failed to import\n",
+            success: false,
+        };
+        let error = import_wireguard_config(&config.to_string_lossy(), &failing).unwrap_err();
+        assert_eq!(error.to_string(), "Error: failed to import");
+    }
+
+    #[test]
+    fn test_import_rejects_bad_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = RecordingCommandRunner {
+            calls: Mutex::new(Vec::new()),
+            stderr: "",
+            success: true,
+        };
+
+        let long = dir.path().join("a-very-long-tunnel-name.conf");
+        fs::write(&long, "").unwrap();
+        for path in [
+            dir.path().join("office.txt"),
+            long,
+            dir.path().join("missing.conf"),
+        ] {
+            assert!(import_wireguard_config(&path.to_string_lossy(), &runner).is_err());
+        }
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,601 @@
+//! Bare wpa_supplicant Wi-Fi backend, driven through `wpa_cli`.
+//!
+//! Used when neither NetworkManager nor iwd manages the radio. Networks are
+//! added to the running configuration and persisted with `save_config`, which
+//! needs `update_config=1` in wpa_supplicant.conf.
+
+use crate::command::{is_command_installed, CommandRunner};
+use crate::networkmanager::wifi_network_action;
+use crate::nm_dbus::strength_to_stars;
+use crate::utils::prompt_for_password;
+use crate::{parse_wifi_action, WifiAction};
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+/// Time wpa_supplicant usually needs to complete a scan.
+const SCAN_WAIT: Duration = Duration::from_secs(3);
+const ASSOCIATION_TIMEOUT: Duration = Duration::from_secs(20);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A row of `wpa_cli scan_results`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanResult {
+    pub bssid: String,
+    pub frequency: u32,
+    /// Signal level in dBm
+    pub signal: i32,
+    /// Security in NetworkManager's notation, e.g. `WPA1 WPA2`
+    pub security: String,
+    pub ssid: String,
+}
+
+/// Runs `wpa_cli` against `interface` and returns its trimmed reply.
+async fn wpa_cli(
+    interface: &str,
+    args: &[&str],
+    command_runner: &dyn CommandRunner,
+) -> Result<String, Box<dyn Error>> {
+    let mut command_args = vec!["-i", interface];
+    command_args.extend_from_slice(args);
+    let output = command_runner
+        .run_command_async("wpa_cli", &command_args)
+        .await?;
+    if !output.status.success() {
+        return Err(format!("wpa_cli {} failed", args.join(" ")).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Runs a `wpa_cli` command answered with `OK` or `FAIL`.
+async fn wpa_cli_ok(
+    interface: &str,
+    args: &[&str],
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    Ok(wpa_cli(interface, args, command_runner).await? == "OK")
+}
+
+/// Checks whether wpa_supplicant answers on `interface`'s control socket.
+pub async fn is_wpa_supplicant_active(interface: &str, command_runner: &dyn CommandRunner) -> bool {
+    is_command_installed("wpa_cli")
+        && wpa_cli(interface, &["ping"], command_runner)
+            .await
+            .is_ok_and(|reply| reply == "PONG")
+}
+
+/// Converts `scan_results` flags such as `[WPA2-PSK-CCMP][ESS]` to the
+/// security notation used by the other backends.
+pub fn security_from_flags(flags: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut push = |part| {
+        if !parts.contains(&part) {
+            parts.push(part);
+        }
+    };
+
+    for flag in flags.split(['[', ']']).filter(|flag| !flag.is_empty()) {
+        let (protocol, key_mgmt) = flag.split_once('-').unwrap_or((flag, ""));
+        match protocol {
+            "WEP" => push("WEP"),
+            "OWE" => push("OWE"),
+            "WPA" => push("WPA1"),
+            "WPA2" | "RSN" | "WPA3" => {
+                if key_mgmt.contains("PSK") {
+                    push("WPA2");
+                }
+                if key_mgmt.contains("SAE") || protocol == "WPA3" {
+                    push("WPA3");
+                }
+                if key_mgmt.contains("EAP") {
+                    push("WPA2");
+                    push("802.1X");
+                }
+                if key_mgmt.contains("OWE") {
+                    push("OWE");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Parses `wpa_cli scan_results`, skipping hidden networks.
+pub fn parse_scan_results(output: &str) -> Vec<ScanResult> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+            let bssid = fields.next()?;
+            let frequency = fields.next()?.parse().ok()?;
+            let signal = fields.next()?.parse().ok()?;
+            let flags = fields.next()?;
+            let ssid = fields.next()?;
+            if ssid.is_empty() || ssid.starts_with("\\x00") {
+                return None;
+            }
+            Some(ScanResult {
+                bssid: bssid.to_string(),
+                frequency,
+                signal,
+                security: security_from_flags(flags),
+                ssid: ssid.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads a `key=value` field of `wpa_cli status`.
+fn status_field<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// SSID of the network `interface` is associated with.
+async fn connected_ssid(interface: &str, command_runner: &dyn CommandRunner) -> Option<String> {
+    let status = wpa_cli(interface, &["status"], command_runner).await.ok()?;
+    if status_field(&status, "wpa_state") != Some("COMPLETED") {
+        return None;
+    }
+    status_field(&status, "ssid").map(str::to_string)
+}
+
+/// Maps a signal level in dBm to a percentage, -100 dBm being 0% and -50 dBm
+/// or better 100%.
+fn dbm_to_percent(signal: i32) -> u8 {
+    ((signal + 100) * 2).clamp(0, 100) as u8
+}
+
+/// Builds the Wi-Fi menu entries, one per SSID with the strongest signal.
+fn scan_results_to_actions(
+    mut results: Vec<ScanResult>,
+    connected: Option<&str>,
+) -> Vec<WifiAction> {
+    let is_connected = |result: &ScanResult| connected == Some(result.ssid.as_str());
+    results.sort_by(|a, b| {
+        is_connected(b)
+            .cmp(&is_connected(a))
+            .then_with(|| b.signal.cmp(&a.signal))
+    });
+
+    let mut seen: Vec<String> = Vec::new();
+    let mut actions = Vec::new();
+    for result in results {
+        if seen.contains(&result.ssid) {
+            continue;
+        }
+        actions.push(wifi_network_action(
+            is_connected(&result),
+            &result.ssid,
+            &result.security,
+            &strength_to_stars(dbm_to_percent(result.signal)),
+        ));
+        seen.push(result.ssid);
+    }
+    actions
+}
+
+/// Retrieves available Wi-Fi networks from wpa_supplicant, rescanning when
+/// not connected.
+pub async fn get_wpa_networks(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<WifiAction>, Box<dyn Error>> {
+    let connected = connected_ssid(interface, command_runner).await;
+    if connected.is_none() && wpa_cli_ok(interface, &["scan"], command_runner).await? {
+        tokio::time::sleep(SCAN_WAIT).await;
+    }
+
+    let results = wpa_cli(interface, &["scan_results"], command_runner).await?;
+    Ok(scan_results_to_actions(
+        parse_scan_results(&results),
+        connected.as_deref(),
+    ))
+}
+
+/// Returns `(network id, ssid)` for every configured network.
+pub async fn list_networks(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    Ok(wpa_cli(interface, &["list_networks"], command_runner)
+        .await?
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some((fields.next()?.to_string(), fields.next()?.to_string()))
+        })
+        .collect())
+}
+
+/// Checks if a Wi-Fi network is configured in wpa_supplicant.
+pub async fn is_known_network(
+    ssid: &str,
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    Ok(list_networks(interface, command_runner)
+        .await?
+        .iter()
+        .any(|(_, name)| name == ssid))
+}
+
+fn quoted(value: &str) -> String {
+    format!("\"{value}\"")
+}
+
+fn has_security(security: &str, part: &str) -> bool {
+    security.split_whitespace().any(|p| p == part)
+}
+
+/// Rejects security wpa_supplicant cannot be set up for from the menu.
+fn check_supported(security: &str) -> Result<(), Box<dyn Error>> {
+    if has_security(security, "802.1X") {
+        return Err("Enterprise networks are not supported with wpa_supplicant".into());
+    }
+    Ok(())
+}
+
+fn needs_password(security: &str) -> bool {
+    !(security.trim().is_empty() || security.trim() == "--" || has_security(security, "OWE"))
+}
+
+/// Builds the `set_network` settings for a new network. The SSID is hex
+/// encoded so any byte survives without quoting.
+fn network_settings(
+    ssid: &str,
+    security: &str,
+    password: Option<&str>,
+    hidden: bool,
+) -> Result<Vec<(&'static str, String)>, Box<dyn Error>> {
+    let mut settings = vec![(
+        "ssid",
+        ssid.bytes().map(|b| format!("{b:02x}")).collect::<String>(),
+    )];
+    if hidden {
+        settings.push(("scan_ssid", "1".to_string()));
+    }
+
+    check_supported(security)?;
+    let has = |part: &str| has_security(security, part);
+    let password = match password {
+        Some(password) if !password.is_empty() => Some(password),
+        _ if needs_password(security) => return Err(format!("{ssid} needs a password").into()),
+        _ => None,
+    };
+
+    if has("WPA3") && !has("WPA2") && !has("WPA1") {
+        settings.push(("key_mgmt", "SAE".to_string()));
+        settings.push(("ieee80211w", "2".to_string()));
+        settings.push(("sae_password", quoted(password.unwrap_or_default())));
+    } else if has("WPA1") || has("WPA2") {
+        let key_mgmt = if has("WPA3") {
+            "WPA-PSK SAE"
+        } else {
+            "WPA-PSK"
+        };
+        settings.push(("key_mgmt", key_mgmt.to_string()));
+        if has("WPA3") {
+            settings.push(("ieee80211w", "1".to_string()));
+        }
+        settings.push(("psk", quoted(password.unwrap_or_default())));
+    } else if has("WEP") {
+        settings.push(("key_mgmt", "NONE".to_string()));
+        settings.push(("wep_key0", quoted(password.unwrap_or_default())));
+    } else if has("OWE") {
+        settings.push(("key_mgmt", "OWE".to_string()));
+        settings.push(("ieee80211w", "2".to_string()));
+    } else {
+        settings.push(("key_mgmt", "NONE".to_string()));
+    }
+
+    Ok(settings)
+}
+
+/// Waits until `interface` is associated with `ssid`.
+async fn wait_for_association(
+    interface: &str,
+    ssid: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let started = Instant::now();
+    loop {
+        if connected_ssid(interface, command_runner).await.as_deref() == Some(ssid) {
+            return Ok(true);
+        }
+        if started.elapsed() >= ASSOCIATION_TIMEOUT {
+            return Ok(false);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Connects to a Wi-Fi network with wpa_supplicant, adding and saving it when
+/// it is not configured yet.
+pub async fn connect_to_wpa_wifi(
+    interface: &str,
+    action: &str,
+    hidden: bool,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    let (ssid, security) = parse_wifi_action(action)?;
+    connect_to_wpa_network(interface, ssid, security, None, hidden, command_runner).await
+}
+
+/// Connects to `ssid`, asking for the password of a new secured network when
+/// none is given.
+pub async fn connect_to_wpa_network(
+    interface: &str,
+    ssid: &str,
+    security: &str,
+    password: Option<&str>,
+    hidden: bool,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    if let Some((id, _)) = list_networks(interface, command_runner)
+        .await?
+        .into_iter()
+        .find(|(_, name)| name == ssid)
+    {
+        if !wpa_cli_ok(interface, &["select_network", &id], command_runner).await? {
+            return Ok(false);
+        }
+        return wait_for_association(interface, ssid, command_runner).await;
+    }
+
+    // Fail before asking for a password that could not be used
+    check_supported(security)?;
+    let password = match password {
+        Some(password) => Some(password.to_string()),
+        None if needs_password(security) => Some(prompt_for_password(ssid)?),
+        None => None,
+    };
+    let settings = network_settings(ssid, security, password.as_deref(), hidden)?;
+
+    let id = wpa_cli(interface, &["add_network"], command_runner).await?;
+    if id.parse::<u32>().is_err() {
+        return Err(format!("wpa_supplicant refused to add {ssid}: {id}").into());
+    }
+
+    for (key, value) in &settings {
+        if !wpa_cli_ok(interface, &["set_network", &id, key, value], command_runner).await? {
+            let _ = wpa_cli(interface, &["remove_network", &id], command_runner).await;
+            return Err(format!("wpa_supplicant rejected {key} for {ssid}").into());
+        }
+    }
+
+    let connected = wpa_cli_ok(interface, &["select_network", &id], command_runner).await?
+        && wait_for_association(interface, ssid, command_runner).await?;
+
+    if connected {
+        if !wpa_cli_ok(interface, &["save_config"], command_runner).await? {
+            log::warn!("wpa_supplicant could not save {ssid}, is update_config=1 set?");
+        }
+    } else {
+        // Drop the profile so a wrong password is asked again next time
+        let _ = wpa_cli(interface, &["remove_network", &id], command_runner).await;
+    }
+
+    Ok(connected)
+}
+
+/// Disconnects `interface` from its current network.
+pub async fn disconnect_wpa_wifi(
+    interface: &str,
+    command_runner: &dyn CommandRunner,
+) -> Result<bool, Box<dyn Error>> {
+    wpa_cli_ok(interface, &["disconnect"], command_runner).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    /// Answers `wpa_cli` calls from a script and records them.
+    struct ScriptedCommandRunner {
+        responses: Vec<(&'static str, &'static str)>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedCommandRunner {
+        fn new(responses: Vec<(&'static str, &'static str)>) -> Self {
+            Self {
+                responses,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl CommandRunner for ScriptedCommandRunner {
+        fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, std::io::Error> {
+            let call = format!("{} {}", command, args.join(" "));
+            self.calls.lock().unwrap().push(call.clone());
+            let response = self
+                .responses
+                .iter()
+                .find(|(expected, _)| *expected == call)
+                .map(|(_, stdout)| *stdout);
+            Ok(Output {
+                status: ExitStatus::from_raw(if response.is_some() { 0 } else { 1 << 8 }),
+                stdout: response.unwrap_or_default().as_bytes().to_vec(),
+                stderr: vec![],
+            })
+        }
+    }
+
+    const SCAN_RESULTS: &str = "bssid / frequency / signal level / flags / ssid\n\
+        aa:bb:cc:dd:ee:01\t2437\t-70\t[WPA2-PSK-CCMP][ESS]\tHome\n\
+        aa:bb:cc:dd:ee:02\t5180\t-48\t[WPA2-PSK-CCMP][ESS]\tHome\n\
+        aa:bb:cc:dd:ee:03\t2412\t-60\t[ESS]\tCafe\n\
+        aa:bb:cc:dd:ee:04\t2462\t-80\t[WPA2-PSK-CCMP][ESS]\t\n\
+        aa:bb:cc:dd:ee:05\t5745\t-55\t[WPA2-EAP-CCMP][ESS]\teduroam\n";
+
+    #[test]
+    fn test_security_from_flags() {
+        assert_eq!(security_from_flags("[ESS]"), "");
+        assert_eq!(security_from_flags("[WEP][ESS]"), "WEP");
+        assert_eq!(
+            security_from_flags("[WPA-PSK-TKIP][WPA2-PSK-CCMP][ESS]"),
+            "WPA1 WPA2"
+        );
+        assert_eq!(security_from_flags("[WPA2-PSK+SAE-CCMP][ESS]"), "WPA2 WPA3");
+        assert_eq!(security_from_flags("[RSN-SAE-CCMP][ESS]"), "WPA3");
+        assert_eq!(security_from_flags("[WPA2-EAP-CCMP][ESS]"), "WPA2 802.1X");
+        assert_eq!(security_from_flags("[WPA2-OWE-CCMP][ESS]"), "OWE");
+    }
+
+    #[test]
+    fn test_parse_scan_results_skips_hidden() {
+        let results = parse_scan_results(SCAN_RESULTS);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].ssid, "Home");
+        assert_eq!(results[0].frequency, 2437);
+        assert_eq!(results[0].signal, -70);
+        assert_eq!(results[2].security, "");
+    }
+
+    #[tokio::test]
+    async fn test_get_wpa_networks_connected_first_and_deduplicated() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                "wpa_cli -i wlan0 status",
+                "bssid=aa:bb:cc:dd:ee:02\nssid=Home\nwpa_state=COMPLETED\n",
+            ),
+            ("wpa_cli -i wlan0 scan_results", SCAN_RESULTS),
+        ]);
+
+        let actions = get_wpa_networks("wlan0", &runner).await.unwrap();
+        let parsed: Vec<(String, String)> = actions
+            .iter()
+            .map(|action| match action {
+                WifiAction::Network(display) => {
+                    let (ssid, security) = parse_wifi_action(display).unwrap();
+                    (ssid.to_string(), security.to_string())
+                }
+                other => panic!("unexpected action {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                ("Home".to_string(), "WPA2".to_string()),
+                ("eduroam".to_string(), "WPA2 802.1X".to_string()),
+                ("Cafe".to_string(), "".to_string()),
+            ]
+        );
+        assert!(!runner
+            .calls
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|call| call.ends_with(" scan")));
+    }
+
+    #[test]
+    fn test_network_settings() {
+        let settings = network_settings("Home", "WPA1 WPA2", Some("secret"), true).unwrap();
+        assert_eq!(
+            settings,
+            vec![
+                ("ssid", "486f6d65".to_string()),
+                ("scan_ssid", "1".to_string()),
+                ("key_mgmt", "WPA-PSK".to_string()),
+                ("psk", "\"secret\"".to_string()),
+            ]
+        );
+
+        let sae = network_settings("Home", "WPA3", Some("secret"), false).unwrap();
+        assert!(sae.contains(&("key_mgmt", "SAE".to_string())));
+        assert!(sae.contains(&("sae_password", "\"secret\"".to_string())));
+
+        let open = network_settings("Cafe", "", None, false).unwrap();
+        assert_eq!(open.last().unwrap(), &("key_mgmt", "NONE".to_string()));
+
+        assert!(network_settings("Home", "WPA2", None, false).is_err());
+        assert!(network_settings("eduroam", "WPA2 802.1X", Some("pw"), false).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_rejects_enterprise_before_prompting() {
+        let runner = ScriptedCommandRunner::new(vec![(
+            "wpa_cli -i wlan0 list_networks",
+            "network id / ssid / bssid / flags\n",
+        )]);
+
+        let WifiAction::Network(eduroam) =
+            wifi_network_action(false, "eduroam", "WPA2 802.1X", "***")
+        else {
+            unreachable!()
+        };
+        let error = connect_to_wpa_wifi("wlan0", &eduroam, false, &runner)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Enterprise networks"));
+        assert_eq!(runner.calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connect_adds_and_saves_open_network() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                "wpa_cli -i wlan0 list_networks",
+                "network id / ssid / bssid / flags\n0\tHome\tany\t\n",
+            ),
+            ("wpa_cli -i wlan0 add_network", "1\n"),
+            ("wpa_cli -i wlan0 set_network 1 ssid 43616665", "OK\n"),
+            ("wpa_cli -i wlan0 set_network 1 key_mgmt NONE", "OK\n"),
+            ("wpa_cli -i wlan0 select_network 1", "OK\n"),
+            (
+                "wpa_cli -i wlan0 status",
+                "ssid=Cafe\nwpa_state=COMPLETED\n",
+            ),
+            ("wpa_cli -i wlan0 save_config", "OK\n"),
+        ]);
+
+        let WifiAction::Network(cafe) = wifi_network_action(false, "Cafe", "", "**") else {
+            unreachable!()
+        };
+        assert!(connect_to_wpa_wifi("wlan0", &cafe, false, &runner)
+            .await
+            .unwrap());
+        assert_eq!(
+            runner.calls.lock().unwrap().last().unwrap(),
+            "wpa_cli -i wlan0 save_config"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_selects_known_network() {
+        let runner = ScriptedCommandRunner::new(vec![
+            (
+                "wpa_cli -i wlan0 list_networks",
+                "network id / ssid / bssid / flags\n0\tHome\tany\t[DISABLED]\n",
+            ),
+            ("wpa_cli -i wlan0 select_network 0", "OK\n"),
+            (
+                "wpa_cli -i wlan0 status",
+                "ssid=Home\nwpa_state=COMPLETED\n",
+            ),
+        ]);
+
+        let WifiAction::Network(home) = wifi_network_action(false, "Home", "WPA2", "***") else {
+            unreachable!()
+        };
+        assert!(connect_to_wpa_wifi("wlan0", &home, false, &runner)
+            .await
+            .unwrap());
+        assert!(!runner
+            .calls
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|call| call.contains("add_network")));
+        assert!(is_known_network("Home", "wlan0", &runner).await.unwrap());
+    }
+}